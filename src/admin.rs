@@ -0,0 +1,263 @@
+// Packets that program command, jigsaw and structure blocks, only operators may send them
+// https://wiki.vg/Protocol#Program_Command_Block
+
+use std::sync::{Arc, Mutex};
+
+use log::{info, warn};
+
+use crate::{events::Event, player::{Gamemode, Player}, server::Server, types::{position::Position, reader::PacketReader}};
+
+/// Same level vanilla requires to edit command blocks
+pub const ADMIN_PERMISSION_LEVEL: u8 = 2;
+/// Furthest a structure block's offset can reach along each axis
+const MAX_STRUCTURE_OFFSET: i8 = 48;
+/// Largest structure along each axis
+const MAX_STRUCTURE_SIZE: i8 = 48;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandBlockMode {
+    Sequence = 0,
+    Auto = 1,
+    Redstone = 2,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandBlockProgram {
+    pub position: Position,
+    pub command: String,
+    pub mode: CommandBlockMode,
+    pub track_output: bool,
+    pub conditional: bool,
+    pub automatic: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandBlockMinecartProgram {
+    pub entity_id: i32,
+    pub command: String,
+    pub track_output: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JigsawProgram {
+    pub position: Position,
+    pub name: String,
+    pub target: String,
+    pub pool: String,
+    pub final_state: String,
+    /// "rollable" or "aligned"
+    pub joint_type: String,
+    pub selection_priority: i32,
+    pub placement_priority: i32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructureBlockProgram {
+    pub position: Position,
+    /// 0 update data, 1 save, 2 load, 3 detect size
+    pub action: i32,
+    /// 0 save, 1 load, 2 corner, 3 data
+    pub mode: i32,
+    pub name: String,
+    pub offset: (i8, i8, i8),
+    pub size: (i8, i8, i8),
+    /// 0 none, 1 left-right, 2 front-back
+    pub mirror: i32,
+    /// 0 none, 1 clockwise 90, 2 clockwise 180, 3 counterclockwise 90
+    pub rotation: i32,
+    pub metadata: String,
+    /// Chance each block is placed, 0 to 1
+    pub integrity: f32,
+    pub seed: i64,
+    pub ignore_entities: bool,
+    pub show_air: bool,
+    pub show_bounding_box: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AdminProgram {
+    CommandBlock(CommandBlockProgram),
+    CommandBlockMinecart(CommandBlockMinecartProgram),
+    Jigsaw(JigsawProgram),
+    StructureBlock(StructureBlockProgram),
+}
+
+pub fn parse_command_block(reader: &mut PacketReader) -> Result<CommandBlockProgram, &'static str> {
+    let position = reader.read_position()?;
+    let command = reader.read_utf8_string(32767)?;
+    let mode = match reader.read_ivar()? {
+        0 => CommandBlockMode::Sequence,
+        1 => CommandBlockMode::Auto,
+        2 => CommandBlockMode::Redstone,
+        _ => return Err("Unknown command block mode"),
+    };
+    let flags = reader.read_u8()?;
+    Ok(CommandBlockProgram {
+        position,
+        command,
+        mode,
+        track_output: flags & 0x01 != 0,
+        conditional: flags & 0x02 != 0,
+        automatic: flags & 0x04 != 0,
+    })
+}
+
+pub fn parse_command_block_minecart(reader: &mut PacketReader) -> Result<CommandBlockMinecartProgram, &'static str> {
+    Ok(CommandBlockMinecartProgram {
+        entity_id: reader.read_ivar()?,
+        command: reader.read_utf8_string(32767)?,
+        track_output: reader.read_bool()?,
+    })
+}
+
+pub fn parse_jigsaw(reader: &mut PacketReader) -> Result<JigsawProgram, &'static str> {
+    let program = JigsawProgram {
+        position: reader.read_position()?,
+        name: reader.read_utf8_string(32767)?,
+        target: reader.read_utf8_string(32767)?,
+        pool: reader.read_utf8_string(32767)?,
+        final_state: reader.read_utf8_string(32767)?,
+        joint_type: reader.read_utf8_string(32767)?,
+        selection_priority: reader.read_ivar()?,
+        placement_priority: reader.read_ivar()?,
+    };
+    if program.joint_type != "rollable" && program.joint_type != "aligned" {
+        return Err("Unknown jigsaw joint type");
+    }
+    Ok(program)
+}
+
+pub fn parse_structure_block(reader: &mut PacketReader) -> Result<StructureBlockProgram, &'static str> {
+    let position = reader.read_position()?;
+    let action = reader.read_ivar()?;
+    let mode = reader.read_ivar()?;
+    let name = reader.read_utf8_string(32767)?;
+    let mut read_i8 = || reader.read_u8().map(|b| b as i8);
+    let offset = (read_i8()?, read_i8()?, read_i8()?);
+    let size = (read_i8()?, read_i8()?, read_i8()?);
+    let mirror = reader.read_ivar()?;
+    let rotation = reader.read_ivar()?;
+    let metadata = reader.read_utf8_string(128)?;
+    let integrity = reader.read_f32()?;
+    let seed = reader.read_lvar()?;
+    let flags = reader.read_u8()?;
+
+    if !(0..=3).contains(&action) || !(0..=3).contains(&mode) {
+        return Err("Unknown structure block action or mode");
+    }
+    if !(0..=2).contains(&mirror) || !(0..=3).contains(&rotation) {
+        return Err("Unknown structure block mirror or rotation");
+    }
+    let offset_range = -MAX_STRUCTURE_OFFSET..=MAX_STRUCTURE_OFFSET;
+    if ![offset.0, offset.1, offset.2].iter().all(|o| offset_range.contains(o)) {
+        return Err("Structure block offset out of range");
+    }
+    if ![size.0, size.1, size.2].iter().all(|s| (0..=MAX_STRUCTURE_SIZE).contains(s)) {
+        return Err("Structure block size out of range");
+    }
+    if !(0.0..=1.0).contains(&integrity) {
+        return Err("Structure block integrity out of range");
+    }
+    Ok(StructureBlockProgram {
+        position,
+        action,
+        mode,
+        name,
+        offset,
+        size,
+        mirror,
+        rotation,
+        metadata,
+        integrity,
+        seed,
+        ignore_entities: flags & 0x01 != 0,
+        show_air: flags & 0x02 != 0,
+        show_bounding_box: flags & 0x04 != 0,
+    })
+}
+
+/// Parses an admin packet and passes it on to listeners if the sender is allowed to send it,
+/// rejected programs are logged and otherwise ignored
+pub fn handle_program(server: &Server, player: &Arc<Mutex<Player>>, packet_id: i32, reader: &mut PacketReader) -> Result<(), &'static str> {
+    let program = match packet_id {
+        0x30 => AdminProgram::CommandBlock(parse_command_block(reader)?),
+        0x31 => AdminProgram::CommandBlockMinecart(parse_command_block_minecart(reader)?),
+        0x33 => AdminProgram::Jigsaw(parse_jigsaw(reader)?),
+        0x34 => AdminProgram::StructureBlock(parse_structure_block(reader)?),
+        _ => return Err("Not an admin packet"),
+    };
+    let (uuid, name, allowed) = {
+        let player = player.lock().unwrap();
+        (player.uuid(), player.name(), is_allowed(&player))
+    };
+    if !allowed {
+        warn!("Ignored {program:?} from {name}, who isn't an operator");
+        return Ok(());
+    }
+    info!("{name} sent {program:?}");
+    server.fire_event(&Event::AdminProgram { player: uuid, program });
+    Ok(())
+}
+
+/// Vanilla also requires creative mode to edit these blocks
+pub fn is_allowed(player: &Player) -> bool {
+    player.permission_level() >= ADMIN_PERMISSION_LEVEL && player.gamemode() == Gamemode::Creative
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::ServerConfig, server::tests::join, utils::write_utf8_string};
+
+    fn command_block_packet(command: &str) -> Vec<u8> {
+        let mut packet = Position::new(1, 64, 2).as_bytes().to_vec();
+        write_utf8_string(&mut packet, command.to_string());
+        packet.push(CommandBlockMode::Redstone as u8);
+        packet.push(0x01 | 0x04);
+        packet
+    }
+
+    fn recorded_events(server: &Server) -> Arc<Mutex<Vec<Event>>> {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let received = Arc::clone(&events);
+        server.add_listener(Box::new(move |_, event| received.lock().unwrap().push(event.clone())));
+        events
+    }
+
+    #[test]
+    fn parses_a_command_block_program() {
+        let program = parse_command_block(&mut PacketReader::new(&command_block_packet("say hi"))).unwrap();
+        assert_eq!(program, CommandBlockProgram {
+            position: Position::new(1, 64, 2),
+            command: "say hi".into(),
+            mode: CommandBlockMode::Redstone,
+            track_output: true,
+            conditional: false,
+            automatic: true,
+        });
+    }
+
+    #[test]
+    fn programs_from_non_operators_are_ignored() {
+        let server = Server::new(ServerConfig::default());
+        let (player, _client) = join(&server, "Steve", 1);
+        player.lock().unwrap().set_gamemode(Gamemode::Creative).unwrap();
+        let events = recorded_events(&server);
+        handle_program(&server, &player, 0x30, &mut PacketReader::new(&command_block_packet("op Steve"))).unwrap();
+        assert!(events.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn operators_in_creative_can_program_command_blocks() {
+        let server = Server::new(ServerConfig::default());
+        let (player, _client) = join(&server, "Steve", 1);
+        player.lock().unwrap().set_permission_level(ADMIN_PERMISSION_LEVEL);
+        // Operators still need to be in creative
+        assert!(!is_allowed(&player.lock().unwrap()));
+        player.lock().unwrap().set_gamemode(Gamemode::Creative).unwrap();
+        let events = recorded_events(&server);
+        handle_program(&server, &player, 0x30, &mut PacketReader::new(&command_block_packet("say hi"))).unwrap();
+        let program = parse_command_block(&mut PacketReader::new(&command_block_packet("say hi"))).unwrap();
+        assert_eq!(*events.lock().unwrap(), vec![Event::AdminProgram { player: 1, program: AdminProgram::CommandBlock(program) }]);
+    }
+}
@@ -0,0 +1,138 @@
+// Looks up profiles from Mojang's session server, offline mode servers can use it to show real skins
+// https://wiki.vg/Mojang_API#UUID_to_Profile_and_Skin.2FCape
+
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::{profile::{GameProfile, ProfileProperty}, utils::decode_base64};
+
+const SESSION_SERVER: &str = "https://sessionserver.mojang.com/session/minecraft/profile";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug)]
+pub enum AuthError {
+    Request(Box<ureq::Error>),
+    /// No account has the uuid
+    NotFound,
+    Io(std::io::Error),
+    Parse(serde_json::Error),
+    InvalidUuid(String),
+}
+
+impl std::fmt::Display for AuthError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Request(e) => write!(f, "Profile request failed: {e}"),
+            Self::NotFound => write!(f, "No profile was found"),
+            Self::Io(e) => write!(f, "Could not read the profile: {e}"),
+            Self::Parse(e) => write!(f, "Could not parse the profile: {e}"),
+            Self::InvalidUuid(id) => write!(f, "Profile has an invalid uuid {id:?}"),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ProfileResponse {
+    id: String,
+    name: String,
+    #[serde(default)]
+    properties: Vec<ProfileProperty>,
+}
+
+/// Fetches the profile with its signed textures property, blocking until the session server answers
+pub fn fetch_profile(uuid: u128) -> Result<GameProfile, AuthError> {
+    let url = format!("{SESSION_SERVER}/{uuid:032x}?unsigned=false");
+    let response = match ureq::get(&url).timeout(REQUEST_TIMEOUT).call() {
+        Ok(r) => r,
+        Err(ureq::Error::Status(404, _)) => return Err(AuthError::NotFound),
+        Err(e) => return Err(AuthError::Request(Box::new(e))),
+    };
+    // Unknown uuids get an empty 204 rather than a 404
+    if response.status() == 204 {
+        return Err(AuthError::NotFound);
+    }
+    parse_profile(&response.into_string().map_err(AuthError::Io)?)
+}
+
+/// Parses a session server profile, its uuid is sent without dashes
+pub fn parse_profile(body: &str) -> Result<GameProfile, AuthError> {
+    let response: ProfileResponse = serde_json::from_str(body).map_err(AuthError::Parse)?;
+    let uuid = u128::from_str_radix(&response.id.replace('-', ""), 16).map_err(|_| AuthError::InvalidUuid(response.id.clone()))?;
+    Ok(GameProfile {
+        uuid,
+        name: response.name,
+        properties: response.properties,
+    })
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct Textures {
+    #[serde(rename = "SKIN")]
+    pub skin: Option<Texture>,
+    #[serde(rename = "CAPE")]
+    pub cape: Option<Texture>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Texture {
+    pub url: String,
+    pub metadata: Option<TextureMetadata>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct TextureMetadata {
+    /// "slim" for the thin armed model, missing for the classic one
+    pub model: String,
+}
+
+#[derive(Deserialize)]
+struct TexturesProperty {
+    textures: Textures,
+}
+
+/// Skin and cape from the profile's base64 encoded textures property, None if it has none
+pub fn textures(profile: &GameProfile) -> Option<Textures> {
+    let property = profile.properties.iter().find(|p| p.name == "textures")?;
+    let json = decode_base64(&property.value)?;
+    serde_json::from_slice::<TexturesProperty>(&json).ok().map(|p| p.textures)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::encode_base64;
+
+    #[test]
+    fn profiles_are_parsed_with_their_textures() {
+        let property = r#"{"textures":{"SKIN":{"url":"http://textures.minecraft.net/texture/1","metadata":{"model":"slim"}}}}"#;
+        let body = format!(r#"{{
+            "id": "069a79f444e94726a5befca90e38aaf5",
+            "name": "Notch",
+            "properties": [{{"name": "textures", "value": "{}", "signature": "c2lnbmF0dXJl"}}]
+        }}"#, encode_base64(property.as_bytes()));
+        let profile = parse_profile(&body).unwrap();
+        assert_eq!(profile.uuid, 0x069a79f444e94726a5befca90e38aaf5);
+        assert_eq!(profile.name, "Notch");
+        assert_eq!(profile.properties, vec![ProfileProperty {
+            name: "textures".to_string(),
+            value: encode_base64(property.as_bytes()),
+            signature: Some("c2lnbmF0dXJl".to_string()),
+        }]);
+        let skin = textures(&profile).unwrap().skin.unwrap();
+        assert_eq!(skin.url, "http://textures.minecraft.net/texture/1");
+        assert_eq!(skin.metadata.unwrap().model, "slim");
+    }
+
+    #[test]
+    fn invalid_uuids_are_rejected() {
+        let body = r#"{"id": "not a uuid", "name": "Notch"}"#;
+        assert!(matches!(parse_profile(body), Err(AuthError::InvalidUuid(_))));
+    }
+
+    #[test]
+    fn profiles_without_textures_have_none() {
+        let profile = GameProfile { uuid: 1, name: "Steve".to_string(), properties: Vec::new() };
+        assert_eq!(textures(&profile), None);
+    }
+}
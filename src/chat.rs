@@ -0,0 +1,221 @@
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crate::{rsa::RsaPublicKey, types::reader::PacketReader};
+
+/// Longest DER encoded public key the client may send, same as vanilla
+pub const MAX_PUBLIC_KEY_LENGTH: usize = 512;
+pub const MAX_KEY_SIGNATURE_LENGTH: usize = 4096;
+/// Length of a message signature made with the 2048 bit keys clients use
+pub const MESSAGE_SIGNATURE_LENGTH: usize = 256;
+/// Bytes in the acknowledged bitset, one bit for each of the last 20 messages
+const ACKNOWLEDGED_LENGTH: usize = 3;
+/// Version of the signed message format
+const SIGNED_MESSAGE_VERSION: i32 = 1;
+
+pub const DEFAULT_CHAT_MESSAGES_PER_SECOND: f32 = 2.0;
+
+/// Token bucket limiting how many chat messages a player can send per second
+pub struct ChatRateLimiter {
+    messages_per_second: f32,
+    /// Most messages that can be sent at once, at least one so slow rates still let a message through
+    burst: f32,
+    allowance: f32,
+    last_message: Instant,
+}
+
+impl ChatRateLimiter {
+    pub fn new(messages_per_second: f32) -> Self {
+        let burst = messages_per_second.max(1.0);
+        Self {
+            messages_per_second,
+            burst,
+            allowance: burst,
+            last_message: Instant::now(),
+        }
+    }
+
+    /// Records a message sent at `now`, returning false if it goes over the limit
+    pub fn check(&mut self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.last_message).as_secs_f32();
+        self.last_message = now;
+        self.allowance = (self.allowance + elapsed * self.messages_per_second).min(self.burst);
+        if self.allowance < 1.0 {
+            return false;
+        }
+        self.allowance -= 1.0;
+        true
+    }
+}
+
+/// Chat session a client sends with Player Session, its key signs every chat message for the rest of the session.
+/// The key signature is Mojang's and is kept as sent, it isn't checked against Mojang's public keys.
+// https://wiki.vg/Protocol#Player_Session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChatSession {
+    pub session_id: u128,
+    /// Milliseconds since the epoch when the key stops being valid
+    pub expires_at: i64,
+    /// DER encoded X.509 RSA public key
+    pub public_key: Vec<u8>,
+    pub key_signature: Vec<u8>,
+}
+
+impl ChatSession {
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        let now = now.duration_since(UNIX_EPOCH).map(|d| d.as_millis() as i64).unwrap_or(0);
+        self.expires_at <= now
+    }
+
+    /// Whether the message was signed with this session's key, `index` counts the player's messages since the session started
+    pub fn verify(&self, sender: u128, index: i32, message: &ChatMessage) -> bool {
+        let Some(signature) = &message.signature else {
+            return false;
+        };
+        let Some(key) = RsaPublicKey::from_der(&self.public_key) else {
+            return false;
+        };
+        key.verify_sha256(&signed_data(sender, self.session_id, index, message), signature)
+    }
+}
+
+/// Serverbound Chat Message
+// https://wiki.vg/Protocol#Chat_Message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChatMessage {
+    pub message: String,
+    /// Milliseconds since the epoch
+    pub timestamp: i64,
+    pub salt: i64,
+    pub signature: Option<Vec<u8>>,
+    pub message_count: i32,
+    /// Which of the last 20 player chat messages sent to the client it has seen
+    pub acknowledged: [u8; ACKNOWLEDGED_LENGTH],
+}
+
+impl ChatMessage {
+    /// Player Chat is never sent so a client can't have seen any, other than what a modified client claims
+    pub fn acknowledges_anything(&self) -> bool {
+        self.acknowledged.iter().any(|b| *b != 0)
+    }
+}
+
+pub fn parse_chat_message(reader: &mut PacketReader) -> Result<ChatMessage, &'static str> {
+    let message = reader.read_utf8_string(256)?;
+    let timestamp = reader.read_i64()?;
+    let salt = reader.read_i64()?;
+    let signature = match reader.read_bool()? {
+        true => Some(reader.read_bytes(MESSAGE_SIGNATURE_LENGTH)?.to_vec()),
+        false => None,
+    };
+    let message_count = reader.read_ivar()?;
+    let acknowledged = reader.read_bytes(ACKNOWLEDGED_LENGTH)?.try_into().unwrap();
+    Ok(ChatMessage {
+        message,
+        timestamp,
+        salt,
+        signature,
+        message_count,
+        acknowledged,
+    })
+}
+
+/// What the client signs: the format version, the link to the session and the message body.
+/// The body ends with the signatures of the messages the client has seen, which is always none.
+pub fn signed_data(sender: u128, session_id: u128, index: i32, message: &ChatMessage) -> Vec<u8> {
+    let mut data: Vec<u8> = Vec::new();
+    data.extend_from_slice(&SIGNED_MESSAGE_VERSION.to_be_bytes());
+    data.extend_from_slice(&sender.to_be_bytes());
+    data.extend_from_slice(&session_id.to_be_bytes());
+    data.extend_from_slice(&index.to_be_bytes());
+    data.extend_from_slice(&message.salt.to_be_bytes());
+    // Signed in whole seconds
+    data.extend_from_slice(&message.timestamp.div_euclid(1000).to_be_bytes());
+    data.extend_from_slice(&(message.message.len() as i32).to_be_bytes());
+    data.extend_from_slice(message.message.as_bytes());
+    data.extend_from_slice(&0i32.to_be_bytes());
+    data
+}
+
+pub fn parse_player_session(reader: &mut PacketReader) -> Result<ChatSession, &'static str> {
+    let session_id = reader.read_u128()?;
+    let expires_at = reader.read_i64()?;
+    let public_key = read_prefixed_bytes(reader, MAX_PUBLIC_KEY_LENGTH)?;
+    let key_signature = read_prefixed_bytes(reader, MAX_KEY_SIGNATURE_LENGTH)?;
+    Ok(ChatSession {
+        session_id,
+        expires_at,
+        public_key,
+        key_signature,
+    })
+}
+
+fn read_prefixed_bytes(reader: &mut PacketReader, max_length: usize) -> Result<Vec<u8>, &'static str> {
+    let length = reader.read_ivar()?;
+    if length < 0 || length as usize > max_length {
+        return Err("Byte array length out of range");
+    }
+    Ok(reader.read_bytes(length as usize)?.to_vec())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::utils::write_ivar;
+
+    #[test]
+    fn allows_a_burst_then_refills() {
+        let mut limiter = ChatRateLimiter::new(2.0);
+        let now = limiter.last_message;
+        assert!(limiter.check(now));
+        assert!(limiter.check(now));
+        assert!(!limiter.check(now));
+        assert!(limiter.check(now + Duration::from_millis(500)));
+        // A long wait doesn't save up more than the burst
+        let later = now + Duration::from_secs(60);
+        assert!(limiter.check(later));
+        assert!(limiter.check(later));
+        assert!(!limiter.check(later));
+    }
+
+    #[test]
+    fn slow_rates_still_allow_one_message() {
+        let mut limiter = ChatRateLimiter::new(0.5);
+        let now = limiter.last_message;
+        assert!(limiter.check(now));
+        assert!(!limiter.check(now + Duration::from_secs(1)));
+        assert!(limiter.check(now + Duration::from_secs(3)));
+    }
+
+    pub fn player_session_packet(session: &ChatSession) -> Vec<u8> {
+        let mut packet = session.session_id.to_be_bytes().to_vec();
+        packet.extend_from_slice(&session.expires_at.to_be_bytes());
+        write_ivar(&mut packet, session.public_key.len() as i32);
+        packet.extend_from_slice(&session.public_key);
+        write_ivar(&mut packet, session.key_signature.len() as i32);
+        packet.extend_from_slice(&session.key_signature);
+        packet
+    }
+
+    #[test]
+    fn parses_a_player_session() {
+        let session = ChatSession { session_id: 0xABCD, expires_at: 1_700_000_000_000, public_key: vec![0x30; 294], key_signature: vec![7; 512] };
+        let packet = player_session_packet(&session);
+        assert_eq!(parse_player_session(&mut PacketReader::new(&packet)).unwrap(), session);
+        assert!(parse_player_session(&mut PacketReader::new(&packet[..packet.len() - 1])).is_err());
+    }
+
+    #[test]
+    fn oversized_keys_are_rejected() {
+        let session = ChatSession { session_id: 1, expires_at: 0, public_key: vec![0; MAX_PUBLIC_KEY_LENGTH + 1], key_signature: Vec::new() };
+        assert!(parse_player_session(&mut PacketReader::new(&player_session_packet(&session))).is_err());
+    }
+
+    #[test]
+    fn sessions_expire_at_their_timestamp() {
+        let session = ChatSession { session_id: 1, expires_at: 10_000, public_key: Vec::new(), key_signature: Vec::new() };
+        assert!(!session.is_expired(UNIX_EPOCH + Duration::from_millis(9_999)));
+        assert!(session.is_expired(UNIX_EPOCH + Duration::from_millis(10_000)));
+    }
+}
@@ -0,0 +1,127 @@
+// Replays recorded serverbound packets against a running server, for turning captured sessions into regression tests
+
+use std::{io::{ErrorKind, Read}, net::TcpStream, thread, time::Duration};
+
+use log::{debug, info};
+
+use crate::{trace::{self, Direction, TraceEntry}, types::varint::ivar, utils::write_packet};
+
+pub struct ReplayOptions {
+    /// Wait between each sent packet, useful when the server needs time to act on them
+    pub delay: Option<Duration>,
+    /// How long to wait for more responses before moving on to the next packet
+    pub response_timeout: Duration,
+}
+
+impl Default for ReplayOptions {
+    fn default() -> Self {
+        Self {
+            delay: None,
+            response_timeout: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Sends every serverbound packet in the trace to `address` and returns what the server sent back.
+/// Responses are labelled with the state of the packet that was last sent.
+pub fn replay(address: &str, trace_file: &str, options: &ReplayOptions) -> Result<Vec<TraceEntry>, std::io::Error> {
+    let entries = trace::read_entries(trace_file)?;
+    let mut stream = TcpStream::connect(address)?;
+    stream.set_read_timeout(Some(options.response_timeout))?;
+
+    let mut responses = Vec::new();
+    for entry in entries.iter().filter(|e| e.direction == Direction::Serverbound) {
+        if let Some(delay) = options.delay {
+            thread::sleep(delay);
+        }
+        debug!("Replaying {} packet {:?}", entry.state, entry.packet);
+        write_packet(&mut stream, &entry.packet)?;
+        while let Some(packet) = read_packet(&mut stream)? {
+            responses.push(TraceEntry {
+                direction: Direction::Clientbound,
+                state: entry.state.clone(),
+                packet,
+            });
+        }
+    }
+    Ok(responses)
+}
+
+/// Reads one framed packet, None once the server stops sending or closes the connection
+fn read_packet(stream: &mut TcpStream) -> Result<Option<Vec<u8>>, std::io::Error> {
+    let mut length_bytes: Vec<u8> = Vec::new();
+    loop {
+        let mut byte = [0; 1];
+        match stream.read(&mut byte) {
+            Ok(0) => return Ok(None),
+            Ok(_) => length_bytes.push(byte[0]),
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => return Ok(None),
+            Err(e) => return Err(e),
+        }
+        if byte[0] & 0x80 == 0 {
+            break;
+        }
+        if length_bytes.len() >= 3 {
+            return Err(std::io::Error::new(ErrorKind::InvalidData, "Packet length VarInt is too long"));
+        }
+    }
+    let length = match ivar::read(&length_bytes) {
+        Ok(v) => v.value.max(0) as usize,
+        Err(_) => return Err(std::io::Error::new(ErrorKind::InvalidData, "Invalid packet length")),
+    };
+    let mut packet = vec![0; length];
+    stream.read_exact(&mut packet)?;
+    Ok(Some(packet))
+}
+
+/// Replays a trace and logs every response
+pub fn replay_and_log(address: &str, trace_file: &str) -> Result<(), std::io::Error> {
+    for response in replay(address, trace_file, &ReplayOptions::default())? {
+        info!("{} {:?}", response.state, response.packet);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, io::Write, net::TcpListener, sync::Arc};
+
+    use super::*;
+    use crate::{config::ServerConfig, protocol::Packet, server::Server, utils::{write_ivar, write_utf8_string}};
+
+    #[test]
+    fn replayed_status_trace_gets_a_status_response() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = Arc::new(Server::new(ServerConfig::default()));
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            crate::start_connection(stream, server);
+        });
+
+        let mut handshake: Vec<u8> = Vec::new();
+        write_ivar(&mut handshake, 0x00);
+        write_ivar(&mut handshake, 767);
+        write_utf8_string(&mut handshake, "localhost".to_string());
+        handshake.extend_from_slice(&25565u16.to_be_bytes());
+        write_ivar(&mut handshake, 1);
+        let entries = [
+            TraceEntry { direction: Direction::Serverbound, state: "Handshake".into(), packet: handshake },
+            TraceEntry { direction: Direction::Serverbound, state: "Status".into(), packet: vec![0x00] },
+            // Responses recorded alongside the requests are not sent again
+            TraceEntry { direction: Direction::Clientbound, state: "Status".into(), packet: vec![0x00] },
+        ];
+        let path = std::env::temp_dir().join(format!("blahaj-replay-{}.bin", std::process::id())).to_string_lossy().to_string();
+        let mut file = File::create(&path).unwrap();
+        for entry in &entries {
+            file.write_all(&entry.as_bytes()).unwrap();
+        }
+        drop(file);
+
+        let responses = replay(&address, &path, &ReplayOptions::default()).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(responses.len(), 1);
+        assert_eq!(responses[0].state, "Status");
+        assert_eq!(responses[0].packet[0] as i32, Packet::StatusResponse.id());
+    }
+}
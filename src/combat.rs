@@ -0,0 +1,62 @@
+// Combat calculations shared by anything that hurts entities
+
+use crate::{packets, player::Player};
+
+/// Knockback strength of a hit without any enchantments
+pub const ATTACK_KNOCKBACK: f64 = 0.4;
+
+/// Pushes the player away from the given point, the same way vanilla knocks back from an attack.
+/// Velocity isn't tracked yet so the player is assumed to be standing still.
+pub fn apply_knockback(victim: &mut Player, from_x: f64, from_z: f64, strength: f64) -> Result<(), std::io::Error> {
+    let (x, _, z) = victim.position();
+    let (mut dx, mut dz) = (x - from_x, z - from_z);
+    let length = (dx * dx + dz * dz).sqrt();
+    if length < 1.0E-5 {
+        // Directly on top of each other, vanilla picks a random direction but a fixed one will do
+        (dx, dz) = (1.0, 0.0);
+    } else {
+        (dx, dz) = (dx / length, dz / length);
+    }
+    let vy = match victim.on_ground() {
+        true => strength.min(0.4),
+        false => 0.0,
+    };
+    let velocity = (dx * strength, vy, dz * strength);
+    victim.send_packet(&packets::play::set_entity_velocity(victim.entity_id(), velocity))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::ServerConfig, protocol::Packet, types::reader::PacketReader, utils::tests::{read_packet, socket_pair}};
+
+    fn knockback_velocity(victim: &mut Player, client: &mut std::net::TcpStream, from_x: f64, from_z: f64) -> (i16, i16, i16) {
+        apply_knockback(victim, from_x, from_z, ATTACK_KNOCKBACK).unwrap();
+        victim.flush().unwrap();
+        let packet = read_packet(client);
+        let mut reader = PacketReader::new(&packet);
+        assert_eq!(reader.read_ivar().unwrap(), Packet::SetEntityVelocity.id());
+        assert_eq!(reader.read_ivar().unwrap(), victim.entity_id());
+        (reader.read_i16().unwrap(), reader.read_i16().unwrap(), reader.read_i16().unwrap())
+    }
+
+    #[test]
+    fn knockback_from_the_west_pushes_east() {
+        let (server, mut client) = socket_pair();
+        let mut victim = Player::new(server, "Steve".into(), 1, 7, &ServerConfig::default());
+        victim.set_position(0.5, 64.0, 0.5);
+        victim.set_on_ground(true);
+        let (x, y, z) = knockback_velocity(&mut victim, &mut client, -2.0, 0.5);
+        assert_eq!((x, y, z), (3200, 3200, 0));
+    }
+
+    #[test]
+    fn knockback_in_the_air_is_only_sideways() {
+        let (server, mut client) = socket_pair();
+        let mut victim = Player::new(server, "Steve".into(), 1, 7, &ServerConfig::default());
+        victim.set_position(0.5, 70.0, 0.5);
+        victim.set_on_ground(false);
+        let (x, y, z) = knockback_velocity(&mut victim, &mut client, 0.5, 3.0);
+        assert_eq!((x, y, z), (0, 0, -3200));
+    }
+}
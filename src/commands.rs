@@ -0,0 +1,1019 @@
+use std::sync::{Arc, Mutex};
+
+use log::info;
+
+use crate::{cookie, packets::{self, play::{Anchor, SoundCategory}}, player::{Gamemode, Player}, resource_pack::{self, ResourcePack}, server::Server, types::text_component::TextComponent, types::position::Position, world::{blocks, border, weather::Weather}};
+
+pub enum CommandSender {
+    Console,
+    Player(u128),
+}
+
+impl CommandSender {
+    pub fn name(&self, server: &Server) -> String {
+        match self {
+            Self::Console => "Server".into(),
+            Self::Player(uuid) => match server.get_player(*uuid) {
+                Some(player) => player.lock().unwrap().name(),
+                None => "Unknown".into(),
+            },
+        }
+    }
+
+    /// Operator level of the sender, the console can always run everything
+    pub fn permission_level(&self, server: &Server) -> u8 {
+        match self {
+            Self::Console => 4,
+            Self::Player(uuid) => match server.get_player(*uuid) {
+                Some(player) => player.lock().unwrap().permission_level(),
+                None => 0,
+            },
+        }
+    }
+}
+
+fn require_permission(server: &Server, sender: &CommandSender, level: u8) -> Result<(), String> {
+    match sender.permission_level(server) >= level {
+        true => Ok(()),
+        false => Err("You do not have permission to use this command".into()),
+    }
+}
+
+/// Runs a command line (with or without the leading slash), returning the feedback for the sender
+pub fn dispatch(server: &Server, sender: &CommandSender, input: &str) -> Result<String, String> {
+    let input = input.trim().trim_start_matches('/');
+    let mut parts = input.split_whitespace();
+    let name = match parts.next() {
+        Some(n) => n,
+        None => return Err("Empty command".into()),
+    };
+    let args: Vec<&str> = parts.collect();
+
+    match name {
+        "list" => list(server),
+        "say" => say(server, sender, &args),
+        "stop" => stop(server, sender),
+        "op" => set_operator(server, sender, &args, 4),
+        "deop" => set_operator(server, sender, &args, 0),
+        "kick" => kick(server, sender, &args),
+        "gamemode" => gamemode(server, sender, &args),
+        "tp" | "teleport" => teleport(server, sender, &args),
+        "time" => time(server, sender, &args),
+        "speed" => speed(server, sender, &args),
+        "distance" => distance(server, sender, &args),
+        "xp" | "experience" => experience(server, sender, &args),
+        "tick" => tick(server, sender, &args),
+        "transfer" => transfer(server, sender, &args),
+        "weather" => weather(server, sender, &args),
+        "worldborder" => world_border(server, sender, &args),
+        "gamerule" => gamerule(server, sender, &args),
+        "resourcepack" => resource_pack(server, sender, &args),
+        "playsound" => play_sound(server, sender, &args),
+        "stopsound" => stop_sound(server, sender, &args),
+        "setblock" => setblock(server, sender, &args),
+        "fill" => fill(server, sender, &args),
+        "save-all" => save_all(server, sender),
+        _ => Err(format!("Unknown command: {name}")),
+    }
+}
+
+/// Finds the named player, or the sender themselves when no name is given
+fn target_player(server: &Server, sender: &CommandSender, name: Option<&&str>) -> Result<Arc<Mutex<Player>>, String> {
+    match (name, sender) {
+        (Some(name), _) => server.get_player_by_name(name).ok_or(format!("No player was found with the name {name}")),
+        (None, CommandSender::Player(uuid)) => server.get_player(*uuid).ok_or("You are not online".into()),
+        (None, CommandSender::Console) => Err("A player must be given when run from the console".into()),
+    }
+}
+
+fn list(server: &Server) -> Result<String, String> {
+    let players = server.players();
+    let names = players.iter().map(|(name, _)| name.as_str()).collect::<Vec<&str>>();
+    Ok(format!("There are {}/{} players online: {}", names.len(), server.config.max_players, names.join(", ")))
+}
+
+fn say(server: &Server, sender: &CommandSender, args: &[&str]) -> Result<String, String> {
+    require_permission(server, sender, 2)?;
+    if args.is_empty() {
+        return Err("Usage: /say <message>".into());
+    }
+    let message = format!("[{}] {}", sender.name(server), args.join(" "));
+    server.broadcast(&packets::play::system_chat(&TextComponent::new(message.clone()), false));
+    Ok(message)
+}
+
+fn stop(server: &Server, sender: &CommandSender) -> Result<String, String> {
+    require_permission(server, sender, 4)?;
+    info!("{} stopped the server", sender.name(server));
+    server.stop(&TextComponent::translate("multiplayer.disconnect.server_shutdown", Vec::new()));
+    Ok("Stopping the server".into())
+}
+
+/// Console only, players aren't authenticated so a name alone can't be trusted to grant operator.
+/// The level lasts until the player disconnects
+fn set_operator(server: &Server, sender: &CommandSender, args: &[&str], level: u8) -> Result<String, String> {
+    if !matches!(sender, CommandSender::Console) {
+        return Err("Operators can only be changed from the console".into());
+    }
+    let player = match args {
+        [name] => target_player(server, sender, Some(name))?,
+        _ => return Err(format!("Usage: /{} <player>", if level > 0 { "op" } else { "deop" })),
+    };
+    let mut player = player.lock().unwrap();
+    player.set_permission_level(level);
+    info!("Set {}'s permission level to {level}", player.name());
+    Ok(match level {
+        0 => format!("Made {} no longer a server operator", player.name()),
+        _ => format!("Made {} a server operator", player.name()),
+    })
+}
+
+fn save_all(server: &Server, sender: &CommandSender) -> Result<String, String> {
+    require_permission(server, sender, 4)?;
+    if server.config.world_directory.is_none() {
+        return Err("No world_directory is configured, there is nowhere to save to".into());
+    }
+    match server.save_world() {
+        Ok(saved) => Ok(format!("Saved the game ({saved} changed chunks)")),
+        Err(e) => Err(format!("Could not save the game: {e}")),
+    }
+}
+
+fn kick(server: &Server, sender: &CommandSender, args: &[&str]) -> Result<String, String> {
+    require_permission(server, sender, 3)?;
+    let name = match args.first() {
+        Some(n) => *n,
+        None => return Err("Usage: /kick <player> [reason]".into()),
+    };
+    let player = match server.get_player_by_name(name) {
+        Some(p) => p,
+        None => return Err(format!("No player was found with the name {name}")),
+    };
+    let reason = match args.len() > 1 {
+        true => args[1..].join(" "),
+        false => "Kicked by an operator".into(),
+    };
+    let (name, uuid) = {
+        let mut player = player.lock().unwrap();
+        let _ = player.disconnect(&TextComponent::new(reason.clone()));
+        (player.name(), player.uuid())
+    };
+    server.remove_player(uuid);
+    Ok(format!("Kicked {name}: {reason}"))
+}
+
+fn gamemode(server: &Server, sender: &CommandSender, args: &[&str]) -> Result<String, String> {
+    require_permission(server, sender, 2)?;
+    let gamemode = match args.first().and_then(|mode| Gamemode::parse(mode)) {
+        Some(g) => g,
+        None => return Err("Usage: /gamemode <survival|creative|adventure|spectator> [player]".into()),
+    };
+    let player = target_player(server, sender, args.get(1))?;
+    let (name, uuid) = {
+        let mut player = player.lock().unwrap();
+        let _ = player.set_gamemode(gamemode);
+        (player.name(), player.uuid())
+    };
+    server.broadcast(&packets::play::player_info_game_mode(&[(uuid, gamemode)]));
+    Ok(format!("Set {name}'s game mode to {gamemode:?}"))
+}
+
+const MAX_HORIZONTAL: f64 = 30_000_000.0;
+
+/// Parses an absolute coordinate, or one relative to `current` when prefixed with `~`
+fn parse_coordinate(arg: &str, current: f64) -> Result<f64, String> {
+    let value = match arg.strip_prefix('~') {
+        Some("") => Ok(current),
+        Some(offset) => offset.parse::<f64>().map(|o| current + o),
+        None => arg.parse::<f64>(),
+    };
+    match value {
+        Ok(v) if v.is_finite() => Ok(v),
+        _ => Err(format!("Invalid coordinate: {arg}")),
+    }
+}
+
+fn teleport(server: &Server, sender: &CommandSender, args: &[&str]) -> Result<String, String> {
+    require_permission(server, sender, 2)?;
+    let usage = "Usage: /tp [target] <x y z | player> [facing <x y z | entity player [eyes|feet]>]";
+    let (args, facing) = match args.iter().position(|arg| *arg == "facing") {
+        Some(i) => (&args[..i], Some(&args[i + 1..])),
+        None => (args, None),
+    };
+    let (target, destination) = match args.len() {
+        1 => (target_player(server, sender, None)?, &args[0..1]),
+        2 => (target_player(server, sender, args.first())?, &args[1..2]),
+        3 => (target_player(server, sender, None)?, &args[0..3]),
+        4 => (target_player(server, sender, args.first())?, &args[1..4]),
+        _ => return Err(usage.into()),
+    };
+
+    let (x, y, z) = match destination {
+        [name] => match server.get_player_by_name(name) {
+            Some(p) => p.lock().unwrap().position(),
+            None => return Err(format!("No player was found with the name {name}")),
+        },
+        coordinates => {
+            let current = target.lock().unwrap().position();
+            (
+                parse_coordinate(coordinates[0], current.0)?,
+                parse_coordinate(coordinates[1], current.1)?,
+                parse_coordinate(coordinates[2], current.2)?,
+            )
+        },
+    };
+    if x.abs() > MAX_HORIZONTAL || z.abs() > MAX_HORIZONTAL || !server.config.world.contains_y(y) {
+        return Err(format!("{x} {y} {z} is outside of the world"));
+    }
+    // Relative facing coordinates are from the destination
+    let look_at = match facing {
+        None => None,
+        Some(["entity", name, anchor @ ..]) => {
+            let anchor = match anchor {
+                [] | ["eyes"] => Anchor::Eyes,
+                ["feet"] => Anchor::Feet,
+                _ => return Err(usage.into()),
+            };
+            match server.get_player_by_name(name) {
+                Some(p) => {
+                    let p = p.lock().unwrap();
+                    let (fx, fy, fz) = p.position();
+                    Some(packets::play::look_at(Anchor::Eyes, fx, fy, fz, Some((p.entity_id(), anchor))))
+                },
+                None => return Err(format!("No player was found with the name {name}")),
+            }
+        },
+        Some([fx, fy, fz]) => Some(packets::play::look_at(Anchor::Eyes, parse_coordinate(fx, x)?, parse_coordinate(fy, y)?, parse_coordinate(fz, z)?, None)),
+        Some(_) => return Err(usage.into()),
+    };
+
+    let mut target = target.lock().unwrap();
+    let _ = target.teleport(x, y, z);
+    if let Some(packet) = look_at {
+        let _ = target.send_packet(&packet);
+    }
+    Ok(format!("Teleported {} to {x:.2}, {y:.2}, {z:.2}", target.name()))
+}
+
+fn time(server: &Server, sender: &CommandSender, args: &[&str]) -> Result<String, String> {
+    require_permission(server, sender, 2)?;
+    let usage = "Usage: /time <set <day|noon|night|midnight|ticks> | add <ticks>>";
+    let time = match args {
+        ["set", value] => match *value {
+            "day" => 1000,
+            "noon" => 6000,
+            "night" => 13000,
+            "midnight" => 18000,
+            ticks => ticks.parse::<i64>().map_err(|_| usage.to_string())?,
+        },
+        ["add", ticks] => ticks.parse::<i64>().ok().and_then(|t| server.time_of_day().checked_add(t)).ok_or_else(|| usage.to_string())?,
+        _ => return Err(usage.into()),
+    };
+    server.set_time_of_day(time);
+    server.broadcast_time();
+    Ok(format!("Set the time to {time}"))
+}
+
+fn tick(server: &Server, sender: &CommandSender, args: &[&str]) -> Result<String, String> {
+    require_permission(server, sender, 3)?;
+    let usage = "Usage: /tick <freeze|unfreeze|step [ticks]>";
+    match args {
+        ["freeze"] => {
+            server.set_frozen(true);
+            Ok("The game is frozen".into())
+        },
+        ["unfreeze"] => {
+            server.set_frozen(false);
+            Ok("The game is running normally".into())
+        },
+        ["step", rest @ ..] => {
+            if !server.is_frozen() {
+                return Err("The game must be frozen first".into());
+            }
+            let steps = match rest {
+                [] => 1,
+                [ticks] => ticks.parse::<i32>().ok().filter(|t| *t > 0).ok_or_else(|| usage.to_string())?,
+                _ => return Err(usage.into()),
+            };
+            server.step(steps);
+            Ok(format!("Stepping {steps} ticks"))
+        },
+        _ => Err(usage.into()),
+    }
+}
+
+/// Vanilla's default port, used when a transfer doesn't give one
+const DEFAULT_PORT: u16 = 25565;
+
+/// Highest walk or flying speed, anything faster is unplayable
+const MAX_SPEED: f64 = 1.0;
+
+fn speed(server: &Server, sender: &CommandSender, args: &[&str]) -> Result<String, String> {
+    require_permission(server, sender, 2)?;
+    let usage = "Usage: /speed <walk|fly> <value> [player]";
+    let (kind, value) = match args {
+        [kind, value] | [kind, value, _] => (*kind, value.parse::<f64>().map_err(|_| usage.to_string())?),
+        _ => return Err(usage.into()),
+    };
+    if !value.is_finite() {
+        return Err(usage.into());
+    }
+    let value = value.clamp(0.0, MAX_SPEED);
+    let player = target_player(server, sender, args.get(2))?;
+    let mut player = player.lock().unwrap();
+    let result = match kind {
+        "walk" => player.set_walk_speed(value),
+        "fly" => player.set_flying_speed(value as f32),
+        _ => return Err(usage.into()),
+    };
+    result.map_err(|e| format!("Could not set the speed of {}: {e}", player.name()))?;
+    Ok(format!("Set the {kind} speed of {} to {value}", player.name()))
+}
+
+/// Distances are clamped by the player, so the feedback shows what was actually set
+fn distance(server: &Server, sender: &CommandSender, args: &[&str]) -> Result<String, String> {
+    require_permission(server, sender, 2)?;
+    let usage = "Usage: /distance <view|simulation> <chunks> [player]";
+    let (kind, chunks) = match args {
+        [kind, chunks] | [kind, chunks, _] => (*kind, chunks.parse::<i32>().map_err(|_| usage.to_string())?),
+        _ => return Err(usage.into()),
+    };
+    let player = target_player(server, sender, args.get(2))?;
+    let mut player = player.lock().unwrap();
+    let result = match kind {
+        "view" => player.set_view_distance(chunks).map(|_| player.view_distance()),
+        "simulation" => player.set_simulation_distance(chunks).map(|_| player.simulation_distance()),
+        _ => return Err(usage.into()),
+    };
+    let chunks = result.map_err(|e| format!("Could not set the {kind} distance of {}: {e}", player.name()))?;
+    Ok(format!("Set the {kind} distance of {} to {chunks} chunks", player.name()))
+}
+
+/// Only adding points is supported, levels can't be taken away
+fn experience(server: &Server, sender: &CommandSender, args: &[&str]) -> Result<String, String> {
+    require_permission(server, sender, 2)?;
+    let usage = "Usage: /xp add <player> <amount>";
+    let (name, amount) = match args {
+        ["add", name, amount] => (name, amount.parse::<i32>().ok().filter(|a| *a >= 0).ok_or_else(|| usage.to_string())?),
+        _ => return Err(usage.into()),
+    };
+    let player = target_player(server, sender, Some(name))?;
+    let mut player = player.lock().unwrap();
+    player.give_experience(amount).map_err(|e| format!("Could not give experience to {}: {e}", player.name()))?;
+    Ok(format!("Gave {amount} experience points to {}", player.name()))
+}
+
+fn transfer(server: &Server, sender: &CommandSender, args: &[&str]) -> Result<String, String> {
+    require_permission(server, sender, 3)?;
+    let usage = "Usage: /transfer <host> [port] [player]";
+    let (host, port, target) = match args {
+        [host] => (*host, DEFAULT_PORT, None),
+        [host, port] => (*host, port.parse::<u16>().map_err(|_| usage.to_string())?, None),
+        [host, port, name] => (*host, port.parse::<u16>().map_err(|_| usage.to_string())?, Some(*name)),
+        _ => return Err(usage.into()),
+    };
+    let players = match target {
+        Some(name) => match server.get_player_by_name(name) {
+            Some(p) => vec![p],
+            None => return Err(format!("No player was found with the name {name}")),
+        },
+        None => server.online_players(),
+    };
+    let mut transferred = 0;
+    for player in &players {
+        let mut player = player.lock().unwrap();
+        let position = player.position();
+        if player.store_cookie(cookie::LAST_POSITION, &position).is_ok() && player.transfer(host, port, None).is_ok() {
+            transferred += 1;
+        }
+    }
+    Ok(format!("Transferred {transferred} player(s) to {host}:{port}"))
+}
+
+fn weather(server: &Server, sender: &CommandSender, args: &[&str]) -> Result<String, String> {
+    require_permission(server, sender, 2)?;
+    let usage = "Usage: /weather <clear|rain|thunder> [duration in seconds]";
+    let weather = match args.first().and_then(|w| Weather::parse(w)) {
+        Some(w) => w,
+        None => return Err(usage.into()),
+    };
+    let duration = match args.get(1) {
+        Some(seconds) => Some(seconds.parse::<u64>().ok()
+            .and_then(|s| s.checked_mul(server.config.tick_rate.round() as u64))
+            .ok_or_else(|| usage.to_string())?),
+        None => None,
+    };
+    server.set_weather(weather, duration);
+    Ok(format!("Set the weather to {weather:?}"))
+}
+
+/// Only the rules the server acts on can be read or changed
+fn gamerule(server: &Server, sender: &CommandSender, args: &[&str]) -> Result<String, String> {
+    require_permission(server, sender, 2)?;
+    match args {
+        ["doImmediateRespawn"] => Ok(format!("Gamerule doImmediateRespawn is currently set to: {}", !server.respawn_screen_enabled())),
+        ["doImmediateRespawn", value] => {
+            let value = value.parse::<bool>().map_err(|_| "Usage: /gamerule doImmediateRespawn [true|false]".to_string())?;
+            server.set_respawn_screen(!value);
+            Ok(format!("Gamerule doImmediateRespawn is now set to: {value}"))
+        },
+        [rule, ..] => Err(format!("Unknown game rule: {rule}")),
+        [] => Err("Usage: /gamerule <rule> [value]".into()),
+    }
+}
+
+fn world_border(server: &Server, sender: &CommandSender, args: &[&str]) -> Result<String, String> {
+    require_permission(server, sender, 2)?;
+    let usage = "Usage: /worldborder <center <x> <z> | set <diameter> [seconds] | warning <distance|time> <value>>";
+    let number = |arg: &str| arg.parse::<f64>().ok().filter(|v| v.is_finite()).ok_or_else(|| usage.to_string());
+    match args {
+        ["center", x, z] => {
+            let (x, z) = (number(x)?, number(z)?);
+            server.set_border_center(x, z);
+            Ok(format!("Set the center of the world border to {x:.2}, {z:.2}"))
+        },
+        ["set", diameter, rest @ ..] => {
+            let diameter = number(diameter)?;
+            if !(1.0..=border::MAX_DIAMETER).contains(&diameter) {
+                return Err(format!("The world border can't be smaller than 1 block or wider than {} blocks", border::MAX_DIAMETER));
+            }
+            let speed = match rest {
+                [] => 0,
+                [seconds] => seconds.parse::<i64>().ok().filter(|s| *s >= 0).and_then(|s| s.checked_mul(1000)).ok_or_else(|| usage.to_string())?,
+                _ => return Err(usage.into()),
+            };
+            server.set_border_size(diameter, speed);
+            Ok(match speed {
+                0 => format!("Set the world border to {diameter:.1} blocks wide"),
+                _ => format!("Growing the world border to {diameter:.1} blocks wide over {} seconds", speed / 1000),
+            })
+        },
+        ["warning", kind, value] => {
+            let value = value.parse::<i32>().ok().filter(|v| *v >= 0).ok_or_else(|| usage.to_string())?;
+            let (time, blocks) = {
+                let border = server.border();
+                (border.warning_time, border.warning_blocks)
+            };
+            match *kind {
+                "distance" => server.set_border_warning(time, value),
+                "time" => server.set_border_warning(value, blocks),
+                _ => return Err(usage.into()),
+            }
+            Ok(format!("Set the world border warning {kind} to {value}"))
+        },
+        _ => Err(usage.into()),
+    }
+}
+
+fn resource_pack(server: &Server, sender: &CommandSender, args: &[&str]) -> Result<String, String> {
+    require_permission(server, sender, 2)?;
+    let usage = "Usage: /resourcepack <add|remove|clear|list> <player> [url [sha1] [forced] | uuid]";
+    let (action, name, rest) = match args {
+        [action, name, rest @ ..] => (*action, *name, rest),
+        _ => return Err(usage.into()),
+    };
+    let player = target_player(server, sender, Some(&name))?;
+    let mut player = player.lock().unwrap();
+    let name = player.name();
+    let result = match (action, rest) {
+        ("add", [url, options @ ..]) => {
+            let forced = options.last() == Some(&"forced");
+            let hash = match options {
+                [hash, ..] if *hash != "forced" => *hash,
+                _ => "",
+            };
+            let pack = ResourcePack::new(resource_pack::url_uuid(url), url, hash)?.forced(forced);
+            let feedback = match player.resource_packs().get(pack.uuid) {
+                Some(_) => format!("Replaced {url} for {name}"),
+                None => format!("Sent {url} to {name} as {:032x}", pack.uuid),
+            };
+            player.add_resource_pack(pack).map(|_| feedback)
+        },
+        ("remove", [uuid]) => {
+            let uuid = u128::from_str_radix(&uuid.replace('-', ""), 16).map_err(|_| usage.to_string())?;
+            match player.remove_resource_pack(uuid) {
+                Ok(true) => Ok(format!("Removed {uuid:032x} from {name}")),
+                Ok(false) => return Err(format!("{name} wasn't sent a resource pack {uuid:032x}")),
+                Err(e) => Err(e),
+            }
+        },
+        ("clear", []) => player.remove_all_resource_packs().map(|_| format!("Removed every resource pack from {name}")),
+        ("list", []) => match player.resource_packs().is_empty() {
+            true => Ok(format!("{name} has no resource packs")),
+            false => {
+                let packs: Vec<String> = player.resource_packs().packs().iter().map(|p| format!("{:032x} {}", p.uuid, p.url)).collect();
+                Ok(format!("{name} has {}", packs.join(", ")))
+            },
+        },
+        _ => return Err(usage.into()),
+    };
+    result.map_err(|e| format!("Could not update {name}'s resource packs: {e}"))
+}
+
+/// The sound follows the player as they move rather than staying where it started
+fn play_sound(server: &Server, sender: &CommandSender, args: &[&str]) -> Result<String, String> {
+    require_permission(server, sender, 2)?;
+    let usage = "Usage: /playsound <sound> <source> <player> [volume] [pitch]";
+    let [sound, source, name, options @ ..] = args else {
+        return Err(usage.into());
+    };
+    let source = SoundCategory::parse(source).ok_or(format!("Unknown sound source: {source}"))?;
+    let (volume, pitch) = match options {
+        [] => (1.0, 1.0),
+        [volume] => (volume.parse::<f32>().map_err(|_| usage.to_string())?, 1.0),
+        [volume, pitch] => (volume.parse::<f32>().map_err(|_| usage.to_string())?, pitch.parse::<f32>().map_err(|_| usage.to_string())?),
+        _ => return Err(usage.into()),
+    };
+    // Same limits as vanilla
+    let (volume, pitch) = (volume.max(0.0), pitch.clamp(0.0, 2.0));
+    let player = target_player(server, sender, Some(name))?;
+    let mut player = player.lock().unwrap();
+    let packet = packets::play::entity_sound_effect(sound, source, player.entity_id(), volume, pitch, 0);
+    player.send_packet(&packet).map_err(|e| format!("Could not play {sound} to {}: {e}", player.name()))?;
+    Ok(format!("Played sound {sound} to {}", player.name()))
+}
+
+fn stop_sound(server: &Server, sender: &CommandSender, args: &[&str]) -> Result<String, String> {
+    require_permission(server, sender, 2)?;
+    let usage = "Usage: /stopsound <player> [source|*] [sound]";
+    let Some(name) = args.first() else {
+        return Err(usage.into());
+    };
+    // * stops a sound from every source
+    let source = match args.get(1) {
+        Some(&"*") | None => None,
+        Some(source) => Some(SoundCategory::parse(source).ok_or(format!("Unknown sound source: {source}"))?),
+    };
+    let sound = match args {
+        [_, _, sound] => Some(*sound),
+        [_] | [_, _] => None,
+        _ => return Err(usage.into()),
+    };
+    let player = target_player(server, sender, Some(name))?;
+    let mut player = player.lock().unwrap();
+    player.send_packet(&packets::play::stop_sound(source, sound)).map_err(|e| format!("Could not stop sounds for {}: {e}", player.name()))?;
+    Ok(match sound {
+        Some(sound) => format!("Stopped sound {sound} for {}", player.name()),
+        None => format!("Stopped sounds for {}", player.name()),
+    })
+}
+
+/// Parses `x y z` into a block position, `~` is relative to the sender
+fn parse_block_position(server: &Server, sender: &CommandSender, args: &[&str]) -> Result<Position, String> {
+    let current = match sender {
+        CommandSender::Player(uuid) => server.get_player(*uuid).map(|p| p.lock().unwrap().position()),
+        CommandSender::Console => None,
+    };
+    if current.is_none() && args.iter().any(|a| a.starts_with('~')) {
+        return Err("Relative coordinates can only be used by players".into());
+    }
+    let (x, y, z) = current.unwrap_or_default();
+    let (x, y, z) = (parse_coordinate(args[0], x)?, parse_coordinate(args[1], y)?, parse_coordinate(args[2], z)?);
+    if x.abs() > MAX_HORIZONTAL || z.abs() > MAX_HORIZONTAL {
+        return Err(format!("{x} {y} {z} is outside of the world"));
+    }
+    Ok(Position::new(x.floor() as i32, y.floor() as i32, z.floor() as i32))
+}
+
+fn parse_block(name: &str) -> Result<i32, String> {
+    blocks::state_id(name).ok_or(format!("Unknown block: {name}"))
+}
+
+fn setblock(server: &Server, sender: &CommandSender, args: &[&str]) -> Result<String, String> {
+    require_permission(server, sender, 2)?;
+    let [x, y, z, block] = args else {
+        return Err("Usage: /setblock <x> <y> <z> <block>".into());
+    };
+    let position = parse_block_position(server, sender, &[x, y, z])?;
+    let state = parse_block(block)?;
+    match server.set_block(position, state).map_err(|e| e.to_string())? {
+        true => Ok(format!("Changed the block at {}, {}, {}", position.x, position.y, position.z)),
+        false => Err("Could not set the block".into()),
+    }
+}
+
+fn fill(server: &Server, sender: &CommandSender, args: &[&str]) -> Result<String, String> {
+    require_permission(server, sender, 2)?;
+    if args.len() != 7 {
+        return Err("Usage: /fill <x1> <y1> <z1> <x2> <y2> <z2> <block>".into());
+    }
+    let from = parse_block_position(server, sender, &args[0..3])?;
+    let to = parse_block_position(server, sender, &args[3..6])?;
+    let state = parse_block(args[6])?;
+    match server.fill(from, to, state).map_err(|e| e.to_string())? {
+        0 => Err("No blocks were filled".into()),
+        changed => Ok(format!("Successfully filled {changed} block(s)")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::ServerConfig, packets::play::Attribute, protocol::Packet, server::tests::join, types::reader::PacketReader, utils::tests::read_packet};
+
+    #[test]
+    fn list_names_every_online_player() {
+        let server = Server::new(ServerConfig::default());
+        let _steve = join(&server, "Steve", 1);
+        let _alex = join(&server, "Alex", 2);
+        let feedback = dispatch(&server, &CommandSender::Console, "/list").unwrap();
+        let max = server.config.max_players;
+        assert!(feedback == format!("There are 2/{max} players online: Steve, Alex") || feedback == format!("There are 2/{max} players online: Alex, Steve"));
+    }
+
+    #[test]
+    fn say_broadcasts_to_every_player() {
+        let server = Server::new(ServerConfig::default());
+        let (_, mut steve) = join(&server, "Steve", 1);
+        let (_, mut alex) = join(&server, "Alex", 2);
+        assert_eq!(dispatch(&server, &CommandSender::Console, "/say hello").unwrap(), "[Server] hello");
+        server.flush_players();
+
+        let expected = packets::play::system_chat(&TextComponent::new("[Server] hello"), false);
+        assert_eq!(read_packet(&mut steve), expected);
+        assert_eq!(read_packet(&mut alex), expected);
+    }
+
+    #[test]
+    fn say_needs_permission_level_two() {
+        let server = Server::new(ServerConfig::default());
+        let (player, _client) = join(&server, "Steve", 1);
+        assert!(dispatch(&server, &CommandSender::Player(1), "/say hello").is_err());
+        player.lock().unwrap().set_permission_level(2);
+        assert_eq!(dispatch(&server, &CommandSender::Player(1), "/say hello").unwrap(), "[Steve] hello");
+    }
+
+    #[test]
+    fn kick_disconnects_and_removes_the_player() {
+        let server = Server::new(ServerConfig::default());
+        let (_, mut steve) = join(&server, "Steve", 1);
+        assert_eq!(dispatch(&server, &CommandSender::Console, "/kick steve Too loud").unwrap(), "Kicked Steve: Too loud");
+        assert_eq!(read_packet(&mut steve), packets::play::disconnect(&TextComponent::new("Too loud")));
+        assert!(server.get_player(1).is_none());
+    }
+
+    #[test]
+    fn kick_has_a_default_reason_and_rejects_unknown_players() {
+        let server = Server::new(ServerConfig::default());
+        let (_, mut steve) = join(&server, "Steve", 1);
+        assert!(dispatch(&server, &CommandSender::Console, "/kick Alex").is_err());
+        dispatch(&server, &CommandSender::Console, "/kick Steve").unwrap();
+        assert_eq!(read_packet(&mut steve), packets::play::disconnect(&TextComponent::new("Kicked by an operator")));
+    }
+
+    #[test]
+    fn gamemode_creative_updates_the_player_and_the_tab_list() {
+        let server = Server::new(ServerConfig::default());
+        let (steve, mut steve_client) = join(&server, "Steve", 1);
+        let (_alex, mut alex_client) = join(&server, "Alex", 2);
+        steve.lock().unwrap().set_permission_level(2);
+        assert_eq!(dispatch(&server, &CommandSender::Player(1), "/gamemode creative").unwrap(), "Set Steve's game mode to Creative");
+        assert_eq!(steve.lock().unwrap().gamemode(), Gamemode::Creative);
+        server.flush_players();
+
+        let info = packets::play::player_info_game_mode(&[(1, Gamemode::Creative)]);
+        assert_eq!(read_packet(&mut steve_client), packets::play::game_event(packets::play::GameEvent::ChangeGameMode, 1.0));
+        assert_eq!(read_packet(&mut steve_client), info);
+        assert_eq!(read_packet(&mut alex_client), info);
+    }
+
+    #[test]
+    fn gamemode_accepts_numbers_and_other_players() {
+        let server = Server::new(ServerConfig::default());
+        let (steve, _steve_client) = join(&server, "Steve", 1);
+        let (alex, _alex_client) = join(&server, "Alex", 2);
+        dispatch(&server, &CommandSender::Console, "/gamemode 3 alex").unwrap();
+        assert_eq!(alex.lock().unwrap().gamemode(), Gamemode::Spectator);
+        assert_eq!(steve.lock().unwrap().gamemode(), Gamemode::Survival);
+        assert!(dispatch(&server, &CommandSender::Console, "/gamemode 4 alex").is_err());
+    }
+
+    #[test]
+    fn tp_sends_an_absolute_position_sync() {
+        let server = Server::new(ServerConfig::default());
+        let (steve, mut steve_client) = join(&server, "Steve", 1);
+        steve.lock().unwrap().set_position(50.0, 70.0, 50.0);
+        assert_eq!(dispatch(&server, &CommandSender::Console, "/tp steve 0 100 0").unwrap(), "Teleported Steve to 0.00, 100.00, 0.00");
+        assert_eq!(steve.lock().unwrap().position(), (0.0, 100.0, 0.0));
+        server.flush_players();
+        assert_eq!(read_packet(&mut steve_client), packets::play::synchronize_player_position(0.0, 100.0, 0.0, 0.0, 0.0, 0, 1));
+    }
+
+    #[test]
+    fn tp_relative_coordinates_and_bounds() {
+        let server = Server::new(ServerConfig::default());
+        let (steve, _steve_client) = join(&server, "Steve", 1);
+        let (alex, _alex_client) = join(&server, "Alex", 2);
+        steve.lock().unwrap().set_position(10.0, 64.0, -10.0);
+        dispatch(&server, &CommandSender::Console, "/tp steve ~5 ~ ~-2.5").unwrap();
+        assert_eq!(steve.lock().unwrap().position(), (15.0, 64.0, -12.5));
+
+        dispatch(&server, &CommandSender::Console, "/tp alex steve").unwrap();
+        assert_eq!(alex.lock().unwrap().position(), (15.0, 64.0, -12.5));
+
+        for command in ["/tp steve 0 100000 0", "/tp steve 30000001 64 0", "/tp steve NaN 64 0", "/tp steve ~x 64 0"] {
+            assert!(dispatch(&server, &CommandSender::Console, command).is_err(), "{command}");
+        }
+        assert_eq!(steve.lock().unwrap().position(), (15.0, 64.0, -12.5));
+    }
+
+    #[test]
+    fn time_set_day_is_broadcast() {
+        let server = Server::new(ServerConfig::default());
+        let (_steve, mut steve_client) = join(&server, "Steve", 1);
+        server.set_time_of_day(15000);
+        assert_eq!(dispatch(&server, &CommandSender::Console, "/time set day").unwrap(), "Set the time to 1000");
+        assert_eq!(server.time_of_day(), 1000);
+        server.flush_players();
+        assert_eq!(read_packet(&mut steve_client), packets::play::update_time(server.world_age(), 1000));
+
+        dispatch(&server, &CommandSender::Console, "/time add 500").unwrap();
+        assert_eq!(server.time_of_day(), 1500);
+        assert!(dispatch(&server, &CommandSender::Console, "/time set dusk").is_err());
+        assert!(dispatch(&server, &CommandSender::Console, &format!("/time add {}", i64::MAX)).is_err());
+        assert_eq!(server.time_of_day(), 1500);
+    }
+
+    #[test]
+    fn weather_rain_begins_raining() {
+        let server = Server::new(ServerConfig::default());
+        let (_steve, mut steve_client) = join(&server, "Steve", 1);
+        assert_eq!(dispatch(&server, &CommandSender::Console, "/weather rain").unwrap(), "Set the weather to Rain");
+        assert_eq!(server.weather(), Weather::Rain);
+        server.flush_players();
+        assert_eq!(read_packet(&mut steve_client), packets::play::game_event(packets::play::GameEvent::BeginRaining, 0.0));
+        assert_eq!(read_packet(&mut steve_client), packets::play::game_event(packets::play::GameEvent::RainLevelChange, 1.0));
+    }
+
+    #[test]
+    fn weather_durations_that_overflow_are_rejected() {
+        let server = Server::new(ServerConfig::default());
+        assert_eq!(dispatch(&server, &CommandSender::Console, "/weather thunder 60").unwrap(), "Set the weather to Thunder");
+        assert!(dispatch(&server, &CommandSender::Console, &format!("/weather clear {}", u64::MAX)).is_err());
+        assert_eq!(server.weather(), Weather::Thunder);
+    }
+
+    #[test]
+    fn transfer_only_sends_the_targeted_player() {
+        let server = Server::new(ServerConfig::default());
+        let (steve, mut steve_client) = join(&server, "Steve", 1);
+        let (alex, mut alex_client) = join(&server, "Alex", 2);
+        let feedback = dispatch(&server, &CommandSender::Console, "/transfer example.com 25566 Alex").unwrap();
+        assert_eq!(feedback, "Transferred 1 player(s) to example.com:25566");
+        let chat = packets::play::system_chat(&TextComponent::new("Still here"), false);
+        server.broadcast(&chat);
+        server.flush_players();
+
+        assert!(alex.lock().unwrap().is_transferring());
+        assert_eq!(read_packet(&mut alex_client), packets::play::store_cookie(cookie::LAST_POSITION, b"[0.0,0.0,0.0]"));
+        assert_eq!(read_packet(&mut alex_client), packets::play::transfer("example.com", 25566));
+        assert!(!steve.lock().unwrap().is_transferring());
+        assert_eq!(read_packet(&mut steve_client), chat);
+    }
+
+    #[test]
+    fn speed_walk_sets_the_movement_speed_attribute() {
+        let server = Server::new(ServerConfig::default());
+        let (_player, mut client) = join(&server, "Steve", 1);
+        assert_eq!(dispatch(&server, &CommandSender::Console, "/speed walk 0.2 Steve").unwrap(), "Set the walk speed of Steve to 0.2");
+        server.flush_players();
+
+        let packet = read_packet(&mut client);
+        let mut reader = PacketReader::new(&packet);
+        assert_eq!(reader.read_ivar().unwrap(), Packet::UpdateAttributes.id());
+        assert_eq!(reader.read_ivar().unwrap(), 1);
+        assert_eq!(reader.read_ivar().unwrap(), 1);
+        assert_eq!(reader.read_ivar().unwrap(), Attribute::MovementSpeed as i32);
+        assert_eq!(reader.read_f64().unwrap(), 0.2);
+        assert_eq!(reader.read_ivar().unwrap(), 0);
+    }
+
+    #[test]
+    fn speed_is_clamped() {
+        let server = Server::new(ServerConfig::default());
+        let (player, _client) = join(&server, "Steve", 1);
+        assert_eq!(dispatch(&server, &CommandSender::Console, "/speed fly 5 Steve").unwrap(), "Set the fly speed of Steve to 1");
+        assert_eq!(dispatch(&server, &CommandSender::Console, "/speed walk -1 Steve").unwrap(), "Set the walk speed of Steve to 0");
+        assert_eq!(player.lock().unwrap().walk_speed(), 0.0);
+        assert!(dispatch(&server, &CommandSender::Console, "/speed run 0.2 Steve").is_err());
+        assert!(dispatch(&server, &CommandSender::Console, "/speed walk NaN Steve").is_err());
+    }
+
+    #[test]
+    fn only_operators_can_stop_the_server() {
+        let server = Server::new(ServerConfig::default());
+        let (steve, mut steve_client) = join(&server, "Steve", 1);
+        steve.lock().unwrap().set_permission_level(3);
+        assert!(dispatch(&server, &CommandSender::Player(1), "/stop").is_err());
+        assert!(!server.is_stopping());
+
+        steve.lock().unwrap().set_permission_level(4);
+        assert_eq!(dispatch(&server, &CommandSender::Player(1), "/stop").unwrap(), "Stopping the server");
+        assert!(server.is_stopping());
+        let reason = TextComponent::translate("multiplayer.disconnect.server_shutdown", Vec::new());
+        assert_eq!(read_packet(&mut steve_client), packets::play::disconnect(&reason));
+    }
+
+    #[test]
+    fn tp_can_face_a_position_or_a_player() {
+        let server = Server::new(ServerConfig::default());
+        let (steve, mut steve_client) = join(&server, "Steve", 1);
+        let (alex, _alex_client) = join(&server, "Alex", 2);
+        alex.lock().unwrap().set_position(5.0, 64.0, 5.0);
+        dispatch(&server, &CommandSender::Console, "/tp steve 0 64 0 facing ~10 ~ ~").unwrap();
+        dispatch(&server, &CommandSender::Console, "/tp steve 0 64 0 facing entity alex").unwrap();
+        dispatch(&server, &CommandSender::Console, "/tp steve 0 64 0 facing entity alex feet").unwrap();
+        assert!(dispatch(&server, &CommandSender::Console, "/tp steve 0 64 0 facing entity alex knees").is_err());
+        assert!(dispatch(&server, &CommandSender::Console, "/tp steve 0 64 0 facing entity").is_err());
+        assert!(dispatch(&server, &CommandSender::Console, "/tp steve 0 64 0 facing entity nobody").is_err());
+        server.flush_players();
+
+        read_packet(&mut steve_client);
+        assert_eq!(read_packet(&mut steve_client), packets::play::look_at(Anchor::Eyes, 10.0, 64.0, 0.0, None));
+        read_packet(&mut steve_client);
+        assert_eq!(read_packet(&mut steve_client), packets::play::look_at(Anchor::Eyes, 5.0, 64.0, 5.0, Some((2, Anchor::Eyes))));
+        read_packet(&mut steve_client);
+        assert_eq!(read_packet(&mut steve_client), packets::play::look_at(Anchor::Eyes, 5.0, 64.0, 5.0, Some((2, Anchor::Feet))));
+        assert_eq!(steve.lock().unwrap().position(), (0.0, 64.0, 0.0));
+    }
+
+    #[test]
+    fn resource_packs_can_be_added_listed_and_removed() {
+        let server = Server::new(ServerConfig::default());
+        let (steve, _steve_client) = join(&server, "Steve", 1);
+        let one = resource_pack::url_uuid("https://example.com/one.zip");
+        dispatch(&server, &CommandSender::Console, "/resourcepack add steve https://example.com/one.zip forced").unwrap();
+        dispatch(&server, &CommandSender::Console, "/resourcepack add steve https://example.com/two.zip 2fd4e1c67a2d28fced849ee1bb76e7391b93eb12").unwrap();
+        assert!(steve.lock().unwrap().resource_packs().get(one).unwrap().forced);
+        assert!(dispatch(&server, &CommandSender::Console, "/resourcepack add steve https://example.com/three.zip nothex").is_err());
+
+        let feedback = dispatch(&server, &CommandSender::Console, &format!("/resourcepack remove steve {one:032x}")).unwrap();
+        assert_eq!(feedback, format!("Removed {one:032x} from Steve"));
+        assert!(dispatch(&server, &CommandSender::Console, &format!("/resourcepack remove steve {one:032x}")).is_err());
+        let two = resource_pack::url_uuid("https://example.com/two.zip");
+        let feedback = dispatch(&server, &CommandSender::Console, "/resourcepack list steve").unwrap();
+        assert_eq!(feedback, format!("Steve has {two:032x} https://example.com/two.zip"));
+
+        dispatch(&server, &CommandSender::Console, "/resourcepack clear steve").unwrap();
+        assert_eq!(dispatch(&server, &CommandSender::Console, "/resourcepack list steve").unwrap(), "Steve has no resource packs");
+    }
+
+    #[test]
+    fn setblock_sends_a_block_update() {
+        let server = Server::new(ServerConfig::default());
+        let (steve, mut steve_client) = join(&server, "Steve", 1);
+        steve.lock().unwrap().update_chunks().unwrap();
+        let feedback = dispatch(&server, &CommandSender::Console, "/setblock 0 64 0 minecraft:stone").unwrap();
+        assert_eq!(feedback, "Changed the block at 0, 64, 0");
+        steve.lock().unwrap().flush().unwrap();
+        let update = packets::play::block_update(Position::new(0, 64, 0), blocks::state_id("stone").unwrap());
+        while read_packet(&mut steve_client) != update {}
+        assert_eq!(server.blocks().get(Position::new(0, 64, 0)), blocks::state_id("stone").unwrap());
+        assert_eq!(dispatch(&server, &CommandSender::Console, "/setblock 0 64 0 stone"), Err("Could not set the block".into()));
+    }
+
+    #[test]
+    fn block_commands_check_their_arguments() {
+        let server = Server::new(ServerConfig::default());
+        let (steve, _steve_client) = join(&server, "Steve", 1);
+        steve.lock().unwrap().set_position(10.5, 70.0, -3.5);
+        assert_eq!(dispatch(&server, &CommandSender::Console, "/setblock 0 64 0 nothing"), Err("Unknown block: nothing".into()));
+        assert!(dispatch(&server, &CommandSender::Console, "/setblock ~ 64 0 stone").is_err());
+        assert!(dispatch(&server, &CommandSender::Console, "/setblock 0 10000 0 stone").is_err());
+        assert!(dispatch(&server, &CommandSender::Console, "/setblock 0 x 0 stone").is_err());
+
+        steve.lock().unwrap().set_permission_level(2);
+        dispatch(&server, &CommandSender::Player(1), "/setblock ~ ~-1 ~1 dirt").unwrap();
+        assert_eq!(server.blocks().get(Position::new(10, 69, -3)), blocks::state_id("dirt").unwrap());
+    }
+
+    #[test]
+    fn fill_reports_how_many_blocks_changed() {
+        let server = Server::new(ServerConfig::default());
+        assert_eq!(dispatch(&server, &CommandSender::Console, "/fill 0 64 0 2 66 2 stone").unwrap(), "Successfully filled 27 block(s)");
+        assert_eq!(dispatch(&server, &CommandSender::Console, "/fill 0 64 0 2 66 2 stone"), Err("No blocks were filled".into()));
+        assert!(dispatch(&server, &CommandSender::Console, "/fill 0 0 0 100 100 100 stone").is_err());
+    }
+
+    #[test]
+    fn stopsound_sends_stop_sound() {
+        let server = Server::new(ServerConfig::default());
+        let (steve, mut steve_client) = join(&server, "Steve", 1);
+        assert_eq!(dispatch(&server, &CommandSender::Console, "/stopsound steve").unwrap(), "Stopped sounds for Steve");
+        dispatch(&server, &CommandSender::Console, "/stopsound steve * minecraft:music.game").unwrap();
+        dispatch(&server, &CommandSender::Console, "/stopsound steve record").unwrap();
+        assert_eq!(dispatch(&server, &CommandSender::Console, "/stopsound steve loud"), Err("Unknown sound source: loud".into()));
+        assert!(dispatch(&server, &CommandSender::Console, "/stopsound").is_err());
+        steve.lock().unwrap().flush().unwrap();
+        assert_eq!(read_packet(&mut steve_client), packets::play::stop_sound(None, None));
+        assert_eq!(read_packet(&mut steve_client), packets::play::stop_sound(None, Some("minecraft:music.game")));
+        assert_eq!(read_packet(&mut steve_client), packets::play::stop_sound(Some(SoundCategory::Records), None));
+    }
+
+    #[test]
+    fn playsound_follows_the_player() {
+        let server = Server::new(ServerConfig::default());
+        let (steve, mut steve_client) = join(&server, "Steve", 1);
+        assert_eq!(dispatch(&server, &CommandSender::Console, "/playsound minecraft:block.note_block.harp block steve 2 5").unwrap(), "Played sound minecraft:block.note_block.harp to Steve");
+        assert!(dispatch(&server, &CommandSender::Console, "/playsound minecraft:block.note_block.harp loud steve").is_err());
+        assert!(dispatch(&server, &CommandSender::Console, "/playsound minecraft:block.note_block.harp block").is_err());
+        steve.lock().unwrap().flush().unwrap();
+        let expected = packets::play::entity_sound_effect("minecraft:block.note_block.harp", SoundCategory::Blocks, 1, 2.0, 2.0, 0);
+        assert_eq!(read_packet(&mut steve_client), expected);
+    }
+
+    #[test]
+    fn only_the_console_can_change_operators() {
+        let server = Server::new(ServerConfig::default());
+        let (steve, _steve_client) = join(&server, "Steve", 1);
+        let (alex, _alex_client) = join(&server, "Alex", 2);
+        assert!(dispatch(&server, &CommandSender::Player(2), "/op Alex").is_err());
+        assert_eq!(alex.lock().unwrap().permission_level(), 0);
+
+        assert_eq!(dispatch(&server, &CommandSender::Console, "/op Steve").unwrap(), "Made Steve a server operator");
+        assert_eq!(steve.lock().unwrap().permission_level(), 4);
+        // Even an operator can't hand it on
+        assert!(dispatch(&server, &CommandSender::Player(1), "/op Alex").is_err());
+        assert_eq!(alex.lock().unwrap().permission_level(), 0);
+
+        assert_eq!(dispatch(&server, &CommandSender::Console, "/deop Steve").unwrap(), "Made Steve no longer a server operator");
+        assert_eq!(steve.lock().unwrap().permission_level(), 0);
+        assert!(dispatch(&server, &CommandSender::Console, "/op Nobody").is_err());
+    }
+
+    #[test]
+    fn worldborder_moves_and_resizes_the_border() {
+        let server = Server::new(ServerConfig::default());
+        let (_steve, mut client) = join(&server, "Steve", 1);
+        assert_eq!(dispatch(&server, &CommandSender::Console, "/worldborder center 10 -20").unwrap(), "Set the center of the world border to 10.00, -20.00");
+        assert_eq!(dispatch(&server, &CommandSender::Console, "/worldborder set 100").unwrap(), "Set the world border to 100.0 blocks wide");
+        assert_eq!(dispatch(&server, &CommandSender::Console, "/worldborder set 50 10").unwrap(), "Growing the world border to 50.0 blocks wide over 10 seconds");
+        server.flush_players();
+        assert_eq!(read_packet(&mut client), packets::play::set_border_center(10.0, -20.0));
+        assert_eq!(read_packet(&mut client), packets::play::set_border_size(100.0));
+        assert_eq!(read_packet(&mut client), packets::play::set_border_lerp_size(100.0, 50.0, 10000));
+
+        let border = server.border();
+        assert_eq!((border.center_x, border.center_z, border.old_diameter, border.new_diameter), (10.0, -20.0, 100.0, 50.0));
+    }
+
+    #[test]
+    fn worldborder_rejects_bad_sizes() {
+        let server = Server::new(ServerConfig::default());
+        assert!(dispatch(&server, &CommandSender::Console, "/worldborder set 0").is_err());
+        assert!(dispatch(&server, &CommandSender::Console, "/worldborder set 1e9").is_err());
+        assert!(dispatch(&server, &CommandSender::Console, "/worldborder set 100 -5").is_err());
+        assert!(dispatch(&server, &CommandSender::Console, &format!("/worldborder set 100 {}", i64::MAX)).is_err());
+        assert_eq!(server.border().new_diameter, border::MAX_DIAMETER);
+    }
+
+    #[test]
+    fn worldborder_warning_keeps_the_other_setting() {
+        let server = Server::new(ServerConfig::default());
+        assert_eq!(dispatch(&server, &CommandSender::Console, "/worldborder warning distance 8").unwrap(), "Set the world border warning distance to 8");
+        assert_eq!(dispatch(&server, &CommandSender::Console, "/worldborder warning time 30").unwrap(), "Set the world border warning time to 30");
+        let border = server.border();
+        assert_eq!((border.warning_time, border.warning_blocks), (30, 8));
+        drop(border);
+        assert!(dispatch(&server, &CommandSender::Console, "/worldborder warning time -1").is_err());
+        assert!(dispatch(&server, &CommandSender::Console, "/worldborder warning size 3").is_err());
+    }
+
+    #[test]
+    fn immediate_respawn_hides_the_respawn_screen() {
+        let server = Server::new(ServerConfig::default());
+        let (_steve, mut client) = join(&server, "Steve", 1);
+        assert_eq!(dispatch(&server, &CommandSender::Console, "/gamerule doImmediateRespawn true").unwrap(), "Gamerule doImmediateRespawn is now set to: true");
+        assert!(!server.respawn_screen_enabled());
+        assert_eq!(dispatch(&server, &CommandSender::Console, "/gamerule doImmediateRespawn").unwrap(), "Gamerule doImmediateRespawn is currently set to: true");
+        assert!(dispatch(&server, &CommandSender::Console, "/gamerule doImmediateRespawn yes").is_err());
+        assert!(dispatch(&server, &CommandSender::Console, "/gamerule keepInventory true").is_err());
+        server.flush_players();
+        assert_eq!(read_packet(&mut client), packets::play::game_event(packets::play::GameEvent::EnableRespawnScreen, 1.0));
+    }
+
+    #[test]
+    fn distance_changes_the_view_and_simulation_distance() {
+        let server = Server::new(ServerConfig::default());
+        let (player, mut client) = join(&server, "Steve", 1);
+        assert_eq!(dispatch(&server, &CommandSender::Console, "/distance simulation 40 Steve").unwrap(), "Set the simulation distance of Steve to 32 chunks");
+        assert_eq!(dispatch(&server, &CommandSender::Console, "/distance view 4 Steve").unwrap(), "Set the view distance of Steve to 4 chunks");
+        assert_eq!(player.lock().unwrap().view_distance(), 4);
+        assert!(dispatch(&server, &CommandSender::Console, "/distance render 4 Steve").is_err());
+        assert!(dispatch(&server, &CommandSender::Console, "/distance view far Steve").is_err());
+        server.flush_players();
+        assert_eq!(read_packet(&mut client), packets::play::set_simulation_distance(32));
+        assert_eq!(read_packet(&mut client), packets::play::set_render_distance(4));
+    }
+
+    #[test]
+    fn xp_add_gives_experience_points() {
+        let server = Server::new(ServerConfig::default());
+        let (_player, mut client) = join(&server, "Steve", 1);
+        assert_eq!(dispatch(&server, &CommandSender::Console, "/xp add Steve 5").unwrap(), "Gave 5 experience points to Steve");
+        assert!(dispatch(&server, &CommandSender::Console, "/xp add Steve -5").is_err());
+        assert!(dispatch(&server, &CommandSender::Console, "/xp set Steve 5").is_err());
+        server.flush_players();
+        assert_eq!(read_packet(&mut client), packets::play::set_experience(5.0 / 7.0, 0, 5));
+    }
+}
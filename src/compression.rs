@@ -0,0 +1,163 @@
+// Packet framing once Set Compression has been sent, everything before it (handshake, status and
+// the start of login) keeps the plain length + packet framing from `utils::write_packet`.
+// https://wiki.vg/Protocol#With_compression
+
+use std::io::{Read, Write};
+
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+
+use crate::{types::varint::ivar, utils::write_ivar};
+
+/// Same as vanilla's network-compression-threshold
+pub const DEFAULT_COMPRESSION_THRESHOLD: i32 = 256;
+
+#[derive(Debug)]
+pub enum CompressionError {
+    Io(std::io::Error),
+    BadDataLength,
+    /// Uncompressed packets must be smaller than the threshold
+    UncompressedAboveThreshold(usize),
+    /// Compressed packets must be at least the threshold
+    CompressedBelowThreshold(usize),
+    /// Claimed size of the decompressed packet, over the limit for the connection's state
+    TooLarge(usize),
+    LengthMismatch,
+}
+
+impl std::fmt::Display for CompressionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "Could not decompress the packet: {e}"),
+            Self::BadDataLength => write!(f, "Malformed data length"),
+            Self::UncompressedAboveThreshold(size) => write!(f, "Uncompressed packet of {size} bytes is above the threshold"),
+            Self::CompressedBelowThreshold(size) => write!(f, "Compressed packet of {size} bytes is below the threshold"),
+            Self::TooLarge(size) => write!(f, "Decompressed packet of {size} bytes is too large"),
+            Self::LengthMismatch => write!(f, "Decompressed packet doesn't match its data length"),
+        }
+    }
+}
+
+impl From<std::io::Error> for CompressionError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PacketCompression {
+    /// Packets of at least this many bytes are compressed
+    pub threshold: usize,
+    /// zlib level from 0 (stored) to 9 (smallest)
+    pub level: u32,
+}
+
+impl PacketCompression {
+    /// None for a negative threshold, which leaves compression off
+    pub fn new(threshold: i32, level: u32) -> Option<Self> {
+        (threshold >= 0).then_some(Self { threshold: threshold as usize, level })
+    }
+
+    /// Frames an unframed packet as packet length, data length and the packet,
+    /// which is compressed if it reaches the threshold
+    pub fn frame(&self, packet: &[u8]) -> Result<Vec<u8>, std::io::Error> {
+        let mut body: Vec<u8> = Vec::new();
+        if packet.len() < self.threshold {
+            // A data length of 0 marks the packet as uncompressed
+            write_ivar(&mut body, 0);
+            body.extend_from_slice(packet);
+        } else {
+            write_ivar(&mut body, packet.len() as i32);
+            let mut encoder = ZlibEncoder::new(body, Compression::new(self.level));
+            encoder.write_all(packet)?;
+            body = encoder.finish()?;
+        }
+        let mut frame = ivar::new(body.len() as i32).as_bytes();
+        frame.append(&mut body);
+        Ok(frame)
+    }
+
+    /// Packet id and data from the body of a frame, everything after the packet length
+    pub fn unframe(&self, body: &[u8], max_packet_size: usize) -> Result<Vec<u8>, CompressionError> {
+        let data_length = ivar::read(body).map_err(|_| CompressionError::BadDataLength)?;
+        let data = &body[data_length.length()..];
+        if data_length.value == 0 {
+            if data.len() >= self.threshold {
+                return Err(CompressionError::UncompressedAboveThreshold(data.len()));
+            }
+            return Ok(data.to_vec());
+        }
+        if data_length.value < 0 {
+            return Err(CompressionError::BadDataLength);
+        }
+        let length = data_length.value as usize;
+        if length < self.threshold {
+            return Err(CompressionError::CompressedBelowThreshold(length));
+        }
+        // Checked before decompressing so a small packet can't inflate into a huge one
+        if length > max_packet_size {
+            return Err(CompressionError::TooLarge(length));
+        }
+        let mut packet: Vec<u8> = Vec::with_capacity(length);
+        ZlibDecoder::new(data).take(length as u64 + 1).read_to_end(&mut packet)?;
+        if packet.len() != length {
+            return Err(CompressionError::LengthMismatch);
+        }
+        Ok(packet)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inflate(compressed: &[u8]) -> Vec<u8> {
+        let mut data = Vec::new();
+        ZlibDecoder::new(compressed).read_to_end(&mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn packets_below_the_threshold_are_left_uncompressed() {
+        let compression = PacketCompression::new(256, 6).unwrap();
+        assert_eq!(compression.frame(&[0x26, 1, 2]).unwrap(), vec![4, 0, 0x26, 1, 2]);
+        assert_eq!(compression.unframe(&[0, 0x26, 1, 2], 1024).unwrap(), vec![0x26, 1, 2]);
+    }
+
+    #[test]
+    fn every_level_is_decompressible() {
+        let packet: Vec<u8> = (0..2000).map(|i| (i % 7) as u8).collect();
+        for level in 0..=9 {
+            let compression = PacketCompression::new(256, level).unwrap();
+            let frame = compression.frame(&packet).unwrap();
+            let length = ivar::read(&frame).unwrap();
+            let body = &frame[length.length()..];
+            assert_eq!(body.len(), length.value as usize);
+            let data_length = ivar::read(body).unwrap();
+            assert_eq!(data_length.value, 2000);
+            assert_eq!(inflate(&body[data_length.length()..]), packet);
+            assert_eq!(compression.unframe(body, 4096).unwrap(), packet);
+        }
+    }
+
+    #[test]
+    fn level_zero_is_stored() {
+        let packet: Vec<u8> = vec![0xAB; 1000];
+        let frame = PacketCompression::new(256, 0).unwrap().frame(&packet).unwrap();
+        // zlib header, stored block headers and checksum on top of the packet itself
+        assert!(frame.len() > packet.len());
+        assert!(frame.windows(packet.len()).any(|window| window == packet.as_slice()));
+    }
+
+    #[test]
+    fn rejects_packets_on_the_wrong_side_of_the_threshold() {
+        let compression = PacketCompression::new(4, 6).unwrap();
+        assert!(matches!(compression.unframe(&[0, 1, 2, 3, 4], 1024), Err(CompressionError::UncompressedAboveThreshold(4))));
+        assert!(matches!(compression.unframe(&[2, 0x78, 0x01], 1024), Err(CompressionError::CompressedBelowThreshold(2))));
+        assert!(matches!(compression.unframe(&[0x80, 0x10, 0x78], 1024), Err(CompressionError::TooLarge(2048))));
+    }
+
+    #[test]
+    fn negative_threshold_disables_compression() {
+        assert_eq!(PacketCompression::new(-1, 6), None);
+    }
+}
@@ -0,0 +1,361 @@
+use std::{fs, net::IpAddr};
+
+use log::{error, info};
+use serde::Deserialize;
+
+use crate::{chat::DEFAULT_CHAT_MESSAGES_PER_SECOND, compression::DEFAULT_COMPRESSION_THRESHOLD, connection::ConnectionState, handshake::ModLoaderAction, keep_alive::{DEFAULT_KEEP_ALIVE_INTERVAL, DEFAULT_MAX_MISSED_KEEP_ALIVES}, player::Gamemode, registry, resource_pack::{self, ResourcePack}, status_response::{DEFAULT_MOTD, DEFAULT_SERVER_ICON}, tick::{DEFAULT_TICK_RATE, TICK_RATE_RANGE}, world::{batch::DEFAULT_MAX_BATCHES, entity_tracker::DEFAULT_TRACKING_RANGE, settings::WorldSettings, tracker::{DEFAULT_VIEW_DISTANCE, VIEW_DISTANCE_RANGE}}, types::text_component::TextComponent};
+
+/// Same default as zlib
+pub const DEFAULT_COMPRESSION_LEVEL: u32 = 6;
+pub const MAX_COMPRESSION_LEVEL: u32 = 9;
+
+pub const DEFAULT_READ_BUFFER_SIZE: usize = 4096;
+pub const DEFAULT_WRITE_BUFFER_SIZE: usize = 8192;
+/// Smallest read or write buffer, anything less would take several reads for most packets
+pub const MIN_BUFFER_SIZE: usize = 256;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    pub address: String,
+    /// Connections the OS queues while waiting to be accepted
+    pub connection_backlog: i32,
+    pub max_players: usize,
+    /// Answer status and legacy pings, can be disabled when a proxy handles them instead
+    pub enable_status_ping: bool,
+    /// Shown in the server list, a json text component or legacy § formatted text.
+    /// %online%, %max% and %version% are replaced with the current values
+    pub motd: String,
+    /// 64x64 PNG shown in the server list, loaded at startup. The server has no icon if it is missing or invalid
+    pub server_icon: String,
+    /// How much of the player list the status response reveals
+    pub status_privacy: StatusPrivacy,
+    /// Only accept connections from `allowed_proxies`, for servers that should only be reached through a proxy
+    pub proxy_only: bool,
+    pub allowed_proxies: Vec<IpAddr>,
+    /// Let in clients sent here by another server's Transfer packet
+    pub accept_transfers: bool,
+    /// What to do with clients whose handshake carries a Forge style modloader marker
+    pub mod_loader_action: ModLoaderAction,
+    /// Reason shown to modded clients when `mod_loader_action` is Reject
+    pub mod_loader_message: String,
+    /// Reason shown to clients disconnected because of a malformed packet
+    pub protocol_error_message: String,
+    /// Chat messages a player can send per second before `chat_spam_action` is taken
+    pub chat_messages_per_second: f32,
+    pub chat_spam_action: SpamAction,
+    /// Packets of at least this many bytes are compressed once logging in, negative turns compression off
+    pub network_compression_threshold: i32,
+    /// zlib level from 0 (stored) to 9 (smallest) used for compressed packets
+    pub compression_level: u32,
+    /// Bytes read from a connection at a time, larger packets are read in more than one go
+    pub read_buffer_size: usize,
+    /// Bytes of outgoing packets buffered before they are written to the socket
+    pub write_buffer_size: usize,
+    /// Largest inbound packet body accepted in each state
+    pub max_packet_size: PacketSizeLimits,
+    /// Gamemode new players join with
+    pub default_gamemode: Gamemode,
+    pub world: WorldSettings,
+    /// Require signed chat, advertised to clients in the status response and on join.
+    /// Messages are checked against the key each player sends, but that key's signature from Mojang isn't,
+    /// so this stops tampering between client and server without proving who the player is
+    pub enforce_secure_chat: bool,
+    /// Directory whose region files changed chunks are saved to and loaded from, nothing is saved when unset
+    pub world_directory: Option<String>,
+    /// Seconds between saves of the world, 0 to only save on /save-all and when stopping
+    pub auto_save_interval: u64,
+    /// File every packet is recorded to for debugging, read back with `--dump-trace <file>`
+    pub trace_file: Option<String>,
+    /// Ticks per second, between 1 and 10000
+    pub tick_rate: f32,
+    /// Hide coordinates and other details from the F3 screen
+    pub reduced_debug_info: bool,
+    /// Show the death screen, players respawn straight away when disabled
+    pub enable_respawn_screen: bool,
+    /// Milliseconds between latency updates sent to everyone's tab list
+    pub tab_refresh_interval: u64,
+    /// Latency in milliseconds above which a player is logged as lagging, 0 to disable
+    pub latency_warning: i32,
+    /// Seconds a looked up game profile is trusted before it is looked up again
+    pub profile_cache_ttl: u64,
+    /// Look up each player's skin from Mojang's session server when they join, for offline servers
+    pub fetch_skins: bool,
+    /// Chunks sent around each player, clients that ask for less get less. Between 2 and 32
+    pub view_distance: i32,
+    /// Blocks away along x or z at which entities are spawned for a player, and removed past it
+    pub entity_tracking_range: f64,
+    /// Chunk batches a client can have unacknowledged before sending pauses, at least 1
+    pub max_chunk_batches: u32,
+    /// Seconds between keep alives sent to each player
+    pub keep_alive_interval: u64,
+    /// Keep alives in a row a player can leave unanswered before they are timed out, at least 1
+    pub max_missed_keep_alives: u32,
+    /// Seconds a client has to reach Play after connecting before it is disconnected, 0 to disable
+    pub login_timeout: u64,
+    /// What happens when a player logs in while already online
+    pub duplicate_login: DuplicateLoginAction,
+    /// Registry ids sent during configuration, in order
+    pub registries: Vec<String>,
+    /// Links shown in the pause menu as (label, url). Labels such as bug_report or website are
+    /// translated by the client, any other label is shown as written
+    pub server_links: Vec<(String, String)>,
+    /// Url of a pack every player is sent while configuring
+    pub resource_pack: Option<String>,
+    /// Hex encoded SHA-1 of `resource_pack`, the client doesn't check the download when empty
+    pub resource_pack_sha1: String,
+    /// Disconnect players who decline the pack
+    pub require_resource_pack: bool,
+    /// Shown on the client's prompt in place of the default message
+    pub resource_pack_prompt: Option<String>,
+    /// off, error, warn, info, debug or trace, overridden by `--log-level` and BLAHAJ_LOG_LEVEL
+    pub log_level: Option<String>,
+    /// File every log line is also appended to
+    pub log_file: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct PacketSizeLimits {
+    pub handshake: usize,
+    pub status: usize,
+    pub login: usize,
+    pub play: usize,
+}
+
+impl Default for PacketSizeLimits {
+    fn default() -> Self {
+        Self {
+            handshake: 2048,
+            status: 256,
+            login: 32768,
+            play: 2097151, // Largest length a 3 byte VarInt can hold, same as vanilla
+        }
+    }
+}
+
+impl PacketSizeLimits {
+    pub fn for_state(&self, state: &ConnectionState) -> usize {
+        match state {
+            ConnectionState::Handshake | ConnectionState::Unknown => self.handshake,
+            ConnectionState::Status => self.status,
+            ConnectionState::Login | ConnectionState::Transfer => self.login,
+            ConnectionState::Configuration | ConnectionState::Play => self.play,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum StatusPrivacy {
+    /// Online count and a sample of names
+    Show,
+    /// Online count without names
+    HideSample,
+    /// Only the max players
+    HideCount,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum SpamAction {
+    Ignore,
+    Kick,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum DuplicateLoginAction {
+    /// Keep the player that is already online
+    RejectNew,
+    /// Disconnect the player that is already online, same as vanilla
+    KickExisting,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            address: "127.0.0.1:25565".into(),
+            connection_backlog: 128,
+            max_players: 100,
+            enable_status_ping: true,
+            motd: DEFAULT_MOTD.into(),
+            server_icon: DEFAULT_SERVER_ICON.into(),
+            status_privacy: StatusPrivacy::Show,
+            proxy_only: false,
+            allowed_proxies: Vec::new(),
+            accept_transfers: true,
+            mod_loader_action: ModLoaderAction::Strip,
+            mod_loader_message: "This server doesn't support modded clients".into(),
+            protocol_error_message: "Protocol error".into(),
+            chat_messages_per_second: DEFAULT_CHAT_MESSAGES_PER_SECOND,
+            chat_spam_action: SpamAction::Kick,
+            network_compression_threshold: DEFAULT_COMPRESSION_THRESHOLD,
+            compression_level: DEFAULT_COMPRESSION_LEVEL,
+            read_buffer_size: DEFAULT_READ_BUFFER_SIZE,
+            write_buffer_size: DEFAULT_WRITE_BUFFER_SIZE,
+            max_packet_size: PacketSizeLimits::default(),
+            default_gamemode: Gamemode::Survival,
+            world: WorldSettings::default(),
+            enforce_secure_chat: false,
+            world_directory: None,
+            auto_save_interval: 300,
+            trace_file: None,
+            tick_rate: DEFAULT_TICK_RATE,
+            reduced_debug_info: false,
+            enable_respawn_screen: true,
+            tab_refresh_interval: 1000,
+            latency_warning: 1000,
+            profile_cache_ttl: 86400,
+            fetch_skins: false,
+            view_distance: DEFAULT_VIEW_DISTANCE,
+            entity_tracking_range: DEFAULT_TRACKING_RANGE,
+            max_chunk_batches: DEFAULT_MAX_BATCHES,
+            keep_alive_interval: DEFAULT_KEEP_ALIVE_INTERVAL,
+            max_missed_keep_alives: DEFAULT_MAX_MISSED_KEEP_ALIVES,
+            login_timeout: 30,
+            duplicate_login: DuplicateLoginAction::KickExisting,
+            registries: registry::REGISTRIES.iter().map(|r| r.to_string()).collect(),
+            server_links: Vec::new(),
+            resource_pack: None,
+            resource_pack_sha1: String::new(),
+            require_resource_pack: false,
+            resource_pack_prompt: None,
+            log_level: None,
+            log_file: None,
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Whether a connection from the address may join, always true unless `proxy_only` is set
+    pub fn is_allowed_address(&self, ip: IpAddr) -> bool {
+        !self.proxy_only || self.allowed_proxies.contains(&ip)
+    }
+
+    /// Loads the config from a json file, falling back to the defaults if it is missing or invalid
+    pub fn load(path: &str) -> Self {
+        let contents = match fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(_) => {
+                info!("No config found at {path}, using defaults");
+                return Self::default();
+            }
+        };
+        match serde_json::from_str::<Self>(&contents) {
+            Ok(config) => config.validated(),
+            Err(e) => {
+                error!("Could not parse config {path}, using defaults!\n{e:?}");
+                Self::default()
+            }
+        }
+    }
+
+    /// The pack from `resource_pack`, sent to everyone with the same uuid
+    pub fn server_resource_pack(&self) -> Option<Result<ResourcePack, &'static str>> {
+        let url = self.resource_pack.as_ref()?;
+        let pack = ResourcePack::new(resource_pack::url_uuid(url), url, &self.resource_pack_sha1).map(|p| p.forced(self.require_resource_pack));
+        Some(match &self.resource_pack_prompt {
+            Some(prompt) => pack.map(|p| p.with_prompt(TextComponent::new(prompt.clone()))),
+            None => pack,
+        })
+    }
+
+    /// Replaces out of range values with their defaults, logging each one
+    fn validated(mut self) -> Self {
+        self.world = self.world.validated();
+        self.registries.retain(|r| {
+            let implemented = registry::is_implemented(r);
+            if !implemented {
+                error!("Registry {r} isn't implemented, it won't be sent");
+            }
+            implemented
+        });
+        if self.chat_messages_per_second.is_nan() || self.chat_messages_per_second <= 0.0 {
+            error!("Chat messages per second {} must be positive, using {DEFAULT_CHAT_MESSAGES_PER_SECOND}", self.chat_messages_per_second);
+            self.chat_messages_per_second = DEFAULT_CHAT_MESSAGES_PER_SECOND;
+        }
+        if self.compression_level > MAX_COMPRESSION_LEVEL {
+            error!("Compression level {} is above {MAX_COMPRESSION_LEVEL}, using {DEFAULT_COMPRESSION_LEVEL}", self.compression_level);
+            self.compression_level = DEFAULT_COMPRESSION_LEVEL;
+        }
+        if self.read_buffer_size < MIN_BUFFER_SIZE || self.write_buffer_size < MIN_BUFFER_SIZE {
+            error!("Read and write buffers must be at least {MIN_BUFFER_SIZE} bytes, using the defaults");
+            self.read_buffer_size = DEFAULT_READ_BUFFER_SIZE;
+            self.write_buffer_size = DEFAULT_WRITE_BUFFER_SIZE;
+        }
+        if !VIEW_DISTANCE_RANGE.contains(&self.view_distance) {
+            error!("View distance {} is outside of {VIEW_DISTANCE_RANGE:?}, using {DEFAULT_VIEW_DISTANCE}", self.view_distance);
+            self.view_distance = DEFAULT_VIEW_DISTANCE;
+        }
+        if self.entity_tracking_range.is_nan() || self.entity_tracking_range <= 0.0 {
+            error!("Entity tracking range {} must be positive, using {DEFAULT_TRACKING_RANGE}", self.entity_tracking_range);
+            self.entity_tracking_range = DEFAULT_TRACKING_RANGE;
+        }
+        if let Some(Err(e)) = self.server_resource_pack() {
+            error!("{e}, no resource pack will be sent");
+            self.resource_pack = None;
+        }
+        if !TICK_RATE_RANGE.contains(&self.tick_rate) {
+            error!("Tick rate {} is outside of {TICK_RATE_RANGE:?}, using {DEFAULT_TICK_RATE}", self.tick_rate);
+            self.tick_rate = DEFAULT_TICK_RATE;
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compression_levels_above_nine_use_the_default() {
+        for (level, expected) in [(0, 0), (9, 9), (10, DEFAULT_COMPRESSION_LEVEL)] {
+            let config = ServerConfig { compression_level: level, ..ServerConfig::default() }.validated();
+            assert_eq!(config.compression_level, expected);
+        }
+    }
+
+    #[test]
+    fn tiny_buffers_use_the_defaults() {
+        let config = ServerConfig { read_buffer_size: 65536, write_buffer_size: 32768, ..Default::default() }.validated();
+        assert_eq!((config.read_buffer_size, config.write_buffer_size), (65536, 32768));
+        let config = ServerConfig { read_buffer_size: 65536, write_buffer_size: MIN_BUFFER_SIZE - 1, ..Default::default() }.validated();
+        assert_eq!((config.read_buffer_size, config.write_buffer_size), (DEFAULT_READ_BUFFER_SIZE, DEFAULT_WRITE_BUFFER_SIZE));
+    }
+
+    #[test]
+    fn invalid_resource_packs_are_not_sent() {
+        let config = ServerConfig {
+            resource_pack: Some("https://example.com/pack.zip".to_string()),
+            resource_pack_sha1: "not a hash".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(config.validated().resource_pack, None);
+
+        let config = ServerConfig {
+            resource_pack: Some("https://example.com/pack.zip".to_string()),
+            require_resource_pack: true,
+            ..Default::default()
+        };
+        assert!(config.validated().server_resource_pack().unwrap().unwrap().forced);
+    }
+
+    #[test]
+    fn tracking_ranges_must_be_positive() {
+        for range in [0.0, -5.0, f64::NAN] {
+            let config = ServerConfig { entity_tracking_range: range, ..Default::default() };
+            assert_eq!(config.validated().entity_tracking_range, DEFAULT_TRACKING_RANGE);
+        }
+        let config = ServerConfig { entity_tracking_range: 8.0, ..Default::default() };
+        assert_eq!(config.validated().entity_tracking_range, 8.0);
+    }
+
+    #[test]
+    fn only_listed_proxies_are_allowed_when_proxy_only() {
+        let proxy: IpAddr = "10.0.0.2".parse().unwrap();
+        let other: IpAddr = "10.0.0.3".parse().unwrap();
+        let config = ServerConfig { allowed_proxies: vec![proxy], ..Default::default() };
+        assert!(config.is_allowed_address(other));
+        let config = ServerConfig { proxy_only: true, ..config };
+        assert!(config.is_allowed_address(proxy));
+        assert!(!config.is_allowed_address(other));
+    }
+}
@@ -1,19 +1,28 @@
-use std::{io::Read, net::{IpAddr, Shutdown, TcpStream}};
+use std::{io::{BufWriter, Read, Write}, net::{IpAddr, Shutdown, TcpStream}};
 
 use log::{error, info, debug};
 use serde::Serialize;
 
-use crate::types::varint::{ivar, VarIntDecodeError};
+use crate::{compression::{CompressionError, PacketCompression}, config::PacketSizeLimits, protocol::Version, trace::{self, Direction}, types::varint::{ivar, VarIntDecodeError}, utils::write_packet};
 
 pub struct Connection {
     stream: TcpStream,
+    /// Second handle to `stream` that packets are written through
+    writer: BufWriter<TcpStream>,
     ip: IpAddr,
     state: ConnectionState,
+    limits: PacketSizeLimits,
+    max_packet_size: usize,
+    /// Protocol version the client gave in its handshake
+    protocol_version: i32,
+    /// Bytes read from the socket that don't make up a whole frame yet
+    received: Vec<u8>,
+    compression: Option<PacketCompression>,
 }
 
 impl Connection {
-    pub fn new(stream: TcpStream) -> Result<Self, ()> {
-        let ip = match stream.local_addr() {
+    pub fn new(stream: TcpStream, limits: PacketSizeLimits, write_buffer_size: usize) -> Result<Self, ()> {
+        let ip = match stream.peer_addr() {
             Ok(addr) => addr.ip(),
             Err(_) => {
                 error!("Disconnecting, Reason: Could not establish connection IP!");
@@ -21,10 +30,24 @@ impl Connection {
                 return Err(());
             },
         };
+        let writer = match stream.try_clone() {
+            Ok(s) => BufWriter::with_capacity(write_buffer_size, s),
+            Err(e) => {
+                error!("Disconnecting, Reason: Could not clone the stream for writing!: {e:?}");
+                let _ = stream.shutdown(std::net::Shutdown::Both);
+                return Err(());
+            },
+        };
         Ok(Self {
             stream,
+            writer,
             ip,          
             state: ConnectionState::Handshake, // Will always start with a handshake
+            max_packet_size: limits.for_state(&ConnectionState::Handshake),
+            limits,
+            protocol_version: 0,
+            received: Vec::new(),
+            compression: None,
         })
     }
 
@@ -32,37 +55,115 @@ impl Connection {
         self.stream.read(buffer)
     }
 
-    pub fn read(&mut self, buffer: &mut [u8]) -> Result<(Vec<u8>, usize), VarIntDecodeError> {
-        let _ = match self.stream.read(buffer) {
-            Err(e) => {
-                self.shutdown(Shutdown::Both, Some(format!("{e:?}")))
-            },
-            Ok(_) => Ok(()),
-        };
-        match ivar::read(buffer) {
-            Ok(value) => {
-                let buffer: Vec<u8> = Vec::from(&buffer[..=value.value as usize]);
-                debug!("b: {buffer:?}");
-                Ok((buffer, value.length()))
-            },
-            Err(e) => {
-                error!("VarIntDecodeError whilst reading buffer!: {e:?}");
-                Err(e)
+    /// Reads the next packet (id + data), decompressing it once Set Compression has been sent.
+    /// Whatever the socket gave past its frame is kept for the next call.
+    pub fn read(&mut self, buffer: &mut [u8]) -> Result<Vec<u8>, ReadError> {
+        loop {
+            match split_frame(&mut self.received, self.max_packet_size) {
+                Ok(Some((frame, length))) => {
+                    debug!("b: {frame:?}");
+                    let packet = match self.compression {
+                        Some(compression) => match compression.unframe(&frame[length..], self.max_packet_size) {
+                            Ok(packet) => packet,
+                            Err(e) => {
+                                let _ = self.shutdown(Shutdown::Both, Some(e.to_string()));
+                                return Err(ReadError::Compression(e));
+                            },
+                        },
+                        None => frame[length..].to_vec(),
+                    };
+                    trace::record(Direction::Serverbound, &self.state.to_string(), &packet);
+                    return Ok(packet);
+                },
+                Ok(None) => (),
+                Err(e) => {
+                    let reason = format!("Bad packet frame for {} (limit {} bytes): {e:?}", self.state, self.max_packet_size);
+                    let _ = self.shutdown(Shutdown::Both, Some(reason));
+                    return Err(ReadError::Frame(e));
+                },
             }
+            // Whatever was sent in reply to the last packets has to go out before waiting for the next
+            if let Err(e) = self.writer.flush() {
+                let _ = self.shutdown(Shutdown::Both, Some(format!("{e:?}")));
+                return Err(ReadError::Closed);
+            }
+            match self.stream.read(buffer) {
+                Ok(0) => return Err(ReadError::Closed),
+                Ok(n) => self.received.extend_from_slice(&buffer[..n]),
+                Err(e) => {
+                    let _ = self.shutdown(Shutdown::Both, Some(format!("{e:?}")));
+                    return Err(ReadError::Closed);
+                },
+            }
+        }
+    }
+
+    /// Sends an unframed packet, remapping its id to the negotiated protocol version
+    pub fn send_packet(&mut self, packet: &[u8]) -> Result<(), std::io::Error> {
+        let remapped = Version::from_protocol(self.protocol_version).and_then(|v| v.remap(self.state, packet));
+        let packet = remapped.as_deref().unwrap_or(packet);
+        trace::record(Direction::Clientbound, &self.state.to_string(), packet);
+        match self.compression {
+            Some(compression) => self.writer.write_all(&compression.frame(packet)?),
+            None => write_packet(&mut self.writer, packet),
         }
     }
 
+    pub fn compression(&self) -> Option<PacketCompression> {
+        self.compression
+    }
+
+    /// Frames every packet after this with compression, Set Compression must already have been sent
+    pub fn set_compression(&mut self, compression: Option<PacketCompression>) {
+        self.compression = compression;
+    }
+
+    /// Writes out every packet sent since the last flush, done before waiting on the client
+    pub fn flush(&mut self) -> Result<(), std::io::Error> {
+        self.writer.flush()
+    }
+
+    /// Second handle to the same socket, used for writing from outside the connection thread
+    pub fn try_clone_stream(&self) -> Result<TcpStream, std::io::Error> {
+        self.stream.try_clone()
+    }
+
     pub fn shutdown(&mut self,how: Shutdown, reason: Option<String>) -> Result<(), std::io::Error>{
         if reason.is_some() {
             info!("Disconnecting {}, Reason: {}", self.ip, reason.unwrap());
         } else {
             info!("Disconnecting {}, reason unspecified!", self.ip);
         } 
+        let _ = self.writer.flush();
         self.stream.shutdown(how)
     }
 
-    pub fn set_state(&mut self, state: ConnectionState) {
+    pub fn set_state(&mut self, state: ConnectionState) -> Result<(), IllegalStateTransition> {
+        if !self.state.can_transition_to(&state) {
+            let error = IllegalStateTransition {
+                from: self.state,
+                to: state,
+            };
+            error!("Illegal state transition for {}: {error}", self.ip);
+            return Err(error);
+        }
+        debug!("Connection {} state: {} -> {}", self.ip, self.state, state);
         self.state = state;
+        self.max_packet_size = self.limits.for_state(&state);
+        Ok(())
+    }
+
+    /// Overrides the inbound packet limit set by the current state
+    pub fn set_max_packet_size(&mut self, size: usize) {
+        self.max_packet_size = size;
+    }
+
+    pub fn protocol_version(&self) -> i32 {
+        self.protocol_version
+    }
+
+    pub fn set_protocol_version(&mut self, version: i32) {
+        self.protocol_version = version;
     }
 
     pub fn get_state(&self) -> &ConnectionState {
@@ -78,16 +179,80 @@ impl Connection {
     }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug)]
+pub enum ReadError {
+    /// The client closed the connection or the socket failed
+    Closed,
+    Frame(VarIntDecodeError),
+    Compression(CompressionError),
+}
+
+impl std::fmt::Display for ReadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Closed => write!(f, "Connection closed"),
+            Self::Frame(e) => write!(f, "Malformed packet frame: {e:?}"),
+            Self::Compression(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+/// Takes the first complete frame off the front of `received`, None until all of it has arrived
+fn split_frame(received: &mut Vec<u8>, max_packet_size: usize) -> Result<Option<(Vec<u8>, usize)>, VarIntDecodeError> {
+    // The length prefix itself can be split across reads
+    if !received.iter().take(5).any(|b| b & 0x80 == 0) {
+        return if received.len() < 5 { Ok(None) } else { Err(VarIntDecodeError::TooLarge) };
+    }
+    let length = ivar::read(received)?;
+    if length.value <= 0 || length.value as usize > max_packet_size {
+        return Err(VarIntDecodeError::TooLarge);
+    }
+    let frame_length = length.length() + length.value as usize;
+    if received.len() < frame_length {
+        return Ok(None);
+    }
+    let rest = received.split_off(frame_length);
+    Ok(Some((std::mem::replace(received, rest), length.length())))
+}
+
+#[derive(Debug)]
+pub struct IllegalStateTransition {
+    pub from: ConnectionState,
+    pub to: ConnectionState,
+}
+
+impl std::fmt::Display for IllegalStateTransition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} -> {}", self.from, self.to)
+    }
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
 pub enum ConnectionState {
     Handshake = 0,
     Status = 1,
     Login = 2,
     Transfer = 3,
     Unknown = 4,
+    Configuration = 5,
+    Play = 6,
 }
 
 impl ConnectionState {
+    pub fn can_transition_to(&self, next: &ConnectionState) -> bool {
+        matches!((self, next),
+            (Self::Handshake, Self::Status)
+            | (Self::Handshake, Self::Login)
+            | (Self::Handshake, Self::Transfer)
+            | (Self::Transfer, Self::Login)
+            | (Self::Login, Self::Configuration)
+            // Transferred clients log in the same way, they just arrive with cookies
+            | (Self::Transfer, Self::Configuration)
+            | (Self::Configuration, Self::Play)
+            | (Self::Play, Self::Configuration)
+        )
+    }
+
     pub fn from_u8(value: u8) -> ConnectionState {
         match value {
             0 => Self::Handshake,
@@ -101,3 +266,131 @@ impl ConnectionState {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_frames_read_together() {
+        let mut received = vec![2, 0x00, 0xAA, 1, 0x01, 3, 0x02];
+        assert_eq!(split_frame(&mut received, 64).unwrap(), Some((vec![2, 0x00, 0xAA], 1)));
+        assert_eq!(split_frame(&mut received, 64).unwrap(), Some((vec![1, 0x01], 1)));
+        // Only part of the last frame has arrived
+        assert_eq!(split_frame(&mut received, 64).unwrap(), None);
+        assert_eq!(received, vec![3, 0x02]);
+        received.extend_from_slice(&[0xBB, 0xCC, 4]);
+        assert_eq!(split_frame(&mut received, 64).unwrap(), Some((vec![3, 0x02, 0xBB, 0xCC], 1)));
+        assert_eq!(received, vec![4]);
+    }
+
+    #[test]
+    fn waits_for_a_split_length_prefix() {
+        let mut received = vec![0x80];
+        assert_eq!(split_frame(&mut received, 1024).unwrap(), None);
+        received.extend_from_slice(&[0x01]);
+        received.extend_from_slice(&[0; 128]);
+        let (packet, length) = split_frame(&mut received, 1024).unwrap().unwrap();
+        assert_eq!((packet.len(), length), (130, 2));
+        assert!(received.is_empty());
+    }
+
+    #[test]
+    fn rejects_oversized_frames() {
+        assert!(split_frame(&mut vec![0x80, 0x01], 64).is_err());
+        assert!(split_frame(&mut vec![0], 64).is_err());
+    }
+
+    #[test]
+    fn applies_the_configured_write_buffer() {
+        let (server, client) = crate::utils::tests::socket_pair();
+        let mut connection = Connection::new(server, PacketSizeLimits::default(), 12345).unwrap();
+        assert_eq!(connection.writer.capacity(), 12345);
+        connection.send_packet(&[0x01, 0x02]).unwrap();
+        // Nothing is written until the connection flushes
+        assert_eq!(connection.writer.buffer(), &[2, 0x01, 0x02]);
+        connection.flush().unwrap();
+        assert!(connection.writer.buffer().is_empty());
+        let received = crate::utils::tests::drain(client);
+        let _ = connection.shutdown(Shutdown::Both, None);
+        assert_eq!(received.join().unwrap(), vec![2, 0x01, 0x02]);
+    }
+
+    #[test]
+    fn reads_every_packet_of_one_segment() {
+        let (server, mut client) = crate::utils::tests::socket_pair();
+        let mut connection = Connection::new(server, PacketSizeLimits::default(), 64).unwrap();
+        client.write_all(&[2, 0x00, 0x01, 2, 0x01, 0x02]).unwrap();
+        let mut buffer = vec![0; 16];
+        assert_eq!(connection.read(&mut buffer).unwrap(), vec![0x00, 0x01]);
+        assert_eq!(connection.read(&mut buffer).unwrap(), vec![0x01, 0x02]);
+    }
+
+    #[test]
+    fn decompresses_packets_once_compression_is_set() {
+        let (server, mut client) = crate::utils::tests::socket_pair();
+        let mut connection = Connection::new(server, PacketSizeLimits::default(), 64).unwrap();
+        let compression = PacketCompression::new(8, 6).unwrap();
+        connection.set_compression(Some(compression));
+        let large: Vec<u8> = (0..100).collect();
+        client.write_all(&compression.frame(&large).unwrap()).unwrap();
+        client.write_all(&compression.frame(&[0x04, 1]).unwrap()).unwrap();
+        let mut buffer = vec![0; 16];
+        assert_eq!(connection.read(&mut buffer).unwrap(), large);
+        assert_eq!(connection.read(&mut buffer).unwrap(), vec![0x04, 1]);
+    }
+
+    #[test]
+    fn follows_legal_transitions_and_rejects_illegal_ones() {
+        let (server_side, _client) = crate::utils::tests::socket_pair();
+        let mut connection = Connection::new(server_side, PacketSizeLimits::default(), 1024).unwrap();
+        assert!(connection.set_state(ConnectionState::Login).is_ok());
+        assert!(connection.set_state(ConnectionState::Configuration).is_ok());
+        assert!(connection.set_state(ConnectionState::Play).is_ok());
+
+        let error = connection.set_state(ConnectionState::Handshake).unwrap_err();
+        assert_eq!((error.from, error.to), (ConnectionState::Play, ConnectionState::Handshake));
+        assert_eq!(*connection.get_state(), ConnectionState::Play);
+    }
+
+    #[test]
+    fn status_can_not_log_in() {
+        assert!(ConnectionState::Handshake.can_transition_to(&ConnectionState::Status));
+        assert!(!ConnectionState::Status.can_transition_to(&ConnectionState::Login));
+        assert!(!ConnectionState::Login.can_transition_to(&ConnectionState::Play));
+    }
+
+    #[test]
+    fn transitions_are_logged_at_debug_level() {
+        let (server_side, _client) = crate::utils::tests::socket_pair();
+        let mut connection = Connection::new(server_side, PacketSizeLimits::default(), 1024).unwrap();
+        let logged = crate::logging::tests::capture(|| {
+            connection.set_state(ConnectionState::Login).unwrap();
+            assert!(connection.set_state(ConnectionState::Handshake).is_err());
+        });
+        assert!(logged.iter().any(|line| line.starts_with("DEBUG") && line.ends_with("state: Handshake -> Login")));
+        assert!(logged.iter().any(|line| line.starts_with("ERROR") && line.contains("Illegal state transition")));
+    }
+
+    #[test]
+    fn limits_follow_the_connection_state() {
+        let limits = PacketSizeLimits::default();
+        let mut packet = vec![0x00];
+        packet.resize(limits.handshake + 1, 0xAB);
+        assert!(packet.len() < limits.play);
+
+        let (server, mut client) = crate::utils::tests::socket_pair();
+        let mut connection = Connection::new(server, limits, 64).unwrap();
+        write_packet(&mut client, &packet).unwrap();
+        let mut buffer = vec![0; 4096];
+        assert!(matches!(connection.read(&mut buffer), Err(ReadError::Frame(_))));
+
+        let (server, mut client) = crate::utils::tests::socket_pair();
+        let mut connection = Connection::new(server, limits, 64).unwrap();
+        connection.set_state(ConnectionState::Login).unwrap();
+        connection.set_state(ConnectionState::Configuration).unwrap();
+        connection.set_state(ConnectionState::Play).unwrap();
+        write_packet(&mut client, &packet).unwrap();
+        assert_eq!(connection.read(&mut buffer).unwrap(), packet);
+    }
+}
@@ -0,0 +1,108 @@
+// Per-player data kept in client cookies, so it survives transfers and reconnects.
+// Values are stored as json, the server only sees them again after requesting them.
+
+use std::collections::HashMap;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Largest payload the client will store for a cookie
+pub const MAX_COOKIE_SIZE: usize = 5120;
+/// Where the player was when they were transferred away, read back when they're transferred here
+pub const LAST_POSITION: &str = "blahaj:last_position";
+
+#[derive(Debug)]
+pub enum CookieError {
+    Serialize(serde_json::Error),
+    TooLarge(usize),
+    Io(std::io::Error),
+}
+
+impl std::fmt::Display for CookieError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Serialize(e) => write!(f, "Could not serialize cookie: {e}"),
+            Self::TooLarge(size) => write!(f, "Cookie is {size} bytes, the limit is {MAX_COOKIE_SIZE}"),
+            Self::Io(e) => write!(f, "Could not send cookie: {e}"),
+        }
+    }
+}
+
+pub fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, CookieError> {
+    let payload = serde_json::to_vec(value).map_err(CookieError::Serialize)?;
+    if payload.len() > MAX_COOKIE_SIZE {
+        return Err(CookieError::TooLarge(payload.len()));
+    }
+    Ok(payload)
+}
+
+/// None if the payload isn't a `T`, such as a cookie written by another server
+pub fn decode<T: DeserializeOwned>(payload: &[u8]) -> Option<T> {
+    serde_json::from_slice(payload).ok()
+}
+
+/// Cookies the client has sent back, keyed by identifier
+#[derive(Default)]
+pub struct CookieStore {
+    /// None when the client has no cookie for the key
+    received: HashMap<String, Option<Vec<u8>>>,
+}
+
+impl CookieStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a Cookie Response
+    pub fn receive(&mut self, key: String, payload: Option<Vec<u8>>) {
+        self.received.insert(key, payload);
+    }
+
+    pub fn payload(&self, key: &str) -> Option<&[u8]> {
+        self.received.get(key)?.as_deref()
+    }
+
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        decode(self.payload(key)?)
+    }
+
+    /// Keeps what was just stored so it can be read back without another request
+    pub fn set(&mut self, key: &str, payload: Vec<u8>) {
+        self.received.insert(key.to_string(), Some(payload));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct LastPosition {
+        world: String,
+        position: (f64, f64, f64),
+    }
+
+    #[test]
+    fn structured_values_round_trip_through_the_store() {
+        let value = LastPosition { world: "minecraft:overworld".into(), position: (1.5, 64.0, -3.0) };
+        let mut store = CookieStore::new();
+        assert_eq!(store.payload("blahaj:last_position"), None);
+        store.receive("blahaj:last_position".into(), Some(encode(&value).unwrap()));
+        assert_eq!(store.get::<LastPosition>("blahaj:last_position"), Some(value));
+    }
+
+    #[test]
+    fn missing_and_foreign_cookies_read_as_none() {
+        let mut store = CookieStore::new();
+        store.receive("blahaj:missing".into(), None);
+        store.receive("other:cookie".into(), Some(b"not json".to_vec()));
+        assert_eq!(store.get::<LastPosition>("blahaj:missing"), None);
+        assert_eq!(store.get::<LastPosition>("other:cookie"), None);
+    }
+
+    #[test]
+    fn oversized_values_are_refused() {
+        assert!(matches!(encode(&"a".repeat(MAX_COOKIE_SIZE)), Err(CookieError::TooLarge(size)) if size == MAX_COOKIE_SIZE + 2));
+    }
+}
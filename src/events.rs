@@ -0,0 +1,98 @@
+// Events raised by players that the rest of the server can listen for
+
+use log::trace;
+
+use crate::{admin::AdminProgram, play::{ClickContainer, PlayerCommand, PlayerInput}, resource_pack::ResourcePackStatus, server::Server, types::{position::Position, slot::Slot}};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InteractionType {
+    Interact,
+    Attack,
+    /// Target position relative to the entity
+    InteractAt { x: f32, y: f32, z: f32 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    PlayerJoin {
+        player: u128,
+    },
+    PlayerLeave {
+        player: u128,
+    },
+    InteractEntity {
+        player: u128,
+        target: i32,
+        interaction: InteractionType,
+        /// 0 for the main hand, 1 for the off hand, absent for attacks
+        hand: Option<i32>,
+        sneaking: bool,
+    },
+    ClickContainer {
+        player: u128,
+        click: ClickContainer,
+    },
+    PlayerInput {
+        player: u128,
+        input: PlayerInput,
+    },
+    /// Sneaking, sprinting and other actions without a packet of their own
+    PlayerCommand {
+        player: u128,
+        command: PlayerCommand,
+    },
+    /// `signed` is false for messages without a valid signature, which are only let through when secure chat isn't enforced
+    ChatMessage {
+        player: u128,
+        message: String,
+        signed: bool,
+    },
+    /// The client answered a cookie request, read the value from the player's cookies
+    CookieResponse {
+        player: u128,
+        key: String,
+    },
+    /// The client reported progress on a pack it was sent
+    ResourcePackResponse {
+        player: u128,
+        pack: u128,
+        status: ResourcePackStatus,
+    },
+    /// `state` is the block that was there before it was broken
+    BlockBreak {
+        player: u128,
+        position: Position,
+        state: i32,
+    },
+    /// Only fired for operators allowed to program the block
+    AdminProgram {
+        player: u128,
+        program: AdminProgram,
+    },
+    CreativeModeSlot {
+        player: u128,
+        slot: i16,
+        item: Slot,
+    },
+}
+
+pub type Listener = Box<dyn Fn(&Server, &Event) + Send + Sync>;
+
+/// Registered on startup so every event shows up in the trace log
+pub fn log_event(_: &Server, event: &Event) {
+    trace!("{event:?}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::ServerConfig, logging};
+
+    #[test]
+    fn events_are_logged_at_trace_level() {
+        let server = Server::new(ServerConfig::default());
+        server.add_listener(Box::new(log_event));
+        let lines = logging::tests::capture(|| server.fire_event(&Event::PlayerJoin { player: 1 }));
+        assert_eq!(lines, vec!["TRACE PlayerJoin { player: 1 }"]);
+    }
+}
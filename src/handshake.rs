@@ -0,0 +1,138 @@
+// Parsing of the Handshake packet and the markers modloaders add to its server address.
+// https://wiki.vg/Protocol#Handshake
+
+use serde::Deserialize;
+
+use crate::{connection::ConnectionState, types::reader::PacketReader};
+
+/// Longest server address vanilla accepts, modloader markers are counted towards it
+pub const MAX_ADDRESS_LENGTH: usize = 255;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Handshake {
+    pub protocol_version: i32,
+    /// Address the client connected to, without any modloader marker
+    pub server_address: String,
+    pub server_port: u16,
+    pub next_state: ConnectionState,
+    /// Loader whose marker was found after the address
+    pub mod_loader: Option<ModLoader>,
+}
+
+/// Modloaders that announce themselves by appending `\0<marker>\0` to the server address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModLoader {
+    /// Forge for 1.7 to 1.12
+    Fml,
+    /// Forge for 1.13 to 1.17
+    Fml2,
+    /// Forge for 1.18 to 1.20.1
+    Fml3,
+    /// Forge and NeoForge from 1.20.2
+    Forge,
+}
+
+impl ModLoader {
+    pub fn from_marker(marker: &str) -> Option<Self> {
+        match marker {
+            "FML" => Some(Self::Fml),
+            "FML2" => Some(Self::Fml2),
+            "FML3" => Some(Self::Fml3),
+            // Newer versions follow the marker with their network version
+            m if m.starts_with("FORGE") => Some(Self::Forge),
+            _ => None,
+        }
+    }
+
+    pub fn marker(self) -> &'static str {
+        match self {
+            Self::Fml => "FML",
+            Self::Fml2 => "FML2",
+            Self::Fml3 => "FML3",
+            Self::Forge => "FORGE",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum ModLoaderAction {
+    /// Remove the marker and let the client join like a vanilla one
+    Strip,
+    /// Disconnect clients that send a marker
+    Reject,
+}
+
+pub fn parse(buffer: &[u8]) -> Result<Handshake, &'static str> {
+    let mut reader = PacketReader::new(buffer);
+    let protocol_version = reader.read_ivar()?;
+    let address = reader.read_utf8_string(MAX_ADDRESS_LENGTH)?;
+    let server_port = reader.read_u16()?;
+    let next_state = match reader.read_ivar()? {
+        1 => ConnectionState::Status,
+        2 => ConnectionState::Login,
+        3 => ConnectionState::Transfer,
+        _ => return Err("Unknown handshake state"),
+    };
+    let (server_address, mod_loader) = split_marker(&address);
+    Ok(Handshake {
+        protocol_version,
+        server_address: server_address.to_string(),
+        server_port,
+        next_state,
+        mod_loader,
+    })
+}
+
+/// Splits `host\0FML3\0` into the host and the loader, unknown markers are left in the address
+/// since other software (such as proxy forwarding) also uses null separated fields
+pub fn split_marker(address: &str) -> (&str, Option<ModLoader>) {
+    let Some((host, rest)) = address.split_once('\0') else {
+        return (address, None);
+    };
+    let marker = rest.split('\0').next().unwrap_or_default();
+    match ModLoader::from_marker(marker) {
+        Some(loader) => (host, Some(loader)),
+        None => (address, None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{write_ivar, write_utf8_string};
+
+    fn handshake(address: &str, next_state: i32) -> Vec<u8> {
+        let mut buffer: Vec<u8> = Vec::new();
+        write_ivar(&mut buffer, 767);
+        write_utf8_string(&mut buffer, address.to_string());
+        buffer.extend_from_slice(&25565u16.to_be_bytes());
+        write_ivar(&mut buffer, next_state);
+        buffer
+    }
+
+    #[test]
+    fn fml3_markers_are_split_from_the_address() {
+        let parsed = parse(&handshake("localhost\0FML3\0", 2)).unwrap();
+        assert_eq!(parsed, Handshake {
+            protocol_version: 767,
+            server_address: "localhost".to_string(),
+            server_port: 25565,
+            next_state: ConnectionState::Login,
+            mod_loader: Some(ModLoader::Fml3),
+        });
+    }
+
+    #[test]
+    fn vanilla_and_unknown_addresses_are_left_alone() {
+        assert_eq!(split_marker("localhost"), ("localhost", None));
+        assert_eq!(split_marker("localhost\0FORGE1\0"), ("localhost", Some(ModLoader::Forge)));
+        assert_eq!(split_marker("localhost\0proxy\0uuid"), ("localhost\0proxy\0uuid", None));
+    }
+
+    #[test]
+    fn unknown_states_are_rejected() {
+        assert!(parse(&handshake("localhost", 3)).is_ok());
+        assert!(parse(&handshake("localhost", 4)).is_err());
+        assert!(parse(&handshake("localhost", 2)[..3]).is_err());
+    }
+}
@@ -0,0 +1,222 @@
+// Player inventory, indexed the same way as the player's window (id 0)
+// https://wiki.vg/Inventory#Player_Inventory
+
+use crate::{recipe::{Ingredient, Recipe}, types::slot::Slot};
+
+pub const CRAFTING_OUTPUT: usize = 0;
+pub const CRAFTING_INPUT_START: usize = 1;
+/// Width and height of the crafting grid
+pub const CRAFTING_GRID_SIZE: usize = 2;
+// 5 to 8 are the armor slots, helmet first
+pub const MAIN_START: usize = 9;
+pub const HOTBAR_START: usize = 36;
+pub const OFFHAND: usize = 45;
+pub const SLOT_COUNT: usize = 46;
+
+/// Item properties aren't known yet, every item stacks to 64
+const MAX_STACK_SIZE: i32 = 64;
+
+pub struct PlayerInventory {
+    slots: [Slot; SLOT_COUNT],
+    /// Incremented on every server side change so the client can spot stale clicks
+    state_id: i32,
+}
+
+impl Default for PlayerInventory {
+    fn default() -> Self {
+        Self {
+            slots: [Slot::EMPTY; SLOT_COUNT],
+            state_id: 0,
+        }
+    }
+}
+
+impl PlayerInventory {
+    pub fn set(&mut self, slot: usize, item: Slot) -> Result<(), &'static str> {
+        match self.slots.get_mut(slot) {
+            Some(s) => {
+                *s = item;
+                Ok(())
+            },
+            None => Err("Inventory slot out of range"),
+        }
+    }
+
+    /// Next state id to send with a container packet
+    pub fn next_state_id(&mut self) -> i32 {
+        self.state_id = self.state_id.wrapping_add(1);
+        self.state_id
+    }
+
+    pub fn slots(&self) -> &[Slot] {
+        &self.slots
+    }
+
+    /// Item in the given hotbar slot (0-8)
+    pub fn hotbar(&self, selected_slot: u8) -> &Slot {
+        &self.slots[HOTBAR_START + (selected_slot as usize).min(8)]
+    }
+
+    /// Fills the crafting grid from the main inventory and hotbar with the recipe's ingredients, once or as many
+    /// times as the stacks allow with `make_all`, and sets the output. Whatever was in the grid is put away first.
+    /// Returns the slots that changed, the output stays empty when there aren't enough ingredients.
+    pub fn place_recipe(&mut self, recipe: &Recipe, make_all: bool) -> Result<Vec<usize>, &'static str> {
+        let layout = recipe.grid_layout(CRAFTING_GRID_SIZE).ok_or("Recipe doesn't fit in the crafting grid")?;
+        let before = self.slots;
+        self.clear_crafting_grid();
+        let limit = match make_all {
+            true => MAX_STACK_SIZE,
+            false => 1,
+        };
+        let mut crafts = 0;
+        while crafts < limit {
+            // Taken from a copy so a craft that runs out part way doesn't leave half its ingredients moved
+            let mut slots = self.slots;
+            let placed = layout.iter().enumerate().all(|(i, ingredient)| match ingredient {
+                Some(ingredient) => take_ingredient(&mut slots, CRAFTING_INPUT_START + i, ingredient),
+                None => true,
+            });
+            if !placed {
+                break;
+            }
+            self.slots = slots;
+            crafts += 1;
+        }
+        self.slots[CRAFTING_OUTPUT] = match crafts > 0 {
+            true => recipe.result,
+            false => Slot::EMPTY,
+        };
+        Ok((0..SLOT_COUNT).filter(|&i| before[i] != self.slots[i]).collect())
+    }
+
+    /// Moves the crafting grid back into the inventory, anything that doesn't fit stays in the grid
+    fn clear_crafting_grid(&mut self) {
+        for index in CRAFTING_INPUT_START..CRAFTING_INPUT_START + CRAFTING_GRID_SIZE * CRAFTING_GRID_SIZE {
+            let mut item = std::mem::take(&mut self.slots[index]);
+            self.add(&mut item);
+            self.slots[index] = item;
+        }
+    }
+
+    /// Adds the item to the hotbar then the main inventory, topping up matching stacks before empty slots.
+    /// Returns the slots that changed, `item` is left holding whatever didn't fit.
+    pub fn add(&mut self, item: &mut Slot) -> Vec<usize> {
+        let order: Vec<usize> = (HOTBAR_START..OFFHAND).chain(MAIN_START..HOTBAR_START).collect();
+        let mut changed = Vec::new();
+        for &index in &order {
+            let slot = &mut self.slots[index];
+            if item.is_empty() {
+                break;
+            }
+            if slot.is_empty() || slot.item_id != item.item_id || slot.count >= MAX_STACK_SIZE {
+                continue;
+            }
+            let moved = item.count.min(MAX_STACK_SIZE - slot.count);
+            slot.count += moved;
+            item.count -= moved;
+            changed.push(index);
+        }
+        for &index in &order {
+            let slot = &mut self.slots[index];
+            if item.is_empty() {
+                break;
+            }
+            if !slot.is_empty() {
+                continue;
+            }
+            let moved = item.count.min(MAX_STACK_SIZE);
+            *slot = Slot::new(item.item_id, moved);
+            item.count -= moved;
+            changed.push(index);
+        }
+        if item.is_empty() {
+            *item = Slot::EMPTY;
+        }
+        changed
+    }
+}
+
+/// Moves one matching item from the main inventory or hotbar onto the grid slot
+fn take_ingredient(slots: &mut [Slot; SLOT_COUNT], grid_slot: usize, ingredient: &Ingredient) -> bool {
+    let target = slots[grid_slot];
+    if target.count >= MAX_STACK_SIZE {
+        return false;
+    }
+    let source = (MAIN_START..OFFHAND).find(|&i| {
+        let slot = slots[i];
+        !slot.is_empty()
+            && ingredient.iter().any(|option| option.item_id == slot.item_id)
+            && (target.is_empty() || target.item_id == slot.item_id)
+    });
+    let Some(source) = source else {
+        return false;
+    };
+    let item_id = slots[source].item_id;
+    slots[source].count -= 1;
+    if slots[source].count == 0 {
+        slots[source] = Slot::EMPTY;
+    }
+    slots[grid_slot] = Slot::new(item_id, target.count.max(0) + 1);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recipe::{self, CRAFTING_TABLE, OAK_PLANKS, STICK};
+
+    #[test]
+    fn slots_read_back() {
+        let mut inventory = PlayerInventory::default();
+        let helmet = Slot::new(900, 1);
+        inventory.set(5, helmet).unwrap();
+        assert_eq!(inventory.slots()[5], helmet);
+        assert_eq!(inventory.slots()[6], Slot::EMPTY);
+        assert!(inventory.set(SLOT_COUNT, helmet).is_err());
+    }
+
+    #[test]
+    fn hotbar_slots_follow_the_main_inventory() {
+        let mut inventory = PlayerInventory::default();
+        inventory.set(HOTBAR_START + 4, Slot::new(1, 3)).unwrap();
+        assert_eq!(*inventory.hotbar(4), Slot::new(1, 3));
+        assert_eq!(*inventory.hotbar(0), Slot::EMPTY);
+    }
+
+    #[test]
+    fn placing_a_recipe_fills_the_grid_and_output() {
+        let mut inventory = PlayerInventory::default();
+        inventory.set(HOTBAR_START, Slot::new(OAK_PLANKS, 5)).unwrap();
+        let table = recipe::find("minecraft:crafting_table").unwrap();
+        let changed = inventory.place_recipe(&table, false).unwrap();
+        assert_eq!(changed, vec![CRAFTING_OUTPUT, 1, 2, 3, 4, HOTBAR_START]);
+        assert_eq!(inventory.slots()[CRAFTING_OUTPUT], Slot::new(CRAFTING_TABLE, 1));
+        for slot in 1..=4 {
+            assert_eq!(inventory.slots()[slot], Slot::new(OAK_PLANKS, 1));
+        }
+        assert_eq!(inventory.slots()[HOTBAR_START], Slot::new(OAK_PLANKS, 1));
+    }
+
+    #[test]
+    fn make_all_uses_as_many_ingredients_as_there_are() {
+        let mut inventory = PlayerInventory::default();
+        inventory.set(MAIN_START, Slot::new(OAK_PLANKS, 7)).unwrap();
+        let sticks = recipe::find("minecraft:stick").unwrap();
+        inventory.place_recipe(&sticks, true).unwrap();
+        // Two planks a craft, the odd one is left over
+        assert_eq!(inventory.slots()[1], Slot::new(OAK_PLANKS, 3));
+        assert_eq!(inventory.slots()[3], Slot::new(OAK_PLANKS, 3));
+        assert_eq!(inventory.slots()[MAIN_START], Slot::new(OAK_PLANKS, 1));
+        assert_eq!(inventory.slots()[CRAFTING_OUTPUT], Slot::new(STICK, 4));
+    }
+
+    #[test]
+    fn missing_ingredients_leave_the_output_empty() {
+        let mut inventory = PlayerInventory::default();
+        inventory.set(MAIN_START, Slot::new(OAK_PLANKS, 3)).unwrap();
+        let table = recipe::find("minecraft:crafting_table").unwrap();
+        assert!(inventory.place_recipe(&table, false).unwrap().is_empty());
+        assert_eq!(inventory.slots()[CRAFTING_OUTPUT], Slot::EMPTY);
+        assert_eq!(inventory.slots()[MAIN_START], Slot::new(OAK_PLANKS, 3));
+    }
+}
@@ -0,0 +1,138 @@
+// Keep alives sent to a player during Play, and how long the client took to answer them.
+// A player is only timed out after several keep alives in a row go unanswered, so a short
+// network blip doesn't kick anyone.
+// https://wiki.vg/Protocol#Clientbound_Keep_Alive_(play)
+
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_KEEP_ALIVE_INTERVAL: u64 = 15;
+pub const DEFAULT_MAX_MISSED_KEEP_ALIVES: u32 = 2;
+
+/// Upper bound in milliseconds of every histogram bucket but the last, which holds everything slower
+pub const ROUND_TRIP_BUCKETS: [u64; 6] = [25, 50, 100, 250, 500, 1000];
+
+/// Counts of keep alive round trips by how long they took
+#[derive(Debug, Clone, Default)]
+pub struct RoundTripHistogram {
+    counts: [u32; ROUND_TRIP_BUCKETS.len() + 1],
+}
+
+impl RoundTripHistogram {
+    pub fn record(&mut self, round_trip: Duration) {
+        let millis = round_trip.as_millis() as u64;
+        let bucket = ROUND_TRIP_BUCKETS.iter().position(|bound| millis <= *bound).unwrap_or(ROUND_TRIP_BUCKETS.len());
+        self.counts[bucket] = self.counts[bucket].saturating_add(1);
+    }
+
+    pub fn total(&self) -> u32 {
+        self.counts.iter().sum()
+    }
+}
+
+impl std::fmt::Display for RoundTripHistogram {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} round trips", self.total())?;
+        for (bound, count) in ROUND_TRIP_BUCKETS.iter().zip(self.counts) {
+            write!(f, ", <={bound}ms: {count}")?;
+        }
+        write!(f, ", slower: {}", self.counts[ROUND_TRIP_BUCKETS.len()])
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct KeepAliveTracker {
+    /// Keep alives in a row that can go unanswered before the player is timed out, at least 1
+    max_missed: u32,
+    next_id: i64,
+    pending: Option<(i64, Instant)>,
+    missed: u32,
+    histogram: RoundTripHistogram,
+}
+
+impl KeepAliveTracker {
+    pub fn new(max_missed: u32) -> Self {
+        Self {
+            max_missed: max_missed.max(1),
+            next_id: 0,
+            pending: None,
+            missed: 0,
+            histogram: RoundTripHistogram::default(),
+        }
+    }
+
+    /// Id of the next keep alive to send, None once too many in a row have gone unanswered
+    pub fn next(&mut self, now: Instant) -> Option<i64> {
+        if self.pending.is_some() {
+            self.missed += 1;
+            if self.missed >= self.max_missed {
+                return None;
+            }
+        }
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        self.pending = Some((id, now));
+        Some(id)
+    }
+
+    /// Returns the round trip if the id answers the last keep alive sent, late answers to older ones are ignored
+    pub fn receive(&mut self, id: i64, now: Instant) -> Option<Duration> {
+        let (pending_id, sent) = self.pending?;
+        if pending_id != id {
+            return None;
+        }
+        let round_trip = now.saturating_duration_since(sent);
+        self.histogram.record(round_trip);
+        self.pending = None;
+        self.missed = 0;
+        Some(round_trip)
+    }
+
+    /// Unanswered keep alives in a row
+    pub fn missed(&self) -> u32 {
+        self.missed
+    }
+
+    pub fn histogram(&self) -> &RoundTripHistogram {
+        &self.histogram
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_missed_keep_alive_is_forgiven() {
+        let start = Instant::now();
+        let mut tracker = KeepAliveTracker::new(3);
+        assert_eq!(tracker.next(start), Some(0));
+        assert_eq!(tracker.next(start), Some(1));
+        assert_eq!(tracker.missed(), 1);
+        // Answering the latest one resets the count, the missed one is never answered
+        assert_eq!(tracker.receive(0, start), None);
+        assert_eq!(tracker.receive(1, start + Duration::from_millis(40)), Some(Duration::from_millis(40)));
+        assert_eq!(tracker.missed(), 0);
+        assert_eq!(tracker.next(start), Some(2));
+    }
+
+    #[test]
+    fn consecutive_misses_time_the_player_out() {
+        let start = Instant::now();
+        let mut tracker = KeepAliveTracker::new(3);
+        assert!(tracker.next(start).is_some());
+        assert!(tracker.next(start).is_some());
+        assert!(tracker.next(start).is_some());
+        assert_eq!(tracker.next(start), None);
+        assert_eq!(tracker.missed(), 3);
+    }
+
+    #[test]
+    fn round_trips_are_bucketed() {
+        let mut histogram = RoundTripHistogram::default();
+        for millis in [10, 25, 26, 400, 5000] {
+            histogram.record(Duration::from_millis(millis));
+        }
+        assert_eq!(histogram.counts, [2, 1, 0, 0, 1, 0, 1]);
+        assert_eq!(histogram.to_string(), "5 round trips, <=25ms: 2, <=50ms: 1, <=100ms: 0, <=250ms: 0, <=500ms: 1, <=1000ms: 0, slower: 1");
+    }
+}
@@ -0,0 +1,119 @@
+// Console logging with an optional copy written to a file.
+// The level is taken from the first valid one of `--log-level <level>`, the BLAHAJ_LOG_LEVEL
+// environment variable and `log_level` in the config.
+
+use std::{fs::{File, OpenOptions}, io::Write, str::FromStr, sync::{Mutex, OnceLock}, time::{SystemTime, UNIX_EPOCH}};
+
+use log::{warn, LevelFilter, Log, Metadata, Record};
+use simple_logger::SimpleLogger;
+
+pub const LOG_LEVEL_ENV: &str = "BLAHAJ_LOG_LEVEL";
+pub const DEFAULT_LOG_LEVEL: LevelFilter = LevelFilter::Info;
+
+struct Logger {
+    console: SimpleLogger,
+    file: Mutex<Option<File>>,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.console.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        self.console.log(record);
+        #[cfg(test)]
+        tests::CAPTURED.with(|captured| {
+            if let Some(lines) = captured.borrow_mut().as_mut() {
+                lines.push(format!("{:<5} {}", record.level(), record.args()));
+            }
+        });
+        if let Some(file) = self.file.lock().unwrap().as_mut() {
+            let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+            let _ = writeln!(file, "{timestamp} {:<5} [{}] {}", record.level(), record.target(), record.args());
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(file) = self.file.lock().unwrap().as_mut() {
+            let _ = file.flush();
+        }
+    }
+}
+
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+/// Starts logging to the console at the default level, so the config can log while it loads
+pub fn init() {
+    let logger = LOGGER.get_or_init(|| Logger {
+        // Filtering is left to the max level so it can be changed later
+        console: SimpleLogger::new().with_level(LevelFilter::Trace),
+        file: Mutex::new(None),
+    });
+    if log::set_logger(logger).is_ok() {
+        log::set_max_level(DEFAULT_LOG_LEVEL);
+    }
+}
+
+/// Applies the level and starts copying logs to the file once the config is loaded
+pub fn configure(level: LevelFilter, file: Option<&str>) -> Result<(), std::io::Error> {
+    log::set_max_level(level);
+    if let (Some(path), Some(logger)) = (file, LOGGER.get()) {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        *logger.file.lock().unwrap() = Some(file);
+    }
+    Ok(())
+}
+
+pub fn parse_level(level: &str) -> Option<LevelFilter> {
+    LevelFilter::from_str(level.trim()).ok()
+}
+
+/// First valid level from the command line, environment and config, invalid ones are skipped
+pub fn resolve_level(cli: Option<&str>, env: Option<&str>, config: Option<&str>) -> LevelFilter {
+    for (source, level) in [("command line", cli), ("environment", env), ("config", config)] {
+        let Some(level) = level else {
+            continue;
+        };
+        match parse_level(level) {
+            Some(filter) => return filter,
+            None => warn!("Invalid log level {level:?} from the {source}, ignoring it"),
+        }
+    }
+    DEFAULT_LOG_LEVEL
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+
+    thread_local! {
+        /// Lines logged by this thread while a test is capturing them
+        pub(super) static CAPTURED: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
+    }
+
+    /// Runs `f` with every level enabled and returns what it logged on this thread, as "LEVEL message"
+    pub fn capture(f: impl FnOnce()) -> Vec<String> {
+        init();
+        log::set_max_level(LevelFilter::Trace);
+        CAPTURED.with(|captured| *captured.borrow_mut() = Some(Vec::new()));
+        f();
+        CAPTURED.with(|captured| captured.borrow_mut().take().unwrap_or_default())
+    }
+
+    #[test]
+    fn invalid_levels_fall_back_to_the_default() {
+        assert_eq!(parse_level("loud"), None);
+        assert_eq!(resolve_level(Some("loud"), None, None), DEFAULT_LOG_LEVEL);
+        assert_eq!(resolve_level(None, Some(""), Some("verbose")), DEFAULT_LOG_LEVEL);
+    }
+
+    #[test]
+    fn command_line_beats_environment_beats_config() {
+        assert_eq!(resolve_level(Some("debug"), Some("warn"), Some("error")), LevelFilter::Debug);
+        assert_eq!(resolve_level(None, Some(" WARN "), Some("error")), LevelFilter::Warn);
+        assert_eq!(resolve_level(Some("nope"), None, Some("error")), LevelFilter::Error);
+    }
+}
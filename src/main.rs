@@ -1,14 +1,15 @@
 use core::fmt;
-use std::{borrow::Borrow, collections::HashMap, io::{stdout, Read, Write}, isize, net::{IpAddr, Shutdown, TcpListener, TcpStream}, thread, u128, usize};
+use std::{borrow::Borrow, collections::HashMap, io::{stdout, Read, Write}, isize, net::{IpAddr, Shutdown, TcpListener, TcpStream, ToSocketAddrs}, thread, time::{Duration, Instant}, u128, usize};
 
+use commands::CommandSender;
+use config::{DuplicateLoginAction, ServerConfig};
 use connection::ConnectionState;
-use fastnbt::SerOpts;
-use log::{debug, error, info, trace};
-use registry::biomes::Biome;
-use registry_data::{construct_registry_packet, send_registry_packet, RegistryEntry};
+use handshake::ModLoaderAction;
+use log::{debug, error, info, log_enabled, trace, warn, Level};
+use registry_data::construct_registry_packet;
 use serde::{de::Error, Serialize};
-use simple_logger::SimpleLogger;
-use types::varint::{self, ivar, VarIntDecodeError};
+use socket2::{Domain, Protocol, Socket, Type};
+use types::{reader::PacketReader, text_component::TextComponent, varint::{self, ivar, VarIntDecodeError}};
 use utils::{write_ivar, write_utf8_string};
 use std::sync::{Arc, Mutex};
 
@@ -18,108 +19,216 @@ mod connection;
 mod utils;
 mod registry_data;
 mod registry;
+mod packets;
+mod config;
+mod server;
+mod player;
+mod commands;
+mod chat;
+mod play;
+mod world;
+mod tick;
+mod events;
+mod inventory;
+mod trace;
+mod client;
+mod combat;
+mod protocol;
+mod admin;
+mod logging;
+mod cookie;
+mod recipe;
+mod nbt;
+mod profile;
+mod auth;
+mod resource_pack;
+mod handshake;
+mod keep_alive;
+mod rsa;
+mod compression;
 
-use crate::{status_response::StatusResponse, connection::Connection};
+use crate::{profile::GameProfile, compression::PacketCompression, protocol::{Packet, Version}, status_response::{Motd, MotdPlaceholders, StatusResponse, VERSION_NAME}, connection::Connection, packets::ServerLinkLabel, player::Player, server::Server};
 
 
-struct Server<'a> {
-    address: &'a str,
-    connections: HashMap<String, TcpStream>,
-    players: HashMap<String, Player>,
-}
+fn main() {
+    logging::init();
+
+    let mut args: Vec<String> = std::env::args().collect();
+    let cli_log_level = match args.iter().position(|a| a == "--log-level") {
+        Some(i) if i + 1 < args.len() => Some(args.drain(i..=i + 1).nth(1).unwrap()),
+        _ => None,
+    };
+    match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        [_, "--dump-trace", path] => {
+            if let Err(e) = trace::dump(path) {
+                error!("Could not read packet trace {path}!\n{e:?}");
+            }
+            return;
+        },
+        [_, "--replay", address, path] => {
+            if let Err(e) = client::replay_and_log(address, path) {
+                error!("Could not replay packet trace {path}!\n{e:?}");
+            }
+            return;
+        },
+        _ => (),
+    }
 
-impl Server<'_> {
-    pub fn new(address: &'static str) -> Self {
-        Self {
-            address,
-            connections: HashMap::new(),
-            players: HashMap::new(),
+    let server = Arc::new(Server::new(ServerConfig::load("config.json")));
+    server.add_listener(Box::new(events::log_event));
+    let env_log_level = std::env::var(logging::LOG_LEVEL_ENV).ok();
+    let level = logging::resolve_level(cli_log_level.as_deref(), env_log_level.as_deref(), server.config.log_level.as_deref());
+    if let Err(e) = logging::configure(level, server.config.log_file.as_deref()) {
+        error!("Could not open the log file!\n{e:?}");
+    }
+    if let Some(path) = &server.config.trace_file {
+        if let Err(e) = trace::init(path) {
+            error!("Could not start packet trace {path}!\n{e:?}");
         }
     }
-}
+    
+    let listener = match bind(&server.config.address, server.config.connection_backlog) {
+        Ok(l) => l,
+        Err(e) => panic!("{e:?}"),
+    };
 
+    let console_server = Arc::clone(&server);
+    thread::spawn(move || console(console_server));
 
+    let tick_server = Arc::clone(&server);
+    thread::spawn(move || tick::run(tick_server));
 
-struct Player {
-    connection: Connection,
-    name: String,
-    uuid: u128,
-}
+    let handles = accept_connections(listener.incoming(), &server);
 
-impl Player {
-    pub fn new(connection: Connection, name: String, uuid: u128) -> Self {
-        Self {
-            connection,
-            name,
-            uuid
-        }
-    }
+    drop(listener);
 
-    pub fn uuid(&self) -> u128 {
-        self.uuid
-    }
-    
-    pub fn name(&self) -> String {
-        self.name.clone()
+    for handle in handles {
+        if let Err(e) = handle.join() {
+            error!("A connection thread panicked!\n{e:?}");
+        }
     }
+    info!("Server stopped");
+    log::logger().flush();
 }
 
-fn main() {
-    let server = Server::new("127.0.0.1:25565");
-    
-    let listener = match TcpListener::bind(server.address) {
-        Ok(l) => l,
-        Err(e) => panic!("{e:?}"),
+/// Binds the listening socket, std has no way to set the backlog
+fn bind(address: &str, backlog: i32) -> Result<TcpListener, std::io::Error> {
+    let address = match address.to_socket_addrs()?.next() {
+        Some(a) => a,
+        None => return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "Address did not resolve")),
     };
+    let socket = Socket::new(Domain::for_address(address), Type::STREAM, Some(Protocol::TCP))?;
+    socket.set_reuse_address(true)?;
+    socket.bind(&address.into())?;
+    socket.listen(backlog)?;
+    Ok(socket.into())
+}
 
+/// Starts a connection thread for each accepted stream until the server stops
+fn accept_connections(incoming: impl Iterator<Item = Result<TcpStream, std::io::Error>>, server: &Arc<Server>) -> Vec<thread::JoinHandle<()>> {
     let mut handles = Vec::new();
-    
-    SimpleLogger::new().init().unwrap();
-
-    for stream in listener.incoming() {
+    let mut failures: u32 = 0;
+    for stream in incoming {
+        if server.is_stopping() {
+            break;
+        }
         match stream {
-            Ok(s) => handles.push(thread::spawn(move || start_connection(s))),
-            Err(e) => println!("{e:?}"),
+            Ok(s) => {
+                failures = 0;
+                let server = Arc::clone(server);
+                handles.push(thread::spawn(move || start_connection(s, server)))
+            },
+            Err(e) => {
+                failures = failures.saturating_add(1);
+                let backoff = accept_backoff(failures);
+                error!("Could not accept a connection, retrying in {backoff:?}!\n{e:?}");
+                thread::sleep(backoff);
+            },
         }
     }
+    handles
+}
 
-    for handle in handles {
-        match handle.join() {
-            Err(e) => println!("{e:?}"),
-            _ => (),
+const MAX_ACCEPT_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Time to wait after repeated accept errors, such as running out of file descriptors,
+/// doubling from 10ms up to a second
+fn accept_backoff(failures: u32) -> Duration {
+    let millis: u64 = 10 << failures.saturating_sub(1).min(7);
+    Duration::from_millis(millis).min(MAX_ACCEPT_BACKOFF)
+}
+
+fn console(server: Arc<Server>) {
+    let stdin = std::io::stdin();
+    let mut line = String::new();
+    loop {
+        line.clear();
+        match stdin.read_line(&mut line) {
+            Ok(0) => break,
+            Ok(_) => (),
+            Err(e) => {
+                error!("Could not read console input!\n{e:?}");
+                break;
+            },
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+        match commands::dispatch(&server, &CommandSender::Console, &line) {
+            Ok(feedback) => info!("{feedback}"),
+            Err(e) => error!("{e}"),
         }
     }
 }
 
-fn start_connection(stream: TcpStream) {
-    match stream.local_addr() {
-        Ok(addr) => info!("Starting connection with: {}", addr.ip()),
+fn start_connection(stream: TcpStream, server: Arc<Server>) {
+    let address = match stream.peer_addr() {
+        Ok(addr) => {
+            info!("Starting connection with: {}", addr.ip());
+            addr.to_string()
+        },
         Err(e) => {
             error!("Could not get ip from client!\n{e:?}");
             return;
         },
+    };
+    if let Ok(s) = stream.try_clone() {
+        server.add_connection(address.clone(), s);
+        debug!("{} open connections", server.connection_count());
     }
-    let mut connection = match Connection::new(stream) {
+    let mut connection = match Connection::new(stream, server.config.max_packet_size, server.config.write_buffer_size) {
         Ok(conn) => conn,
         Err(e) => {
             error!("{e:?}");
+            server.remove_connection(&address);
             return;
         }
     };
+    handle_connection(&mut connection, &server, &address);
+    server.remove_connection(&address);
+}
+
+fn handle_connection(connection: &mut Connection, server: &Server, address: &str) {
+    let config = &server.config;
+    let mut read_buffer = vec![0; config.read_buffer_size];
 
     loop {
-        let mut buf: [u8; 4096] = [0; 4096];
-        let (buf, length) = connection.read(&mut buf).unwrap();
-        if buf[0] == 0 {
-            break;
-        }
-        let buf = &buf;       
-        
-        let raw_buffer = buf; // Some packets can be sent back
-        
-        let buf = &buf[length..];
+        let buf = match connection.read(&mut read_buffer) {
+            Ok(v) => v,
+            Err(e) => {
+                debug!("Stopped reading from {}: {e}", connection.ip());
+                break;
+            },
+        };
+        let buf = buf.as_slice();
 
-        let packet_id_ivar = ivar::read(buf).unwrap();
+        let packet_id_ivar = match ivar::read(buf) {
+            Ok(v) => v,
+            Err(e) => {
+                protocol_error(connection, config, &format!("Could not read the packet id: {e:?}"));
+                break;
+            },
+        };
         let packet_id = packet_id_ivar.value;
 
         info!("Packet ID: {:#x?}", packet_id);
@@ -132,9 +241,13 @@ fn start_connection(stream: TcpStream) {
             ConnectionState::Handshake => {
                 match packet_id {
                     0x00 => {
-                        handshake(&mut connection, buf);
+                        handshake(connection, buf, config);
                     },
                     0xFE => {
+                        if !config.enable_status_ping {
+                            let _ = connection.shutdown(Shutdown::Both, Some("Status pings are disabled".into()));
+                            break;
+                        }
                         info!("Legacy ping detected, IP: {}", connection.ip());
                     },
                     _ => {
@@ -144,52 +257,124 @@ fn start_connection(stream: TcpStream) {
                     },
                 } 
             },
-            ConnectionState::Login => {
+            ConnectionState::Login | ConnectionState::Transfer => {
                 match packet_id {
                     0x00 => {
                         match login(buf) {
                             Ok((name, uuid)) => {
-                                match login_success(connection, name, uuid) {
-                                    Ok(mut player) => {
-                                        // clientbound_pack(&mut player);
-                                        registry_data(&mut player);
-                                        // let _ = player.connection.get_stream().write_all(&ivar::new(0x03).as_bytes());
+                                let transferred = *connection.get_state() == ConnectionState::Transfer;
+                                if transferred {
+                                    info!("{name} was transferred from another server");
+                                }
+                                match login_success(connection, server, name, uuid, config) {
+                                    Ok(player) => {
+                                        server.set_connection_compression(address, connection.compression());
+                                        let player = Arc::new(Mutex::new(player));
+                                        server.add_player(Arc::clone(&player));
+                                        // Answered before the login is acknowledged, so it's there by the time they join
+                                        if transferred {
+                                            let _ = player.lock().unwrap().request_cookie(cookie::LAST_POSITION);
+                                        }
+                                        connection.set_max_packet_size(config.max_packet_size.play);
+                                        // Configuration starts once the client acknowledges the login
                                         loop {
-                                            let mut buf: [u8; 4096] = [0; 4096];
-                                            let _ = player.connection.read(&mut buf);
-                                            if buf[0] == 0 {
+                                            // Replies to the last packet go out together before waiting on the next
+                                            if player.lock().unwrap().flush().is_err() {
                                                 break;
                                             }
-                                            // debug!("{:?}", convert_buf_to_string(&buf));
+                                            let buf = match connection.read(&mut read_buffer) {
+                                                Ok(v) => v,
+                                                Err(e) => {
+                                                    debug!("Stopped reading from {}: {e}", connection.ip());
+                                                    break;
+                                                },
+                                            };
+                                            let packet_id = match ivar::read(&buf) {
+                                                Ok(v) => v,
+                                                Err(_) => break,
+                                            };
+                                            let body = &buf[packet_id.length()..];
+                                            let result = match (*connection.get_state(), packet_id.value) {
+                                                (ConnectionState::Login | ConnectionState::Transfer, 0x03) => acknowledge_configuration(connection, config, &player),
+                                                (ConnectionState::Login | ConnectionState::Transfer, 0x04) => play::cookie_response(server, &player, &mut PacketReader::new(body)),
+                                                (ConnectionState::Login | ConnectionState::Transfer, id) => {
+                                                    debug!("Unhandled login packet: {id:#x}");
+                                                    Ok(())
+                                                },
+                                                (ConnectionState::Configuration, 0x03) => finish_configuration(connection, server, &player),
+                                                (ConnectionState::Configuration, 0x00) => play::client_information(&player, &mut PacketReader::new(body)),
+                                                (ConnectionState::Configuration, 0x01) => play::cookie_response(server, &player, &mut PacketReader::new(body)),
+                                                (ConnectionState::Configuration, 0x06) => play::resource_pack_response(server, &player, &mut PacketReader::new(body)),
+                                                // Answers a keep alive sent before the player went back to configuration
+                                                (ConnectionState::Configuration, 0x04) => PacketReader::new(body).read_i64()
+                                                    .map(|id| player.lock().unwrap().handle_keep_alive(id)),
+                                                (ConnectionState::Configuration, id) => {
+                                                    debug!("Unhandled configuration packet: {id:#x}");
+                                                    Ok(())
+                                                },
+                                                (_, 0x0C) => acknowledge_configuration(connection, config, &player),
+                                                (_, id) => play::handle_packet(server, &player, id, body),
+                                            };
+                                            if let Err(e) = result {
+                                                // The player's writer is already compressing, so it sends the disconnect
+                                                error!("Protocol error from {} in {} packet {:#x}: {e}", connection.ip(), connection.get_state(), packet_id.value);
+                                                let _ = player.lock().unwrap().disconnect(&TextComponent::new(config.protocol_error_message.clone()));
+                                                break;
+                                            }
+                                            server.set_connection_state(address, *connection.get_state());
+                                        }
+                                        if player.lock().unwrap().is_transferring() {
+                                            info!("{} left after being transferred", player.lock().unwrap().name());
+                                        }
+                                        // A duplicate login may already have replaced this player
+                                        if server.get_player(uuid).is_some_and(|current| Arc::ptr_eq(&current, &player)) {
+                                            server.remove_player(uuid);
                                         }
                                     },
                                     Err(e) => error!("Error with login success!: {}", e),
                                 }
                                 break;
                             },
-                            Err(e) => error!("{e}"),
+                            Err(e) => {
+                                protocol_error(connection, config, e);
+                                break;
+                            },
                         };
                     },
-                    _ => unimplemented!(),
+                    _ => {
+                        protocol_error(connection, config, &format!("Unrecognised login packet {packet_id:#x}"));
+                        break;
+                    },
                 }
             },
             ConnectionState::Status => {
                 match packet_id {
                     0x00 => {
-                        status(&mut connection.get_stream());
+                        status(connection, server);
                     },
                     0x01 => {
-                        ping(&mut connection.get_stream(), &raw_buffer);
+                        // The client is done with the connection once it has its pong
+                        ping(connection, buf);
+                        let _ = connection.shutdown(Shutdown::Both, None);
+                        break;
                     }
-                    _ => unimplemented!(),
+                    _ => {
+                        protocol_error(connection, config, &format!("Unrecognised status packet {packet_id:#x}"));
+                        break;
+                    },
                 }
             },
-            _ => unimplemented!(),
+            state => {
+                let error = format!("Packet {packet_id:#x} in unexpected state {state}");
+                protocol_error(connection, config, &error);
+                break;
+            },
         }
+        server.set_connection_state(address, *connection.get_state());
     }
 }
 
-fn handshake(connection: &mut Connection, buffer: &[u8]) {
+fn handshake(connection: &mut Connection, buffer: &[u8], config: &ServerConfig) {
     match connection.get_stream().local_addr() {
         Ok(addr) => info!("Starting handshake with: {}", addr.ip()),
         Err(e) => {
@@ -198,47 +383,98 @@ fn handshake(connection: &mut Connection, buffer: &[u8]) {
         },
     }
 
-    let protocol_varint: ivar = match ivar::read(&buffer) {
-        Ok(e) => e,
+    let handshake = match handshake::parse(buffer) {
+        Ok(h) => h,
         Err(e) => {
-            error!("{buffer:?}\n{e:?}");
-            ivar::new(1000)
+            error!("{buffer:?}\n{e}");
+            let _ = connection.shutdown(std::net::Shutdown::Both, Some(e.to_string()));
+            return;
         },
     };
+    let state = handshake.next_state;
+    debug!("Protocl: {}", handshake.protocol_version);
+    connection.set_protocol_version(handshake.protocol_version);
 
-    let state: ConnectionState = ConnectionState::from_u8(*buffer.last().unwrap());
-    debug!("Protocl: {}", protocol_varint.value);
-    
-    match state {
-        ConnectionState::Unknown => {
-            let msg = match connection.get_stream().local_addr() {
-                Ok(addr) => String::from("Unknown handshake state!"),
-                Err(e) => format!("Could not get ip from client!\n{e:?}").to_string(),
-            };
-            let _ = connection.shutdown(std::net::Shutdown::Both, Some(msg));
+    if let Some(loader) = handshake.mod_loader {
+        info!("{} connected to {} with modloader marker {}", connection.ip(), handshake.server_address, loader.marker());
+        let logging_in = matches!(state, ConnectionState::Login | ConnectionState::Transfer);
+        if logging_in && config.mod_loader_action == ModLoaderAction::Reject {
+            // Login disconnect can only be sent once the connection is in the Login state
+            if connection.set_state(state).is_ok() {
+                let _ = connection.send_packet(&packets::login::disconnect(&TextComponent::new(config.mod_loader_message.clone())));
+            }
+            let _ = connection.shutdown(Shutdown::Both, Some(format!("Rejected {loader:?} client")));
             return;
+        }
+    }
+
+    if matches!(state, ConnectionState::Login | ConnectionState::Transfer) && Version::from_protocol(handshake.protocol_version).is_none() {
+        match Version::known(handshake.protocol_version) {
+            Some(version) => info!("Refused {} using {version:?}, only its clientbound packets are translated", connection.ip()),
+            None => info!("Refused {} using unsupported protocol {}", connection.ip(), handshake.protocol_version),
+        }
+        if connection.set_state(state).is_ok() {
+            let _ = connection.send_packet(&packets::login::disconnect(&TextComponent::translate("multiplayer.disconnect.incompatible", vec![TextComponent::new(VERSION_NAME)])));
+        }
+        let _ = connection.shutdown(Shutdown::Both, Some("Unsupported protocol version".into()));
+        return;
+    }
+
+    if !config.is_allowed_address(connection.ip()) {
+        info!("Refused direct connection from {}, it isn't an allowed proxy", connection.ip());
+        if matches!(state, ConnectionState::Login | ConnectionState::Transfer) && connection.set_state(state).is_ok() {
+            let _ = connection.send_packet(&packets::login::disconnect(&TextComponent::new("This server can only be joined through its proxy")));
+        }
+        let _ = connection.shutdown(Shutdown::Both, Some("Not an allowed proxy".into()));
+        return;
+    }
+
+    match state {
+        ConnectionState::Transfer if !config.accept_transfers => {
+            info!("Refused transfer from {}, transfers are disabled", connection.ip());
+            if connection.set_state(state).is_ok() {
+                let _ = connection.send_packet(&packets::login::disconnect(&TextComponent::translate("multiplayer.disconnect.transfers_disabled", Vec::new())));
+            }
+            let _ = connection.shutdown(Shutdown::Both, Some("Transfers are disabled".into()));
+        },
+        ConnectionState::Status if !config.enable_status_ping => {
+            let _ = connection.shutdown(Shutdown::Both, Some("Status pings are disabled".into()));
+        },
+        _ => {
+            if connection.set_state(state).is_err() {
+                let _ = connection.shutdown(Shutdown::Both, Some("Illegal state transition".into()));
+            }
         },
-        _ => connection.set_state(state),
     }
 }
 
-fn status(stream: &mut TcpStream) {
-    let x = StatusResponse::new();
-    let packet_id = ivar::new(0).as_bytes();
+/// Disconnects the client over a malformed packet, the details are only logged server side
+fn protocol_error(connection: &mut Connection, config: &ServerConfig, error: &str) {
+    error!("Protocol error from {}: {error}", connection.ip());
+    let reason = TextComponent::new(config.protocol_error_message.clone());
+    if let Some(packet) = packets::disconnect(*connection.get_state(), &reason) {
+        let _ = connection.send_packet(&packet);
+    }
+    let _ = connection.shutdown(Shutdown::Both, Some(error.to_string()));
+}
+
+fn status(connection: &mut Connection, server: &Server) {
+    let x = StatusResponse::new(&server.config, server.player_count(), server.icon());
     let response_string = serde_json::to_string(&x).unwrap();
     let mut buffer: Vec<u8> = Vec::new();
-    
-    buffer.extend_from_slice(&packet_id);
+    write_ivar(&mut buffer, Packet::StatusResponse.id());
     write_utf8_string(&mut buffer, response_string);
-
-    let length = ivar::new(buffer.len() as i32).as_bytes();
-    let _ = stream.write_all(&length);
-    let _ = stream.write_all(&buffer);
+    let _ = connection.send_packet(&buffer);
 }
 
-fn ping(stream: &mut TcpStream, data: &[u8]) {
-    debug!("{data:?}");
-    let _ = stream.write_all(data);
+fn ping(connection: &mut Connection, buffer: &[u8]) {
+    if buffer.len() != 8 {
+        error!("Invalid ping request, expected an 8 byte payload but got {}", buffer.len());
+        return;
+    }
+    let payload = PacketReader::new(buffer).read_i64().unwrap();
+    debug!("Ping {payload}");
+    let _ = connection.send_packet(&packets::status::pong_response(payload));
 }
 
 
@@ -246,29 +482,74 @@ fn ping(stream: &mut TcpStream, data: &[u8]) {
 fn login(buffer: &[u8]) -> Result<(String, u128), &'static str>{
     // Login Start Packet 
     // 0x00 Login Name (string 16) Player UUID (u128)
-    let string_ivar = ivar::read(buffer).unwrap();
+    let string_ivar = match ivar::read(buffer) {
+        Ok(v) => v,
+        Err(_) => return Err("Could not read login name length"),
+    };
+    let name_end = string_ivar.length() + string_ivar.value.max(0) as usize;
+    if string_ivar.value < 0 || buffer.len() < name_end + std::mem::size_of::<u128>() {
+        return Err("Login start packet too short");
+    }
     
-    let player_name_bytes = &buffer[..=string_ivar.value as usize];
+    let player_name_bytes = &buffer[string_ivar.length()..name_end];
     let tmp_buf = buffer[buffer.len()-std::mem::size_of::<u128>()..].iter().map(|x| *x).collect::<Vec<u8>>();
     let byte_array: [u8; 16] = tmp_buf.try_into().unwrap();
 
     let player_name = convert_buf_to_string(player_name_bytes);
+    if !valid_player_name(&player_name) {
+        return Err("Invalid login name");
+    }
     let uuid = u128::from_be_bytes(byte_array);
     info!("Connecting: {} ({:#x})", player_name, uuid); 
     Ok((player_name, uuid))
 }
 
-fn login_success(connection: Connection, name: String, uuid: u128) -> Result<Player, &'static str>{
-   
+/// Vanilla names, at most 16 letters, digits or underscores
+fn valid_player_name(name: &str) -> bool {
+    !name.is_empty() && name.len() <= 16 && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn login_success(connection: &mut Connection, server: &Server, name: String, uuid: u128, config: &ServerConfig) -> Result<Player, &'static str>{
+    let existing = server.get_player(uuid).or_else(|| server.get_player_by_name(&name));
+    if let Some(existing) = existing {
+        match config.duplicate_login {
+            DuplicateLoginAction::RejectNew => {
+                info!("Rejected {name}, they are already online");
+                let _ = connection.send_packet(&packets::login::disconnect(&TextComponent::new("You are already logged in")));
+                return Err("Duplicate login");
+            },
+            DuplicateLoginAction::KickExisting => {
+                let existing_uuid = {
+                    let mut existing = existing.lock().unwrap();
+                    let _ = existing.disconnect(&TextComponent::translate("multiplayer.disconnect.duplicate_login", Vec::new()));
+                    existing.uuid()
+                };
+                server.remove_player(existing_uuid);
+            },
+        }
+    }
+
+    if let Some(compression) = PacketCompression::new(config.network_compression_threshold, config.compression_level) {
+        // Set Compression itself is the last uncompressed packet
+        connection.send_packet(&packets::login::set_compression(config.network_compression_threshold)).map_err(|_| "Could not send set compression")?;
+        connection.set_compression(Some(compression));
+    }
+
     debug!("Constructing login success packet");
-    let mut player = Player::new(connection, name, uuid);
+    let stream = match connection.try_clone_stream() {
+        Ok(s) => s,
+        Err(_) => return Err("Could not clone the connection stream"),
+    };
+    let profile = login_profile(server, &name, uuid, config);
+    let mut player = Player::new(stream, name, uuid, server.next_entity_id(), config);
+    player.set_protocol_version(connection.protocol_version());
+    player.set_compression(connection.compression());
 
-    let packet_id = ivar::new(0x02).as_bytes();
+    let packet_id = ivar::new(Packet::LoginSuccess.id()).as_bytes();
     let uuid = player.uuid().to_be_bytes();
     let name = player.name();
-    let name = name.as_bytes();
 
-    let num_of_properties = ivar::new(0).as_bytes();
+    let num_of_properties = ivar::new(profile.properties.len() as i32).as_bytes();
     let property: [u8; 0] = [];
     let error_handling: bool = true;
 
@@ -276,62 +557,127 @@ fn login_success(connection: Connection, name: String, uuid: u128) -> Result<Pla
 
     bytes.extend_from_slice(&packet_id);
     bytes.extend_from_slice(&uuid);
-    bytes.extend_from_slice(&name);
+    write_utf8_string(&mut bytes, name);
     bytes.extend_from_slice(&num_of_properties);
+    for property in profile.properties {
+        write_utf8_string(&mut bytes, property.name);
+        write_utf8_string(&mut bytes, property.value);
+        bytes.push(property.signature.is_some() as u8);
+        if let Some(signature) = property.signature {
+            write_utf8_string(&mut bytes, signature);
+        }
+    }
     bytes.push(0x1);
 
 
-    let mut new_bytes: Vec<u8> = Vec::new();
-    new_bytes.extend_from_slice(&ivar::new(bytes.len() as i32).as_bytes());
-
-    new_bytes.extend_from_slice(&bytes);
-
-    debug!("Writing packet\n {:?}", new_bytes);
-    player.connection.get_stream().write_all(&new_bytes);
+    // Anything the connection still has buffered has to arrive before the player's packets
+    connection.flush().map_err(|_| "Could not flush the connection")?;
+    debug!("Writing packet\n {:?}", bytes);
+    player.send_packet(&bytes).map_err(|_| "Could not send login success")?;
     debug!("Sent packets!");
     Ok(player)
 }
 
-fn registry_data(player: &mut Player) {
-    // https://wiki.vg/Protocol#Registry_Data
-    // https://gist.github.com/WinX64/ab8c7a8df797c273b32d3a3b66522906
-    
-    let biome = Biome::default();
-    let entry = RegistryEntry {
-        entry_id: "minecraft:plains".into(),
-        has_data: true,
-        data: Some(fastnbt::to_bytes_with_opts(&biome, SerOpts::network_nbt()).unwrap()),
+/// The profile sent in Login Success, with the player's real skin if `fetch_skins` is on and Mojang knows the uuid
+fn login_profile(server: &Server, name: &str, uuid: u128, config: &ServerConfig) -> GameProfile {
+    let offline = GameProfile { uuid, name: name.to_string(), properties: Vec::new() };
+    if !config.fetch_skins {
+        server.profiles().insert(offline.clone(), Instant::now());
+        return offline;
+    }
+    // Looked up without holding the cache so a slow session server can't stall the tick
+    let cached = server.profiles().by_uuid(uuid, Instant::now()).cloned();
+    let properties = match cached {
+        Some(profile) => profile.properties,
+        None => match auth::fetch_profile(uuid) {
+            Ok(profile) => {
+                if let Some(skin) = auth::textures(&profile).and_then(|t| t.skin) {
+                    debug!("{name} has the skin {} ({})", skin.url, skin.metadata.map_or("classic".to_string(), |m| m.model));
+                }
+                profile.properties
+            },
+            Err(e) => {
+                warn!("Could not look up {name}'s skin: {e}");
+                Vec::new()
+            },
+        },
     };
-
-    let reg = construct_registry_packet("minecraft:worldgen/biome".into(), vec!(entry));
-    debug!("{}", reg.len());
-    send_registry_packet(player.connection.get_stream(), &reg);
+    // The profile is cached under the name they logged in with, offline names needn't match the account
+    let profile = GameProfile { properties, ..offline };
+    server.profiles().insert(profile.clone(), Instant::now());
+    profile
 }
 
-fn clientbound_pack(player: &mut Player) {
-    let mut buf: Vec<u8> = Vec::new();
-    buf.push(0x0E);
-    write_ivar(&mut buf, 0x01);
-    // let x = vec!("minecraft".as_bytes(), "core".as_bytes(), "1.21".as_bytes());
-    buf.extend_from_slice(&"minecraft".as_bytes());
-    buf.extend_from_slice(&"core".as_bytes());
-    buf.extend_from_slice(&"1.21".as_bytes());
-    // buf.extend_from_slice(&x); 
-    debug!("Client bound: {buf:?}");
-    send_buffer(&player.connection.get_stream(), &buf);
+/// Everything the client needs before it can enter Play, sent on login and again when reconfiguring
+fn send_configuration(player: &mut Player, config: &ServerConfig) {
+    let _ = player.send_packet(&packets::configuration::feature_flags(&["minecraft:vanilla"]));
+    let _ = player.send_packet(&packets::configuration::known_packs(&[("minecraft", "core", "1.21")]));
+    registry_data(player, config);
+    let _ = player.send_packet(&packets::configuration::update_tags(&registry::tags::core_groups()));
+    if !config.server_links.is_empty() {
+        let links: Vec<(ServerLinkLabel, String)> = config.server_links.iter()
+            .map(|(label, url)| (ServerLinkLabel::parse(label), url.clone()))
+            .collect();
+        let _ = player.send_packet(&packets::configuration::server_links(&links));
+    }
+    if let Some(Ok(pack)) = config.server_resource_pack() {
+        let _ = player.add_resource_pack(pack);
+    }
 }
 
-fn send_buffer(mut stream: &TcpStream, buffer: &[u8]) {
-    send_length(stream, buffer);
-    let _ = stream.write_all(&buffer);
+/// The client has acknowledged Login Success or left Play after a Start Configuration,
+/// configure it then send it on to Play
+fn acknowledge_configuration(connection: &mut Connection, config: &ServerConfig, player: &Arc<Mutex<Player>>) -> Result<(), &'static str> {
+    connection.set_state(ConnectionState::Configuration).map_err(|_| "Illegal state transition")?;
+    let mut player = player.lock().unwrap();
+    player.set_state(ConnectionState::Configuration);
+    send_configuration(&mut player, config);
+    let _ = player.send_packet(&packets::configuration::finish_configuration());
+    Ok(())
 }
 
-fn send_length(mut stream: &TcpStream, buffer: &[u8]) {
-    let length = ivar::new(buffer.len() as i32);
-    let _ = stream.write_all(&length.as_bytes());
+/// Only the first time the player enters play is announced, later reconfigurations are not
+fn finish_configuration(connection: &mut Connection, server: &Server, player: &Arc<Mutex<Player>>) -> Result<(), &'static str> {
+    connection.set_state(ConnectionState::Play).map_err(|_| "Illegal state transition")?;
+    let motd = Motd::new(&server.config.motd, MotdPlaceholders {
+        online: server.player_count(),
+        max: server.config.max_players,
+        version: VERSION_NAME,
+    });
+    let announced = {
+        let mut player = player.lock().unwrap();
+        player.set_state(ConnectionState::Play);
+        let _ = play::join(server, &mut player, &motd);
+        player.is_announced()
+    };
+    if !announced {
+        play::announce_join(server, player);
+    }
+    Ok(())
 }
 
-
+fn registry_data(player: &mut Player, config: &ServerConfig) {
+    // https://wiki.vg/Protocol#Registry_Data
+    // https://gist.github.com/WinX64/ab8c7a8df797c273b32d3a3b66522906
+    for registry_id in &config.registries {
+        // Unknown ids were already dropped when the config was loaded
+        let Some(entries) = registry::entries(registry_id, &config.world) else {
+            continue;
+        };
+        if log_enabled!(Level::Trace) {
+            for entry in &entries {
+                match entry.data.as_deref().map(nbt::pretty) {
+                    Some(Ok(tree)) => trace!("{registry_id} {}:\n{tree}", entry.entry_id),
+                    Some(Err(e)) => trace!("{registry_id} {} has malformed NBT: {e}", entry.entry_id),
+                    None => trace!("{registry_id} {} has no data", entry.entry_id),
+                }
+            }
+        }
+        let reg = construct_registry_packet(registry_id.clone(), entries);
+        debug!("{registry_id}: {}", reg.len());
+        let _ = player.send_packet(&reg);
+    }
+}
 
 impl fmt::Display for ConnectionState {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -340,6 +686,8 @@ impl fmt::Display for ConnectionState {
             Self::Status => write!(f, "Status"),
             Self::Login => write!(f, "Login"),
             Self::Transfer => write!(f, "Transfer"),
+            Self::Configuration => write!(f, "Configuration"),
+            Self::Play => write!(f, "Play"),
             Self::Unknown => write!(f, "Unknown"),
         }
     }
@@ -354,5 +702,375 @@ fn convert_buf_to_string(buff: &[u8]) -> String {
     constructed_string
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::tests::{drain, read_packet, socket_pair};
+
+    #[test]
+    fn login_acknowledged_moves_to_configuration() {
+        let config = ServerConfig::default();
+        let (server_side, client) = socket_pair();
+        let _received = drain(client);
+        let mut connection = Connection::new(server_side.try_clone().unwrap(), config.max_packet_size, config.write_buffer_size).unwrap();
+        connection.set_state(ConnectionState::Login).unwrap();
+        let player = Arc::new(Mutex::new(Player::new(server_side, "Steve".into(), 1, 1, &config)));
+
+        acknowledge_configuration(&mut connection, &config, &player).unwrap();
+        assert_eq!(*connection.get_state(), ConnectionState::Configuration);
+        assert_eq!(player.lock().unwrap().state(), ConnectionState::Configuration);
+    }
+
+    #[test]
+    fn login_protocol_errors_send_a_login_disconnect() {
+        let config = ServerConfig::default();
+        let (server_side, client) = socket_pair();
+        let received = drain(client);
+        let mut connection = Connection::new(server_side, config.max_packet_size, config.write_buffer_size).unwrap();
+        connection.set_state(ConnectionState::Login).unwrap();
+        protocol_error(&mut connection, &config, "Bad login start");
+
+        let frame = received.join().unwrap();
+        let length = ivar::read(&frame).unwrap();
+        assert_eq!(frame.len(), length.length() + length.value as usize);
+        assert_eq!(frame[length.length()] as i32, Packet::LoginDisconnect.id());
+        let reason = TextComponent::new(config.protocol_error_message.clone());
+        assert_eq!(&frame[length.length()..], packets::login::disconnect(&reason).as_slice());
+    }
+
+    #[test]
+    fn status_is_framed_uncompressed_even_with_a_favicon() {
+        let server = Server::new(ServerConfig::default());
+        assert!(server.icon().is_some());
+        let (server_side, client) = socket_pair();
+        let received = drain(client);
+        let mut connection = Connection::new(server_side, server.config.max_packet_size, server.config.write_buffer_size).unwrap();
+        connection.set_state(ConnectionState::Status).unwrap();
+        status(&mut connection, &server);
+        let _ = connection.shutdown(Shutdown::Both, None);
+
+        let frame = received.join().unwrap();
+        assert!(frame.len() > compression::DEFAULT_COMPRESSION_THRESHOLD as usize);
+        let length = ivar::read(&frame).unwrap();
+        assert_eq!(frame.len(), length.length() + length.value as usize);
+        // The packet id follows the length directly, there is no data length
+        assert_eq!(frame[length.length()] as i32, Packet::StatusResponse.id());
+        assert!(String::from_utf8_lossy(&frame).contains("data:image/png;base64,"));
+    }
+
+    #[test]
+    fn large_play_packets_are_compressed() {
+        let config = ServerConfig::default();
+        let (server_side, client) = socket_pair();
+        let received = drain(client);
+        let compression = PacketCompression::new(config.network_compression_threshold, config.compression_level).unwrap();
+        let mut player = Player::new(server_side, "Steve".into(), 1, 1, &config);
+        player.set_state(ConnectionState::Play);
+        player.set_compression(Some(compression));
+        let mut packet: Vec<u8> = Vec::new();
+        write_ivar(&mut packet, Packet::ChunkDataAndUpdateLight.id());
+        packet.extend_from_slice(&[0; 1000]);
+        player.send_packet(&packet).unwrap();
+        let _ = player.disconnect(&TextComponent::new("Done"));
+
+        let frame = received.join().unwrap();
+        let length = ivar::read(&frame).unwrap();
+        let body = &frame[length.length()..length.length() + length.value as usize];
+        let data_length = ivar::read(body).unwrap();
+        assert_eq!(data_length.value as usize, packet.len());
+        assert_eq!(compression.unframe(body, config.max_packet_size.play).unwrap(), packet);
+    }
+
+    fn handshake_body(protocol: i32, next_state: i32) -> Vec<u8> {
+        let mut buffer: Vec<u8> = Vec::new();
+        write_ivar(&mut buffer, protocol);
+        write_utf8_string(&mut buffer, "localhost".to_string());
+        buffer.extend_from_slice(&25565u16.to_be_bytes());
+        write_ivar(&mut buffer, next_state);
+        buffer
+    }
+
+    #[test]
+    fn status_handshakes_are_closed_when_pings_are_disabled() {
+        let config = ServerConfig { enable_status_ping: false, ..ServerConfig::default() };
+        let (server_side, client) = socket_pair();
+        let received = drain(client);
+        let mut connection = Connection::new(server_side, config.max_packet_size, config.write_buffer_size).unwrap();
+        handshake(&mut connection, &handshake_body(767, 1), &config);
+        assert_eq!(*connection.get_state(), ConnectionState::Handshake);
+        assert!(received.join().unwrap().is_empty());
+    }
+
+    #[test]
+    fn status_handshakes_move_to_status_when_pings_are_enabled() {
+        let config = ServerConfig::default();
+        let (server_side, _client) = socket_pair();
+        let mut connection = Connection::new(server_side, config.max_packet_size, config.write_buffer_size).unwrap();
+        handshake(&mut connection, &handshake_body(767, 1), &config);
+        assert_eq!(*connection.get_state(), ConnectionState::Status);
+    }
+
+    #[test]
+    fn transfers_are_refused_when_disabled() {
+        let config = ServerConfig { accept_transfers: false, ..ServerConfig::default() };
+        let (server_side, mut client) = socket_pair();
+        let mut connection = Connection::new(server_side, config.max_packet_size, config.write_buffer_size).unwrap();
+        handshake(&mut connection, &handshake_body(767, 3), &config);
+        let reason = TextComponent::translate("multiplayer.disconnect.transfers_disabled", Vec::new());
+        assert_eq!(read_packet(&mut client), packets::login::disconnect(&reason));
+    }
+
+    #[test]
+    fn direct_logins_are_refused_when_proxy_only() {
+        let config = ServerConfig { proxy_only: true, allowed_proxies: vec!["10.0.0.2".parse().unwrap()], ..ServerConfig::default() };
+        let (server_side, mut client) = socket_pair();
+        let mut connection = Connection::new(server_side, config.max_packet_size, config.write_buffer_size).unwrap();
+        handshake(&mut connection, &handshake_body(767, 2), &config);
+        let reason = TextComponent::new("This server can only be joined through its proxy");
+        assert_eq!(read_packet(&mut client), packets::login::disconnect(&reason));
+
+        let config = ServerConfig { allowed_proxies: vec!["127.0.0.1".parse().unwrap()], ..config };
+        let (server_side, _client) = socket_pair();
+        let mut connection = Connection::new(server_side, config.max_packet_size, config.write_buffer_size).unwrap();
+        handshake(&mut connection, &handshake_body(767, 2), &config);
+        assert_eq!(*connection.get_state(), ConnectionState::Login);
+    }
+
+    #[test]
+    fn transfers_are_accepted_when_enabled() {
+        let config = ServerConfig { accept_transfers: true, ..ServerConfig::default() };
+        let (server_side, _client) = socket_pair();
+        let mut connection = Connection::new(server_side, config.max_packet_size, config.write_buffer_size).unwrap();
+        handshake(&mut connection, &handshake_body(767, 3), &config);
+        assert_eq!(*connection.get_state(), ConnectionState::Transfer);
+    }
+
+    #[test]
+    fn pings_get_a_framed_pong_with_the_same_payload() {
+        let config = ServerConfig::default();
+        let (server_side, client) = socket_pair();
+        let received = drain(client);
+        let mut connection = Connection::new(server_side, config.max_packet_size, config.write_buffer_size).unwrap();
+        connection.set_state(ConnectionState::Status).unwrap();
+        ping(&mut connection, &12345i64.to_be_bytes());
+        let _ = connection.shutdown(Shutdown::Both, None);
+
+        let frame = received.join().unwrap();
+        let length = ivar::read(&frame).unwrap();
+        assert_eq!(frame.len(), length.length() + length.value as usize);
+        let mut reader = PacketReader::new(&frame[length.length()..]);
+        assert_eq!(reader.read_ivar().unwrap(), Packet::PongResponse.id());
+        assert_eq!(reader.read_i64().unwrap(), 12345);
+        assert_eq!(reader.remaining(), 0);
+    }
+
+    #[test]
+    fn pong_matches_a_hand_built_frame() {
+        let config = ServerConfig::default();
+        let (server_side, client) = socket_pair();
+        let received = drain(client);
+        let mut connection = Connection::new(server_side, config.max_packet_size, config.write_buffer_size).unwrap();
+        connection.set_state(ConnectionState::Status).unwrap();
+        let payload = 0x0102030405060708i64;
+        ping(&mut connection, &payload.to_be_bytes());
+        let _ = connection.shutdown(Shutdown::Both, None);
+
+        let mut expected = vec![9, 0x01];
+        expected.extend_from_slice(&payload.to_be_bytes());
+        assert_eq!(received.join().unwrap(), expected);
+    }
+
+    #[test]
+    fn pings_with_a_bad_payload_length_get_no_pong() {
+        let config = ServerConfig::default();
+        let (server_side, client) = socket_pair();
+        let received = drain(client);
+        let mut connection = Connection::new(server_side, config.max_packet_size, config.write_buffer_size).unwrap();
+        connection.set_state(ConnectionState::Status).unwrap();
+        ping(&mut connection, &[0; 12]);
+        let _ = connection.shutdown(Shutdown::Both, None);
+        assert!(received.join().unwrap().is_empty());
+    }
+
+    #[test]
+    fn acknowledging_configuration_returns_to_configuration() {
+        let config = ServerConfig::default();
+        let (server_side, _client) = socket_pair();
+        let mut connection = Connection::new(server_side.try_clone().unwrap(), config.max_packet_size, config.write_buffer_size).unwrap();
+        connection.set_state(ConnectionState::Login).unwrap();
+        connection.set_state(ConnectionState::Configuration).unwrap();
+        connection.set_state(ConnectionState::Play).unwrap();
+        let player = Arc::new(Mutex::new(Player::new(server_side, "Steve".into(), 1, 1, &config)));
+        player.lock().unwrap().set_state(ConnectionState::Play);
+
+        acknowledge_configuration(&mut connection, &config, &player).unwrap();
+        assert_eq!(*connection.get_state(), ConnectionState::Configuration);
+        assert_eq!(player.lock().unwrap().state(), ConnectionState::Configuration);
+    }
+
+    #[test]
+    fn duplicate_logins_can_be_rejected() {
+        let config = ServerConfig { duplicate_login: DuplicateLoginAction::RejectNew, ..ServerConfig::default() };
+        let server = Server::new(config.clone());
+        let (existing, _existing_client) = crate::server::tests::join(&server, "Steve", 1);
+        let (server_side, client) = socket_pair();
+        let received = drain(client);
+        let mut connection = Connection::new(server_side, config.max_packet_size, config.write_buffer_size).unwrap();
+        connection.set_state(ConnectionState::Login).unwrap();
+
+        assert!(login_success(&mut connection, &server, "Steve".into(), 1, &config).is_err());
+        let _ = connection.shutdown(Shutdown::Both, None);
+        let frame = received.join().unwrap();
+        let length = ivar::read(&frame).unwrap();
+        assert_eq!(&frame[length.length()..], packets::login::disconnect(&TextComponent::new("You are already logged in")).as_slice());
+        assert!(server.get_player(1).is_some_and(|player| Arc::ptr_eq(&player, &existing)));
+    }
+
+    #[test]
+    fn duplicate_logins_can_kick_the_existing_player() {
+        let config = ServerConfig { duplicate_login: DuplicateLoginAction::KickExisting, ..ServerConfig::default() };
+        let server = Server::new(config.clone());
+        let (_existing, mut existing_client) = crate::server::tests::join(&server, "Steve", 1);
+        let (server_side, _client) = socket_pair();
+        let mut connection = Connection::new(server_side, config.max_packet_size, config.write_buffer_size).unwrap();
+        connection.set_state(ConnectionState::Login).unwrap();
+
+        let player = login_success(&mut connection, &server, "Steve".into(), 1, &config).unwrap();
+        assert_eq!(player.uuid(), 1);
+        assert!(server.get_player(1).is_none());
+        let reason = TextComponent::translate("multiplayer.disconnect.duplicate_login", Vec::new());
+        assert_eq!(read_packet(&mut existing_client), packets::play::disconnect(&reason));
+    }
+
+    #[test]
+    fn trimmed_registry_lists_leave_the_others_out() {
+        let config = ServerConfig {
+            registries: vec!["minecraft:damage_type".into(), "minecraft:dimension_type".into()],
+            ..ServerConfig::default()
+        };
+        let (server_side, mut client) = socket_pair();
+        let mut player = Player::new(server_side, "Steve".into(), 1, 1, &config);
+        player.set_state(ConnectionState::Configuration);
+        registry_data(&mut player, &config);
+        player.send_packet(&packets::configuration::finish_configuration()).unwrap();
+        player.flush().unwrap();
+
+        let mut sent = Vec::new();
+        loop {
+            let packet = read_packet(&mut client);
+            let mut reader = PacketReader::new(&packet);
+            if reader.read_ivar().unwrap() != Packet::RegistryData.id() {
+                break;
+            }
+            sent.push(reader.read_utf8_string(32767).unwrap());
+        }
+        assert_eq!(sent, config.registries);
+    }
+
+    #[test]
+    fn accept_backoff_doubles_up_to_a_second() {
+        assert_eq!(accept_backoff(1), Duration::from_millis(10));
+        assert_eq!(accept_backoff(2), Duration::from_millis(20));
+        assert_eq!(accept_backoff(7), Duration::from_millis(640));
+        assert_eq!(accept_backoff(8), MAX_ACCEPT_BACKOFF);
+        assert_eq!(accept_backoff(u32::MAX), MAX_ACCEPT_BACKOFF);
+    }
+
+    #[test]
+    fn repeated_accept_errors_do_not_busy_loop() {
+        let server = Arc::new(Server::new(ServerConfig::default()));
+        let errors = (0..5).map(|_| Err(std::io::Error::from_raw_os_error(24)));
+        let start = std::time::Instant::now();
+        assert!(accept_connections(errors, &server).is_empty());
+        // 10 + 20 + 40 + 80 + 160ms spent waiting rather than retrying straight away
+        assert!(start.elapsed() >= Duration::from_millis(310));
+    }
+
+    #[test]
+    fn accept_errors_are_logged_as_errors() {
+        let server = Arc::new(Server::new(ServerConfig::default()));
+        let logged = logging::tests::capture(|| {
+            accept_connections(std::iter::once(Err(std::io::Error::from_raw_os_error(24))), &server);
+        });
+        assert!(logged.iter().any(|line| line.starts_with("ERROR") && line.contains("Could not accept a connection")));
+    }
+
+    #[test]
+    fn transfer_handshakes_log_in_like_logins() {
+        let server = Arc::new(Server::new(ServerConfig { network_compression_threshold: -1, ..ServerConfig::default() }));
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let connection_server = Arc::clone(&server);
+        let handle = thread::spawn(move || start_connection(listener.accept().unwrap().0, connection_server));
+
+        let mut client = TcpStream::connect(address).unwrap();
+        let mut handshake = vec![0x00];
+        handshake.extend_from_slice(&handshake_body(767, 3));
+        utils::write_packet(&mut client, &handshake).unwrap();
+        let mut login_start = vec![0x00];
+        write_utf8_string(&mut login_start, "Steve".to_string());
+        login_start.extend_from_slice(&7u128.to_be_bytes());
+        utils::write_packet(&mut client, &login_start).unwrap();
+
+        let packet = read_packet(&mut client);
+        assert_eq!(packet[0] as i32, Packet::LoginSuccess.id());
+        assert!(server.get_player(7).is_some());
+        client.shutdown(Shutdown::Both).unwrap();
+        handle.join().unwrap();
+    }
 
+    fn login_start(name: &str) -> Vec<u8> {
+        let mut buffer: Vec<u8> = Vec::new();
+        write_utf8_string(&mut buffer, name.to_string());
+        buffer.extend_from_slice(&7u128.to_be_bytes());
+        buffer
+    }
+
+    #[test]
+    fn login_names_must_be_vanilla_names() {
+        assert_eq!(login(&login_start("Steve_123")), Ok(("Steve_123".to_string(), 7)));
+        assert_eq!(login(&login_start("Sixteen_Letters_")).unwrap().0, "Sixteen_Letters_");
+        assert!(login(&login_start("Seventeen_Letters")).is_err());
+        assert!(login(&login_start("")).is_err());
+        assert!(login(&login_start("Steve Jobs")).is_err());
+        assert!(login(&login_start("Stéve")).is_err());
+    }
+
+    /// Runs a connection on its own thread, true if it ended without panicking
+    fn connection_survives(frames: &[&[u8]]) -> bool {
+        let server = Arc::new(Server::new(ServerConfig::default()));
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let handle = thread::spawn(move || start_connection(listener.accept().unwrap().0, server));
+        let mut client = TcpStream::connect(address).unwrap();
+        for frame in frames {
+            let _ = client.write_all(frame);
+        }
+        handle.join().is_ok()
+    }
 
+    #[test]
+    fn unknown_status_packets_close_the_connection() {
+        let mut handshake = vec![0x00];
+        handshake.extend_from_slice(&handshake_body(767, 1));
+        let mut frame: Vec<u8> = Vec::new();
+        utils::write_packet(&mut frame, &handshake).unwrap();
+        assert!(connection_survives(&[&frame, &[1, 0x05]]));
+    }
+
+    #[test]
+    fn unreadable_packet_ids_close_the_connection() {
+        assert!(connection_survives(&[&[1, 0x80]]));
+    }
+
+    #[test]
+    fn newer_known_versions_are_refused_by_name() {
+        let config = ServerConfig::default();
+        let (server_side, mut client) = socket_pair();
+        let mut connection = Connection::new(server_side, config.max_packet_size, config.write_buffer_size).unwrap();
+        let lines = logging::tests::capture(|| handshake(&mut connection, &handshake_body(768, 2), &config));
+        let reason = TextComponent::translate("multiplayer.disconnect.incompatible", vec![TextComponent::new(VERSION_NAME)]);
+        assert_eq!(read_packet(&mut client), packets::login::disconnect(&reason));
+        assert!(lines.iter().any(|line| line.contains("using V1_21_2")));
+    }
+}
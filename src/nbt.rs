@@ -0,0 +1,89 @@
+// Readable dumps of network NBT, for checking what registry data actually encodes to
+
+use std::fmt::Write;
+
+use fastnbt::{DeOpts, Value};
+
+/// Parses network NBT (a nameless root compound) into an indented tree, one tag per line
+pub fn pretty(data: &[u8]) -> Result<String, fastnbt::error::Error> {
+    let value: Value = fastnbt::from_bytes_with_opts(data, DeOpts::network_nbt())?;
+    let mut output = String::new();
+    write_value(&mut output, &value, 0);
+    Ok(output)
+}
+
+fn write_value(output: &mut String, value: &Value, depth: usize) {
+    let indent = "  ".repeat(depth);
+    let _ = match value {
+        Value::Byte(v) => write!(output, "{v}b"),
+        Value::Short(v) => write!(output, "{v}s"),
+        Value::Int(v) => write!(output, "{v}"),
+        Value::Long(v) => write!(output, "{v}L"),
+        Value::Float(v) => write!(output, "{v}f"),
+        Value::Double(v) => write!(output, "{v}d"),
+        Value::String(v) => write!(output, "{v:?}"),
+        Value::ByteArray(v) => write!(output, "[B; {}]", v.iter().map(|b| b.to_string()).collect::<Vec<_>>().join(", ")),
+        Value::IntArray(v) => write!(output, "[I; {}]", v.iter().map(|i| i.to_string()).collect::<Vec<_>>().join(", ")),
+        Value::LongArray(v) => write!(output, "[L; {}]", v.iter().map(|l| l.to_string()).collect::<Vec<_>>().join(", ")),
+        Value::List(values) => {
+            output.push_str("[\n");
+            for value in values {
+                output.push_str(&indent);
+                output.push_str("  ");
+                write_value(output, value, depth + 1);
+                output.push('\n');
+            }
+            write!(output, "{indent}]")
+        },
+        Value::Compound(tags) => {
+            // Sorted so the same data always prints the same way
+            let mut names: Vec<&String> = tags.keys().collect();
+            names.sort();
+            output.push_str("{\n");
+            for name in names {
+                let _ = write!(output, "{indent}  {name}: ");
+                write_value(output, &tags[name], depth + 1);
+                output.push('\n');
+            }
+            write!(output, "{indent}}}")
+        },
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn named(tag: u8, name: &str) -> Vec<u8> {
+        let mut buffer = vec![tag];
+        buffer.extend_from_slice(&(name.len() as u16).to_be_bytes());
+        buffer.extend_from_slice(name.as_bytes());
+        buffer
+    }
+
+    #[test]
+    fn compounds_print_sorted_and_indented() {
+        // Network NBT root compound, the root has no name
+        let mut data = vec![0x0A];
+        data.append(&mut named(0x03, "height"));
+        data.extend_from_slice(&384i32.to_be_bytes());
+        data.append(&mut named(0x08, "name"));
+        data.extend_from_slice(&9u16.to_be_bytes());
+        data.extend_from_slice(b"overworld");
+        data.append(&mut named(0x01, "has_skylight"));
+        data.push(1);
+        data.append(&mut named(0x0A, "effect"));
+        data.append(&mut named(0x05, "scale"));
+        data.extend_from_slice(&1.0f32.to_be_bytes());
+        data.push(0x00);
+        data.push(0x00);
+
+        let expected = "{\n  effect: {\n    scale: 1f\n  }\n  has_skylight: 1b\n  height: 384\n  name: \"overworld\"\n}";
+        assert_eq!(pretty(&data).unwrap(), expected);
+    }
+
+    #[test]
+    fn truncated_data_is_an_error() {
+        assert!(pretty(&[0x0A, 0x03, 0x00]).is_err());
+    }
+}
@@ -0,0 +1,100 @@
+// Clientbound packets for the Configuration state
+// https://wiki.vg/Protocol#Configuration
+
+use crate::{packets::{play::write_optional_uuid, write_server_links, ServerLinkLabel}, protocol::Packet, registry::tags::TagGroup, resource_pack::ResourcePack, types::text_component::TextComponent, utils::{write_ivar, write_utf8_string}};
+
+pub fn disconnect(reason: &TextComponent) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::ConfigurationDisconnect.id());
+    buffer.append(&mut reason.to_nbt_bytes());
+    buffer
+}
+
+pub fn cookie_request(key: &str) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::ConfigurationCookieRequest.id());
+    write_utf8_string(&mut buffer, key.to_string());
+    buffer
+}
+
+pub fn add_resource_pack(pack: &ResourcePack) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::ConfigurationAddResourcePack.id());
+    buffer.append(&mut pack.as_bytes());
+    buffer
+}
+
+pub fn remove_resource_pack(uuid: Option<u128>) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::ConfigurationRemoveResourcePack.id());
+    write_optional_uuid(&mut buffer, uuid);
+    buffer
+}
+
+/// Tells the client configuration is done, it moves to Play once it acknowledges this
+pub fn finish_configuration() -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::FinishConfiguration.id());
+    buffer
+}
+
+pub fn update_tags(groups: &[TagGroup]) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::ConfigurationUpdateTags.id());
+    write_ivar(&mut buffer, groups.len() as i32);
+    for group in groups {
+        buffer.append(&mut group.as_bytes());
+    }
+    buffer
+}
+
+pub fn feature_flags(flags: &[&str]) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::FeatureFlags.id());
+    write_ivar(&mut buffer, flags.len() as i32);
+    for flag in flags {
+        write_utf8_string(&mut buffer, flag.to_string());
+    }
+    buffer
+}
+
+/// Data packs the server uses as (namespace, id, version), the client replies with the ones it also has
+pub fn known_packs(packs: &[(&str, &str, &str)]) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::KnownPacks.id());
+    write_ivar(&mut buffer, packs.len() as i32);
+    for (namespace, id, version) in packs {
+        write_utf8_string(&mut buffer, namespace.to_string());
+        write_utf8_string(&mut buffer, id.to_string());
+        write_utf8_string(&mut buffer, version.to_string());
+    }
+    buffer
+}
+
+pub fn server_links(links: &[(ServerLinkLabel, String)]) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::ConfigurationServerLinks.id());
+    write_server_links(&mut buffer, links);
+    buffer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_tags_are_a_zero_count() {
+        assert_eq!(update_tags(&[]), vec![Packet::ConfigurationUpdateTags.id() as u8, 0]);
+    }
+
+    #[test]
+    fn feature_flags_are_a_counted_list_of_strings() {
+        let mut expected = vec![Packet::FeatureFlags.id() as u8, 1];
+        write_utf8_string(&mut expected, "minecraft:vanilla".into());
+        assert_eq!(feature_flags(&["minecraft:vanilla"]), expected);
+
+        expected[1] = 2;
+        write_utf8_string(&mut expected, "minecraft:trade_rebalance".into());
+        assert_eq!(feature_flags(&["minecraft:vanilla", "minecraft:trade_rebalance"]), expected);
+    }
+}
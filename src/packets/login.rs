@@ -0,0 +1,28 @@
+// Clientbound packets for the Login state
+// https://wiki.vg/Protocol#Login
+
+use crate::{protocol::Packet, types::text_component::TextComponent, utils::{write_ivar, write_utf8_string}};
+
+pub fn disconnect(reason: &TextComponent) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::LoginDisconnect.id());
+    write_utf8_string(&mut buffer, reason.to_json());
+    buffer
+}
+
+/// Every packet after this one is framed with compression, see `compression::PacketCompression`.
+/// A negative threshold turns compression off.
+pub fn set_compression(threshold: i32) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::SetCompression.id());
+    write_ivar(&mut buffer, threshold);
+    buffer
+}
+
+/// Only sent after Login Success, the client may have cookies from the server that transferred it
+pub fn cookie_request(key: &str) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::LoginCookieRequest.id());
+    write_utf8_string(&mut buffer, key.to_string());
+    buffer
+}
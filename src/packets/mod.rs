@@ -0,0 +1,117 @@
+pub mod configuration;
+pub mod login;
+pub mod play;
+pub mod status;
+
+use crate::{connection::ConnectionState, types::text_component::TextComponent, utils::{write_ivar, write_utf8_string}};
+
+/// Disconnect packet of the state, None before Login where there isn't one
+pub fn disconnect(state: ConnectionState, reason: &TextComponent) -> Option<Vec<u8>> {
+    match state {
+        ConnectionState::Login | ConnectionState::Transfer => Some(login::disconnect(reason)),
+        ConnectionState::Configuration => Some(configuration::disconnect(reason)),
+        ConnectionState::Play => Some(play::disconnect(reason)),
+        ConnectionState::Handshake | ConnectionState::Status | ConnectionState::Unknown => None,
+    }
+}
+
+/// Label of a link shown in the pause menu, either one the client translates itself or custom text
+// https://wiki.vg/Protocol#Server_Links
+#[derive(Debug, Clone)]
+pub enum ServerLinkLabel {
+    BugReport,
+    CommunityGuidelines,
+    Support,
+    Status,
+    Feedback,
+    Community,
+    Website,
+    Forums,
+    News,
+    Announcements,
+    Custom(TextComponent),
+}
+
+impl ServerLinkLabel {
+    /// Built in labels by their snake case name, anything else becomes custom text
+    pub fn parse(label: &str) -> Self {
+        match label {
+            "bug_report" => Self::BugReport,
+            "community_guidelines" => Self::CommunityGuidelines,
+            "support" => Self::Support,
+            "status" => Self::Status,
+            "feedback" => Self::Feedback,
+            "community" => Self::Community,
+            "website" => Self::Website,
+            "forums" => Self::Forums,
+            "news" => Self::News,
+            "announcements" => Self::Announcements,
+            custom => Self::Custom(TextComponent::new(custom.to_string())),
+        }
+    }
+
+    /// Id of a built in label, custom labels are sent as text instead
+    fn builtin_id(&self) -> i32 {
+        match self {
+            Self::BugReport => 0,
+            Self::CommunityGuidelines => 1,
+            Self::Support => 2,
+            Self::Status => 3,
+            Self::Feedback => 4,
+            Self::Community => 5,
+            Self::Website => 6,
+            Self::Forums => 7,
+            Self::News => 8,
+            Self::Announcements => 9,
+            Self::Custom(_) => -1,
+        }
+    }
+}
+
+pub fn write_server_links(buffer: &mut Vec<u8>, links: &[(ServerLinkLabel, String)]) {
+    write_ivar(buffer, links.len() as i32);
+    for (label, url) in links {
+        match label {
+            ServerLinkLabel::Custom(text) => {
+                buffer.push(0);
+                buffer.append(&mut text.to_nbt_bytes());
+            },
+            builtin => {
+                buffer.push(1);
+                write_ivar(buffer, builtin.builtin_id());
+            },
+        }
+        write_utf8_string(buffer, url.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::Packet;
+
+    #[test]
+    fn disconnect_matches_the_state() {
+        let reason = TextComponent::new("Bye".to_string());
+        assert_eq!(disconnect(ConnectionState::Login, &reason), Some(login::disconnect(&reason)));
+        assert_eq!(disconnect(ConnectionState::Configuration, &reason), Some(configuration::disconnect(&reason)));
+        assert_eq!(disconnect(ConnectionState::Play, &reason), Some(play::disconnect(&reason)));
+        assert_eq!(disconnect(ConnectionState::Status, &reason), None);
+        assert_eq!(disconnect(ConnectionState::Handshake, &reason), None);
+    }
+
+    #[test]
+    fn server_links_with_a_built_in_and_a_custom_label() {
+        let label = TextComponent::new("Map".to_string());
+        let links = [
+            (ServerLinkLabel::parse("bug_report"), "https://example.com/bugs".to_string()),
+            (ServerLinkLabel::parse("Map"), "https://example.com/map".to_string()),
+        ];
+        let mut expected = vec![Packet::ConfigurationServerLinks.id() as u8, 2, 1, 0];
+        write_utf8_string(&mut expected, "https://example.com/bugs".into());
+        expected.push(0);
+        expected.append(&mut label.to_nbt_bytes());
+        write_utf8_string(&mut expected, "https://example.com/map".into());
+        assert_eq!(configuration::server_links(&links), expected);
+    }
+}
@@ -0,0 +1,1413 @@
+// Clientbound packets for the Play state
+// https://wiki.vg/Protocol#Play
+
+use crate::{chat::ChatSession, player::Gamemode, protocol::Packet, recipe::Recipe, resource_pack::ResourcePack, types::{angle::Angle, metadata::Metadata, position::Position, slot::Slot, text_component::TextComponent}, utils::{write_ivar, write_lvar, write_utf8_string}, world::{block_entity::BlockEntity, border::WorldBorder, light::LightData}};
+
+pub fn set_entity_metadata(entity_id: i32, metadata: &Metadata) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::SetEntityMetadata.id());
+    write_ivar(&mut buffer, entity_id);
+    buffer.append(&mut metadata.as_bytes());
+    buffer
+}
+
+pub fn remove_entities(ids: &[i32]) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::RemoveEntities.id());
+    write_ivar(&mut buffer, ids.len() as i32);
+    for id in ids {
+        write_ivar(&mut buffer, *id);
+    }
+    buffer
+}
+
+/// Rotations are sent as angles, steps of 1/256 of a full turn
+pub struct SpawnEntity {
+    pub entity_id: i32,
+    pub uuid: u128,
+    pub entity_type: i32,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub pitch: u8,
+    pub yaw: u8,
+    pub head_yaw: u8,
+    pub data: i32,
+    pub velocity: (i16, i16, i16),
+}
+
+pub fn spawn_entity(entity: &SpawnEntity) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::SpawnEntity.id());
+    write_ivar(&mut buffer, entity.entity_id);
+    buffer.extend_from_slice(&entity.uuid.to_be_bytes());
+    write_ivar(&mut buffer, entity.entity_type);
+    buffer.extend_from_slice(&entity.x.to_be_bytes());
+    buffer.extend_from_slice(&entity.y.to_be_bytes());
+    buffer.extend_from_slice(&entity.z.to_be_bytes());
+    buffer.push(entity.pitch);
+    buffer.push(entity.yaw);
+    buffer.push(entity.head_yaw);
+    write_ivar(&mut buffer, entity.data);
+    buffer.extend_from_slice(&entity.velocity.0.to_be_bytes());
+    buffer.extend_from_slice(&entity.velocity.1.to_be_bytes());
+    buffer.extend_from_slice(&entity.velocity.2.to_be_bytes());
+    buffer
+}
+
+pub fn pickup_item(collected_entity_id: i32, collector_entity_id: i32, count: i32) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::PickupItem.id());
+    write_ivar(&mut buffer, collected_entity_id);
+    write_ivar(&mut buffer, collector_entity_id);
+    write_ivar(&mut buffer, count);
+    buffer
+}
+
+pub fn system_chat(content: &TextComponent, overlay: bool) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::SystemChatMessage.id());
+    buffer.append(&mut content.to_nbt_bytes());
+    buffer.push(overlay as u8);
+    buffer
+}
+
+pub fn disconnect(reason: &TextComponent) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::Disconnect.id());
+    buffer.append(&mut reason.to_nbt_bytes());
+    buffer
+}
+
+/// Update Recipes with every recipe the server knows, some clients wait for it during join
+pub fn update_recipes(recipes: &[Recipe]) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::UpdateRecipes.id());
+    write_ivar(&mut buffer, recipes.len() as i32);
+    for recipe in recipes {
+        buffer.append(&mut recipe.as_bytes());
+    }
+    buffer
+}
+
+pub fn player_info_remove(uuids: &[u128]) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::PlayerInfoRemove.id());
+    write_ivar(&mut buffer, uuids.len() as i32);
+    for uuid in uuids {
+        buffer.extend_from_slice(&uuid.to_be_bytes());
+    }
+    buffer
+}
+
+pub fn disguised_chat(content: &TextComponent, chat_type: i32, sender_name: &TextComponent, target_name: Option<&TextComponent>) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::DisguisedChatMessage.id());
+    buffer.append(&mut content.to_nbt_bytes());
+    write_ivar(&mut buffer, chat_type);
+    buffer.append(&mut sender_name.to_nbt_bytes());
+    buffer.push(target_name.is_some() as u8);
+    if let Some(target_name) = target_name {
+        buffer.append(&mut target_name.to_nbt_bytes());
+    }
+    buffer
+}
+
+pub fn initialize_world_border(border: &WorldBorder) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::InitializeWorldBorder.id());
+    buffer.extend_from_slice(&border.center_x.to_be_bytes());
+    buffer.extend_from_slice(&border.center_z.to_be_bytes());
+    buffer.extend_from_slice(&border.old_diameter.to_be_bytes());
+    buffer.extend_from_slice(&border.new_diameter.to_be_bytes());
+    write_lvar(&mut buffer, border.speed);
+    write_ivar(&mut buffer, border.portal_teleport_boundary);
+    write_ivar(&mut buffer, border.warning_blocks);
+    write_ivar(&mut buffer, border.warning_time);
+    buffer
+}
+
+pub fn set_border_center(x: f64, z: f64) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::SetBorderCenter.id());
+    buffer.extend_from_slice(&x.to_be_bytes());
+    buffer.extend_from_slice(&z.to_be_bytes());
+    buffer
+}
+
+pub fn set_border_lerp_size(old_diameter: f64, new_diameter: f64, speed: i64) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::SetBorderLerpSize.id());
+    buffer.extend_from_slice(&old_diameter.to_be_bytes());
+    buffer.extend_from_slice(&new_diameter.to_be_bytes());
+    write_lvar(&mut buffer, speed);
+    buffer
+}
+
+pub fn set_border_size(diameter: f64) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::SetBorderSize.id());
+    buffer.extend_from_slice(&diameter.to_be_bytes());
+    buffer
+}
+
+pub fn set_border_warning_delay(warning_time: i32) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::SetBorderWarningDelay.id());
+    write_ivar(&mut buffer, warning_time);
+    buffer
+}
+
+pub fn set_border_warning_distance(warning_blocks: i32) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::SetBorderWarningDistance.id());
+    write_ivar(&mut buffer, warning_blocks);
+    buffer
+}
+
+/// `source_type` is a damage_type registry id, the cause/direct entities are optional
+pub fn damage_event(entity_id: i32, source_type: i32, source_cause: Option<i32>, source_direct: Option<i32>, position: Option<(f64, f64, f64)>) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::DamageEvent.id());
+    write_ivar(&mut buffer, entity_id);
+    write_ivar(&mut buffer, source_type);
+    // Entity ids are sent + 1, 0 meaning there is no entity
+    write_ivar(&mut buffer, source_cause.map_or(0, |id| id + 1));
+    write_ivar(&mut buffer, source_direct.map_or(0, |id| id + 1));
+    buffer.push(position.is_some() as u8);
+    if let Some((x, y, z)) = position {
+        buffer.extend_from_slice(&x.to_be_bytes());
+        buffer.extend_from_slice(&y.to_be_bytes());
+        buffer.extend_from_slice(&z.to_be_bytes());
+    }
+    buffer
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntityAnimation {
+    SwingMainArm = 0,
+    SwingOffhand = 3,
+}
+
+pub fn entity_animation(entity_id: i32, animation: EntityAnimation) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::EntityAnimation.id());
+    write_ivar(&mut buffer, entity_id);
+    buffer.push(animation as u8);
+    buffer
+}
+
+pub fn set_passengers(vehicle_id: i32, passengers: &[i32]) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::SetPassengers.id());
+    write_ivar(&mut buffer, vehicle_id);
+    write_ivar(&mut buffer, passengers.len() as i32);
+    for passenger in passengers {
+        write_ivar(&mut buffer, *passenger);
+    }
+    buffer
+}
+
+/// Part of an entity that is looked from or at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Anchor {
+    Feet = 0,
+    Eyes = 1,
+}
+
+/// Turns the player so their `anchor` points at x, y, z, or at the entity's anchor when one is given
+pub fn look_at(anchor: Anchor, x: f64, y: f64, z: f64, entity: Option<(i32, Anchor)>) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::LookAt.id());
+    write_ivar(&mut buffer, anchor as i32);
+    buffer.extend_from_slice(&x.to_be_bytes());
+    buffer.extend_from_slice(&y.to_be_bytes());
+    buffer.extend_from_slice(&z.to_be_bytes());
+    buffer.push(entity.is_some() as u8);
+    if let Some((entity_id, entity_anchor)) = entity {
+        write_ivar(&mut buffer, entity_id);
+        write_ivar(&mut buffer, entity_anchor as i32);
+    }
+    buffer
+}
+
+/// `flags` marks which of the values are relative to the current position, 0 for all absolute
+pub fn synchronize_player_position(x: f64, y: f64, z: f64, yaw: f32, pitch: f32, flags: u8, teleport_id: i32) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::SynchronizePlayerPosition.id());
+    buffer.extend_from_slice(&x.to_be_bytes());
+    buffer.extend_from_slice(&y.to_be_bytes());
+    buffer.extend_from_slice(&z.to_be_bytes());
+    buffer.extend_from_slice(&yaw.to_be_bytes());
+    buffer.extend_from_slice(&pitch.to_be_bytes());
+    buffer.push(flags);
+    write_ivar(&mut buffer, teleport_id);
+    buffer
+}
+
+/// Statuses for the Entity Event packet
+// https://wiki.vg/Entity_statuses
+pub mod entity_status {
+    pub const OP_PERMISSION_LEVEL_0: u8 = 24;
+    pub const OP_PERMISSION_LEVEL_4: u8 = 28;
+}
+
+pub fn entity_event(entity_id: i32, status: u8) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::EntityEvent.id());
+    buffer.extend_from_slice(&entity_id.to_be_bytes());
+    buffer.push(status);
+    buffer
+}
+
+pub fn set_health(health: f32, food: i32, saturation: f32) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::SetHealth.id());
+    buffer.extend_from_slice(&health.to_be_bytes());
+    write_ivar(&mut buffer, food);
+    buffer.extend_from_slice(&saturation.to_be_bytes());
+    buffer
+}
+
+/// Shows the death screen with the message, should follow a Set Health of 0
+pub fn combat_death(player_id: i32, message: &TextComponent) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::CombatDeath.id());
+    write_ivar(&mut buffer, player_id);
+    buffer.append(&mut message.to_nbt_bytes());
+    buffer
+}
+
+pub fn open_sign_editor(position: Position, is_front_text: bool) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::OpenSignEditor.id());
+    buffer.extend_from_slice(&position.as_bytes());
+    buffer.push(is_front_text as u8);
+    buffer
+}
+
+/// Replaces the block entity's NBT on the client, e.g. to show edited sign text
+pub fn block_entity_data(position: Position, entity: &BlockEntity) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::BlockEntityData.id());
+    buffer.extend_from_slice(&position.as_bytes());
+    write_ivar(&mut buffer, entity.type_id());
+    buffer.append(&mut entity.to_nbt_bytes());
+    buffer
+}
+
+pub fn server_data(motd: &TextComponent, icon: Option<Vec<u8>>, enforces_secure_chat: bool) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::ServerData.id());
+    buffer.append(&mut motd.to_nbt_bytes());
+    buffer.push(icon.is_some() as u8);
+    if let Some(icon) = icon {
+        write_ivar(&mut buffer, icon.len() as i32);
+        buffer.extend_from_slice(&icon);
+    }
+    buffer.push(enforces_secure_chat as u8);
+    buffer
+}
+
+// Player Info Update action bits
+pub const PLAYER_INFO_ADD_PLAYER: u8 = 0x01;
+pub const PLAYER_INFO_INITIALIZE_CHAT: u8 = 0x02;
+pub const PLAYER_INFO_UPDATE_GAME_MODE: u8 = 0x04;
+pub const PLAYER_INFO_UPDATE_LISTED: u8 = 0x08;
+pub const PLAYER_INFO_UPDATE_LATENCY: u8 = 0x10;
+
+/// Player Info Update that adds (uuid, name, gamemode) entries to the tab list
+pub fn player_info_add(entries: &[(u128, String, Gamemode)]) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::PlayerInfoUpdate.id());
+    buffer.push(PLAYER_INFO_ADD_PLAYER | PLAYER_INFO_UPDATE_GAME_MODE | PLAYER_INFO_UPDATE_LISTED);
+    write_ivar(&mut buffer, entries.len() as i32);
+    for (uuid, name, gamemode) in entries {
+        buffer.extend_from_slice(&uuid.to_be_bytes());
+        write_utf8_string(&mut buffer, name.clone());
+        // No skin properties
+        write_ivar(&mut buffer, 0);
+        write_ivar(&mut buffer, *gamemode as i32);
+        buffer.push(true as u8);
+    }
+    buffer
+}
+
+/// Player Info Update with only the Update Latency action, latency in milliseconds
+pub fn player_info_latency(entries: &[(u128, i32)]) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::PlayerInfoUpdate.id());
+    buffer.push(PLAYER_INFO_UPDATE_LATENCY);
+    write_ivar(&mut buffer, entries.len() as i32);
+    for (uuid, latency) in entries {
+        buffer.extend_from_slice(&uuid.to_be_bytes());
+        write_ivar(&mut buffer, *latency);
+    }
+    buffer
+}
+
+/// Shares each player's chat session so their signed messages can be verified, None clears it
+pub fn player_info_init_chat(entries: &[(u128, Option<ChatSession>)]) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::PlayerInfoUpdate.id());
+    buffer.push(PLAYER_INFO_INITIALIZE_CHAT);
+    write_ivar(&mut buffer, entries.len() as i32);
+    for (uuid, session) in entries {
+        buffer.extend_from_slice(&uuid.to_be_bytes());
+        match session {
+            Some(session) => {
+                buffer.push(1);
+                buffer.extend_from_slice(&session.session_id.to_be_bytes());
+                buffer.extend_from_slice(&session.expires_at.to_be_bytes());
+                write_ivar(&mut buffer, session.public_key.len() as i32);
+                buffer.extend_from_slice(&session.public_key);
+                write_ivar(&mut buffer, session.key_signature.len() as i32);
+                buffer.extend_from_slice(&session.key_signature);
+            },
+            None => buffer.push(0),
+        }
+    }
+    buffer
+}
+
+pub fn keep_alive(id: i64) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::KeepAlive.id());
+    buffer.extend_from_slice(&id.to_be_bytes());
+    buffer
+}
+
+pub fn ping(id: i32) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::Ping.id());
+    buffer.extend_from_slice(&id.to_be_bytes());
+    buffer
+}
+
+/// (category id, statistic id, value) for each statistic, categories are ids in the stat type registry
+pub fn award_statistics(statistics: &[(i32, i32, i32)]) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::AwardStatistics.id());
+    write_ivar(&mut buffer, statistics.len() as i32);
+    for (category, statistic, value) in statistics {
+        write_ivar(&mut buffer, *category);
+        write_ivar(&mut buffer, *statistic);
+        write_ivar(&mut buffer, *value);
+    }
+    buffer
+}
+
+pub fn acknowledge_block_change(sequence: i32) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::AcknowledgeBlockChange.id());
+    write_ivar(&mut buffer, sequence);
+    buffer
+}
+
+/// Views the world from the entity, the player's own entity id puts the camera back
+pub fn set_camera(entity_id: i32) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::SetCamera.id());
+    write_ivar(&mut buffer, entity_id);
+    buffer
+}
+
+pub fn set_center_chunk(chunk_x: i32, chunk_z: i32) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::SetCenterChunk.id());
+    write_ivar(&mut buffer, chunk_x);
+    write_ivar(&mut buffer, chunk_z);
+    buffer
+}
+
+pub fn set_render_distance(view_distance: i32) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::SetRenderDistance.id());
+    write_ivar(&mut buffer, view_distance);
+    buffer
+}
+
+pub fn set_simulation_distance(simulation_distance: i32) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::SetSimulationDistance.id());
+    write_ivar(&mut buffer, simulation_distance);
+    buffer
+}
+
+/// Chunk Data and Update Light, `data` is the heightmaps, sections and block entities from `world::chunk`
+pub fn chunk_data(chunk_x: i32, chunk_z: i32, data: &[u8], light: &LightData) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::ChunkDataAndUpdateLight.id());
+    buffer.extend_from_slice(&chunk_x.to_be_bytes());
+    buffer.extend_from_slice(&chunk_z.to_be_bytes());
+    buffer.extend_from_slice(data);
+    buffer.append(&mut light.as_bytes());
+    buffer
+}
+
+pub fn chunk_batch_start() -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::ChunkBatchStart.id());
+    buffer
+}
+
+pub fn chunk_batch_finished(batch_size: i32) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::ChunkBatchFinished.id());
+    write_ivar(&mut buffer, batch_size);
+    buffer
+}
+
+/// Chunk coordinates are ints rather than VarInts, and z comes first
+pub fn unload_chunk(chunk_x: i32, chunk_z: i32) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::UnloadChunk.id());
+    buffer.extend_from_slice(&chunk_z.to_be_bytes());
+    buffer.extend_from_slice(&chunk_x.to_be_bytes());
+    buffer
+}
+
+/// Sets many blocks within one 16x16x16 chunk section.
+/// `section` is the section's coordinates and each change is (x, y, z) within it plus the new block state.
+pub fn multi_block_change(section: (i32, i32, i32), changes: &[(u8, u8, u8, i32)]) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::UpdateSectionBlocks.id());
+    let (x, y, z) = section;
+    let section_position = ((x as i64 & 0x3FFFFF) << 42) | ((z as i64 & 0x3FFFFF) << 20) | (y as i64 & 0xFFFFF);
+    buffer.extend_from_slice(&section_position.to_be_bytes());
+    write_ivar(&mut buffer, changes.len() as i32);
+    for (x, y, z, state) in changes {
+        let relative = ((*x as i64 & 0xF) << 8) | ((*z as i64 & 0xF) << 4) | (*y as i64 & 0xF);
+        write_lvar(&mut buffer, ((*state as i64) << 12) | relative);
+    }
+    buffer
+}
+
+pub fn block_update(position: Position, block_state: i32) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::BlockUpdate.id());
+    buffer.extend_from_slice(&position.as_bytes());
+    write_ivar(&mut buffer, block_state);
+    buffer
+}
+
+pub fn close_container(window_id: u8) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::CloseContainer.id());
+    buffer.push(window_id);
+    buffer
+}
+
+/// Window id -1 with slot -1 sets the item carried on the cursor
+pub fn set_container_slot(window_id: i8, state_id: i32, slot: i16, data: &Slot) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::SetContainerSlot.id());
+    buffer.push(window_id as u8);
+    write_ivar(&mut buffer, state_id);
+    buffer.extend_from_slice(&slot.to_be_bytes());
+    buffer.append(&mut data.as_bytes());
+    buffer
+}
+
+pub fn set_experience(progress: f32, level: i32, total: i32) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::SetExperience.id());
+    buffer.extend_from_slice(&progress.to_be_bytes());
+    write_ivar(&mut buffer, level);
+    write_ivar(&mut buffer, total);
+    buffer
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SoundCategory {
+    Master = 0,
+    Music = 1,
+    Records = 2,
+    Weather = 3,
+    Blocks = 4,
+    Hostile = 5,
+    Neutral = 6,
+    Players = 7,
+    Ambient = 8,
+    Voice = 9,
+}
+
+impl SoundCategory {
+    /// Names used by /playsound and /stopsound
+    pub fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "master" => Self::Master,
+            "music" => Self::Music,
+            "record" => Self::Records,
+            "weather" => Self::Weather,
+            "block" => Self::Blocks,
+            "hostile" => Self::Hostile,
+            "neutral" => Self::Neutral,
+            "player" => Self::Players,
+            "ambient" => Self::Ambient,
+            "voice" => Self::Voice,
+            _ => return None,
+        })
+    }
+}
+
+/// Plays a sound by name at a position, the client picks the variant using the seed
+pub fn sound_effect(sound: &str, category: SoundCategory, (x, y, z): (f64, f64, f64), volume: f32, pitch: f32, seed: i64) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::SoundEffect.id());
+    write_sound_event(&mut buffer, sound);
+    write_ivar(&mut buffer, category as i32);
+    // Positions are fixed point with 3 fractional bits
+    buffer.extend_from_slice(&((x * 8.0) as i32).to_be_bytes());
+    buffer.extend_from_slice(&((y * 8.0) as i32).to_be_bytes());
+    buffer.extend_from_slice(&((z * 8.0) as i32).to_be_bytes());
+    buffer.extend_from_slice(&volume.to_be_bytes());
+    buffer.extend_from_slice(&pitch.to_be_bytes());
+    buffer.extend_from_slice(&seed.to_be_bytes());
+    buffer
+}
+
+/// Plays a sound that follows an entity as it moves
+pub fn entity_sound_effect(sound: &str, category: SoundCategory, entity_id: i32, volume: f32, pitch: f32, seed: i64) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::EntitySoundEffect.id());
+    write_sound_event(&mut buffer, sound);
+    write_ivar(&mut buffer, category as i32);
+    write_ivar(&mut buffer, entity_id);
+    buffer.extend_from_slice(&volume.to_be_bytes());
+    buffer.extend_from_slice(&pitch.to_be_bytes());
+    buffer.extend_from_slice(&seed.to_be_bytes());
+    buffer
+}
+
+fn write_sound_event(buffer: &mut Vec<u8>, sound: &str) {
+    // Id 0 means the sound is given inline instead of by registry id
+    write_ivar(buffer, 0);
+    write_utf8_string(buffer, sound.to_string());
+    buffer.push(0); // No fixed range
+}
+
+/// Stops sounds of a category, a sound by name, or every sound when neither is given
+pub fn stop_sound(source: Option<SoundCategory>, sound: Option<&str>) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::StopSound.id());
+    let flags = source.is_some() as u8 | (sound.is_some() as u8) << 1;
+    buffer.push(flags);
+    if let Some(source) = source {
+        write_ivar(&mut buffer, source as i32);
+    }
+    if let Some(sound) = sound {
+        write_utf8_string(&mut buffer, sound.to_string());
+    }
+    buffer
+}
+
+pub fn set_ticking_state(tick_rate: f32, is_frozen: bool) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::SetTickingState.id());
+    buffer.extend_from_slice(&tick_rate.to_be_bytes());
+    buffer.push(is_frozen as u8);
+    buffer
+}
+
+/// Lets a frozen client advance by the given number of ticks
+pub fn step_tick(steps: i32) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::StepTick.id());
+    write_ivar(&mut buffer, steps);
+    buffer
+}
+
+/// Cookies are kept by the client across transfers, the payload can be at most 5120 bytes
+pub fn store_cookie(key: &str, payload: &[u8]) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::StoreCookie.id());
+    write_utf8_string(&mut buffer, key.to_string());
+    write_ivar(&mut buffer, payload.len() as i32);
+    buffer.extend_from_slice(payload);
+    buffer
+}
+
+pub fn cookie_request(key: &str) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::CookieRequest.id());
+    write_utf8_string(&mut buffer, key.to_string());
+    buffer
+}
+
+pub fn add_resource_pack(pack: &ResourcePack) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::AddResourcePack.id());
+    buffer.append(&mut pack.as_bytes());
+    buffer
+}
+
+/// Removes every pack when no uuid is given
+pub fn remove_resource_pack(uuid: Option<u128>) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::RemoveResourcePack.id());
+    write_optional_uuid(&mut buffer, uuid);
+    buffer
+}
+
+pub fn write_optional_uuid(buffer: &mut Vec<u8>, uuid: Option<u128>) {
+    match uuid {
+        Some(uuid) => {
+            buffer.push(1);
+            buffer.extend_from_slice(&uuid.to_be_bytes());
+        },
+        None => buffer.push(0),
+    }
+}
+
+pub fn transfer(host: &str, port: u16) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::Transfer.id());
+    write_utf8_string(&mut buffer, host.to_string());
+    write_ivar(&mut buffer, port as i32);
+    buffer
+}
+
+/// Movement along one axis in the fixed point units of the delta packets, None if it doesn't fit in a short
+fn position_delta(old: f64, new: f64) -> Option<i16> {
+    let delta = (new * 4096.0).round() as i64 - (old * 4096.0).round() as i64;
+    i16::try_from(delta).ok()
+}
+
+pub fn update_entity_position(entity_id: i32, delta: (i16, i16, i16), on_ground: bool) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::UpdateEntityPosition.id());
+    write_ivar(&mut buffer, entity_id);
+    buffer.extend_from_slice(&delta.0.to_be_bytes());
+    buffer.extend_from_slice(&delta.1.to_be_bytes());
+    buffer.extend_from_slice(&delta.2.to_be_bytes());
+    buffer.push(on_ground as u8);
+    buffer
+}
+
+pub fn update_entity_position_and_rotation(entity_id: i32, delta: (i16, i16, i16), yaw: Angle, pitch: Angle, on_ground: bool) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::UpdateEntityPositionAndRotation.id());
+    write_ivar(&mut buffer, entity_id);
+    buffer.extend_from_slice(&delta.0.to_be_bytes());
+    buffer.extend_from_slice(&delta.1.to_be_bytes());
+    buffer.extend_from_slice(&delta.2.to_be_bytes());
+    buffer.push(yaw.0);
+    buffer.push(pitch.0);
+    buffer.push(on_ground as u8);
+    buffer
+}
+
+pub fn teleport_entity(entity_id: i32, (x, y, z): (f64, f64, f64), yaw: Angle, pitch: Angle, on_ground: bool) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::TeleportEntity.id());
+    write_ivar(&mut buffer, entity_id);
+    buffer.extend_from_slice(&x.to_be_bytes());
+    buffer.extend_from_slice(&y.to_be_bytes());
+    buffer.extend_from_slice(&z.to_be_bytes());
+    buffer.push(yaw.0);
+    buffer.push(pitch.0);
+    buffer.push(on_ground as u8);
+    buffer
+}
+
+/// Moves the vehicle the player is steering, used to correct the client
+pub fn move_vehicle((x, y, z): (f64, f64, f64), yaw: f32, pitch: f32) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::MoveVehicle.id());
+    buffer.extend_from_slice(&x.to_be_bytes());
+    buffer.extend_from_slice(&y.to_be_bytes());
+    buffer.extend_from_slice(&z.to_be_bytes());
+    buffer.extend_from_slice(&yaw.to_be_bytes());
+    buffer.extend_from_slice(&pitch.to_be_bytes());
+    buffer
+}
+
+/// Velocity in blocks per tick, sent in units of 1/8000 of a block
+pub fn set_entity_velocity(entity_id: i32, (x, y, z): (f64, f64, f64)) -> Vec<u8> {
+    let to_short = |v: f64| (v * 8000.0).clamp(i16::MIN as f64, i16::MAX as f64) as i16;
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::SetEntityVelocity.id());
+    write_ivar(&mut buffer, entity_id);
+    buffer.extend_from_slice(&to_short(x).to_be_bytes());
+    buffer.extend_from_slice(&to_short(y).to_be_bytes());
+    buffer.extend_from_slice(&to_short(z).to_be_bytes());
+    buffer
+}
+
+pub fn update_entity_rotation(entity_id: i32, yaw: Angle, pitch: Angle, on_ground: bool) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::UpdateEntityRotation.id());
+    write_ivar(&mut buffer, entity_id);
+    buffer.push(yaw.0);
+    buffer.push(pitch.0);
+    buffer.push(on_ground as u8);
+    buffer
+}
+
+/// Picks the smallest packet that moves an entity from `old` to `new`, including the rotation if it changed.
+/// Turning on the spot only sends the rotation.
+/// Moves of 8 blocks or more on any axis don't fit in a delta and are sent as a teleport instead.
+pub fn entity_movement(entity_id: i32, old: (f64, f64, f64), new: (f64, f64, f64), (yaw, pitch): (Angle, Angle), rotated: bool, on_ground: bool) -> Vec<u8> {
+    match (position_delta(old.0, new.0), position_delta(old.1, new.1), position_delta(old.2, new.2)) {
+        (Some(0), Some(0), Some(0)) if rotated => update_entity_rotation(entity_id, yaw, pitch, on_ground),
+        (Some(x), Some(y), Some(z)) if rotated => update_entity_position_and_rotation(entity_id, (x, y, z), yaw, pitch, on_ground),
+        (Some(x), Some(y), Some(z)) => update_entity_position(entity_id, (x, y, z), on_ground),
+        _ => teleport_entity(entity_id, new, yaw, pitch, on_ground),
+    }
+}
+
+/// Events sent through the Game Event packet, the discriminant is the event id
+// https://wiki.vg/Protocol#Game_Event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameEvent {
+    EndRaining = 1,
+    BeginRaining = 2,
+    ChangeGameMode = 3,
+    RainLevelChange = 7,
+    ThunderLevelChange = 8,
+    EnableRespawnScreen = 11,
+    StartWaitingForLevelChunks = 13,
+}
+
+pub fn game_event(event: GameEvent, value: f32) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::GameEvent.id());
+    buffer.push(event as u8);
+    buffer.extend_from_slice(&value.to_be_bytes());
+    buffer
+}
+
+/// Entries of the attribute registry, the discriminant is the registry id
+// https://wiki.vg/Protocol#Update_Attributes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Attribute {
+    MovementSpeed = 21,
+}
+
+/// Sets the base value of each attribute, modifiers aren't supported
+pub fn update_attributes(entity_id: i32, attributes: &[(Attribute, f64)]) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::UpdateAttributes.id());
+    write_ivar(&mut buffer, entity_id);
+    write_ivar(&mut buffer, attributes.len() as i32);
+    for (attribute, value) in attributes {
+        write_ivar(&mut buffer, *attribute as i32);
+        buffer.extend_from_slice(&value.to_be_bytes());
+        write_ivar(&mut buffer, 0);
+    }
+    buffer
+}
+
+// Player Abilities flags
+pub const ABILITY_INVULNERABLE: u8 = 0x01;
+pub const ABILITY_FLYING: u8 = 0x02;
+pub const ABILITY_ALLOW_FLYING: u8 = 0x04;
+pub const ABILITY_INSTANT_BREAK: u8 = 0x08;
+
+pub fn player_abilities(flags: u8, flying_speed: f32, fov_modifier: f32) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::PlayerAbilities.id());
+    buffer.push(flags);
+    buffer.extend_from_slice(&flying_speed.to_be_bytes());
+    buffer.extend_from_slice(&fov_modifier.to_be_bytes());
+    buffer
+}
+
+/// Fields of the Login (play) packet sent when a player joins
+// https://wiki.vg/Protocol#Login_.28play.29
+pub struct JoinGame {
+    pub entity_id: i32,
+    pub is_hardcore: bool,
+    pub dimension_names: Vec<String>,
+    pub max_players: i32,
+    pub view_distance: i32,
+    pub simulation_distance: i32,
+    pub reduced_debug_info: bool,
+    pub enable_respawn_screen: bool,
+    pub do_limited_crafting: bool,
+    /// Id in the dimension_type registry
+    pub dimension_type: i32,
+    pub dimension_name: String,
+    pub hashed_seed: i64,
+    pub gamemode: Gamemode,
+    /// -1 when there is no previous gamemode
+    pub previous_gamemode: i8,
+    pub is_debug: bool,
+    pub is_flat: bool,
+    /// Dimension and block the player last died at, used by the recovery compass
+    pub death_location: Option<(String, Position)>,
+    pub portal_cooldown: i32,
+    pub enforces_secure_chat: bool,
+}
+
+impl Default for JoinGame {
+    fn default() -> Self {
+        Self {
+            entity_id: 0,
+            is_hardcore: false,
+            dimension_names: vec!["minecraft:overworld".into()],
+            max_players: 100,
+            view_distance: 10,
+            simulation_distance: 10,
+            reduced_debug_info: false,
+            enable_respawn_screen: true,
+            do_limited_crafting: false,
+            dimension_type: 0,
+            dimension_name: "minecraft:overworld".into(),
+            hashed_seed: 0,
+            gamemode: Gamemode::Survival,
+            previous_gamemode: -1,
+            is_debug: false,
+            is_flat: false,
+            death_location: None,
+            portal_cooldown: 0,
+            enforces_secure_chat: false,
+        }
+    }
+}
+
+fn write_death_location(buffer: &mut Vec<u8>, death_location: &Option<(String, Position)>) {
+    match death_location {
+        Some((dimension, position)) => {
+            buffer.push(1);
+            write_utf8_string(buffer, dimension.clone());
+            buffer.extend_from_slice(&position.as_bytes());
+        },
+        None => buffer.push(0),
+    }
+}
+
+pub fn join_game(join: &JoinGame) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::Login.id());
+    buffer.extend_from_slice(&join.entity_id.to_be_bytes());
+    buffer.push(join.is_hardcore as u8);
+    write_ivar(&mut buffer, join.dimension_names.len() as i32);
+    for name in &join.dimension_names {
+        write_utf8_string(&mut buffer, name.clone());
+    }
+    write_ivar(&mut buffer, join.max_players);
+    write_ivar(&mut buffer, join.view_distance);
+    write_ivar(&mut buffer, join.simulation_distance);
+    buffer.push(join.reduced_debug_info as u8);
+    buffer.push(join.enable_respawn_screen as u8);
+    buffer.push(join.do_limited_crafting as u8);
+    write_ivar(&mut buffer, join.dimension_type);
+    write_utf8_string(&mut buffer, join.dimension_name.clone());
+    buffer.extend_from_slice(&join.hashed_seed.to_be_bytes());
+    buffer.push(join.gamemode as u8);
+    buffer.push(join.previous_gamemode as u8);
+    buffer.push(join.is_debug as u8);
+    buffer.push(join.is_flat as u8);
+    write_death_location(&mut buffer, &join.death_location);
+    write_ivar(&mut buffer, join.portal_cooldown);
+    buffer.push(join.enforces_secure_chat as u8);
+    buffer
+}
+
+/// Where the compass points and players respawn without a bed
+pub fn set_default_spawn_position(position: Position, angle: f32) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::SetDefaultSpawnPosition.id());
+    buffer.extend_from_slice(&position.encode().to_be_bytes());
+    buffer.extend_from_slice(&angle.to_be_bytes());
+    buffer
+}
+
+/// What the client keeps of the player's entity through a Respawn.
+/// Respawning after death keeps nothing, only a dimension change would keep metadata or attributes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataKept {
+    Nothing,
+}
+
+impl DataKept {
+    pub fn bitmask(self) -> u8 {
+        match self {
+            Self::Nothing => 0,
+        }
+    }
+}
+
+/// Respawn shares the world fields of the Login (play) packet
+pub fn respawn(join: &JoinGame, data_kept: DataKept) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::Respawn.id());
+    write_ivar(&mut buffer, join.dimension_type);
+    write_utf8_string(&mut buffer, join.dimension_name.clone());
+    buffer.extend_from_slice(&join.hashed_seed.to_be_bytes());
+    buffer.push(join.gamemode as u8);
+    buffer.push(join.previous_gamemode as u8);
+    buffer.push(join.is_debug as u8);
+    buffer.push(join.is_flat as u8);
+    write_death_location(&mut buffer, &join.death_location);
+    write_ivar(&mut buffer, join.portal_cooldown);
+    buffer.push(data_kept.bitmask());
+    buffer
+}
+
+/// Player Info Update with only the Update Game Mode action
+pub fn player_info_game_mode(entries: &[(u128, Gamemode)]) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::PlayerInfoUpdate.id());
+    buffer.push(PLAYER_INFO_UPDATE_GAME_MODE);
+    write_ivar(&mut buffer, entries.len() as i32);
+    for (uuid, gamemode) in entries {
+        buffer.extend_from_slice(&uuid.to_be_bytes());
+        write_ivar(&mut buffer, *gamemode as i32);
+    }
+    buffer
+}
+
+pub fn update_time(world_age: i64, time_of_day: i64) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::UpdateTime.id());
+    buffer.extend_from_slice(&world_age.to_be_bytes());
+    buffer.extend_from_slice(&time_of_day.to_be_bytes());
+    buffer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{metadata::{MetadataValue, Pose, POSE_INDEX}, reader::PacketReader};
+
+    #[test]
+    fn set_entity_metadata_is_entity_then_metadata() {
+        let mut metadata = Metadata::new();
+        metadata.set(POSE_INDEX, MetadataValue::Pose(Pose::Sneaking));
+        let packet = set_entity_metadata(300, &metadata);
+        let mut reader = PacketReader::new(&packet);
+        assert_eq!(reader.read_ivar().unwrap(), Packet::SetEntityMetadata.id());
+        assert_eq!(reader.read_ivar().unwrap(), 300);
+        assert_eq!(reader.read_bytes(reader.remaining()).unwrap(), metadata.as_bytes().as_slice());
+    }
+
+    #[test]
+    fn remove_entities_is_a_counted_list() {
+        assert_eq!(remove_entities(&[1, 300]), vec![Packet::RemoveEntities.id() as u8, 2, 1, 0xAC, 0x02]);
+        assert_eq!(remove_entities(&[]), vec![Packet::RemoveEntities.id() as u8, 0]);
+    }
+
+    #[test]
+    fn pickup_item_names_the_collector() {
+        let packet = pickup_item(12, 1, 64);
+        let mut reader = PacketReader::new(&packet);
+        assert_eq!(reader.read_ivar().unwrap(), Packet::PickupItem.id());
+        assert_eq!(reader.read_ivar().unwrap(), 12);
+        assert_eq!(reader.read_ivar().unwrap(), 1);
+        assert_eq!(reader.read_ivar().unwrap(), 64);
+        assert_eq!(reader.remaining(), 0);
+    }
+
+    #[test]
+    fn empty_recipes_are_a_zero_count() {
+        assert_eq!(update_recipes(&[]), vec![Packet::UpdateRecipes.id() as u8, 0]);
+    }
+
+    #[test]
+    fn player_info_remove_with_one_uuid() {
+        let uuid: u128 = 0x0123456789ABCDEF0123456789ABCDEF;
+        let mut expected = vec![Packet::PlayerInfoRemove.id() as u8, 1];
+        expected.extend_from_slice(&uuid.to_be_bytes());
+        assert_eq!(player_info_remove(&[uuid]), expected);
+    }
+
+    #[test]
+    fn disguised_chat_with_the_chat_type() {
+        let content = TextComponent::new("hello");
+        let sender = TextComponent::new("Steve");
+        let chat_type = crate::registry::chat_type::id("minecraft:chat").unwrap();
+        let mut expected = vec![Packet::DisguisedChatMessage.id() as u8];
+        expected.append(&mut content.to_nbt_bytes());
+        expected.push(chat_type as u8);
+        expected.append(&mut sender.to_nbt_bytes());
+        expected.push(0);
+        assert_eq!(disguised_chat(&content, chat_type, &sender, None), expected);
+
+        let target = TextComponent::new("Alex");
+        let packet = disguised_chat(&content, chat_type, &sender, Some(&target));
+        let mut with_target = expected.clone();
+        *with_target.last_mut().unwrap() = 1;
+        with_target.append(&mut target.to_nbt_bytes());
+        assert_eq!(packet, with_target);
+    }
+
+    #[test]
+    fn initialize_world_border_layout() {
+        let border = WorldBorder { speed: 300, ..WorldBorder::default() };
+        let packet = initialize_world_border(&border);
+        let mut reader = PacketReader::new(&packet);
+        assert_eq!(reader.read_ivar().unwrap(), Packet::InitializeWorldBorder.id());
+        assert_eq!(reader.read_f64().unwrap(), 0.0);
+        assert_eq!(reader.read_f64().unwrap(), 0.0);
+        assert_eq!(reader.read_f64().unwrap(), 59999968.0);
+        assert_eq!(reader.read_f64().unwrap(), 59999968.0);
+        assert_eq!(reader.read_lvar().unwrap(), 300);
+        assert_eq!(reader.read_ivar().unwrap(), 29999984);
+        assert_eq!(reader.read_ivar().unwrap(), 5);
+        assert_eq!(reader.read_ivar().unwrap(), 15);
+        assert_eq!(reader.remaining(), 0);
+    }
+
+    #[test]
+    fn border_lerp_time_is_a_varlong() {
+        let packet = set_border_lerp_size(10.0, 20.0, 1 << 40);
+        let mut reader = PacketReader::new(&packet);
+        assert_eq!(reader.read_ivar().unwrap(), Packet::SetBorderLerpSize.id());
+        assert_eq!(reader.read_f64().unwrap(), 10.0);
+        assert_eq!(reader.read_f64().unwrap(), 20.0);
+        assert_eq!(reader.read_lvar().unwrap(), 1 << 40);
+        assert_eq!(reader.remaining(), 0);
+    }
+
+    #[test]
+    fn damage_event_sends_entities_plus_one() {
+        let packet = damage_event(5, 2, Some(0), None, Some((1.0, 2.0, 3.0)));
+        let mut reader = PacketReader::new(&packet);
+        assert_eq!(reader.read_ivar().unwrap(), Packet::DamageEvent.id());
+        assert_eq!(reader.read_ivar().unwrap(), 5);
+        assert_eq!(reader.read_ivar().unwrap(), 2);
+        assert_eq!(reader.read_ivar().unwrap(), 1);
+        assert_eq!(reader.read_ivar().unwrap(), 0);
+        assert!(reader.read_bool().unwrap());
+        assert_eq!((reader.read_f64().unwrap(), reader.read_f64().unwrap(), reader.read_f64().unwrap()), (1.0, 2.0, 3.0));
+        assert_eq!(reader.remaining(), 0);
+    }
+
+    #[test]
+    fn swing_main_arm_animation() {
+        assert_eq!(entity_animation(300, EntityAnimation::SwingMainArm), vec![Packet::EntityAnimation.id() as u8, 0xAC, 0x02, 0]);
+        assert_eq!(entity_animation(1, EntityAnimation::SwingOffhand), vec![Packet::EntityAnimation.id() as u8, 1, 3]);
+    }
+
+    #[test]
+    fn vehicle_with_two_passengers() {
+        assert_eq!(set_passengers(10, &[1, 2]), vec![Packet::SetPassengers.id() as u8, 10, 2, 1, 2]);
+        assert_eq!(set_passengers(10, &[]), vec![Packet::SetPassengers.id() as u8, 10, 0]);
+    }
+
+    #[test]
+    fn entity_event_is_an_int_id_and_a_status_byte() {
+        let packet = entity_event(0x01020304, entity_status::OP_PERMISSION_LEVEL_4);
+        let mut reader = PacketReader::new(&packet);
+        assert_eq!(reader.read_ivar().unwrap(), Packet::EntityEvent.id());
+        assert_eq!(reader.read_bytes(4).unwrap(), [1, 2, 3, 4]);
+        assert_eq!(reader.read_bytes(1).unwrap(), [28]);
+        assert_eq!(reader.remaining(), 0);
+    }
+
+    #[test]
+    fn combat_death_carries_the_player_id_and_message() {
+        let message = TextComponent::translate("death.attack.outsideBorder", vec![TextComponent::new("Steve")]);
+        let packet = combat_death(300, &message);
+        let mut reader = PacketReader::new(&packet);
+        assert_eq!(reader.read_ivar().unwrap(), Packet::CombatDeath.id());
+        assert_eq!(reader.read_ivar().unwrap(), 300);
+        assert_eq!(reader.read_bytes(reader.remaining()).unwrap(), message.to_nbt_bytes());
+    }
+
+    #[test]
+    fn set_health_is_health_food_and_saturation() {
+        let packet = set_health(0.0, 20, 5.0);
+        let mut reader = PacketReader::new(&packet);
+        assert_eq!(reader.read_ivar().unwrap(), Packet::SetHealth.id());
+        assert_eq!(reader.read_bytes(4).unwrap(), 0.0f32.to_be_bytes());
+        assert_eq!(reader.read_ivar().unwrap(), 20);
+        assert_eq!(reader.read_bytes(4).unwrap(), 5.0f32.to_be_bytes());
+        assert_eq!(reader.remaining(), 0);
+    }
+
+    #[test]
+    fn server_data_with_and_without_an_icon() {
+        let motd = TextComponent::new("A Minecraft Server");
+        let motd_bytes = motd.to_nbt_bytes();
+
+        let packet = server_data(&motd, None, true);
+        let mut reader = PacketReader::new(&packet);
+        assert_eq!(reader.read_ivar().unwrap(), Packet::ServerData.id());
+        assert_eq!(reader.read_bytes(motd_bytes.len()).unwrap(), motd_bytes);
+        assert_eq!(reader.read_bytes(reader.remaining()).unwrap(), [0, 1]);
+
+        let packet = server_data(&motd, Some(vec![0x89, b'P', b'N', b'G']), false);
+        let mut reader = PacketReader::new(&packet);
+        assert_eq!(reader.read_ivar().unwrap(), Packet::ServerData.id());
+        assert_eq!(reader.read_bytes(motd_bytes.len()).unwrap(), motd_bytes);
+        assert!(reader.read_bool().unwrap());
+        assert_eq!(reader.read_ivar().unwrap(), 4);
+        assert_eq!(reader.read_bytes(4).unwrap(), [0x89, b'P', b'N', b'G']);
+        assert_eq!(reader.read_bytes(reader.remaining()).unwrap(), [0]);
+    }
+
+    #[test]
+    fn player_info_latency_is_the_update_latency_action() {
+        let packet = player_info_latency(&[(7, 42), (u128::MAX, 300)]);
+        let mut reader = PacketReader::new(&packet);
+        assert_eq!(reader.read_ivar().unwrap(), Packet::PlayerInfoUpdate.id());
+        assert_eq!(reader.read_bytes(1).unwrap(), [PLAYER_INFO_UPDATE_LATENCY]);
+        assert_eq!(reader.read_ivar().unwrap(), 2);
+        assert_eq!(reader.read_bytes(16).unwrap(), 7u128.to_be_bytes());
+        assert_eq!(reader.read_ivar().unwrap(), 42);
+        assert_eq!(reader.read_bytes(16).unwrap(), u128::MAX.to_be_bytes());
+        assert_eq!(reader.read_ivar().unwrap(), 300);
+        assert_eq!(reader.remaining(), 0);
+    }
+
+    #[test]
+    fn game_events_use_their_documented_ids() {
+        for (event, id) in [(GameEvent::EndRaining, 1), (GameEvent::ChangeGameMode, 3), (GameEvent::EnableRespawnScreen, 11), (GameEvent::StartWaitingForLevelChunks, 13)] {
+            assert_eq!(event as u8, id);
+        }
+        let packet = game_event(GameEvent::RainLevelChange, 0.5);
+        let mut reader = PacketReader::new(&packet);
+        assert_eq!(reader.read_ivar().unwrap(), Packet::GameEvent.id());
+        assert_eq!(reader.read_u8().unwrap(), 7);
+        assert_eq!(reader.read_f32().unwrap(), 0.5);
+        assert_eq!(reader.remaining(), 0);
+    }
+
+    #[test]
+    fn close_container_is_the_window_id() {
+        let packet = close_container(3);
+        let mut reader = PacketReader::new(&packet);
+        assert_eq!(reader.read_ivar().unwrap(), Packet::CloseContainer.id());
+        assert_eq!(reader.read_bytes(reader.remaining()).unwrap(), [3]);
+    }
+
+    #[test]
+    fn set_container_slot_with_one_stack() {
+        let packet = set_container_slot(0, 5, 36, &Slot::new(1, 64));
+        let mut reader = PacketReader::new(&packet);
+        assert_eq!(reader.read_ivar().unwrap(), Packet::SetContainerSlot.id());
+        assert_eq!(reader.read_u8().unwrap(), 0);
+        assert_eq!(reader.read_ivar().unwrap(), 5);
+        assert_eq!(reader.read_i16().unwrap(), 36);
+        assert_eq!(Slot::read(&mut reader).unwrap(), Slot::new(1, 64));
+        assert_eq!(reader.remaining(), 0);
+    }
+
+    #[test]
+    fn multi_block_change_with_three_changes_in_one_section() {
+        let packet = multi_block_change((-1, 4, 2), &[(0, 0, 0, 1), (15, 15, 15, 9), (3, 2, 1, 0x1234)]);
+        let mut reader = PacketReader::new(&packet);
+        assert_eq!(reader.read_ivar().unwrap(), Packet::UpdateSectionBlocks.id());
+        let section = reader.read_i64().unwrap();
+        assert_eq!(section >> 42, -1);
+        assert_eq!((section >> 20) & 0x3FFFFF, 2);
+        assert_eq!(section & 0xFFFFF, 4);
+        assert_eq!(reader.read_ivar().unwrap(), 3);
+        assert_eq!(reader.read_lvar().unwrap(), 1 << 12);
+        assert_eq!(reader.read_lvar().unwrap(), (9 << 12) | 0xFFF);
+        assert_eq!(reader.read_lvar().unwrap(), (0x1234 << 12) | (3 << 8) | (1 << 4) | 2);
+        assert_eq!(reader.remaining(), 0);
+    }
+
+    #[test]
+    fn unload_chunk_is_z_then_x_as_ints() {
+        let packet = unload_chunk(3, -7);
+        let mut reader = PacketReader::new(&packet);
+        assert_eq!(reader.read_ivar().unwrap(), Packet::UnloadChunk.id());
+        assert_eq!(reader.read_i32().unwrap(), -7);
+        assert_eq!(reader.read_i32().unwrap(), 3);
+        assert_eq!(reader.remaining(), 0);
+    }
+
+    #[test]
+    fn set_ticking_state_is_rate_then_frozen() {
+        let packet = set_ticking_state(20.0, true);
+        let mut reader = PacketReader::new(&packet);
+        assert_eq!(reader.read_ivar().unwrap(), Packet::SetTickingState.id());
+        assert_eq!(reader.read_f32().unwrap(), 20.0);
+        assert!(reader.read_bool().unwrap());
+        assert_eq!(reader.remaining(), 0);
+    }
+
+    #[test]
+    fn step_tick_is_the_step_count() {
+        let packet = step_tick(300);
+        let mut reader = PacketReader::new(&packet);
+        assert_eq!(reader.read_ivar().unwrap(), Packet::StepTick.id());
+        assert_eq!(reader.read_ivar().unwrap(), 300);
+        assert_eq!(reader.remaining(), 0);
+    }
+
+    #[test]
+    fn small_moves_are_sent_as_deltas() {
+        let packet = entity_movement(300, (0.0, 64.0, 0.0), (1.5, 64.0, -0.25), (Angle(0), Angle(0)), false, true);
+        let mut reader = PacketReader::new(&packet);
+        assert_eq!(reader.read_ivar().unwrap(), Packet::UpdateEntityPosition.id());
+        assert_eq!(reader.read_ivar().unwrap(), 300);
+        assert_eq!(reader.read_i16().unwrap(), 6144);
+        assert_eq!(reader.read_i16().unwrap(), 0);
+        assert_eq!(reader.read_i16().unwrap(), -1024);
+        assert!(reader.read_bool().unwrap());
+        assert_eq!(reader.remaining(), 0);
+
+        let rotated = entity_movement(300, (0.0, 64.0, 0.0), (1.5, 64.0, -0.25), (Angle(64), Angle(32)), true, true);
+        assert_eq!(rotated, update_entity_position_and_rotation(300, (6144, 0, -1024), Angle(64), Angle(32), true));
+    }
+
+    #[test]
+    fn large_moves_fall_back_to_a_teleport() {
+        let packet = entity_movement(300, (0.0, 64.0, 0.0), (8.0, 64.0, 0.0), (Angle(64), Angle(0)), false, false);
+        let mut reader = PacketReader::new(&packet);
+        assert_eq!(reader.read_ivar().unwrap(), Packet::TeleportEntity.id());
+        assert_eq!(reader.read_ivar().unwrap(), 300);
+        assert_eq!(reader.read_f64().unwrap(), 8.0);
+        assert_eq!(reader.read_f64().unwrap(), 64.0);
+        assert_eq!(reader.read_f64().unwrap(), 0.0);
+        assert_eq!(reader.read_u8().unwrap(), 64);
+        assert_eq!(reader.read_u8().unwrap(), 0);
+        assert!(!reader.read_bool().unwrap());
+        assert_eq!(reader.remaining(), 0);
+        // Just under 8 blocks still fits in a short
+        assert_eq!(position_delta(0.0, 7.99), Some(32727));
+        assert_eq!(position_delta(0.0, -8.0), Some(i16::MIN));
+        assert_eq!(position_delta(0.0, 8.0), None);
+    }
+
+    #[test]
+    fn turning_on_the_spot_only_sends_the_rotation() {
+        let packet = entity_movement(300, (0.5, 64.0, 0.5), (0.5, 64.0, 0.5), (Angle(64), Angle(224)), true, true);
+        let mut reader = PacketReader::new(&packet);
+        assert_eq!(reader.read_ivar().unwrap(), Packet::UpdateEntityRotation.id());
+        assert_eq!(reader.read_ivar().unwrap(), 300);
+        assert_eq!(reader.read_u8().unwrap(), 64);
+        assert_eq!(reader.read_u8().unwrap(), 224);
+        assert!(reader.read_bool().unwrap());
+        assert_eq!(reader.remaining(), 0);
+    }
+
+    #[test]
+    fn join_game_death_location_is_optional() {
+        let without = join_game(&JoinGame::default());
+        // No death location, no portal cooldown and no secure chat
+        assert!(without.ends_with(&[0, 0, 0]));
+
+        let death = Position::new(10, 64, -3);
+        let with = join_game(&JoinGame {
+            death_location: Some(("minecraft:overworld".into(), death)),
+            ..JoinGame::default()
+        });
+        let mut expected = without[..without.len() - 3].to_vec();
+        expected.push(1);
+        write_utf8_string(&mut expected, "minecraft:overworld".into());
+        expected.extend_from_slice(&death.as_bytes());
+        expected.extend_from_slice(&[0, 0]);
+        assert_eq!(with, expected);
+
+        let mut reader = PacketReader::new(&with[with.len() - 10..]);
+        assert_eq!(reader.read_position().unwrap(), death);
+    }
+
+    #[test]
+    fn empty_award_statistics_is_just_the_count() {
+        let mut expected = Vec::new();
+        write_ivar(&mut expected, Packet::AwardStatistics.id());
+        expected.push(0);
+        assert_eq!(award_statistics(&[]), expected);
+
+        let packet = award_statistics(&[(8, 300, 2)]);
+        let mut reader = PacketReader::new(&packet);
+        assert_eq!(reader.read_ivar().unwrap(), Packet::AwardStatistics.id());
+        assert_eq!(reader.read_ivar().unwrap(), 1);
+        assert_eq!(reader.read_ivar().unwrap(), 8);
+        assert_eq!(reader.read_ivar().unwrap(), 300);
+        assert_eq!(reader.read_ivar().unwrap(), 2);
+        assert_eq!(reader.remaining(), 0);
+    }
+
+    #[test]
+    fn set_camera_is_the_entity_id() {
+        let mut expected = Vec::new();
+        write_ivar(&mut expected, Packet::SetCamera.id());
+        write_ivar(&mut expected, 300);
+        assert_eq!(set_camera(300), expected);
+        assert_eq!(set_camera(300)[expected.len() - 2..], [0xAC, 0x02]);
+    }
+
+    #[test]
+    fn look_at_a_position() {
+        let packet = look_at(Anchor::Feet, 1.5, 64.0, -2.0, None);
+        let mut reader = PacketReader::new(&packet);
+        assert_eq!(reader.read_ivar().unwrap(), Packet::LookAt.id());
+        assert_eq!(reader.read_ivar().unwrap(), 0);
+        assert_eq!(reader.read_f64().unwrap(), 1.5);
+        assert_eq!(reader.read_f64().unwrap(), 64.0);
+        assert_eq!(reader.read_f64().unwrap(), -2.0);
+        assert!(!reader.read_bool().unwrap());
+        assert_eq!(reader.remaining(), 0);
+    }
+
+    #[test]
+    fn look_at_an_entity() {
+        let packet = look_at(Anchor::Eyes, 0.0, 0.0, 0.0, Some((42, Anchor::Feet)));
+        let mut reader = PacketReader::new(&packet);
+        assert_eq!(reader.read_ivar().unwrap(), Packet::LookAt.id());
+        assert_eq!(reader.read_ivar().unwrap(), 1);
+        reader.read_bytes(24).unwrap();
+        assert!(reader.read_bool().unwrap());
+        assert_eq!(reader.read_ivar().unwrap(), 42);
+        assert_eq!(reader.read_ivar().unwrap(), 0);
+        assert_eq!(reader.remaining(), 0);
+    }
+
+    #[test]
+    fn stop_sound_flags_follow_what_is_given() {
+        let id = Packet::StopSound.id() as u8;
+        assert_eq!(stop_sound(None, None), vec![id, 0]);
+        assert_eq!(stop_sound(Some(SoundCategory::Weather), None), vec![id, 1, 3]);
+        let mut both = vec![id, 3, 7];
+        write_utf8_string(&mut both, "minecraft:entity.player.hurt".to_string());
+        assert_eq!(stop_sound(Some(SoundCategory::Players), Some("minecraft:entity.player.hurt")), both);
+        let mut sound_only = vec![id, 2];
+        write_utf8_string(&mut sound_only, "minecraft:music.game".to_string());
+        assert_eq!(stop_sound(None, Some("minecraft:music.game")), sound_only);
+    }
+
+    #[test]
+    fn entity_sound_effect_layout() {
+        let packet = entity_sound_effect("minecraft:entity.player.levelup", SoundCategory::Players, 12, 0.5, 1.5, 99);
+        let mut reader = PacketReader::new(&packet);
+        assert_eq!(reader.read_ivar().unwrap(), Packet::EntitySoundEffect.id());
+        // Given inline rather than by registry id, without a fixed range
+        assert_eq!(reader.read_ivar().unwrap(), 0);
+        assert_eq!(reader.read_utf8_string(64).unwrap(), "minecraft:entity.player.levelup");
+        assert!(!reader.read_bool().unwrap());
+        assert_eq!(reader.read_ivar().unwrap(), 7);
+        assert_eq!(reader.read_ivar().unwrap(), 12);
+        assert_eq!(reader.read_f32().unwrap(), 0.5);
+        assert_eq!(reader.read_f32().unwrap(), 1.5);
+        assert_eq!(reader.read_bytes(8).unwrap(), 99i64.to_be_bytes());
+        assert_eq!(reader.remaining(), 0);
+    }
+
+    #[test]
+    fn respawn_ends_with_the_data_kept() {
+        let packet = respawn(&JoinGame::default(), DataKept::Nothing);
+        assert_eq!(packet[0] as i32, Packet::Respawn.id());
+        assert_eq!(packet.last(), Some(&0));
+    }
+
+    #[test]
+    fn initialize_chat_with_and_without_a_session() {
+        let session = ChatSession { session_id: 9, expires_at: 1234, public_key: vec![1, 2, 3], key_signature: vec![4, 5] };
+        let packet = player_info_init_chat(&[(1, Some(session)), (2, None)]);
+        let mut reader = PacketReader::new(&packet);
+        assert_eq!(reader.read_ivar().unwrap(), Packet::PlayerInfoUpdate.id());
+        assert_eq!(reader.read_bytes(1).unwrap(), [PLAYER_INFO_INITIALIZE_CHAT]);
+        assert_eq!(reader.read_ivar().unwrap(), 2);
+        assert_eq!(reader.read_bytes(16).unwrap(), 1u128.to_be_bytes());
+        assert!(reader.read_bool().unwrap());
+        assert_eq!(reader.read_bytes(16).unwrap(), 9u128.to_be_bytes());
+        assert_eq!(reader.read_bytes(8).unwrap(), 1234i64.to_be_bytes());
+        assert_eq!(reader.read_ivar().unwrap(), 3);
+        assert_eq!(reader.read_bytes(3).unwrap(), [1, 2, 3]);
+        assert_eq!(reader.read_ivar().unwrap(), 2);
+        assert_eq!(reader.read_bytes(2).unwrap(), [4, 5]);
+        assert_eq!(reader.read_bytes(16).unwrap(), 2u128.to_be_bytes());
+        assert!(!reader.read_bool().unwrap());
+        assert_eq!(reader.remaining(), 0);
+    }
+}
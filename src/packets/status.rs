@@ -0,0 +1,12 @@
+// Clientbound packets for the Status state
+// https://wiki.vg/Protocol#Status
+
+use crate::{protocol::Packet, utils::write_ivar};
+
+/// Echoes the payload of a Ping Request so the client can time the round trip
+pub fn pong_response(payload: i64) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_ivar(&mut buffer, Packet::PongResponse.id());
+    buffer.extend_from_slice(&payload.to_be_bytes());
+    buffer
+}
@@ -0,0 +1,1711 @@
+// Serverbound packet handling for the Play state
+// https://wiki.vg/Protocol#Play
+
+use std::{sync::{Arc, Mutex}, time::{Instant, SystemTime}};
+
+use log::{debug, error, info, warn};
+
+use crate::{admin, chat::{parse_chat_message, parse_player_session, ChatSession}, combat, commands::{self, CommandSender}, config::SpamAction, cookie::{self, MAX_COOKIE_SIZE}, events::{Event, InteractionType}, packets::{self, play::{entity_status, DataKept, EntityAnimation}}, player::{Gamemode, Player}, protocol::Version, recipe, registry::chat_type, resource_pack::ResourcePackStatus, server::Server, status_response::Motd, types::{angle::Angle, direction::Direction, metadata::Pose, position::Position, reader::PacketReader, slot::Slot, text_component::TextComponent}, world::{self, block_entity::{BlockEntity, SignBlockEntity}, edit::AIR}};
+
+/// Login (play) fields for the player, also used for Respawn
+fn join_game(server: &Server, player: &Player) -> packets::play::JoinGame {
+    packets::play::JoinGame {
+        entity_id: player.entity_id(),
+        max_players: server.config.max_players as i32,
+        gamemode: player.gamemode(),
+        enforces_secure_chat: server.config.enforce_secure_chat,
+        view_distance: player.view_distance(),
+        simulation_distance: player.simulation_distance(),
+        death_location: player.death_location(),
+        reduced_debug_info: server.config.reduced_debug_info,
+        enable_respawn_screen: server.respawn_screen_enabled(),
+        hashed_seed: world::seed_hash(server.config.world.seed),
+        ..Default::default()
+    }
+}
+
+/// Sends the Login (play) packet that puts the client into the world.
+/// The MOTD is expanded by the caller since counting players while holding a player lock could deadlock.
+pub fn join(server: &Server, player: &mut Player, motd: &Motd) -> Result<(), std::io::Error> {
+    player.send_packet(&packets::play::join_game(&join_game(server, player)))?;
+    // Tells the client which operator features (gamemode switcher, command suggestions) to unlock
+    let op_level = (entity_status::OP_PERMISSION_LEVEL_0 + player.permission_level()).min(entity_status::OP_PERMISSION_LEVEL_4);
+    player.send_packet(&packets::play::entity_event(player.entity_id(), op_level))?;
+    player.send_packet(&packets::play::server_data(
+        &TextComponent::new(motd.plain_text()),
+        server.icon().map(|icon| icon.png().to_vec()),
+        server.config.enforce_secure_chat,
+    ))?;
+    player.send_packet(&packets::play::initialize_world_border(&server.border()))?;
+    let (x, y, z) = server.config.world.spawn;
+    player.send_packet(&packets::play::set_default_spawn_position(Position::new(x, y, z), 0.0))?;
+    player.send_packet(&packets::play::update_recipes(&recipe::starter_recipes()))?;
+    player.send_packet(&packets::play::set_ticking_state(server.config.tick_rate, server.is_frozen()))?;
+    for packet in server.weather().packets() {
+        player.send_packet(&packet)?;
+    }
+    restore_last_position(server, player)?;
+    player.update_entities(&server.item_entities())?;
+    // Chunks around spawn are queued and sent in batches by the tick loop
+    player.send_packet(&packets::play::game_event(packets::play::GameEvent::StartWaitingForLevelChunks, 0.0))?;
+    player.update_chunks()
+}
+
+/// Transferred players carry on from where the last server left them.
+/// Cookies come from the client, so positions outside the world border are ignored.
+fn restore_last_position(server: &Server, player: &mut Player) -> Result<(), std::io::Error> {
+    let Some((x, y, z)) = player.cookies().get::<(f64, f64, f64)>(cookie::LAST_POSITION) else {
+        return Ok(());
+    };
+    if !(x.is_finite() && y.is_finite() && z.is_finite()) || server.border().distance_outside(x, z) > 0.0 {
+        warn!("{} was transferred with an invalid position, ignoring", player.name());
+        return Ok(());
+    }
+    player.teleport(x, y, z)
+}
+
+/// Adds a newly joined player to everyone's tab list and announces them in chat
+pub fn announce_join(server: &Server, player: &Arc<Mutex<Player>>) {
+    let mut entries: Vec<(u128, String, Gamemode)> = Vec::new();
+    let mut sessions: Vec<(u128, Option<ChatSession>)> = Vec::new();
+    for player in server.online_players() {
+        let player = player.lock().unwrap();
+        entries.push((player.uuid(), player.name(), player.gamemode()));
+        if let Some(session) = player.chat_session() {
+            sessions.push((player.uuid(), Some(session.clone())));
+        }
+    }
+    let (uuid, name, gamemode) = {
+        let mut player = player.lock().unwrap();
+        if let Err(e) = player.send_packet(&packets::play::player_info_add(&entries)) {
+            error!("Could not send the player list to {}!\n{e:?}", player.name());
+        }
+        if !sessions.is_empty() {
+            if let Err(e) = player.send_packet(&packets::play::player_info_init_chat(&sessions)) {
+                error!("Could not send chat sessions to {}!\n{e:?}", player.name());
+            }
+        }
+        player.set_announced();
+        (player.uuid(), player.name(), player.gamemode())
+    };
+    server.broadcast_except(&packets::play::player_info_add(&[(uuid, name.clone(), gamemode)]), uuid);
+    let message = TextComponent::translate("multiplayer.player.joined", vec![TextComponent::new(name)]).color("yellow");
+    server.broadcast(&packets::play::system_chat(&message, false));
+    server.fire_event(&Event::PlayerJoin { player: uuid });
+}
+
+pub fn handle_packet(server: &Server, player: &Arc<Mutex<Player>>, packet_id: i32, buffer: &[u8]) -> Result<(), &'static str> {
+    let mut reader = PacketReader::new(buffer);
+    match packet_id {
+        // Signed commands start the same way, their signatures aren't checked
+        0x04 | 0x05 => chat_command(server, player, &mut reader),
+        0x06 => chat_message(server, player, &mut reader),
+        0x07 => player_session(server, player, &mut reader),
+        0x08 => {
+            player.lock().unwrap().chunk_batch_received(reader.read_f32()?);
+            Ok(())
+        },
+        0x09 => client_status(server, player, &mut reader),
+        0x0A => client_information(player, &mut reader),
+        0x0E => {
+            let uuid = player.lock().unwrap().uuid();
+            let click = parse_click_container(&mut reader)?;
+            server.fire_event(&Event::ClickContainer { player: uuid, click });
+            Ok(())
+        },
+        0x0F => close_container(player, &mut reader),
+        0x11 => cookie_response(server, player, &mut reader),
+        0x16 => interact(server, player, &mut reader),
+        0x18 => {
+            player.lock().unwrap().handle_keep_alive(reader.read_i64()?);
+            Ok(())
+        },
+        0x1A => set_player_position(server, player, &mut reader),
+        0x1B => set_player_position_and_rotation(server, player, &mut reader),
+        0x1C => set_player_rotation(player, &mut reader),
+        0x1D => {
+            player.lock().unwrap().set_on_ground(reader.read_bool()?);
+            Ok(())
+        },
+        0x1E => move_vehicle(server, player, &mut reader),
+        0x25 => player_command(server, player, &mut reader),
+        0x26 => {
+            let (uuid, protocol_version) = {
+                let player = player.lock().unwrap();
+                (player.uuid(), player.protocol_version())
+            };
+            let input = parse_player_input(&mut reader, protocol_version)?;
+            if protocol_version >= KEY_INPUT_PROTOCOL {
+                sync_sneaking(server, player, input.sneak);
+            }
+            if input.sneak {
+                dismount(server, player);
+            }
+            server.fire_event(&Event::PlayerInput { player: uuid, input });
+            Ok(())
+        },
+        0x27 => {
+            player.lock().unwrap().handle_pong(reader.read_i32()?);
+            Ok(())
+        },
+        0x22 => place_recipe(player, &mut reader),
+        0x24 => player_action(server, player, &mut reader),
+        0x28 => {
+            // Nothing keeps player data between sessions, so the settings aren't stored
+            let (book, settings) = parse_recipe_book_settings(&mut reader)?;
+            debug!("{} set the {book:?} recipe book to open {}, filtering {}", player.lock().unwrap().name(), settings.open, settings.filter);
+            Ok(())
+        },
+        0x29 => {
+            // Nothing is unlocked yet so there is nothing to mark as seen
+            let recipe = reader.read_utf8_string(32767)?;
+            debug!("{} saw recipe {recipe}", player.lock().unwrap().name());
+            Ok(())
+        },
+        0x2B => resource_pack_response(server, player, &mut reader),
+        0x2F => set_held_item(player, &mut reader),
+        0x30 | 0x31 | 0x33 | 0x34 => admin::handle_program(server, player, packet_id, &mut reader),
+        0x32 => creative_mode_slot(server, player, &mut reader),
+        0x35 => {
+            let (position, is_front_text, lines) = parse_update_sign(&mut reader)?;
+            update_sign(server, player, position, is_front_text, &lines);
+            Ok(())
+        },
+        0x36 => swing_arm(server, player, &mut reader),
+        0x37 => teleport_to_entity(server, player, &mut reader),
+        0x38 => use_item_on(server, player, &mut reader),
+        0x39 => use_item(player, &mut reader),
+        _ => {
+            debug!("Unhandled play packet: {packet_id:#x}");
+            Ok(())
+        },
+    }
+}
+
+fn client_status(server: &Server, player: &Arc<Mutex<Player>>, reader: &mut PacketReader) -> Result<(), &'static str> {
+    let mut player = player.lock().unwrap();
+    match reader.read_ivar()? {
+        0 => {
+            let _ = respawn(server, &mut player);
+            Ok(())
+        },
+        1 => {
+            // Statistics aren't tracked yet, an empty list still lets the statistics screen load
+            let _ = player.send_packet(&packets::play::award_statistics(&[]));
+            Ok(())
+        },
+        _ => Err("Unknown client status action"),
+    }
+}
+
+/// Brings a dead player back at the world spawn
+pub fn respawn(server: &Server, player: &mut Player) -> Result<(), std::io::Error> {
+    if player.health() > 0.0 {
+        return Ok(());
+    }
+    player.send_packet(&packets::play::respawn(&join_game(server, player), DataKept::Nothing))?;
+    player.restore_health()?;
+    // The client resets its attributes and abilities with the new player entity
+    player.set_walk_speed(player.walk_speed())?;
+    player.send_abilities()?;
+    let (x, y, z) = server.config.world.spawn;
+    player.send_packet(&packets::play::set_default_spawn_position(Position::new(x, y, z), 0.0))?;
+    player.teleport(x as f64 + 0.5, y as f64, z as f64 + 0.5)?;
+    player.reset_chunks();
+    player.update_entities(&server.item_entities())?;
+    player.update_chunks()
+}
+
+/// Runs the command and sends the feedback back to the player
+fn chat_command(server: &Server, player: &Arc<Mutex<Player>>, reader: &mut PacketReader) -> Result<(), &'static str> {
+    let command = reader.read_utf8_string(256)?;
+    let (uuid, name) = {
+        let player = player.lock().unwrap();
+        (player.uuid(), player.name())
+    };
+    info!("{name} issued server command: /{command}");
+    let feedback = match commands::dispatch(server, &CommandSender::Player(uuid), &command) {
+        Ok(feedback) => TextComponent::new(feedback),
+        Err(e) => TextComponent::new(e).color("red"),
+    };
+    // The command may have disconnected the player, such as /stop
+    let _ = player.lock().unwrap().send_packet(&packets::play::system_chat(&feedback, false));
+    Ok(())
+}
+
+/// Stores the session whose key the player's chat messages are signed with
+fn player_session(server: &Server, player: &Arc<Mutex<Player>>, reader: &mut PacketReader) -> Result<(), &'static str> {
+    let session = parse_player_session(reader)?;
+    let uuid = {
+        let mut player = player.lock().unwrap();
+        if server.config.enforce_secure_chat && session.is_expired(SystemTime::now()) {
+            let _ = player.disconnect(&TextComponent::translate("multiplayer.disconnect.expired_public_key", Vec::new()));
+            return Ok(());
+        }
+        debug!("{} started chat session {:x}", player.name(), session.session_id);
+        player.set_chat_session(session.clone());
+        player.uuid()
+    };
+    // Everyone needs the key to show the player's messages as signed
+    server.broadcast(&packets::play::player_info_init_chat(&[(uuid, Some(session))]));
+    Ok(())
+}
+
+/// Messages with a signature that doesn't check out are treated as unsigned unless secure chat is enforced
+fn chat_message(server: &Server, player: &Arc<Mutex<Player>>, reader: &mut PacketReader) -> Result<(), &'static str> {
+    let chat = parse_chat_message(reader)?;
+    let enforced = server.config.enforce_secure_chat;
+
+    let (uuid, name, signed) = {
+        let mut player = player.lock().unwrap();
+        if enforced && chat.signature.is_none() {
+            let _ = player.disconnect(&TextComponent::translate("multiplayer.disconnect.unsigned_chat", Vec::new()));
+            return Ok(());
+        }
+        let signed = chat.signature.is_some() && player.verify_chat(&chat);
+        if chat.signature.is_some() && !signed {
+            if enforced {
+                let _ = player.disconnect(&TextComponent::translate("multiplayer.disconnect.chat_validation_failed", Vec::new()));
+                return Ok(());
+            }
+            debug!("Chat from {} has an invalid signature, treating it as unsigned", player.name());
+        }
+        if !player.chat_limiter().check(Instant::now()) {
+            match server.config.chat_spam_action {
+                SpamAction::Ignore => {
+                    debug!("Ignoring chat from {}, over the rate limit", player.name());
+                },
+                SpamAction::Kick => {
+                    let _ = player.disconnect(&TextComponent::translate("disconnect.spam", Vec::new()));
+                },
+            }
+            return Ok(());
+        }
+        (player.uuid(), player.name(), signed)
+    };
+
+    let message = chat.message;
+    info!("<{name}> {message}");
+    // Relayed without the signature, the client formats it with the chat type like any other message
+    let chat_type = chat_type::id("minecraft:chat").unwrap_or(0);
+    server.broadcast(&packets::play::disguised_chat(&TextComponent::new(message.clone()), chat_type, &TextComponent::new(name), None));
+    server.fire_event(&Event::ChatMessage { player: uuid, message, signed });
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClickContainer {
+    pub window_id: u8,
+    pub state_id: i32,
+    /// -999 when clicking outside of the window
+    pub slot: i16,
+    pub button: i8,
+    pub mode: i32,
+    /// Slots the client predicts changed, as (slot, new contents)
+    pub changed_slots: Vec<(i16, Slot)>,
+    pub carried_item: Slot,
+}
+
+pub fn parse_click_container(reader: &mut PacketReader) -> Result<ClickContainer, &'static str> {
+    let window_id = reader.read_u8()?;
+    let state_id = reader.read_ivar()?;
+    let slot = reader.read_i16()?;
+    let button = reader.read_u8()? as i8;
+    let mode = reader.read_ivar()?;
+    if !(0..=6).contains(&mode) {
+        return Err("Invalid click mode");
+    }
+    let length = reader.read_ivar()?;
+    // The client never changes more than the 128 slots a container can have
+    if !(0..=128).contains(&length) {
+        return Err("Too many changed slots");
+    }
+    let mut changed_slots = Vec::with_capacity(length as usize);
+    for _ in 0..length {
+        changed_slots.push((reader.read_i16()?, Slot::read(reader)?));
+    }
+    let carried_item = Slot::read(reader)?;
+    Ok(ClickContainer { window_id, state_id, slot, button, mode, changed_slots, carried_item })
+}
+
+fn set_held_item(player: &Arc<Mutex<Player>>, reader: &mut PacketReader) -> Result<(), &'static str> {
+    let slot = reader.read_i16()?;
+    let mut player = player.lock().unwrap();
+    if i16::from(player.selected_slot()) == slot {
+        return Ok(());
+    }
+    player.set_selected_slot(slot)
+}
+
+fn close_container(player: &Arc<Mutex<Player>>, reader: &mut PacketReader) -> Result<(), &'static str> {
+    let window_id = reader.read_u8()?;
+    let mut player = player.lock().unwrap();
+    match player.open_window() {
+        Some(open) if open != window_id => debug!("{} closed window {window_id} while {open} was open", player.name()),
+        _ => debug!("{} closed window {window_id}", player.name()),
+    }
+    player.set_open_window(None);
+    Ok(())
+}
+
+/// Slot -1 drops the item out of the inventory
+pub fn parse_creative_mode_slot(reader: &mut PacketReader) -> Result<(i16, Slot), &'static str> {
+    let slot = reader.read_i16()?;
+    if !(-1..=45).contains(&slot) {
+        return Err("Creative slot out of range");
+    }
+    Ok((slot, Slot::read(reader)?))
+}
+
+fn creative_mode_slot(server: &Server, player: &Arc<Mutex<Player>>, reader: &mut PacketReader) -> Result<(), &'static str> {
+    let (slot, item) = parse_creative_mode_slot(reader)?;
+    let uuid = {
+        let mut player = player.lock().unwrap();
+        if player.gamemode() != Gamemode::Creative {
+            debug!("{} set a creative slot outside of creative, ignoring", player.name());
+            return Ok(());
+        }
+        if slot >= 0 {
+            player.update_inventory_slot(slot as usize, item)?;
+        }
+        player.uuid()
+    };
+    server.fire_event(&Event::CreativeModeSlot { player: uuid, slot, item });
+    Ok(())
+}
+
+pub fn parse_interact(player: u128, reader: &mut PacketReader) -> Result<Event, &'static str> {
+    let target = reader.read_ivar()?;
+    let (interaction, hand) = match reader.read_ivar()? {
+        0 => (InteractionType::Interact, Some(reader.read_ivar()?)),
+        1 => (InteractionType::Attack, None),
+        2 => {
+            let (x, y, z) = (reader.read_f32()?, reader.read_f32()?, reader.read_f32()?);
+            (InteractionType::InteractAt { x, y, z }, Some(reader.read_ivar()?))
+        },
+        _ => return Err("Invalid interaction type"),
+    };
+    let sneaking = reader.read_bool()?;
+    Ok(Event::InteractEntity { player, target, interaction, hand, sneaking })
+}
+
+fn interact(server: &Server, player: &Arc<Mutex<Player>>, reader: &mut PacketReader) -> Result<(), &'static str> {
+    let (uuid, spectating) = {
+        let player = player.lock().unwrap();
+        (player.uuid(), player.gamemode() == Gamemode::Spectator)
+    };
+    let event = parse_interact(uuid, reader)?;
+    match event {
+        // Spectators attacking a player view the world through their eyes
+        Event::InteractEntity { target, interaction: InteractionType::Attack, .. }
+            if spectating && server.online_players().iter().any(|other| !Arc::ptr_eq(other, player) && other.lock().unwrap().entity_id() == target) => {
+            let _ = player.lock().unwrap().set_camera(target);
+        },
+        Event::InteractEntity { target, interaction: InteractionType::Attack, .. } if !spectating => attack(server, player, target),
+        Event::InteractEntity { target, interaction: InteractionType::Interact, .. } if !spectating => mount(server, player, target),
+        _ => (),
+    }
+    server.fire_event(&event);
+    Ok(())
+}
+
+/// Hitting another player knocks them away from the attacker, unless they can't be hurt
+fn attack(server: &Server, player: &Arc<Mutex<Player>>, target: i32) {
+    let (x, _, z) = player.lock().unwrap().position();
+    for other in server.online_players() {
+        if Arc::ptr_eq(&other, player) {
+            continue;
+        }
+        let mut other = other.lock().unwrap();
+        if other.entity_id() == target && matches!(other.gamemode(), Gamemode::Survival | Gamemode::Adventure) {
+            let _ = combat::apply_knockback(&mut other, x, z, combat::ATTACK_KNOCKBACK);
+        }
+    }
+}
+
+/// Right clicking a vehicle nobody is riding gets in it
+fn mount(server: &Server, player: &Arc<Mutex<Player>>, vehicle: i32) {
+    if !server.vehicles().contains_key(&vehicle) || server.online_players().iter().any(|p| p.lock().unwrap().vehicle() == Some(vehicle)) {
+        return;
+    }
+    let entity_id = {
+        let mut player = player.lock().unwrap();
+        player.set_vehicle(Some(vehicle));
+        player.entity_id()
+    };
+    server.broadcast(&packets::play::set_passengers(vehicle, &[entity_id]));
+}
+
+/// Sneaking gets out of the vehicle
+fn dismount(server: &Server, player: &Arc<Mutex<Player>>) {
+    let vehicle = {
+        let mut player = player.lock().unwrap();
+        let vehicle = player.vehicle();
+        player.set_vehicle(None);
+        vehicle
+    };
+    if let Some(vehicle) = vehicle {
+        server.broadcast(&packets::play::set_passengers(vehicle, &[]));
+    }
+}
+
+fn set_player_position(server: &Server, player: &Arc<Mutex<Player>>, reader: &mut PacketReader) -> Result<(), &'static str> {
+    let (x, y, z) = (reader.read_f64()?, reader.read_f64()?, reader.read_f64()?);
+    let on_ground = reader.read_bool()?;
+    let mut player = player.lock().unwrap();
+    player.set_position(x, y, z);
+    player.set_on_ground(on_ground);
+    let _ = player.update_chunks();
+    let _ = player.update_entities(&server.item_entities());
+    Ok(())
+}
+
+fn set_player_position_and_rotation(server: &Server, player: &Arc<Mutex<Player>>, reader: &mut PacketReader) -> Result<(), &'static str> {
+    let (x, y, z) = (reader.read_f64()?, reader.read_f64()?, reader.read_f64()?);
+    let (yaw, pitch) = (reader.read_f32()?, reader.read_f32()?);
+    let on_ground = reader.read_bool()?;
+    let mut player = player.lock().unwrap();
+    player.set_position(x, y, z);
+    player.set_rotation(yaw, pitch);
+    player.set_on_ground(on_ground);
+    let _ = player.update_chunks();
+    let _ = player.update_entities(&server.item_entities());
+    Ok(())
+}
+
+fn set_player_rotation(player: &Arc<Mutex<Player>>, reader: &mut PacketReader) -> Result<(), &'static str> {
+    let (yaw, pitch) = (reader.read_f32()?, reader.read_f32()?);
+    let on_ground = reader.read_bool()?;
+    let mut player = player.lock().unwrap();
+    player.set_rotation(yaw, pitch);
+    player.set_on_ground(on_ground);
+    Ok(())
+}
+
+/// Client Information, sent during configuration and again whenever the client's settings change
+// https://wiki.vg/Protocol#Client_Information_(configuration)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientInformation {
+    pub locale: String,
+    pub view_distance: i32,
+    pub chat_mode: i32,
+    pub chat_colors: bool,
+    pub displayed_skin_parts: u8,
+    /// 0 for left, 1 for right
+    pub main_hand: i32,
+    pub text_filtering: bool,
+    pub allow_server_listings: bool,
+}
+
+/// Fields added after 1.21.1 are left unread
+pub fn parse_client_information(reader: &mut PacketReader) -> Result<ClientInformation, &'static str> {
+    Ok(ClientInformation {
+        locale: reader.read_utf8_string(16)?,
+        view_distance: reader.read_u8()? as i8 as i32,
+        chat_mode: reader.read_ivar()?,
+        chat_colors: reader.read_bool()?,
+        displayed_skin_parts: reader.read_u8()?,
+        main_hand: reader.read_ivar()?,
+        text_filtering: reader.read_bool()?,
+        allow_server_listings: reader.read_bool()?,
+    })
+}
+
+pub fn client_information(player: &Arc<Mutex<Player>>, reader: &mut PacketReader) -> Result<(), &'static str> {
+    let information = parse_client_information(reader)?;
+    let mut player = player.lock().unwrap();
+    debug!("{} has a view distance of {}", player.name(), information.view_distance);
+    let _ = player.set_client_view_distance(information.view_distance);
+    Ok(())
+}
+
+/// Movement keys held while riding or flying, from 1.21.2 the client only sends which keys are down
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct PlayerInput {
+    /// Positive to the left
+    pub sideways: f32,
+    pub forward: f32,
+    pub jump: bool,
+    /// Also used to dismount
+    pub sneak: bool,
+    /// Only sent by 1.21.2 and later
+    pub sprint: bool,
+}
+
+/// First protocol version (1.21.2) with the key based Player Input
+pub const KEY_INPUT_PROTOCOL: i32 = Version::V1_21_2 as i32;
+
+pub fn parse_player_input(reader: &mut PacketReader, protocol_version: i32) -> Result<PlayerInput, &'static str> {
+    if protocol_version < KEY_INPUT_PROTOCOL {
+        let sideways = reader.read_f32()?;
+        let forward = reader.read_f32()?;
+        let flags = reader.read_u8()?;
+        return Ok(PlayerInput {
+            sideways,
+            forward,
+            jump: flags & 0x01 != 0,
+            sneak: flags & 0x02 != 0,
+            sprint: false,
+        });
+    }
+    let flags = reader.read_u8()?;
+    let axis = |positive: u8, negative: u8| (flags & positive != 0) as i8 as f32 - (flags & negative != 0) as i8 as f32;
+    Ok(PlayerInput {
+        forward: axis(0x01, 0x02),
+        sideways: axis(0x04, 0x08),
+        jump: flags & 0x10 != 0,
+        sneak: flags & 0x20 != 0,
+        sprint: flags & 0x40 != 0,
+    })
+}
+
+/// From 1.21.2 sneaking is only sent as a held key, so the pose follows it
+fn sync_sneaking(server: &Server, player: &Arc<Mutex<Player>>, sneaking: bool) {
+    let (uuid, packet) = {
+        let mut player = player.lock().unwrap();
+        if (player.pose() == Pose::Sneaking) == sneaking {
+            return;
+        }
+        let pose = match sneaking {
+            true => Pose::Sneaking,
+            false => Pose::Standing,
+        };
+        (player.uuid(), player.set_pose(pose))
+    };
+    server.broadcast_except(&packet, uuid);
+}
+
+/// Cookie Responses can arrive in Login, Configuration and Play
+pub fn cookie_response(server: &Server, player: &Arc<Mutex<Player>>, reader: &mut PacketReader) -> Result<(), &'static str> {
+    let (key, payload) = parse_cookie_response(reader)?;
+    let uuid = {
+        let mut player = player.lock().unwrap();
+        player.handle_cookie_response(key.clone(), payload);
+        player.uuid()
+    };
+    server.fire_event(&Event::CookieResponse { player: uuid, key });
+    Ok(())
+}
+
+/// Resource Pack Response, shared by the Configuration and Play states
+pub fn resource_pack_response(server: &Server, player: &Arc<Mutex<Player>>, reader: &mut PacketReader) -> Result<(), &'static str> {
+    let pack = reader.read_u128()?;
+    let status = ResourcePackStatus::from_id(reader.read_ivar()?).ok_or("Unknown resource pack status")?;
+    let uuid = {
+        let mut player = player.lock().unwrap();
+        if let Err(e) = player.handle_resource_pack_response(pack, status) {
+            warn!("Could not disconnect {} after declining a resource pack: {e}", player.name());
+        }
+        player.uuid()
+    };
+    server.fire_event(&Event::ResourcePackResponse { player: uuid, pack, status });
+    Ok(())
+}
+
+/// (key, payload), no payload when the client has no cookie for the key
+pub fn parse_cookie_response(reader: &mut PacketReader) -> Result<(String, Option<Vec<u8>>), &'static str> {
+    let key = reader.read_utf8_string(32767)?;
+    if !reader.read_bool()? {
+        return Ok((key, None));
+    }
+    let length = reader.read_ivar()?;
+    if length < 0 || length as usize > MAX_COOKIE_SIZE {
+        return Err("Cookie payload is too large");
+    }
+    Ok((key, Some(reader.read_bytes(length as usize)?.to_vec())))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecipeBookType {
+    Crafting = 0,
+    Furnace = 1,
+    BlastFurnace = 2,
+    Smoker = 3,
+}
+
+impl RecipeBookType {
+    pub fn from_id(id: i32) -> Option<Self> {
+        match id {
+            0 => Some(Self::Crafting),
+            1 => Some(Self::Furnace),
+            2 => Some(Self::BlastFurnace),
+            3 => Some(Self::Smoker),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RecipeBookSettings {
+    pub open: bool,
+    /// Only show craftable recipes
+    pub filter: bool,
+}
+
+pub fn parse_recipe_book_settings(reader: &mut PacketReader) -> Result<(RecipeBookType, RecipeBookSettings), &'static str> {
+    let book = RecipeBookType::from_id(reader.read_ivar()?).ok_or("Unknown recipe book")?;
+    Ok((book, RecipeBookSettings {
+        open: reader.read_bool()?,
+        filter: reader.read_bool()?,
+    }))
+}
+
+/// Furthest a vehicle may move in one Move Vehicle, same as vanilla
+const MAX_VEHICLE_MOVE: f64 = 10.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MoveVehicle {
+    pub position: (f64, f64, f64),
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+pub fn parse_move_vehicle(reader: &mut PacketReader) -> Result<MoveVehicle, &'static str> {
+    Ok(MoveVehicle {
+        position: (reader.read_f64()?, reader.read_f64()?, reader.read_f64()?),
+        yaw: reader.read_f32()?,
+        pitch: reader.read_f32()?,
+    })
+}
+
+/// The client steers its own vehicle, the server keeps track of where it ends up and shows everyone else.
+/// Moves further than `MAX_VEHICLE_MOVE` in one go are put back instead.
+fn move_vehicle(server: &Server, player: &Arc<Mutex<Player>>, reader: &mut PacketReader) -> Result<(), &'static str> {
+    let movement = parse_move_vehicle(reader)?;
+    let (vehicle, uuid) = {
+        let player = player.lock().unwrap();
+        (player.vehicle(), player.uuid())
+    };
+    let vehicle = match vehicle {
+        Some(v) => v,
+        None => {
+            debug!("Move vehicle from a player who isn't riding anything, ignoring");
+            return Ok(());
+        },
+    };
+    let outcome = match server.vehicles().get_mut(&vehicle) {
+        Some(v) => {
+            let (x, y, z) = movement.position;
+            let (old_x, old_y, old_z) = v.position;
+            let distance_squared = (x - old_x).powi(2) + (y - old_y).powi(2) + (z - old_z).powi(2);
+            // NaN positions fail the comparison too
+            if distance_squared <= MAX_VEHICLE_MOVE * MAX_VEHICLE_MOVE {
+                let rotated = v.yaw != movement.yaw || v.pitch != movement.pitch;
+                let rotation = (Angle::from_degrees(movement.yaw), Angle::from_degrees(movement.pitch));
+                let packet = packets::play::entity_movement(v.entity_id, v.position, movement.position, rotation, rotated, false);
+                v.position = movement.position;
+                v.yaw = movement.yaw;
+                v.pitch = movement.pitch;
+                Ok(packet)
+            } else {
+                Err(packets::play::move_vehicle(v.position, v.yaw, v.pitch))
+            }
+        },
+        None => {
+            debug!("Vehicle {vehicle} doesn't exist, ignoring");
+            return Ok(());
+        },
+    };
+    match outcome {
+        Ok(packet) => server.broadcast_except(&packet, uuid),
+        Err(correction) => {
+            let mut player = player.lock().unwrap();
+            debug!("{} moved vehicle {vehicle} too quickly, putting it back", player.name());
+            let _ = player.send_packet(&correction);
+        },
+    }
+    Ok(())
+}
+
+/// Spectators teleporting to an entity, only players are tracked so far
+fn teleport_to_entity(server: &Server, player: &Arc<Mutex<Player>>, reader: &mut PacketReader) -> Result<(), &'static str> {
+    let target = reader.read_u128()?;
+    let (x, y, z) = match server.get_player(target) {
+        Some(target) => target.lock().unwrap().position(),
+        None => {
+            debug!("Spectate target {target:#x} is not online, ignoring");
+            return Ok(());
+        },
+    };
+    let mut player = player.lock().unwrap();
+    if player.gamemode() != Gamemode::Spectator {
+        debug!("{} tried to spectate without being a spectator, ignoring", player.name());
+        return Ok(());
+    }
+    let _ = player.teleport(x, y, z);
+    Ok(())
+}
+
+pub fn parse_update_sign(reader: &mut PacketReader) -> Result<(Position, bool, [String; 4]), &'static str> {
+    let position = reader.read_position()?;
+    let is_front_text = reader.read_bool()?;
+    let lines = [
+        reader.read_utf8_string(384)?,
+        reader.read_utf8_string(384)?,
+        reader.read_utf8_string(384)?,
+        reader.read_utf8_string(384)?,
+    ];
+    Ok((position, is_front_text, lines))
+}
+
+fn update_sign(server: &Server, player: &Arc<Mutex<Player>>, position: Position, is_front_text: bool, lines: &[String; 4]) {
+    if player.lock().unwrap().take_editing_sign() != Some((position, is_front_text)) {
+        debug!("Ignored sign update at {position:?}, the editor wasn't opened for it");
+        return;
+    }
+    let packet = {
+        let mut block_entities = server.block_entities();
+        let entity = block_entities.entry(position).or_insert_with(|| BlockEntity::Sign(SignBlockEntity::default()));
+        match entity {
+            BlockEntity::Sign(sign) => match is_front_text {
+                true => sign.front_text.set_lines(lines),
+                false => sign.back_text.set_lines(lines),
+            },
+        }
+        packets::play::block_entity_data(position, entity)
+    };
+    server.broadcast(&packet);
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct PlayerAction {
+    pub status: i32,
+    pub position: Position,
+    pub face: u8,
+    pub sequence: i32,
+}
+
+pub fn parse_player_action(reader: &mut PacketReader) -> Result<PlayerAction, &'static str> {
+    Ok(PlayerAction {
+        status: reader.read_ivar()?,
+        position: reader.read_position()?,
+        face: reader.read_u8()?,
+        sequence: reader.read_ivar()?,
+    })
+}
+
+fn player_action(server: &Server, player: &Arc<Mutex<Player>>, reader: &mut PacketReader) -> Result<(), &'static str> {
+    let action = parse_player_action(reader)?;
+    match action.status {
+        0 | 2 => {
+            let (uuid, gamemode) = {
+                let mut player = player.lock().unwrap();
+                // The client waits on the acknowledgement even when nothing is broken
+                let _ = player.send_packet(&packets::play::acknowledge_block_change(action.sequence));
+                (player.uuid(), player.gamemode())
+            };
+            if !breaks_block(gamemode, action.status) {
+                return Ok(());
+            }
+            let state = server.blocks().get(action.position);
+            match server.set_block(action.position, AIR) {
+                Ok(true) => server.fire_event(&Event::BlockBreak { player: uuid, position: action.position, state }),
+                Ok(false) => {},
+                Err(e) => debug!("Ignored digging at {:?}: {e}", action.position),
+            }
+        },
+        3 | 4 => {
+            let (dropped, (x, y, z)) = {
+                let mut player = player.lock().unwrap();
+                let dropped = player.drop_held_item(action.status == 3).map_err(|_| "Could not send the dropped slot")?;
+                (dropped, player.position())
+            };
+            // Thrown from just below the eyes, same as vanilla
+            if let Some(item) = dropped {
+                server.spawn_item(item, (x, y + 1.32, z));
+            }
+        },
+        _ => debug!("Unhandled player action status: {}", action.status),
+    }
+    Ok(())
+}
+
+/// Started digging breaks instantly in creative, finished digging is only sent in survival.
+/// Adventure and spectator players can't break blocks at all.
+fn breaks_block(gamemode: Gamemode, status: i32) -> bool {
+    matches!((gamemode, status), (Gamemode::Creative, 0) | (Gamemode::Survival, 2))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayerCommandAction {
+    /// Only sent before 1.21.2, later versions send sneaking in Player Input
+    StartSneaking,
+    StopSneaking,
+    LeaveBed,
+    StartSprinting,
+    StopSprinting,
+    StartJumpWithHorse,
+    StopJumpWithHorse,
+    OpenVehicleInventory,
+    StartFlyingWithElytra,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlayerCommand {
+    pub entity_id: i32,
+    pub action: PlayerCommandAction,
+    /// 0 to 100, only used by Start Jump With Horse
+    pub jump_boost: i32,
+}
+
+pub fn parse_player_command(reader: &mut PacketReader, protocol_version: i32) -> Result<PlayerCommand, &'static str> {
+    let entity_id = reader.read_ivar()?;
+    let mut action_id = reader.read_ivar()?;
+    let jump_boost = reader.read_ivar()?;
+    // 1.21.2 dropped the two sneaking actions from the start of the list
+    if protocol_version >= KEY_INPUT_PROTOCOL {
+        action_id += 2;
+    }
+    let action = match action_id {
+        0 => PlayerCommandAction::StartSneaking,
+        1 => PlayerCommandAction::StopSneaking,
+        2 => PlayerCommandAction::LeaveBed,
+        3 => PlayerCommandAction::StartSprinting,
+        4 => PlayerCommandAction::StopSprinting,
+        5 => PlayerCommandAction::StartJumpWithHorse,
+        6 => PlayerCommandAction::StopJumpWithHorse,
+        7 => PlayerCommandAction::OpenVehicleInventory,
+        8 => PlayerCommandAction::StartFlyingWithElytra,
+        _ => return Err("Unknown player command action"),
+    };
+    Ok(PlayerCommand { entity_id, action, jump_boost })
+}
+
+fn player_command(server: &Server, player: &Arc<Mutex<Player>>, reader: &mut PacketReader) -> Result<(), &'static str> {
+    let (uuid, protocol_version) = {
+        let player = player.lock().unwrap();
+        (player.uuid(), player.protocol_version())
+    };
+    let command = parse_player_command(reader, protocol_version)?;
+    let packet = {
+        let mut player = player.lock().unwrap();
+        match command.action {
+            PlayerCommandAction::StartSneaking => Some(player.set_pose(Pose::Sneaking)),
+            PlayerCommandAction::StopSneaking => Some(player.set_pose(Pose::Standing)),
+            // Clients repeat Start Sprinting while the sprint key is held
+            PlayerCommandAction::StartSprinting if !player.is_sprinting() => Some(player.set_sprinting(true)),
+            PlayerCommandAction::StopSprinting if player.is_sprinting() => Some(player.set_sprinting(false)),
+            _ => None,
+        }
+    };
+    if let Some(packet) = packet {
+        server.broadcast_except(&packet, uuid);
+    }
+    server.fire_event(&Event::PlayerCommand { player: uuid, command });
+    Ok(())
+}
+
+/// Only the inventory's own crafting grid is supported, other windows are ignored
+fn place_recipe(player: &Arc<Mutex<Player>>, reader: &mut PacketReader) -> Result<(), &'static str> {
+    let window_id = reader.read_u8()?;
+    let id = reader.read_utf8_string(32767)?;
+    let make_all = reader.read_bool()?;
+    let mut player = player.lock().unwrap();
+    if window_id != 0 {
+        debug!("{} tried to place {id} in window {window_id}, only the inventory is supported", player.name());
+        return Ok(());
+    }
+    let recipe = recipe::find(&id).ok_or("Unknown recipe")?;
+    if let Err(e) = player.place_recipe(&recipe, make_all) {
+        debug!("Could not place {id} for {}: {e}", player.name());
+    }
+    Ok(())
+}
+
+/// Placing isn't supported yet, the sequence is acknowledged so the client reverts its prediction
+fn use_item_on(server: &Server, player: &Arc<Mutex<Player>>, reader: &mut PacketReader) -> Result<(), &'static str> {
+    let _hand = reader.read_ivar()?;
+    let position = reader.read_position()?;
+    let face = Direction::from_id(reader.read_ivar()?).ok_or("Invalid block face")?;
+    let _cursor = (reader.read_f32()?, reader.read_f32()?, reader.read_f32()?);
+    let _inside_block = reader.read_bool()?;
+    let sequence = reader.read_ivar()?;
+    let mut player = player.lock().unwrap();
+    // Where a placed block would go
+    let (dx, dy, dz) = face.offset();
+    let target = Position::new(position.x + dx, position.y + dy, position.z + dz);
+    debug!("Use item on {face:?} of {position:?}, placing at {target:?} facing {:?}, sequence {sequence}", player.facing_direction());
+    let _ = player.send_packet(&packets::play::acknowledge_block_change(sequence));
+    // Signs can be edited again after they are placed, sign rotation isn't tracked so it's always the front
+    let is_sign = matches!(server.block_entities().get(&position), Some(BlockEntity::Sign(_)));
+    if is_sign && player.gamemode() != Gamemode::Spectator && player.pose() != Pose::Sneaking {
+        let _ = player.open_sign_editor(position, true);
+    }
+    Ok(())
+}
+
+fn swing_arm(server: &Server, player: &Arc<Mutex<Player>>, reader: &mut PacketReader) -> Result<(), &'static str> {
+    let animation = match reader.read_ivar()? {
+        0 => EntityAnimation::SwingMainArm,
+        1 => EntityAnimation::SwingOffhand,
+        _ => return Err("Invalid hand"),
+    };
+    let (uuid, entity_id) = {
+        let player = player.lock().unwrap();
+        (player.uuid(), player.entity_id())
+    };
+    server.broadcast_except(&packets::play::entity_animation(entity_id, animation), uuid);
+    Ok(())
+}
+
+/// Using items isn't supported yet, the sequence is acknowledged so the client doesn't wait on it
+fn use_item(player: &Arc<Mutex<Player>>, reader: &mut PacketReader) -> Result<(), &'static str> {
+    let hand = reader.read_ivar()?;
+    let sequence = reader.read_ivar()?;
+    // Yaw and pitch follow, the client's own rotation is already tracked
+    debug!("Use item with hand {hand}, sequence {sequence}");
+    let _ = player.lock().unwrap().send_packet(&packets::play::acknowledge_block_change(sequence));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::ServerConfig, protocol::Packet, server::tests::join, utils::{tests::{drain, read_packet, socket_pair}, write_utf8_string}, world::vehicle::Vehicle};
+
+    #[test]
+    fn only_breaks_blocks_in_survival_and_creative() {
+        assert!(breaks_block(Gamemode::Creative, 0));
+        assert!(!breaks_block(Gamemode::Creative, 2));
+        assert!(breaks_block(Gamemode::Survival, 2));
+        assert!(!breaks_block(Gamemode::Survival, 0));
+        for gamemode in [Gamemode::Adventure, Gamemode::Spectator] {
+            assert!(!breaks_block(gamemode, 0));
+            assert!(!breaks_block(gamemode, 2));
+        }
+    }
+
+    fn update_sign_packet(position: Position, is_front_text: bool, lines: &[&str; 4]) -> Vec<u8> {
+        let mut buffer = position.as_bytes().to_vec();
+        buffer.push(is_front_text as u8);
+        for line in lines {
+            write_utf8_string(&mut buffer, line.to_string());
+        }
+        buffer
+    }
+
+    #[test]
+    fn update_sign_round_trips_four_lines() {
+        let position = Position::new(10, 64, -3);
+        let packet = update_sign_packet(position, false, &["one", "two", "", "four"]);
+        let (parsed, is_front_text, lines) = parse_update_sign(&mut PacketReader::new(&packet)).unwrap();
+        assert_eq!(parsed, position);
+        assert!(!is_front_text);
+        assert_eq!(lines, ["one".to_string(), "two".into(), "".into(), "four".into()]);
+    }
+
+    #[test]
+    fn signs_only_change_where_the_editor_was_opened() {
+        let server = Server::new(ServerConfig::default());
+        let (stream, client) = socket_pair();
+        let _received = drain(client);
+        let player = Arc::new(Mutex::new(Player::new(stream, "Steve".into(), 1, 1, &server.config)));
+        let opened = Position::new(0, 64, 0);
+        let elsewhere = Position::new(5, 64, 5);
+        let lines = ["Hello".to_string(), String::new(), String::new(), String::new()];
+
+        update_sign(&server, &player, elsewhere, true, &lines);
+        player.lock().unwrap().open_sign_editor(opened, true).unwrap();
+        update_sign(&server, &player, elsewhere, true, &lines);
+        assert!(server.block_entities().is_empty());
+
+        player.lock().unwrap().open_sign_editor(opened, true).unwrap();
+        update_sign(&server, &player, opened, true, &lines);
+        assert!(matches!(server.block_entities().get(&opened), Some(BlockEntity::Sign(_))));
+        // The editor closes once the text arrives
+        server.block_entities().clear();
+        update_sign(&server, &player, opened, true, &lines);
+        assert!(server.block_entities().is_empty());
+    }
+
+    #[test]
+    fn spectators_teleport_to_online_players() {
+        let server = Server::new(ServerConfig::default());
+        let (spectator, mut client) = join(&server, "Steve", 1);
+        let (target, _target_client) = join(&server, "Alex", 2);
+        target.lock().unwrap().set_position(12.5, 70.0, -4.0);
+        spectator.lock().unwrap().set_gamemode(Gamemode::Spectator).unwrap();
+
+        teleport_to_entity(&server, &spectator, &mut PacketReader::new(&2u128.to_be_bytes())).unwrap();
+        // Spectating someone who isn't online does nothing
+        teleport_to_entity(&server, &spectator, &mut PacketReader::new(&3u128.to_be_bytes())).unwrap();
+        server.flush_players();
+
+        let _game_event = read_packet(&mut client);
+        let packet = read_packet(&mut client);
+        let mut reader = PacketReader::new(&packet);
+        assert_eq!(reader.read_ivar().unwrap(), Packet::SynchronizePlayerPosition.id());
+        assert_eq!((reader.read_f64().unwrap(), reader.read_f64().unwrap(), reader.read_f64().unwrap()), (12.5, 70.0, -4.0));
+        assert_eq!(spectator.lock().unwrap().position(), (12.5, 70.0, -4.0));
+    }
+
+    #[test]
+    fn only_spectators_can_teleport_to_players() {
+        let server = Server::new(ServerConfig::default());
+        let (player, _client) = join(&server, "Steve", 1);
+        let (target, _target_client) = join(&server, "Alex", 2);
+        target.lock().unwrap().set_position(12.5, 70.0, -4.0);
+        teleport_to_entity(&server, &player, &mut PacketReader::new(&2u128.to_be_bytes())).unwrap();
+        assert_ne!(player.lock().unwrap().position(), (12.5, 70.0, -4.0));
+    }
+
+    #[test]
+    fn joining_sends_the_operator_level() {
+        let server = Server::new(ServerConfig::default());
+        let (player, mut client) = join(&server, "Steve", 1);
+        let motd = Motd::new("A Minecraft Server", crate::status_response::MotdPlaceholders { online: 1, max: 20, version: "1.20.5" });
+        {
+            let mut player = player.lock().unwrap();
+            player.set_permission_level(4);
+            super::join(&server, &mut player, &motd).unwrap();
+            player.flush().unwrap();
+        }
+        read_packet(&mut client);
+        assert_eq!(read_packet(&mut client), packets::play::entity_event(1, entity_status::OP_PERMISSION_LEVEL_4));
+    }
+
+    #[test]
+    fn edited_signs_are_shown_to_everyone() {
+        let server = Server::new(ServerConfig::default());
+        let (steve, _steve_client) = join(&server, "Steve", 1);
+        let (_alex, mut alex_client) = join(&server, "Alex", 2);
+        let position = Position::new(0, 64, 0);
+        steve.lock().unwrap().open_sign_editor(position, true).unwrap();
+        update_sign(&server, &steve, position, true, &["Hello".to_string(), String::new(), String::new(), String::new()]);
+        server.flush_players();
+
+        let expected = packets::play::block_entity_data(position, server.block_entities().get(&position).unwrap());
+        let packet = read_packet(&mut alex_client);
+        assert_eq!(packet, expected);
+        let mut reader = PacketReader::new(&packet);
+        assert_eq!(reader.read_ivar().unwrap(), Packet::BlockEntityData.id());
+        assert_eq!(reader.read_position().unwrap(), position);
+        assert_eq!(reader.read_ivar().unwrap(), 7);
+    }
+
+    #[test]
+    fn creative_default_gamemode_is_sent_on_join() {
+        let server = Server::new(ServerConfig { default_gamemode: Gamemode::Creative, ..ServerConfig::default() });
+        let (stream, _client) = socket_pair();
+        let player = Player::new(stream, "Steve".into(), 1, 1, &server.config);
+        assert_eq!(player.gamemode(), Gamemode::Creative);
+
+        let packet = packets::play::join_game(&join_game(&server, &player));
+        let mut reader = PacketReader::new(&packet);
+        assert_eq!(reader.read_ivar().unwrap(), Packet::Login.id());
+        reader.read_i32().unwrap();
+        reader.read_bool().unwrap();
+        for _ in 0..reader.read_ivar().unwrap() {
+            reader.read_utf8_string(32767).unwrap();
+        }
+        for _ in 0..3 {
+            reader.read_ivar().unwrap();
+        }
+        reader.read_bytes(3).unwrap();
+        reader.read_ivar().unwrap();
+        reader.read_utf8_string(32767).unwrap();
+        reader.read_i64().unwrap();
+        assert_eq!(reader.read_u8().unwrap(), 1);
+    }
+
+    #[test]
+    fn swings_are_shown_to_other_players() {
+        let server = Server::new(ServerConfig::default());
+        let (steve, _steve_client) = join(&server, "Steve", 5);
+        let (_alex, mut alex_client) = join(&server, "Alex", 6);
+        swing_arm(&server, &steve, &mut PacketReader::new(&[1])).unwrap();
+        assert!(swing_arm(&server, &steve, &mut PacketReader::new(&[2])).is_err());
+        server.flush_players();
+        assert_eq!(read_packet(&mut alex_client), packets::play::entity_animation(5, EntityAnimation::SwingOffhand));
+    }
+
+    #[test]
+    fn using_items_acknowledges_the_sequence() {
+        let server = Server::new(ServerConfig::default());
+        let (steve, mut steve_client) = join(&server, "Steve", 1);
+        let mut packet = vec![0, 42];
+        packet.extend_from_slice(&0.0f32.to_be_bytes());
+        packet.extend_from_slice(&0.0f32.to_be_bytes());
+        use_item(&steve, &mut PacketReader::new(&packet)).unwrap();
+        server.flush_players();
+        assert_eq!(read_packet(&mut steve_client), packets::play::acknowledge_block_change(42));
+    }
+
+    #[test]
+    fn parses_attacks_and_interactions_at_a_position() {
+        assert_eq!(parse_interact(1, &mut PacketReader::new(&[12, 1, 1])).unwrap(), Event::InteractEntity {
+            player: 1, target: 12, interaction: InteractionType::Attack, hand: None, sneaking: true,
+        });
+
+        let mut packet = vec![12, 2];
+        for value in [0.5f32, 1.0, -0.25] {
+            packet.extend_from_slice(&value.to_be_bytes());
+        }
+        packet.extend_from_slice(&[1, 0]);
+        assert_eq!(parse_interact(1, &mut PacketReader::new(&packet)).unwrap(), Event::InteractEntity {
+            player: 1, target: 12, interaction: InteractionType::InteractAt { x: 0.5, y: 1.0, z: -0.25 }, hand: Some(1), sneaking: false,
+        });
+        assert!(parse_interact(1, &mut PacketReader::new(&[12, 3, 0])).is_err());
+    }
+
+    #[test]
+    fn interactions_reach_the_listeners() {
+        let server = Server::new(ServerConfig::default());
+        let (steve, _client) = join(&server, "Steve", 1);
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let received = Arc::clone(&events);
+        server.add_listener(Box::new(move |_, event| received.lock().unwrap().push(event.clone())));
+        interact(&server, &steve, &mut PacketReader::new(&[12, 0, 0, 0])).unwrap();
+        assert_eq!(*events.lock().unwrap(), vec![Event::InteractEntity {
+            player: 1, target: 12, interaction: InteractionType::Interact, hand: Some(0), sneaking: false,
+        }]);
+    }
+
+    #[test]
+    fn breaking_a_block_fires_block_break() {
+        let server = Server::new(ServerConfig::default());
+        let (steve, _client) = join(&server, "Steve", 1);
+        steve.lock().unwrap().set_gamemode(Gamemode::Survival).unwrap();
+        let position = Position::new(3, 70, 3);
+        server.set_block(position, 9).unwrap();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let received = Arc::clone(&events);
+        server.add_listener(Box::new(move |_, event| received.lock().unwrap().push(event.clone())));
+
+        // Finished digging, then digging the air left behind
+        let mut packet = vec![2];
+        packet.extend_from_slice(&position.as_bytes());
+        packet.extend_from_slice(&[1, 0]);
+        player_action(&server, &steve, &mut PacketReader::new(&packet)).unwrap();
+        player_action(&server, &steve, &mut PacketReader::new(&packet)).unwrap();
+        assert_eq!(server.blocks().get(position), AIR);
+        assert_eq!(*events.lock().unwrap(), vec![Event::BlockBreak { player: 1, position, state: 9 }]);
+    }
+
+    #[test]
+    fn parses_a_left_click_pickup() {
+        // Window 0, state 3, slot 36, left button, mode 0, hotbar slot emptied and its stack now carried
+        let mut packet = vec![0, 3];
+        packet.extend_from_slice(&36i16.to_be_bytes());
+        packet.extend_from_slice(&[0, 0, 1]);
+        packet.extend_from_slice(&36i16.to_be_bytes());
+        packet.extend(Slot::EMPTY.as_bytes());
+        packet.extend(Slot::new(1, 64).as_bytes());
+        assert_eq!(parse_click_container(&mut PacketReader::new(&packet)).unwrap(), ClickContainer {
+            window_id: 0,
+            state_id: 3,
+            slot: 36,
+            button: 0,
+            mode: 0,
+            changed_slots: vec![(36, Slot::EMPTY)],
+            carried_item: Slot::new(1, 64),
+        });
+    }
+
+    #[test]
+    fn closing_a_container_clears_the_open_window() {
+        let (stream, _client) = socket_pair();
+        let player = Arc::new(Mutex::new(Player::new(stream, "Steve".into(), 1, 1, &ServerConfig::default())));
+        player.lock().unwrap().set_open_window(Some(2));
+        close_container(&player, &mut PacketReader::new(&[2])).unwrap();
+        assert_eq!(player.lock().unwrap().open_window(), None);
+        assert!(close_container(&player, &mut PacketReader::new(&[])).is_err());
+    }
+
+    #[test]
+    fn set_held_item_selects_hotbar_slots_only() {
+        let (stream, _client) = socket_pair();
+        let player = Arc::new(Mutex::new(Player::new(stream, "Steve".into(), 1, 1, &ServerConfig::default())));
+        set_held_item(&player, &mut PacketReader::new(&8i16.to_be_bytes())).unwrap();
+        assert_eq!(player.lock().unwrap().selected_slot(), 8);
+        for slot in [9i16, -1] {
+            assert!(set_held_item(&player, &mut PacketReader::new(&slot.to_be_bytes())).is_err());
+        }
+        assert_eq!(player.lock().unwrap().selected_slot(), 8);
+    }
+
+    #[test]
+    fn parses_a_creative_stack_of_diamonds() {
+        // Any item id works, the server doesn't know item names yet
+        let diamond = Slot::new(800, 64);
+        let mut packet = 36i16.to_be_bytes().to_vec();
+        packet.extend(diamond.as_bytes());
+        assert_eq!(parse_creative_mode_slot(&mut PacketReader::new(&packet)).unwrap(), (36, diamond));
+
+        let mut packet = 46i16.to_be_bytes().to_vec();
+        packet.extend(diamond.as_bytes());
+        assert!(parse_creative_mode_slot(&mut PacketReader::new(&packet)).is_err());
+    }
+
+    #[test]
+    fn creative_slots_only_change_in_creative() {
+        let server = Server::new(ServerConfig::default());
+        let (steve, _client) = join(&server, "Steve", 1);
+        let mut packet = 36i16.to_be_bytes().to_vec();
+        packet.extend(Slot::new(800, 64).as_bytes());
+
+        creative_mode_slot(&server, &steve, &mut PacketReader::new(&packet)).unwrap();
+        assert_eq!(steve.lock().unwrap().held_item(), Slot::EMPTY);
+        steve.lock().unwrap().set_gamemode(Gamemode::Creative).unwrap();
+        creative_mode_slot(&server, &steve, &mut PacketReader::new(&packet)).unwrap();
+        assert_eq!(steve.lock().unwrap().held_item(), Slot::new(800, 64));
+    }
+
+    #[test]
+    fn dropped_items_are_picked_up_after_a_delay() {
+        let server = Server::new(ServerConfig::default());
+        let (steve, mut client) = join(&server, "Steve", 1);
+        steve.lock().unwrap().update_inventory_slot(crate::inventory::HOTBAR_START, Slot::new(1, 5)).unwrap();
+        let mut drop = vec![4];
+        drop.extend_from_slice(&Position::new(0, 0, 0).as_bytes());
+        drop.extend_from_slice(&[0, 0]);
+        player_action(&server, &steve, &mut PacketReader::new(&drop)).unwrap();
+        assert_eq!(steve.lock().unwrap().held_item(), Slot::new(1, 4));
+        let (entity_id, spawn) = {
+            let items = server.item_entities();
+            let entity = items.values().next().unwrap();
+            assert_eq!(entity.item, Slot::new(1, 1));
+            (entity.entity_id, entity.spawn_packets())
+        };
+
+        // Too soon for the dropper to take it back
+        server.pickup_items();
+        assert_eq!(server.item_entities().len(), 1);
+        server.item_entities().get_mut(&entity_id).unwrap().pickup_delay = 1;
+        server.pickup_items();
+        assert!(server.item_entities().is_empty());
+        assert_eq!(steve.lock().unwrap().held_item(), Slot::new(1, 5));
+
+        server.flush_players();
+        assert_eq!(read_packet(&mut client), packets::play::set_container_slot(0, 1, 36, &Slot::new(1, 4)));
+        assert_eq!(read_packet(&mut client), spawn[0]);
+        assert_eq!(read_packet(&mut client), spawn[1]);
+        assert_eq!(read_packet(&mut client), packets::play::set_container_slot(0, 2, 36, &Slot::new(1, 5)));
+        assert_eq!(read_packet(&mut client), packets::play::pickup_item(entity_id, 1, 1));
+        assert_eq!(read_packet(&mut client), packets::play::remove_entities(&[entity_id]));
+    }
+
+    #[test]
+    fn crossing_a_chunk_boundary_moves_the_center_first() {
+        let server = Server::new(ServerConfig::default());
+        let (steve, mut client) = join(&server, "Steve", 1);
+        let position = |x: f64, z: f64| {
+            let mut packet = Vec::new();
+            for value in [x, 64.0, z] {
+                packet.extend_from_slice(&value.to_be_bytes());
+            }
+            packet.push(1);
+            packet
+        };
+        set_player_position(&server, &steve, &mut PacketReader::new(&position(15.5, 0.0))).unwrap();
+        set_player_position(&server, &steve, &mut PacketReader::new(&position(15.9, 3.0))).unwrap();
+        set_player_position(&server, &steve, &mut PacketReader::new(&position(16.5, 3.0))).unwrap();
+        server.flush_players();
+
+        assert_eq!(read_packet(&mut client), packets::play::set_center_chunk(0, 0));
+        assert_eq!(read_packet(&mut client), packets::play::set_center_chunk(1, 0));
+        let unload = read_packet(&mut client);
+        let mut reader = PacketReader::new(&unload);
+        assert_eq!(reader.read_ivar().unwrap(), Packet::UnloadChunk.id());
+        reader.read_i32().unwrap();
+        assert_eq!(reader.read_i32().unwrap(), -server.config.view_distance);
+    }
+
+
+    fn unsigned_chat(message: &str) -> Vec<u8> {
+        let mut packet = Vec::new();
+        write_utf8_string(&mut packet, message.to_string());
+        packet.extend_from_slice(&0i64.to_be_bytes());
+        packet.extend_from_slice(&0i64.to_be_bytes());
+        packet.extend_from_slice(&[0, 0, 0, 0, 0]);
+        packet
+    }
+
+    #[test]
+    fn enforced_secure_chat_kicks_unsigned_messages() {
+        let server = Server::new(ServerConfig { enforce_secure_chat: true, ..ServerConfig::default() });
+        let (steve, mut client) = join(&server, "Steve", 1);
+        assert!(join_game(&server, &steve.lock().unwrap()).enforces_secure_chat);
+        chat_message(&server, &steve, &mut PacketReader::new(&unsigned_chat("hello"))).unwrap();
+        let reason = TextComponent::translate("multiplayer.disconnect.unsigned_chat", Vec::new());
+        assert_eq!(read_packet(&mut client), packets::play::disconnect(&reason));
+    }
+
+    #[test]
+    fn unsigned_chat_is_relayed_when_not_enforced() {
+        let server = Server::new(ServerConfig::default());
+        let (steve, mut client) = join(&server, "Steve", 1);
+        chat_message(&server, &steve, &mut PacketReader::new(&unsigned_chat("hello"))).unwrap();
+        server.flush_players();
+        let chat_type = chat_type::id("minecraft:chat").unwrap_or(0);
+        assert_eq!(read_packet(&mut client), packets::play::disguised_chat(&TextComponent::new("hello"), chat_type, &TextComponent::new("Steve"), None));
+    }
+
+    #[test]
+    fn move_vehicle_updates_the_ridden_vehicle() {
+        let server = Server::new(ServerConfig::default());
+        let (player, _client) = join(&server, "Steve", 1);
+        server.vehicles().insert(50, Vehicle { entity_id: 50, position: (0.0, 64.0, 0.0), yaw: 0.0, pitch: 0.0 });
+        let mut packet: Vec<u8> = Vec::new();
+        for coordinate in [6.5f64, 63.0, -4.25] {
+            packet.extend_from_slice(&coordinate.to_be_bytes());
+        }
+        packet.extend_from_slice(&90.0f32.to_be_bytes());
+        packet.extend_from_slice(&(-10.0f32).to_be_bytes());
+
+        // Ignored until the player is riding it
+        move_vehicle(&server, &player, &mut PacketReader::new(&packet)).unwrap();
+        assert_eq!(server.vehicles()[&50].position, (0.0, 64.0, 0.0));
+
+        player.lock().unwrap().set_vehicle(Some(50));
+        move_vehicle(&server, &player, &mut PacketReader::new(&packet)).unwrap();
+        assert_eq!(server.vehicles()[&50], Vehicle { entity_id: 50, position: (6.5, 63.0, -4.25), yaw: 90.0, pitch: -10.0 });
+    }
+
+    #[test]
+    fn parses_float_based_player_input() {
+        let mut packet: Vec<u8> = Vec::new();
+        packet.extend_from_slice(&0.98f32.to_be_bytes());
+        packet.extend_from_slice(&(-0.98f32).to_be_bytes());
+        packet.push(0x01 | 0x02);
+        let input = parse_player_input(&mut PacketReader::new(&packet), KEY_INPUT_PROTOCOL - 1).unwrap();
+        assert_eq!(input, PlayerInput { sideways: 0.98, forward: -0.98, jump: true, sneak: true, sprint: false });
+    }
+
+    #[test]
+    fn parses_key_based_player_input() {
+        // Forward, right, sprint
+        let input = parse_player_input(&mut PacketReader::new(&[0x01 | 0x08 | 0x40]), KEY_INPUT_PROTOCOL).unwrap();
+        assert_eq!(input, PlayerInput { sideways: -1.0, forward: 1.0, jump: false, sneak: false, sprint: true });
+        // Opposite keys cancel out
+        let input = parse_player_input(&mut PacketReader::new(&[0x01 | 0x02 | 0x04 | 0x08 | 0x10 | 0x20]), KEY_INPUT_PROTOCOL).unwrap();
+        assert_eq!(input, PlayerInput { sideways: 0.0, forward: 0.0, jump: true, sneak: true, sprint: false });
+    }
+
+    #[test]
+    fn join_carries_the_configured_debug_and_respawn_flags() {
+        let config = ServerConfig { reduced_debug_info: true, enable_respawn_screen: false, ..ServerConfig::default() };
+        let server = Server::new(config);
+        let (player, mut client) = join(&server, "Steve", 1);
+        let motd = Motd::new("A Minecraft Server", crate::status_response::MotdPlaceholders { online: 1, max: 20, version: "1.20.5" });
+        {
+            let mut player = player.lock().unwrap();
+            super::join(&server, &mut player, &motd).unwrap();
+            player.flush().unwrap();
+        }
+        let packet = read_packet(&mut client);
+        let mut reader = PacketReader::new(&packet);
+        assert_eq!(reader.read_ivar().unwrap(), Packet::Login.id());
+        reader.read_i32().unwrap();
+        reader.read_bool().unwrap();
+        for _ in 0..reader.read_ivar().unwrap() {
+            reader.read_utf8_string(32767).unwrap();
+        }
+        for _ in 0..3 {
+            reader.read_ivar().unwrap();
+        }
+        assert!(reader.read_bool().unwrap(), "reduced debug info");
+        assert!(!reader.read_bool().unwrap(), "respawn screen");
+    }
+
+    #[test]
+    fn toggling_the_respawn_screen_tells_everyone() {
+        let server = Server::new(ServerConfig::default());
+        let (_player, mut client) = join(&server, "Steve", 1);
+        server.set_respawn_screen(false);
+        server.flush_players();
+        assert!(!server.respawn_screen_enabled());
+        assert_eq!(read_packet(&mut client), packets::play::game_event(packets::play::GameEvent::EnableRespawnScreen, 1.0));
+    }
+
+    #[test]
+    fn joining_is_announced_to_existing_players() {
+        let server = Server::new(ServerConfig::default());
+        let (_alex, mut alex_client) = join(&server, "Alex", 2);
+        let (steve, mut steve_client) = join(&server, "Steve", 1);
+        announce_join(&server, &steve);
+        server.flush_players();
+
+        assert_eq!(read_packet(&mut alex_client), packets::play::player_info_add(&[(1, "Steve".into(), Gamemode::Survival)]));
+        let message = TextComponent::translate("multiplayer.player.joined", vec![TextComponent::new("Steve")]).color("yellow");
+        assert_eq!(read_packet(&mut alex_client), packets::play::system_chat(&message, false));
+        // The new player is sent everyone already online, then sees their own join message
+        let tab_list = read_packet(&mut steve_client);
+        assert_eq!(tab_list[0] as i32, Packet::PlayerInfoUpdate.id());
+        assert_eq!(read_packet(&mut steve_client), packets::play::system_chat(&message, false));
+    }
+
+    #[test]
+    fn crafting_book_settings_are_parsed() {
+        assert_eq!(
+            parse_recipe_book_settings(&mut PacketReader::new(&[0, 1, 1])).unwrap(),
+            (RecipeBookType::Crafting, RecipeBookSettings { open: true, filter: true }),
+        );
+    }
+
+    #[test]
+    fn unknown_recipe_books_are_rejected() {
+        assert!(parse_recipe_book_settings(&mut PacketReader::new(&[4, 1, 0])).is_err());
+        assert_eq!(
+            parse_recipe_book_settings(&mut PacketReader::new(&[3, 0, 1])).unwrap(),
+            (RecipeBookType::Smoker, RecipeBookSettings { open: false, filter: true }),
+        );
+    }
+
+    #[test]
+    fn respawning_sends_the_speeds_again() {
+        let server = Server::new(ServerConfig::default());
+        let (player, mut client) = join(&server, "Steve", 1);
+        let mut player = player.lock().unwrap();
+        player.set_walk_speed(0.3).unwrap();
+        player.kill(&TextComponent::new("Steve died")).unwrap();
+        respawn(&server, &mut player).unwrap();
+        player.flush().unwrap();
+
+        let attributes = packets::play::update_attributes(1, &[(packets::play::Attribute::MovementSpeed, 0.3)]);
+        assert_eq!(read_packet(&mut client), attributes);
+        loop {
+            let packet = read_packet(&mut client);
+            if packet[0] as i32 == Packet::Respawn.id() {
+                break;
+            }
+        }
+        read_packet(&mut client);
+        assert_eq!(read_packet(&mut client), attributes);
+        assert_eq!(read_packet(&mut client)[0] as i32, Packet::PlayerAbilities.id());
+    }
+
+    #[test]
+    fn perform_respawn_status_sends_the_player_to_spawn() {
+        let server = Server::new(ServerConfig::default());
+        let (player, mut client) = join(&server, "Steve", 1);
+        // Still alive, nothing happens
+        handle_packet(&server, &player, 0x09, &[0]).unwrap();
+        player.lock().unwrap().kill(&TextComponent::new("Steve died")).unwrap();
+
+        handle_packet(&server, &player, 0x09, &[0]).unwrap();
+        player.lock().unwrap().flush().unwrap();
+        let ids: Vec<i32> = std::iter::from_fn(|| {
+            let packet = read_packet(&mut client);
+            Some(PacketReader::new(&packet).read_ivar().unwrap())
+        })
+        .skip_while(|id| *id != Packet::Respawn.id())
+        .take_while(|id| *id != Packet::SynchronizePlayerPosition.id())
+        .collect();
+        assert!(ids.contains(&Packet::SetDefaultSpawnPosition.id()));
+        assert_eq!(player.lock().unwrap().health(), 20.0);
+        assert!(handle_packet(&server, &player, 0x09, &[2]).is_err());
+    }
+
+    #[test]
+    fn statistics_requests_get_an_empty_award_statistics() {
+        let server = Server::new(ServerConfig::default());
+        let (player, mut client) = join(&server, "Steve", 1);
+        handle_packet(&server, &player, 0x09, &[1]).unwrap();
+        player.lock().unwrap().flush().unwrap();
+        assert_eq!(read_packet(&mut client), packets::play::award_statistics(&[]));
+    }
+
+    #[test]
+    fn spectators_attacking_a_player_view_through_them() {
+        let server = Server::new(ServerConfig::default());
+        let (spectator, mut client) = join(&server, "Steve", 1);
+        let _alex = join(&server, "Alex", 2);
+        // Not a spectator yet
+        interact(&server, &spectator, &mut PacketReader::new(&[2, 1, 0])).unwrap();
+        spectator.lock().unwrap().set_gamemode(Gamemode::Spectator).unwrap();
+        // Nobody has entity id 9
+        interact(&server, &spectator, &mut PacketReader::new(&[9, 1, 0])).unwrap();
+        interact(&server, &spectator, &mut PacketReader::new(&[2, 1, 0])).unwrap();
+        spectator.lock().unwrap().set_gamemode(Gamemode::Creative).unwrap();
+        spectator.lock().unwrap().flush().unwrap();
+
+        let change_gamemode = |gamemode: Gamemode| packets::play::game_event(packets::play::GameEvent::ChangeGameMode, gamemode as u8 as f32);
+        assert_eq!(read_packet(&mut client), change_gamemode(Gamemode::Spectator));
+        assert_eq!(read_packet(&mut client), packets::play::set_camera(2));
+        assert_eq!(read_packet(&mut client), packets::play::set_camera(1));
+        assert_eq!(read_packet(&mut client), change_gamemode(Gamemode::Creative));
+    }
+
+    #[test]
+    fn place_recipe_sets_the_crafting_output() {
+        let server = Server::new(ServerConfig::default());
+        let (player, mut client) = join(&server, "Steve", 1);
+        player.lock().unwrap().update_inventory_slot(36, Slot::new(recipe::OAK_LOG, 1)).unwrap();
+        let mut packet = vec![0];
+        write_utf8_string(&mut packet, "minecraft:oak_planks".into());
+        packet.push(0);
+        handle_packet(&server, &player, 0x22, &packet).unwrap();
+        player.lock().unwrap().flush().unwrap();
+
+        assert_eq!(read_packet(&mut client), packets::play::set_container_slot(0, 1, 0, &Slot::new(recipe::OAK_PLANKS, 4)));
+        assert_eq!(read_packet(&mut client), packets::play::set_container_slot(0, 2, 1, &Slot::new(recipe::OAK_LOG, 1)));
+        assert_eq!(read_packet(&mut client), packets::play::set_container_slot(0, 3, 36, &Slot::EMPTY));
+    }
+
+    #[test]
+    fn start_sneaking_shows_everyone_else_the_sneaking_pose() {
+        let server = Server::new(ServerConfig::default());
+        let (steve, _steve_client) = join(&server, "Steve", 1);
+        let (alex, mut alex_client) = join(&server, "Alex", 2);
+        // Entity 1, Start Sneaking, no jump boost
+        player_command(&server, &steve, &mut PacketReader::new(&[1, 0, 0])).unwrap();
+        assert_eq!(steve.lock().unwrap().pose(), Pose::Sneaking);
+        alex.lock().unwrap().flush().unwrap();
+        let expected = Player::new(socket_pair().0, "Steve".into(), 1, 1, &ServerConfig::default()).set_pose(Pose::Sneaking);
+        assert_eq!(read_packet(&mut alex_client), expected);
+    }
+
+    #[test]
+    fn parses_every_player_command_action() {
+        let actions = [
+            PlayerCommandAction::StartSneaking,
+            PlayerCommandAction::StopSneaking,
+            PlayerCommandAction::LeaveBed,
+            PlayerCommandAction::StartSprinting,
+            PlayerCommandAction::StopSprinting,
+            PlayerCommandAction::StartJumpWithHorse,
+            PlayerCommandAction::StopJumpWithHorse,
+            PlayerCommandAction::OpenVehicleInventory,
+            PlayerCommandAction::StartFlyingWithElytra,
+        ];
+        for (id, action) in actions.into_iter().enumerate() {
+            let command = parse_player_command(&mut PacketReader::new(&[5, id as u8, 40]), KEY_INPUT_PROTOCOL - 1).unwrap();
+            assert_eq!(command, PlayerCommand { entity_id: 5, action, jump_boost: 40 });
+        }
+        // Newer clients start the list at Leave Bed
+        assert_eq!(parse_player_command(&mut PacketReader::new(&[5, 0, 0]), KEY_INPUT_PROTOCOL).unwrap().action, PlayerCommandAction::LeaveBed);
+        assert!(parse_player_command(&mut PacketReader::new(&[5, 7, 0]), KEY_INPUT_PROTOCOL).is_err());
+        assert!(parse_player_command(&mut PacketReader::new(&[5, 9, 0]), KEY_INPUT_PROTOCOL - 1).is_err());
+    }
+
+    #[test]
+    fn repeated_sprint_commands_are_only_shown_once() {
+        let server = Server::new(ServerConfig::default());
+        let (steve, _steve_client) = join(&server, "Steve", 1);
+        let (alex, mut alex_client) = join(&server, "Alex", 2);
+        player_command(&server, &steve, &mut PacketReader::new(&[1, 3, 0])).unwrap();
+        player_command(&server, &steve, &mut PacketReader::new(&[1, 3, 0])).unwrap();
+        assert!(steve.lock().unwrap().is_sprinting());
+        player_command(&server, &steve, &mut PacketReader::new(&[1, 4, 0])).unwrap();
+        assert!(!steve.lock().unwrap().is_sprinting());
+        alex.lock().unwrap().flush().unwrap();
+
+        let mut player = Player::new(socket_pair().0, "Steve".into(), 1, 1, &ServerConfig::default());
+        assert_eq!(read_packet(&mut alex_client), player.set_sprinting(true));
+        assert_eq!(read_packet(&mut alex_client), player.set_sprinting(false));
+    }
+
+    #[test]
+    fn player_sessions_are_stored_and_shared() {
+        let server = Server::new(ServerConfig::default());
+        let (steve, _steve_client) = join(&server, "Steve", 1);
+        let (_alex, mut alex_client) = join(&server, "Alex", 2);
+        let session = ChatSession { session_id: 5, expires_at: i64::MAX, public_key: vec![1, 2, 3], key_signature: vec![4, 5] };
+        player_session(&server, &steve, &mut PacketReader::new(&crate::chat::tests::player_session_packet(&session))).unwrap();
+        assert_eq!(steve.lock().unwrap().chat_session(), Some(&session));
+        server.flush_players();
+        assert_eq!(read_packet(&mut alex_client), packets::play::player_info_init_chat(&[(1, Some(session))]));
+    }
+
+    #[test]
+    fn right_clicking_a_vehicle_rides_it_until_sneaking() {
+        let server = Server::new(ServerConfig::default());
+        let (steve, _steve_client) = join(&server, "Steve", 1);
+        let (alex, mut alex_client) = join(&server, "Alex", 2);
+        server.vehicles().insert(50, Vehicle { entity_id: 50, position: (0.0, 64.0, 0.0), yaw: 0.0, pitch: 0.0 });
+        // Interact with the main hand, not sneaking
+        interact(&server, &steve, &mut PacketReader::new(&[50, 0, 0, 0])).unwrap();
+        assert_eq!(steve.lock().unwrap().vehicle(), Some(50));
+        // Someone is already in it
+        interact(&server, &alex, &mut PacketReader::new(&[50, 0, 0, 0])).unwrap();
+        assert_eq!(alex.lock().unwrap().vehicle(), None);
+
+        let mut input: Vec<u8> = Vec::new();
+        input.extend_from_slice(&0.0f32.to_be_bytes());
+        input.extend_from_slice(&0.0f32.to_be_bytes());
+        input.push(0x02);
+        handle_packet(&server, &steve, 0x26, &input).unwrap();
+        assert_eq!(steve.lock().unwrap().vehicle(), None);
+        server.flush_players();
+        assert_eq!(read_packet(&mut alex_client), packets::play::set_passengers(50, &[1]));
+        assert_eq!(read_packet(&mut alex_client), packets::play::set_passengers(50, &[]));
+    }
+
+    #[test]
+    fn vehicles_moved_too_far_are_put_back() {
+        let server = Server::new(ServerConfig::default());
+        let (player, mut client) = join(&server, "Steve", 1);
+        server.vehicles().insert(50, Vehicle { entity_id: 50, position: (0.0, 64.0, 0.0), yaw: 45.0, pitch: 0.0 });
+        player.lock().unwrap().set_vehicle(Some(50));
+        let mut packet: Vec<u8> = Vec::new();
+        for coordinate in [100.0f64, 64.0, 0.0] {
+            packet.extend_from_slice(&coordinate.to_be_bytes());
+        }
+        packet.extend_from_slice(&0.0f32.to_be_bytes());
+        packet.extend_from_slice(&0.0f32.to_be_bytes());
+
+        move_vehicle(&server, &player, &mut PacketReader::new(&packet)).unwrap();
+        assert_eq!(server.vehicles()[&50].position, (0.0, 64.0, 0.0));
+        player.lock().unwrap().flush().unwrap();
+        assert_eq!(read_packet(&mut client), packets::play::move_vehicle((0.0, 64.0, 0.0), 45.0, 0.0));
+    }
+
+    #[test]
+    fn other_players_see_vehicles_move() {
+        let server = Server::new(ServerConfig::default());
+        let (player, _client) = join(&server, "Steve", 1);
+        let (_other, mut other_client) = join(&server, "Alex", 2);
+        server.vehicles().insert(50, Vehicle { entity_id: 50, position: (0.0, 64.0, 0.0), yaw: 0.0, pitch: 0.0 });
+        player.lock().unwrap().set_vehicle(Some(50));
+        let mut packet: Vec<u8> = Vec::new();
+        for coordinate in [1.0f64, 64.0, 0.0] {
+            packet.extend_from_slice(&coordinate.to_be_bytes());
+        }
+        packet.extend_from_slice(&90.0f32.to_be_bytes());
+        packet.extend_from_slice(&0.0f32.to_be_bytes());
+
+        move_vehicle(&server, &player, &mut PacketReader::new(&packet)).unwrap();
+        server.flush_players();
+        let rotation = (Angle::from_degrees(90.0), Angle(0));
+        assert_eq!(read_packet(&mut other_client), packets::play::entity_movement(50, (0.0, 64.0, 0.0), (1.0, 64.0, 0.0), rotation, true, false));
+    }
+
+    #[test]
+    fn hitting_a_player_knocks_them_back() {
+        let server = Server::new(ServerConfig::default());
+        let (steve, _client) = join(&server, "Steve", 1);
+        let (alex, mut alex_client) = join(&server, "Alex", 2);
+        steve.lock().unwrap().set_position(-2.0, 64.0, 0.5);
+        alex.lock().unwrap().set_position(0.5, 64.0, 0.5);
+        alex.lock().unwrap().set_on_ground(true);
+        // Creative players can't be hurt
+        alex.lock().unwrap().set_gamemode(Gamemode::Creative).unwrap();
+        interact(&server, &steve, &mut PacketReader::new(&[2, 1, 0])).unwrap();
+        alex.lock().unwrap().set_gamemode(Gamemode::Survival).unwrap();
+        interact(&server, &steve, &mut PacketReader::new(&[2, 1, 0])).unwrap();
+        alex.lock().unwrap().flush().unwrap();
+
+        let change_gamemode = |gamemode: Gamemode| packets::play::game_event(packets::play::GameEvent::ChangeGameMode, gamemode as u8 as f32);
+        assert_eq!(read_packet(&mut alex_client), change_gamemode(Gamemode::Creative));
+        assert_eq!(read_packet(&mut alex_client), change_gamemode(Gamemode::Survival));
+        assert_eq!(read_packet(&mut alex_client), packets::play::set_entity_velocity(2, (0.4, 0.4, 0.0)));
+    }
+
+    #[test]
+    fn transferred_players_are_put_back_where_they_left() {
+        let server = Server::new(ServerConfig::default());
+        let (player, mut client) = join(&server, "Steve", 1);
+        let mut player = player.lock().unwrap();
+        player.handle_cookie_response(cookie::LAST_POSITION.into(), Some(cookie::encode(&(5.5, 70.0, -3.0)).unwrap()));
+        restore_last_position(&server, &mut player).unwrap();
+        assert_eq!(player.position(), (5.5, 70.0, -3.0));
+
+        // Forged cookies can't put players outside the border
+        player.handle_cookie_response(cookie::LAST_POSITION.into(), Some(cookie::encode(&(1.0e9, 70.0, 0.0)).unwrap()));
+        restore_last_position(&server, &mut player).unwrap();
+        assert_eq!(player.position(), (5.5, 70.0, -3.0));
+        player.flush().unwrap();
+        assert_eq!(read_packet(&mut client), packets::play::synchronize_player_position(5.5, 70.0, -3.0, 0.0, 0.0, 0, 1));
+    }
+}
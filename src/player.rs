@@ -0,0 +1,1094 @@
+use std::{collections::HashMap, io::{BufWriter, Write}, net::{Shutdown, TcpStream}, time::Instant};
+
+use log::{debug, info, warn};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{chat::{ChatMessage, ChatRateLimiter, ChatSession}, compression::PacketCompression, config::ServerConfig, connection::ConnectionState, cookie::{self, CookieError, CookieStore, MAX_COOKIE_SIZE}, inventory::{self, PlayerInventory}, keep_alive::KeepAliveTracker, packets::{self, play::{Attribute, ABILITY_ALLOW_FLYING, ABILITY_FLYING, ABILITY_INSTANT_BREAK, ABILITY_INVULNERABLE}}, protocol::Version, recipe::Recipe, resource_pack::{ResourcePack, ResourcePackStack, ResourcePackStatus}, types::{direction::Direction, metadata::{Metadata, MetadataValue, Pose, ENTITY_FLAGS_INDEX, FLAG_CROUCHING, FLAG_SPRINTING, POSE_INDEX}, position::Position, slot::Slot, text_component::TextComponent}, trace, utils::write_packet, world::{batch::ChunkBatcher, edit::{self, BlockChanges}, entity_tracker::EntityTracker, item_entity::ItemEntity, light::LightData, tracker::{effective_view_distance, ChunkTracker, DEFAULT_SIMULATION_DISTANCE, VIEW_DISTANCE_RANGE}}};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Gamemode {
+    Survival = 0,
+    Creative = 1,
+    Adventure = 2,
+    Spectator = 3,
+}
+
+impl Gamemode {
+    /// Accepts either the name or the number of the gamemode
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "survival" | "0" => Some(Self::Survival),
+            "creative" | "1" => Some(Self::Creative),
+            "adventure" | "2" => Some(Self::Adventure),
+            "spectator" | "3" => Some(Self::Spectator),
+            _ => None,
+        }
+    }
+}
+
+pub const DEFAULT_WALK_SPEED: f64 = 0.1;
+pub const DEFAULT_FLYING_SPEED: f32 = 0.05;
+pub const DEFAULT_FOV_MODIFIER: f32 = 0.1;
+
+pub struct Player {
+    /// Packets are buffered here until `flush`, which the connection and tick threads call once they're done sending
+    writer: BufWriter<TcpStream>,
+    compression: Option<PacketCompression>,
+    name: String,
+    uuid: u128,
+    entity_id: i32,
+    permission_level: u8,
+    chat_limiter: ChatRateLimiter,
+    /// Sent by clients with secure chat, None until then
+    chat_session: Option<ChatSession>,
+    /// Messages received in the current chat session, part of what each one is signed over
+    chat_index: i32,
+    position: (f64, f64, f64),
+    yaw: f32,
+    pitch: f32,
+    on_ground: bool,
+    teleport_id: i32,
+    health: f32,
+    food: i32,
+    saturation: f32,
+    latency: i32,
+    pending_ping: Option<(i32, Instant)>,
+    /// Last time the client answered a ping or keep alive
+    last_seen: Instant,
+    /// Latency in milliseconds above which a warning is logged, 0 to never warn
+    latency_warning: i32,
+    gamemode: Gamemode,
+    /// Window id of the container the player has open, None while only their inventory is
+    open_window: Option<u8>,
+    /// Hotbar slot the player is holding, 0-8
+    selected_slot: u8,
+    inventory: PlayerInventory,
+    chunks: ChunkTracker,
+    /// Chunks in view that haven't been sent yet
+    chunk_batcher: ChunkBatcher,
+    /// The server's view distance, sent to the client
+    view_distance: i32,
+    /// From the client's Client Information
+    client_view_distance: Option<i32>,
+    simulation_distance: i32,
+    experience_level: i32,
+    /// Experience gained towards the next level
+    experience_points: i32,
+    total_experience: i32,
+    /// Set once the player has been sent to another server, their disconnect is expected
+    transferring: bool,
+    /// Set once everyone has been told the player joined, only then is their leave announced
+    announced: bool,
+    /// Dimension and block of the player's last death
+    death_location: Option<(String, Position)>,
+    /// Sign and side the server opened the editor for, the only one Update Sign may change
+    editing_sign: Option<(Position, bool)>,
+    /// Entity id of the vehicle the player is riding
+    vehicle: Option<i32>,
+    protocol_version: i32,
+    /// Configuration or Play, decides which packet ids are remapped
+    state: ConnectionState,
+    /// Base movement speed attribute
+    walk_speed: f64,
+    flying_speed: f32,
+    cookies: CookieStore,
+    metadata: Metadata,
+    resource_packs: ResourcePackStack,
+    entities: EntityTracker,
+    keep_alive: KeepAliveTracker,
+}
+
+impl Player {
+    pub fn new(stream: TcpStream, name: String, uuid: u128, entity_id: i32, config: &ServerConfig) -> Self {
+        Self {
+            writer: BufWriter::with_capacity(config.write_buffer_size, stream),
+            compression: None,
+            name,
+            uuid,
+            entity_id,
+            permission_level: 0,
+            chat_limiter: ChatRateLimiter::new(config.chat_messages_per_second),
+            chat_session: None,
+            chat_index: 0,
+            position: (0.0, 0.0, 0.0),
+            yaw: 0.0,
+            pitch: 0.0,
+            on_ground: false,
+            teleport_id: 0,
+            health: 20.0,
+            food: 20,
+            saturation: 5.0,
+            latency: 0,
+            pending_ping: None,
+            last_seen: Instant::now(),
+            latency_warning: config.latency_warning,
+            gamemode: config.default_gamemode,
+            open_window: None,
+            selected_slot: 0,
+            inventory: PlayerInventory::default(),
+            chunks: ChunkTracker::new(),
+            chunk_batcher: ChunkBatcher::new(config.max_chunk_batches),
+            view_distance: config.view_distance,
+            client_view_distance: None,
+            simulation_distance: DEFAULT_SIMULATION_DISTANCE,
+            experience_level: 0,
+            experience_points: 0,
+            total_experience: 0,
+            transferring: false,
+            announced: false,
+            death_location: None,
+            editing_sign: None,
+            vehicle: None,
+            protocol_version: 0,
+            state: ConnectionState::Login,
+            walk_speed: DEFAULT_WALK_SPEED,
+            flying_speed: DEFAULT_FLYING_SPEED,
+            cookies: CookieStore::new(),
+            metadata: Metadata::new(),
+            resource_packs: ResourcePackStack::new(),
+            entities: EntityTracker::new(config.entity_tracking_range),
+            keep_alive: KeepAliveTracker::new(config.max_missed_keep_alives),
+        }
+    }
+
+    pub fn uuid(&self) -> u128 {
+        self.uuid
+    }
+    
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    pub fn entity_id(&self) -> i32 {
+        self.entity_id
+    }
+
+    pub fn permission_level(&self) -> u8 {
+        self.permission_level
+    }
+
+    pub fn set_permission_level(&mut self, level: u8) {
+        self.permission_level = level;
+    }
+
+    pub fn chat_limiter(&mut self) -> &mut ChatRateLimiter {
+        &mut self.chat_limiter
+    }
+
+    pub fn chat_session(&self) -> Option<&ChatSession> {
+        self.chat_session.as_ref()
+    }
+
+    /// A new session replaces the old one, the client sends one whenever its key is refreshed
+    pub fn set_chat_session(&mut self, session: ChatSession) {
+        self.chat_session = Some(session);
+        self.chat_index = 0;
+    }
+
+    /// Checks a chat message's signature against the chat session, every message counts towards the index even if it fails
+    pub fn verify_chat(&mut self, message: &ChatMessage) -> bool {
+        let index = self.chat_index;
+        self.chat_index = self.chat_index.wrapping_add(1);
+        match &self.chat_session {
+            Some(session) => !message.acknowledges_anything() && session.verify(self.uuid, index, message),
+            None => false,
+        }
+    }
+
+    pub fn position(&self) -> (f64, f64, f64) {
+        self.position
+    }
+
+    pub fn set_position(&mut self, x: f64, y: f64, z: f64) {
+        self.position = (x, y, z);
+    }
+
+    pub fn view_distance(&self) -> i32 {
+        self.view_distance
+    }
+
+    /// Distance chunks are sent to, no further than either the server or the client wants
+    pub fn effective_view_distance(&self) -> i32 {
+        effective_view_distance(self.view_distance, self.client_view_distance)
+    }
+
+    /// Changes the view distance during play, loading or unloading the chunks at the edge of the view
+    pub fn set_view_distance(&mut self, distance: i32) -> Result<(), std::io::Error> {
+        self.view_distance = distance.clamp(*VIEW_DISTANCE_RANGE.start(), *VIEW_DISTANCE_RANGE.end());
+        self.send_packet(&packets::play::set_render_distance(self.view_distance))?;
+        self.update_chunks()
+    }
+
+    /// Sent in Client Information during configuration and whenever the client's settings change
+    pub fn set_client_view_distance(&mut self, distance: i32) -> Result<(), std::io::Error> {
+        self.client_view_distance = Some(distance);
+        // Chunks are first sent on joining, which uses the new distance
+        if self.state == ConnectionState::Play {
+            self.update_chunks()?;
+        }
+        Ok(())
+    }
+
+    pub fn simulation_distance(&self) -> i32 {
+        self.simulation_distance
+    }
+
+    pub fn set_simulation_distance(&mut self, distance: i32) -> Result<(), std::io::Error> {
+        self.simulation_distance = distance.clamp(2, 32);
+        self.send_packet(&packets::play::set_simulation_distance(self.simulation_distance))
+    }
+
+    /// Recenters the client after moving into a different chunk, queues the chunks that came into view
+    /// and unloads the ones that left it
+    pub fn update_chunks(&mut self) -> Result<(), std::io::Error> {
+        let (x, _, z) = self.position;
+        let (chunk_x, chunk_z) = ChunkTracker::chunk_of(x, z);
+        if self.chunks.center() != Some((chunk_x, chunk_z)) {
+            // Must come before any new chunks or the client drops them as out of range
+            self.send_packet(&packets::play::set_center_chunk(chunk_x, chunk_z))?;
+        }
+        let (load, unload) = self.chunks.update(chunk_x, chunk_z, self.effective_view_distance());
+        self.chunk_batcher.queue(load, (chunk_x, chunk_z));
+        self.chunk_batcher.cancel(&unload);
+        for (chunk_x, chunk_z) in unload {
+            self.send_packet(&packets::play::unload_chunk(chunk_x, chunk_z))?;
+        }
+        Ok(())
+    }
+
+    /// Spawns the items that came into tracking range and removes the ones that left it
+    pub fn update_entities(&mut self, items: &HashMap<i32, ItemEntity>) -> Result<(), std::io::Error> {
+        let (spawn, remove) = self.entities.update(self.position, items.values().map(|i| (i.entity_id, i.position)));
+        for entity_id in spawn {
+            for packet in items[&entity_id].spawn_packets() {
+                self.send_packet(&packet)?;
+            }
+        }
+        if !remove.is_empty() {
+            self.send_packet(&packets::play::remove_entities(&remove))?;
+        }
+        Ok(())
+    }
+
+    /// Whether the client has been sent the entity
+    pub fn is_tracking(&self, entity_id: i32) -> bool {
+        self.entities.is_tracking(entity_id)
+    }
+
+    /// Stops tracking entities that left the world, returns the ones the client was sent
+    pub fn forget_entities(&mut self, entity_ids: &[i32]) -> Vec<i32> {
+        self.entities.forget(entity_ids)
+    }
+
+    /// Forgets every chunk and entity sent, the client drops them all when it respawns
+    pub fn reset_chunks(&mut self) {
+        self.entities.clear();
+        self.chunks = ChunkTracker::new();
+        self.chunk_batcher = ChunkBatcher::new(self.chunk_batcher.max_batches());
+    }
+
+    /// Whether the chunk has been sent or is queued to be
+    pub fn has_chunk(&self, chunk: (i32, i32)) -> bool {
+        self.chunks.is_loaded(chunk)
+    }
+
+    /// Sends the next batch of queued chunks if the client is ready for it, every chunk uses the same data
+    /// with the changed `blocks` sent once the chunk they are in has loaded
+    pub fn send_chunk_batch(&mut self, data: &[u8], light: &LightData, blocks: &BlockChanges) -> Result<(), std::io::Error> {
+        let batch = self.chunk_batcher.next_batch();
+        if batch.is_empty() {
+            return Ok(());
+        }
+        self.send_packet(&packets::play::chunk_batch_start())?;
+        for (chunk_x, chunk_z) in &batch {
+            self.send_packet(&packets::play::chunk_data(*chunk_x, *chunk_z, data, light))?;
+            for (_, packet) in edit::change_packets(&blocks.in_chunk(*chunk_x, *chunk_z)) {
+                self.send_packet(&packet)?;
+            }
+        }
+        self.send_packet(&packets::play::chunk_batch_finished(batch.len() as i32))
+    }
+
+    pub fn chunk_batch_received(&mut self, chunks_per_tick: f32) {
+        self.chunk_batcher.batch_received(chunks_per_tick);
+        debug!("{} wants {} chunks per tick", self.name, self.chunk_batcher.chunks_per_tick());
+    }
+
+    /// Attaches the player's view to another entity, as spectators do
+    pub fn set_camera(&mut self, entity_id: i32) -> Result<(), std::io::Error> {
+        self.send_packet(&packets::play::set_camera(entity_id))
+    }
+
+    pub fn reset_camera(&mut self) -> Result<(), std::io::Error> {
+        self.send_packet(&packets::play::set_camera(self.entity_id))
+    }
+
+    pub fn pose(&self) -> Pose {
+        match self.metadata.get(POSE_INDEX) {
+            Some(MetadataValue::Pose(pose)) => *pose,
+            _ => Pose::Standing,
+        }
+    }
+
+    /// Changes the pose and the crouching flag along with it, returns the Set Entity Metadata to send to everyone else
+    pub fn set_pose(&mut self, pose: Pose) -> Vec<u8> {
+        let flags = self.set_entity_flag(FLAG_CROUCHING, pose == Pose::Sneaking);
+        let mut changed = Metadata::new();
+        changed.set(ENTITY_FLAGS_INDEX, MetadataValue::Byte(flags));
+        changed.set(POSE_INDEX, MetadataValue::Pose(pose));
+        self.metadata.set(POSE_INDEX, MetadataValue::Pose(pose));
+        packets::play::set_entity_metadata(self.entity_id, &changed)
+    }
+
+    pub fn is_sprinting(&self) -> bool {
+        self.entity_flags() & FLAG_SPRINTING != 0
+    }
+
+    /// Returns the Set Entity Metadata to send to everyone else
+    pub fn set_sprinting(&mut self, sprinting: bool) -> Vec<u8> {
+        let flags = self.set_entity_flag(FLAG_SPRINTING, sprinting);
+        let mut changed = Metadata::new();
+        changed.set(ENTITY_FLAGS_INDEX, MetadataValue::Byte(flags));
+        packets::play::set_entity_metadata(self.entity_id, &changed)
+    }
+
+    pub fn entity_flags(&self) -> i8 {
+        match self.metadata.get(ENTITY_FLAGS_INDEX) {
+            Some(MetadataValue::Byte(flags)) => *flags,
+            _ => 0,
+        }
+    }
+
+    /// Returns the flags byte after the change
+    fn set_entity_flag(&mut self, flag: i8, value: bool) -> i8 {
+        let flags = match value {
+            true => self.entity_flags() | flag,
+            false => self.entity_flags() & !flag,
+        };
+        self.metadata.set(ENTITY_FLAGS_INDEX, MetadataValue::Byte(flags));
+        flags
+    }
+
+    /// Way the player is looking, up or down when their pitch is steep enough
+    pub fn facing_direction(&self) -> Direction {
+        Direction::from_rotation(self.yaw, self.pitch)
+    }
+
+    pub fn set_rotation(&mut self, yaw: f32, pitch: f32) {
+        self.yaw = yaw;
+        self.pitch = pitch;
+    }
+
+    pub fn on_ground(&self) -> bool {
+        self.on_ground
+    }
+
+    pub fn set_on_ground(&mut self, on_ground: bool) {
+        self.on_ground = on_ground;
+    }
+
+    /// Rolling average of the ping round trips in milliseconds, what the tab list shows
+    pub fn latency(&self) -> i32 {
+        self.latency
+    }
+
+    /// Weighs the new round trip a quarter against the previous average, same as vanilla.
+    /// Warns once when the average goes over the configured threshold, not again until it has dropped back
+    fn record_latency(&mut self, round_trip: i32) {
+        let was_high = self.latency > self.latency_warning;
+        self.latency = match self.latency {
+            0 => round_trip,
+            latency => (latency * 3 + round_trip) / 4,
+        };
+        if self.latency_warning > 0 && !was_high && self.latency > self.latency_warning {
+            warn!("{} has a latency of {}ms", self.name, self.latency);
+        }
+    }
+
+    /// Any keep alive from the client shows it is still there
+    pub fn handle_keep_alive(&mut self, id: i64) {
+        let now = Instant::now();
+        self.last_seen = now;
+        if let Some(round_trip) = self.keep_alive.receive(id, now) {
+            debug!("{} answered keep alive {id} in {round_trip:?}", self.name);
+            self.record_latency(round_trip.as_millis() as i32);
+        }
+    }
+
+    /// Sends the next keep alive, or disconnects the player and returns false if too many went unanswered
+    pub fn send_keep_alive(&mut self, now: Instant) -> Result<bool, std::io::Error> {
+        match self.keep_alive.next(now) {
+            Some(id) => self.send_packet(&packets::play::keep_alive(id)).map(|_| true),
+            None => {
+                warn!("{} missed {} keep alives in a row, last seen {:?} ago, {}", self.name, self.keep_alive.missed(), self.last_seen.elapsed(), self.keep_alive.histogram());
+                self.disconnect(&TextComponent::translate("disconnect.timeout", Vec::new())).map(|_| false)
+            },
+        }
+    }
+
+    pub fn send_ping(&mut self, id: i32) -> Result<(), std::io::Error> {
+        self.pending_ping = Some((id, Instant::now()));
+        self.send_packet(&packets::play::ping(id))
+    }
+
+    /// Updates the latency if the pong answers the last ping sent
+    pub fn handle_pong(&mut self, id: i32) {
+        if let Some((ping_id, sent)) = self.pending_ping {
+            if ping_id == id {
+                self.record_latency(sent.elapsed().as_millis() as i32);
+                self.last_seen = Instant::now();
+                self.pending_ping = None;
+            }
+        }
+    }
+
+    pub fn gamemode(&self) -> Gamemode {
+        self.gamemode
+    }
+
+    /// Changes the gamemode and tells the client with a Game Event
+    pub fn set_gamemode(&mut self, gamemode: Gamemode) -> Result<(), std::io::Error> {
+        // Only spectators can view through another entity
+        if self.gamemode == Gamemode::Spectator && gamemode != Gamemode::Spectator {
+            self.reset_camera()?;
+        }
+        self.gamemode = gamemode;
+        self.send_packet(&packets::play::game_event(packets::play::GameEvent::ChangeGameMode, gamemode as u8 as f32))
+    }
+
+    pub fn walk_speed(&self) -> f64 {
+        self.walk_speed
+    }
+
+    pub fn set_walk_speed(&mut self, speed: f64) -> Result<(), std::io::Error> {
+        self.walk_speed = speed;
+        self.send_packet(&packets::play::update_attributes(self.entity_id, &[(Attribute::MovementSpeed, speed)]))
+    }
+
+    pub fn set_flying_speed(&mut self, speed: f32) -> Result<(), std::io::Error> {
+        self.flying_speed = speed;
+        self.send_abilities()
+    }
+
+    /// Abilities come from the gamemode, only the flying speed can be changed
+    pub fn send_abilities(&mut self) -> Result<(), std::io::Error> {
+        let flags = match self.gamemode {
+            Gamemode::Creative => ABILITY_INVULNERABLE | ABILITY_ALLOW_FLYING | ABILITY_INSTANT_BREAK,
+            Gamemode::Spectator => ABILITY_INVULNERABLE | ABILITY_FLYING | ABILITY_ALLOW_FLYING,
+            Gamemode::Survival | Gamemode::Adventure => 0,
+        };
+        self.send_packet(&packets::play::player_abilities(flags, self.flying_speed, DEFAULT_FOV_MODIFIER))
+    }
+
+    pub fn open_window(&self) -> Option<u8> {
+        self.open_window
+    }
+
+    pub fn set_open_window(&mut self, window_id: Option<u8>) {
+        self.open_window = window_id;
+    }
+
+    /// Forces the open container closed on the client
+    pub fn close_window(&mut self) -> Result<(), std::io::Error> {
+        if let Some(window_id) = self.open_window.take() {
+            self.send_packet(&packets::play::close_container(window_id))?;
+        }
+        Ok(())
+    }
+
+    pub fn selected_slot(&self) -> u8 {
+        self.selected_slot
+    }
+
+    pub fn set_selected_slot(&mut self, slot: i16) -> Result<(), &'static str> {
+        if !(0..=8).contains(&slot) {
+            return Err("Held item slot out of range");
+        }
+        self.selected_slot = slot as u8;
+        Ok(())
+    }
+
+    /// Item in the selected hotbar slot
+    pub fn held_item(&self) -> Slot {
+        *self.inventory.hotbar(self.selected_slot)
+    }
+
+    /// Adds the item to the inventory and sends every slot that changed, `item` keeps what didn't fit
+    pub fn give(&mut self, item: &mut Slot) -> Result<(), std::io::Error> {
+        for slot in self.inventory.add(item) {
+            let state_id = self.inventory.next_state_id();
+            let contents = self.inventory.slots()[slot];
+            self.send_packet(&packets::play::set_container_slot(0, state_id, slot as i16, &contents))?;
+        }
+        Ok(())
+    }
+
+    /// Takes one item, or the whole stack, out of the selected hotbar slot and sends the slot.
+    /// Returns what was dropped, None when the hand was empty.
+    pub fn drop_held_item(&mut self, whole_stack: bool) -> Result<Option<Slot>, std::io::Error> {
+        let held = self.held_item();
+        if held.is_empty() {
+            return Ok(None);
+        }
+        let count = match whole_stack {
+            true => held.count,
+            false => 1,
+        };
+        let left = match held.count - count {
+            0 => Slot::EMPTY,
+            left => Slot::new(held.item_id, left),
+        };
+        let slot = inventory::HOTBAR_START + self.selected_slot as usize;
+        let _ = self.inventory.set(slot, left);
+        let state_id = self.inventory.next_state_id();
+        self.send_packet(&packets::play::set_container_slot(0, state_id, slot as i16, &left))?;
+        Ok(Some(Slot::new(held.item_id, count)))
+    }
+
+    /// Moves the recipe's ingredients into the crafting grid and sends every slot that changed
+    pub fn place_recipe(&mut self, recipe: &Recipe, make_all: bool) -> Result<(), std::io::Error> {
+        let changed = self.inventory.place_recipe(recipe, make_all).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+        for slot in changed {
+            let state_id = self.inventory.next_state_id();
+            let contents = self.inventory.slots()[slot];
+            self.send_packet(&packets::play::set_container_slot(0, state_id, slot as i16, &contents))?;
+        }
+        Ok(())
+    }
+
+    /// Records a change the client already made, such as a creative mode slot
+    pub fn update_inventory_slot(&mut self, slot: usize, item: Slot) -> Result<(), &'static str> {
+        self.inventory.set(slot, item)
+    }
+
+    /// Adds experience points, levelling up as many times as they cover.
+    /// Sends Set Experience, and the level up sound when a level was gained.
+    pub fn give_experience(&mut self, amount: i32) -> Result<(), std::io::Error> {
+        let start_level = self.experience_level;
+        self.total_experience = self.total_experience.saturating_add(amount.max(0));
+        self.experience_points = self.experience_points.saturating_add(amount.max(0));
+        while self.experience_points >= experience_to_next_level(self.experience_level) {
+            self.experience_points -= experience_to_next_level(self.experience_level);
+            self.experience_level += 1;
+        }
+
+        let progress = self.experience_points as f32 / experience_to_next_level(self.experience_level) as f32;
+        self.send_packet(&packets::play::set_experience(progress, self.experience_level, self.total_experience))?;
+        if self.experience_level > start_level {
+            // Gets louder with each level until 30, same as vanilla
+            let volume = (self.experience_level.min(30) as f32 / 30.0) * 0.75;
+            self.send_packet(&packets::play::sound_effect(
+                "minecraft:entity.player.levelup",
+                packets::play::SoundCategory::Players,
+                self.position,
+                volume,
+                1.0,
+                0,
+            ))?;
+        }
+        Ok(())
+    }
+
+    pub fn health(&self) -> f32 {
+        self.health
+    }
+
+    pub fn set_health(&mut self, health: f32) -> Result<(), std::io::Error> {
+        self.health = health.max(0.0);
+        self.send_packet(&packets::play::set_health(self.health, self.food, self.saturation))
+    }
+
+    /// Full health and hunger, as after respawning
+    pub fn restore_health(&mut self) -> Result<(), std::io::Error> {
+        self.food = 20;
+        self.saturation = 5.0;
+        self.set_health(20.0)
+    }
+
+    /// Drops the player's health to 0 and shows the death screen
+    pub fn kill(&mut self, message: &TextComponent) -> Result<(), std::io::Error> {
+        let (x, y, z) = self.position;
+        // Only the overworld exists so far
+        self.death_location = Some(("minecraft:overworld".into(), Position::new(x.floor() as i32, y.floor() as i32, z.floor() as i32)));
+        self.close_window()?;
+        self.set_health(0.0)?;
+        self.send_packet(&packets::play::combat_death(self.entity_id, message))
+    }
+
+    pub fn protocol_version(&self) -> i32 {
+        self.protocol_version
+    }
+
+    pub fn set_protocol_version(&mut self, version: i32) {
+        self.protocol_version = version;
+    }
+
+    pub fn vehicle(&self) -> Option<i32> {
+        self.vehicle
+    }
+
+    pub fn set_vehicle(&mut self, vehicle: Option<i32>) {
+        self.vehicle = vehicle;
+    }
+
+    pub fn open_sign_editor(&mut self, position: Position, is_front_text: bool) -> Result<(), std::io::Error> {
+        self.editing_sign = Some((position, is_front_text));
+        self.send_packet(&packets::play::open_sign_editor(position, is_front_text))
+    }
+
+    /// The sign the editor was opened for, the editor is closed once the client sends its text
+    pub fn take_editing_sign(&mut self) -> Option<(Position, bool)> {
+        self.editing_sign.take()
+    }
+
+    pub fn death_location(&self) -> Option<(String, Position)> {
+        self.death_location.clone()
+    }
+
+    /// Moves the player and tells the client with a Synchronize Player Position
+    pub fn teleport(&mut self, x: f64, y: f64, z: f64) -> Result<(), std::io::Error> {
+        self.position = (x, y, z);
+        self.teleport_id = self.teleport_id.wrapping_add(1);
+        let packet = packets::play::synchronize_player_position(x, y, z, self.yaw, self.pitch, 0, self.teleport_id);
+        self.send_packet(&packet)
+    }
+
+    /// Sends an unframed packet, remapping its id to the player's protocol version
+    pub fn send_packet(&mut self, packet: &[u8]) -> Result<(), std::io::Error> {
+        let remapped = Version::from_protocol(self.protocol_version).and_then(|v| v.remap(self.state, packet));
+        let packet = remapped.as_deref().unwrap_or(packet);
+        trace::record(trace::Direction::Clientbound, &self.state.to_string(), packet);
+        match self.compression {
+            Some(compression) => self.writer.write_all(&compression.frame(packet)?),
+            None => write_packet(&mut self.writer, packet),
+        }
+    }
+
+    /// Matches the connection's framing once it has sent Set Compression
+    pub fn set_compression(&mut self, compression: Option<PacketCompression>) {
+        self.compression = compression;
+    }
+
+    /// Writes out every packet sent since the last flush
+    pub fn flush(&mut self) -> Result<(), std::io::Error> {
+        self.writer.flush()
+    }
+
+    pub fn state(&self) -> ConnectionState {
+        self.state
+    }
+
+    /// Kept in step with the connection as it moves between configuration and play
+    pub fn set_state(&mut self, state: ConnectionState) {
+        self.state = state;
+    }
+
+    /// Sends the player to another server, storing the cookie first so the other server can read it
+    pub fn transfer(&mut self, host: &str, port: u16, cookie: Option<(&str, &[u8])>) -> Result<(), std::io::Error> {
+        if let Some((key, payload)) = cookie {
+            if payload.len() > MAX_COOKIE_SIZE {
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "Cookie payload is too large"));
+            }
+            self.send_packet(&packets::play::store_cookie(key, payload))?;
+        }
+        info!("Transferring {} to {host}:{port}", self.name);
+        self.transferring = true;
+        self.send_packet(&packets::play::transfer(host, port))
+    }
+
+    pub fn is_transferring(&self) -> bool {
+        self.transferring
+    }
+
+    pub fn set_announced(&mut self) {
+        self.announced = true;
+    }
+
+    pub fn is_announced(&self) -> bool {
+        self.announced
+    }
+
+    /// Serializes the value into a cookie the client keeps across transfers and reconnects
+    pub fn store_cookie<T: Serialize>(&mut self, key: &str, value: &T) -> Result<(), CookieError> {
+        let payload = cookie::encode(value)?;
+        self.send_packet(&packets::play::store_cookie(key, &payload)).map_err(CookieError::Io)?;
+        self.cookies.set(key, payload);
+        Ok(())
+    }
+
+    /// Asks the client for a cookie, it arrives later as a Cookie Response
+    pub fn request_cookie(&mut self, key: &str) -> Result<(), std::io::Error> {
+        let packet = match self.state {
+            ConnectionState::Login => packets::login::cookie_request(key),
+            ConnectionState::Configuration => packets::configuration::cookie_request(key),
+            _ => packets::play::cookie_request(key),
+        };
+        self.send_packet(&packet)
+    }
+
+    pub fn cookies(&self) -> &CookieStore {
+        &self.cookies
+    }
+
+    pub fn handle_cookie_response(&mut self, key: String, payload: Option<Vec<u8>>) {
+        self.cookies.receive(key, payload);
+    }
+
+    /// Pushes a pack on top of the ones the client already has
+    pub fn add_resource_pack(&mut self, pack: ResourcePack) -> Result<(), std::io::Error> {
+        let packet = match self.state {
+            ConnectionState::Configuration => packets::configuration::add_resource_pack(&pack),
+            _ => packets::play::add_resource_pack(&pack),
+        };
+        self.resource_packs.add(pack);
+        self.send_packet(&packet)
+    }
+
+    /// False if the player wasn't sent a pack with the uuid
+    pub fn remove_resource_pack(&mut self, uuid: u128) -> Result<bool, std::io::Error> {
+        if self.resource_packs.remove(uuid).is_none() {
+            return Ok(false);
+        }
+        self.send_remove_resource_pack(Some(uuid))?;
+        Ok(true)
+    }
+
+    pub fn remove_all_resource_packs(&mut self) -> Result<(), std::io::Error> {
+        self.resource_packs.clear();
+        self.send_remove_resource_pack(None)
+    }
+
+    fn send_remove_resource_pack(&mut self, uuid: Option<u128>) -> Result<(), std::io::Error> {
+        let packet = match self.state {
+            ConnectionState::Configuration => packets::configuration::remove_resource_pack(uuid),
+            _ => packets::play::remove_resource_pack(uuid),
+        };
+        self.send_packet(&packet)
+    }
+
+    pub fn resource_packs(&self) -> &ResourcePackStack {
+        &self.resource_packs
+    }
+
+    /// Failed packs are dropped from the stack, declining a forced pack disconnects the player
+    pub fn handle_resource_pack_response(&mut self, uuid: u128, status: ResourcePackStatus) -> Result<(), std::io::Error> {
+        debug!("{} resource pack {uuid:032x}: {status:?}", self.name);
+        if !status.is_failure() {
+            return Ok(());
+        }
+        let Some(pack) = self.resource_packs.remove(uuid) else {
+            return Ok(());
+        };
+        if pack.forced && status == ResourcePackStatus::Declined {
+            return self.disconnect(&TextComponent::translate("multiplayer.requiredTexturePrompt.disconnect", Vec::new()));
+        }
+        Ok(())
+    }
+
+    /// Sends a disconnect for the current state and closes the socket, ending the player's connection thread
+    pub fn disconnect(&mut self, reason: &TextComponent) -> Result<(), std::io::Error> {
+        info!("Disconnecting {}, Reason: {}", self.name, reason.text);
+        if let Some(packet) = packets::disconnect(self.state, reason) {
+            let _ = self.send_packet(&packet);
+        }
+        let _ = self.flush();
+        self.writer.get_ref().shutdown(Shutdown::Both)
+    }
+}
+
+/// Experience needed to go from `level` to the next one
+// https://minecraft.wiki/w/Experience#Leveling_up
+pub fn experience_to_next_level(level: i32) -> i32 {
+    match level {
+        0..=15 => 2 * level + 7,
+        16..=30 => 5 * level - 38,
+        _ => 9 * level - 158,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{protocol::Packet, types::reader::PacketReader, utils::tests::{drain, read_packet, socket_pair}};
+
+    #[test]
+    fn buffers_packets_until_flushed() {
+        let (server, client) = socket_pair();
+        let config = ServerConfig { write_buffer_size: 4321, ..ServerConfig::default() };
+        let mut player = Player::new(server, "Steve".into(), 1, 1, &config);
+        assert_eq!(player.writer.capacity(), 4321);
+        player.send_packet(&[0x7F]).unwrap();
+        assert_eq!(player.writer.buffer(), &[1, 0x7F]);
+        player.flush().unwrap();
+        assert!(player.writer.buffer().is_empty());
+        let received = drain(client);
+        player.writer.get_ref().shutdown(Shutdown::Both).unwrap();
+        assert_eq!(received.join().unwrap(), vec![1, 0x7F]);
+    }
+
+    #[test]
+    fn kill_zeroes_health_before_the_death_screen() {
+        let (server, mut client) = socket_pair();
+        let mut player = Player::new(server, "Steve".into(), 1, 7, &ServerConfig::default());
+        player.set_position(1.5, 64.0, -2.5);
+        let message = TextComponent::new("Steve died");
+        player.kill(&message).unwrap();
+        player.flush().unwrap();
+        assert_eq!(player.health(), 0.0);
+        assert_eq!(read_packet(&mut client), packets::play::set_health(0.0, 20, 5.0));
+        assert_eq!(read_packet(&mut client), packets::play::combat_death(7, &message));
+    }
+
+    #[test]
+    fn changing_gamemode_sends_a_game_event() {
+        let (server, mut client) = socket_pair();
+        let mut player = Player::new(server, "Steve".into(), 1, 1, &ServerConfig::default());
+        player.set_gamemode(Gamemode::Spectator).unwrap();
+        player.flush().unwrap();
+        assert_eq!(player.gamemode(), Gamemode::Spectator);
+        assert_eq!(read_packet(&mut client), packets::play::game_event(packets::play::GameEvent::ChangeGameMode, 3.0));
+    }
+
+    #[test]
+    fn dying_closes_the_open_container() {
+        let (server, mut client) = socket_pair();
+        let mut player = Player::new(server, "Steve".into(), 1, 7, &ServerConfig::default());
+        player.set_open_window(Some(3));
+        let message = TextComponent::new("Steve died");
+        player.kill(&message).unwrap();
+        player.flush().unwrap();
+        assert_eq!(player.open_window(), None);
+        assert_eq!(read_packet(&mut client), packets::play::close_container(3));
+        assert_eq!(read_packet(&mut client), packets::play::set_health(0.0, 20, 5.0));
+    }
+
+    #[test]
+    fn levelling_up_plays_the_sound() {
+        let (server, mut client) = socket_pair();
+        let mut player = Player::new(server, "Steve".into(), 1, 1, &ServerConfig::default());
+        player.give_experience(5).unwrap();
+        player.give_experience(10).unwrap();
+        player.flush().unwrap();
+
+        assert_eq!(read_packet(&mut client), packets::play::set_experience(5.0 / 7.0, 0, 5));
+        // 7 points finish level 0, the other 8 are most of level 1's 9
+        assert_eq!(read_packet(&mut client), packets::play::set_experience(8.0 / 9.0, 1, 15));
+        let sound = packets::play::sound_effect("minecraft:entity.player.levelup", packets::play::SoundCategory::Players, (0.0, 0.0, 0.0), (1.0 / 30.0) * 0.75, 1.0, 0);
+        assert_eq!(read_packet(&mut client), sound);
+    }
+
+    #[test]
+    fn experience_needed_per_level() {
+        assert_eq!(experience_to_next_level(0), 7);
+        assert_eq!(experience_to_next_level(15), 37);
+        assert_eq!(experience_to_next_level(16), 42);
+        assert_eq!(experience_to_next_level(30), 112);
+        assert_eq!(experience_to_next_level(31), 121);
+    }
+
+    #[test]
+    fn transfer_stores_the_cookie_first() {
+        let (server, mut client) = socket_pair();
+        let mut player = Player::new(server, "Steve".into(), 1, 1, &ServerConfig::default());
+        player.transfer("example.com", 25566, Some(("blahaj:token", b"abc"))).unwrap();
+        player.flush().unwrap();
+        assert!(player.is_transferring());
+        assert_eq!(read_packet(&mut client), packets::play::store_cookie("blahaj:token", b"abc"));
+        assert_eq!(read_packet(&mut client), packets::play::transfer("example.com", 25566));
+    }
+
+    #[test]
+    fn oversized_transfer_cookies_are_refused() {
+        let (server, _client) = socket_pair();
+        let mut player = Player::new(server, "Steve".into(), 1, 1, &ServerConfig::default());
+        assert!(player.transfer("example.com", 25566, Some(("blahaj:token", &[0; MAX_COOKIE_SIZE + 1]))).is_err());
+        assert!(!player.is_transferring());
+    }
+
+    #[test]
+    fn reducing_view_distance_unloads_the_outer_ring() {
+        let (server, client) = socket_pair();
+        let received = drain(client);
+        let mut player = Player::new(server, "Steve".into(), 1, 1, &ServerConfig::default());
+        player.set_state(ConnectionState::Play);
+        player.set_view_distance(4).unwrap();
+        player.set_view_distance(2).unwrap();
+        player.flush().unwrap();
+        player.writer.get_ref().shutdown(Shutdown::Both).unwrap();
+
+        let frames = received.join().unwrap();
+        let mut reader = PacketReader::new(&frames);
+        let mut unloaded = Vec::new();
+        while reader.remaining() > 0 {
+            let length = reader.read_ivar().unwrap() as usize;
+            let packet = reader.read_bytes(length).unwrap();
+            let mut packet = PacketReader::new(packet);
+            if packet.read_ivar().unwrap() == Packet::UnloadChunk.id() {
+                let (z, x) = (packet.read_i32().unwrap(), packet.read_i32().unwrap());
+                unloaded.push((x, z));
+            }
+        }
+        assert_eq!(unloaded.len(), 81 - 25);
+        assert!(unloaded.iter().all(|(x, z)| x.abs() > 2 || z.abs() > 2));
+    }
+
+    #[test]
+    fn simulation_distance_is_clamped_and_sent() {
+        let (server, mut client) = socket_pair();
+        let mut player = Player::new(server, "Steve".into(), 1, 1, &ServerConfig::default());
+        player.set_simulation_distance(40).unwrap();
+        player.flush().unwrap();
+        assert_eq!(player.simulation_distance(), 32);
+        assert_eq!(read_packet(&mut client), packets::play::set_simulation_distance(32));
+    }
+
+    #[test]
+    fn dying_records_the_death_location() {
+        let (server, _client) = socket_pair();
+        let mut player = Player::new(server, "Steve".into(), 1, 7, &ServerConfig::default());
+        assert_eq!(player.death_location(), None);
+        player.set_position(1.5, 64.0, -2.5);
+        player.kill(&TextComponent::new("Steve died")).unwrap();
+        assert_eq!(player.death_location(), Some(("minecraft:overworld".to_string(), Position::new(1, 64, -3))));
+    }
+
+    #[test]
+    fn chunk_batches_are_wrapped_in_start_and_finished() {
+        let (server, mut client) = socket_pair();
+        let mut player = Player::new(server, "Steve".into(), 1, 1, &ServerConfig::default());
+        player.set_state(ConnectionState::Play);
+        player.update_chunks().unwrap();
+        let data = crate::world::chunk::empty_chunk_data(24);
+        let light = LightData::full_bright(24);
+        player.send_chunk_batch(&data, &light, &BlockChanges::new()).unwrap();
+        player.flush().unwrap();
+
+        assert_eq!(read_packet(&mut client), packets::play::set_center_chunk(0, 0));
+        assert_eq!(read_packet(&mut client), packets::play::chunk_batch_start());
+        assert_eq!(read_packet(&mut client), packets::play::chunk_data(0, 0, &data, &light));
+        for _ in 1..9 {
+            assert_eq!(read_packet(&mut client)[0] as i32, Packet::ChunkDataAndUpdateLight.id());
+        }
+        assert_eq!(read_packet(&mut client), packets::play::chunk_batch_finished(9));
+    }
+
+    #[test]
+    fn latency_is_a_rolling_average_of_round_trips() {
+        let (server, _client) = socket_pair();
+        let mut player = Player::new(server, "Steve".into(), 1, 1, &ServerConfig::default());
+        player.record_latency(100);
+        assert_eq!(player.latency(), 100);
+        player.record_latency(200);
+        assert_eq!(player.latency(), 125);
+        player.record_latency(300);
+        assert_eq!(player.latency(), 168);
+    }
+
+    #[test]
+    fn only_the_pending_ping_is_answered() {
+        let (server, _client) = socket_pair();
+        let mut player = Player::new(server, "Steve".into(), 1, 1, &ServerConfig::default());
+        let joined = player.last_seen;
+        player.send_ping(7).unwrap();
+        player.handle_pong(6);
+        assert!(player.pending_ping.is_some());
+        assert_eq!(player.last_seen, joined);
+        std::thread::sleep(std::time::Duration::from_millis(1));
+        player.handle_pong(7);
+        assert!(player.pending_ping.is_none());
+        assert!(player.last_seen > joined);
+    }
+
+    #[test]
+    fn stored_cookies_are_sent_and_kept() {
+        let (server, mut client) = socket_pair();
+        let mut player = Player::new(server, "Steve".into(), 1, 1, &ServerConfig::default());
+        player.set_state(ConnectionState::Play);
+        player.store_cookie("blahaj:hash", &vec![1u8, 2, 3]).unwrap();
+        player.request_cookie("blahaj:hash").unwrap();
+        player.flush().unwrap();
+        assert_eq!(read_packet(&mut client), packets::play::store_cookie("blahaj:hash", b"[1,2,3]"));
+        assert_eq!(read_packet(&mut client), packets::play::cookie_request("blahaj:hash"));
+        assert_eq!(player.cookies().get::<Vec<u8>>("blahaj:hash"), Some(vec![1, 2, 3]));
+    }
+
+    #[test]
+    fn facing_direction_follows_the_rotation() {
+        let (server, _client) = socket_pair();
+        let mut player = Player::new(server, "Steve".into(), 1, 1, &ServerConfig::default());
+        assert_eq!(player.facing_direction(), Direction::South);
+        player.set_rotation(90.0, 10.0);
+        assert_eq!(player.facing_direction(), Direction::West);
+        player.set_rotation(-270.0, 80.0);
+        assert_eq!(player.facing_direction(), Direction::Down);
+    }
+
+    #[test]
+    fn items_are_spawned_and_removed_as_the_player_moves() {
+        let (server, mut client) = socket_pair();
+        let config = ServerConfig { entity_tracking_range: 16.0, ..ServerConfig::default() };
+        let mut player = Player::new(server, "Steve".into(), 1, 1, &config);
+        let item = ItemEntity::new(5, Slot::new(1, 1), (10.0, 64.0, 0.0));
+        let items = HashMap::from([(5, item.clone())]);
+
+        player.set_position(40.0, 64.0, 0.0);
+        player.update_entities(&items).unwrap();
+        assert!(!player.is_tracking(5));
+
+        player.set_position(20.0, 64.0, 0.0);
+        player.update_entities(&items).unwrap();
+        player.set_position(30.0, 64.0, 0.0);
+        player.update_entities(&items).unwrap();
+        player.flush().unwrap();
+        for packet in item.spawn_packets() {
+            assert_eq!(read_packet(&mut client), packet);
+        }
+        assert_eq!(read_packet(&mut client), packets::play::remove_entities(&[5]));
+    }
+
+    #[test]
+    fn sneaking_sets_the_pose_and_the_crouching_flag() {
+        let (server, _client) = socket_pair();
+        let mut player = Player::new(server, "Steve".into(), 1, 7, &ServerConfig::default());
+        let packet = player.set_pose(Pose::Sneaking);
+        let mut changed = Metadata::new();
+        changed.set(ENTITY_FLAGS_INDEX, MetadataValue::Byte(FLAG_CROUCHING));
+        changed.set(POSE_INDEX, MetadataValue::Pose(Pose::Sneaking));
+        assert_eq!(packet, packets::play::set_entity_metadata(7, &changed));
+        assert_eq!(player.pose(), Pose::Sneaking);
+
+        player.set_pose(Pose::Standing);
+        assert_eq!(player.pose(), Pose::Standing);
+        assert_eq!(player.entity_flags() & FLAG_CROUCHING, 0);
+    }
+
+    #[test]
+    fn keep_alive_round_trips_count_towards_the_latency() {
+        let (server, _client) = socket_pair();
+        let mut player = Player::new(server, "Steve".into(), 1, 1, &ServerConfig::default());
+        let id = player.keep_alive.next(Instant::now() - std::time::Duration::from_millis(100)).unwrap();
+        player.handle_keep_alive(id);
+        assert!((100..200).contains(&player.latency()));
+    }
+
+    #[test]
+    fn high_latency_is_only_warned_about_when_it_is_crossed() {
+        let (server, _client) = socket_pair();
+        let config = ServerConfig { latency_warning: 100, ..ServerConfig::default() };
+        let mut player = Player::new(server, "Steve".into(), 1, 1, &config);
+        let warnings = |logged: Vec<String>| logged.iter().filter(|line| line.starts_with("WARN") && line.contains("Steve has a latency")).count();
+        assert_eq!(warnings(crate::logging::tests::capture(|| {
+            player.record_latency(50);
+            player.record_latency(500);
+            player.record_latency(500);
+            player.record_latency(500);
+        })), 1);
+        // Back under the threshold, the next time it crosses is warned about again
+        assert_eq!(warnings(crate::logging::tests::capture(|| {
+            player.latency = 50;
+            player.record_latency(500);
+        })), 1);
+    }
+}
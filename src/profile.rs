@@ -0,0 +1,96 @@
+// Game profiles seen recently, so the same player isn't looked up again every time
+// https://wiki.vg/Mojang_API
+
+use std::{collections::HashMap, time::{Duration, Instant}};
+
+use serde::Deserialize;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameProfile {
+    pub uuid: u128,
+    pub name: String,
+    /// Such as the player's skin, empty for profiles that weren't looked up
+    pub properties: Vec<ProfileProperty>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ProfileProperty {
+    pub name: String,
+    /// Base64 encoded json
+    pub value: String,
+    /// Mojang's signature of the value, clients only trust signed textures
+    pub signature: Option<String>,
+}
+
+/// Profiles keyed by uuid, entries older than the ttl are treated as missing
+pub struct ProfileCache {
+    ttl: Duration,
+    profiles: HashMap<u128, (GameProfile, Instant)>,
+}
+
+impl ProfileCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            profiles: HashMap::new(),
+        }
+    }
+
+    /// Caches the profile as fetched at `now`, replacing any older one for the uuid
+    pub fn insert(&mut self, profile: GameProfile, now: Instant) {
+        self.profiles.insert(profile.uuid, (profile, now));
+    }
+
+    pub fn by_uuid(&self, uuid: u128, now: Instant) -> Option<&GameProfile> {
+        let (profile, fetched) = self.profiles.get(&uuid)?;
+        match now.saturating_duration_since(*fetched) < self.ttl {
+            true => Some(profile),
+            false => None,
+        }
+    }
+
+    /// Drops expired profiles so the cache doesn't grow forever
+    pub fn remove_expired(&mut self, now: Instant) {
+        let ttl = self.ttl;
+        self.profiles.retain(|_, (_, fetched)| now.saturating_duration_since(*fetched) < ttl);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(name: &str) -> GameProfile {
+        GameProfile { uuid: 1, name: name.to_string(), properties: Vec::new() }
+    }
+
+    #[test]
+    fn cached_profiles_are_returned_within_the_ttl() {
+        let start = Instant::now();
+        let mut cache = ProfileCache::new(Duration::from_secs(60));
+        cache.insert(profile("Steve"), start);
+        let later = start + Duration::from_secs(59);
+        assert_eq!(cache.by_uuid(1, later), Some(&profile("Steve")));
+    }
+
+    #[test]
+    fn expired_profiles_are_replaced_when_fetched_again() {
+        let start = Instant::now();
+        let mut cache = ProfileCache::new(Duration::from_secs(60));
+        cache.insert(profile("Steve"), start);
+        let expired = start + Duration::from_secs(60);
+        assert_eq!(cache.by_uuid(1, expired), None);
+        // The refreshed entry lasts another ttl
+        cache.insert(profile("Alex"), expired);
+        assert_eq!(cache.by_uuid(1, expired + Duration::from_secs(59)), Some(&profile("Alex")));
+    }
+
+    #[test]
+    fn expired_profiles_are_removed() {
+        let start = Instant::now();
+        let mut cache = ProfileCache::new(Duration::from_secs(60));
+        cache.insert(profile("Steve"), start);
+        cache.remove_expired(start + Duration::from_secs(61));
+        assert!(cache.profiles.is_empty());
+    }
+}
@@ -0,0 +1,457 @@
+// Packet ids for every supported protocol version, so a version bump only touches these tables.
+// Builders write the ids of `Version::CURRENT` and packets are remapped to the client's version as they are sent.
+// Only ids are translated, packet layouts are still those of the current version.
+// https://wiki.vg/Protocol_version_numbers
+
+use crate::{connection::ConnectionState, types::varint::ivar, utils::write_ivar};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Version {
+    /// 1.21 and 1.21.1
+    V1_21_1 = 767,
+    /// 1.21.2 and 1.21.3
+    V1_21_2 = 768,
+}
+
+impl Version {
+    /// Version the packet builders are written for
+    pub const CURRENT: Version = Version::V1_21_1;
+
+    /// Version with a packet id table, whether or not it can be negotiated
+    pub fn known(protocol: i32) -> Option<Self> {
+        match protocol {
+            767 => Some(Self::V1_21_1),
+            768 => Some(Self::V1_21_2),
+            _ => None,
+        }
+    }
+
+    /// Version negotiated in the handshake, None if it isn't supported.
+    /// Serverbound packets are only read in the current layout, so no other version is negotiated yet.
+    pub fn from_protocol(protocol: i32) -> Option<Self> {
+        Self::known(protocol).filter(|version| *version == Self::CURRENT)
+    }
+
+    pub fn protocol(self) -> i32 {
+        self as i32
+    }
+
+    /// Id of a clientbound packet in this version
+    pub fn packet_id(self, packet: Packet) -> i32 {
+        match self {
+            Self::V1_21_1 => packet.id_1_21_1(),
+            Self::V1_21_2 => packet.id_1_21_2(),
+        }
+    }
+
+    /// Clientbound packet with the id in the given state
+    pub fn packet(self, state: ConnectionState, id: i32) -> Option<Packet> {
+        Packet::ALL.iter().copied().find(|packet| packet.state() == state && self.packet_id(*packet) == id)
+    }
+
+    /// Rewrites the id of an unframed packet built for `CURRENT` into this version's id.
+    /// Packets this version has no name for are left alone.
+    pub fn remap(self, state: ConnectionState, packet: &[u8]) -> Option<Vec<u8>> {
+        if self == Self::CURRENT {
+            return None;
+        }
+        let id = ivar::read(packet).ok()?;
+        let named = Self::CURRENT.packet(state, id.value)?;
+        let new_id = self.packet_id(named);
+        if new_id == id.value {
+            return None;
+        }
+        let mut buffer: Vec<u8> = Vec::new();
+        write_ivar(&mut buffer, new_id);
+        buffer.extend_from_slice(&packet[id.length()..]);
+        Some(buffer)
+    }
+}
+
+/// Every clientbound packet the server can build
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Packet {
+    // Status
+    StatusResponse,
+    PongResponse,
+    // Login
+    LoginDisconnect,
+    LoginSuccess,
+    SetCompression,
+    LoginCookieRequest,
+    // Configuration
+    ConfigurationCookieRequest,
+    ConfigurationDisconnect,
+    FinishConfiguration,
+    RegistryData,
+    FeatureFlags,
+    ConfigurationUpdateTags,
+    KnownPacks,
+    ConfigurationServerLinks,
+    ConfigurationRemoveResourcePack,
+    ConfigurationAddResourcePack,
+    // Play
+    SpawnEntity,
+    EntityAnimation,
+    AwardStatistics,
+    AcknowledgeBlockChange,
+    BlockEntityData,
+    BlockUpdate,
+    ChunkBatchFinished,
+    ChunkBatchStart,
+    CloseContainer,
+    SetContainerSlot,
+    CookieRequest,
+    DamageEvent,
+    Disconnect,
+    DisguisedChatMessage,
+    EntityEvent,
+    UnloadChunk,
+    GameEvent,
+    InitializeWorldBorder,
+    KeepAlive,
+    ChunkDataAndUpdateLight,
+    Login,
+    UpdateEntityPosition,
+    UpdateEntityPositionAndRotation,
+    UpdateEntityRotation,
+    MoveVehicle,
+    OpenSignEditor,
+    Ping,
+    PlayerAbilities,
+    CombatDeath,
+    PlayerInfoRemove,
+    PlayerInfoUpdate,
+    LookAt,
+    SynchronizePlayerPosition,
+    RemoveEntities,
+    RemoveResourcePack,
+    AddResourcePack,
+    Respawn,
+    UpdateSectionBlocks,
+    ServerData,
+    SetBorderCenter,
+    SetBorderLerpSize,
+    SetBorderSize,
+    SetBorderWarningDelay,
+    SetBorderWarningDistance,
+    SetCamera,
+    SetCenterChunk,
+    SetRenderDistance,
+    SetDefaultSpawnPosition,
+    SetEntityMetadata,
+    SetEntityVelocity,
+    SetExperience,
+    SetHealth,
+    SetPassengers,
+    SetSimulationDistance,
+    UpdateTime,
+    EntitySoundEffect,
+    SoundEffect,
+    StopSound,
+    StoreCookie,
+    SystemChatMessage,
+    PickupItem,
+    TeleportEntity,
+    SetTickingState,
+    StepTick,
+    Transfer,
+    UpdateAttributes,
+    UpdateRecipes,
+}
+
+impl Packet {
+    pub const ALL: &'static [Packet] = &[
+        Self::StatusResponse,
+        Self::PongResponse,
+        Self::LoginDisconnect,
+        Self::LoginSuccess,
+        Self::SetCompression,
+        Self::LoginCookieRequest,
+        Self::ConfigurationCookieRequest,
+        Self::ConfigurationDisconnect,
+        Self::FinishConfiguration,
+        Self::RegistryData,
+        Self::FeatureFlags,
+        Self::ConfigurationUpdateTags,
+        Self::KnownPacks,
+        Self::ConfigurationServerLinks,
+        Self::ConfigurationRemoveResourcePack,
+        Self::ConfigurationAddResourcePack,
+        Self::SpawnEntity,
+        Self::EntityAnimation,
+        Self::AwardStatistics,
+        Self::AcknowledgeBlockChange,
+        Self::BlockEntityData,
+        Self::BlockUpdate,
+        Self::ChunkBatchFinished,
+        Self::ChunkBatchStart,
+        Self::CloseContainer,
+        Self::SetContainerSlot,
+        Self::CookieRequest,
+        Self::DamageEvent,
+        Self::Disconnect,
+        Self::DisguisedChatMessage,
+        Self::EntityEvent,
+        Self::UnloadChunk,
+        Self::GameEvent,
+        Self::InitializeWorldBorder,
+        Self::KeepAlive,
+        Self::ChunkDataAndUpdateLight,
+        Self::Login,
+        Self::UpdateEntityPosition,
+        Self::UpdateEntityPositionAndRotation,
+        Self::UpdateEntityRotation,
+        Self::MoveVehicle,
+        Self::OpenSignEditor,
+        Self::Ping,
+        Self::PlayerAbilities,
+        Self::CombatDeath,
+        Self::PlayerInfoRemove,
+        Self::PlayerInfoUpdate,
+        Self::LookAt,
+        Self::SynchronizePlayerPosition,
+        Self::RemoveEntities,
+        Self::RemoveResourcePack,
+        Self::AddResourcePack,
+        Self::Respawn,
+        Self::UpdateSectionBlocks,
+        Self::ServerData,
+        Self::SetBorderCenter,
+        Self::SetBorderLerpSize,
+        Self::SetBorderSize,
+        Self::SetBorderWarningDelay,
+        Self::SetBorderWarningDistance,
+        Self::SetCamera,
+        Self::SetCenterChunk,
+        Self::SetRenderDistance,
+        Self::SetDefaultSpawnPosition,
+        Self::SetEntityMetadata,
+        Self::SetEntityVelocity,
+        Self::SetExperience,
+        Self::SetHealth,
+        Self::SetPassengers,
+        Self::SetSimulationDistance,
+        Self::UpdateTime,
+        Self::EntitySoundEffect,
+        Self::SoundEffect,
+        Self::StopSound,
+        Self::StoreCookie,
+        Self::SystemChatMessage,
+        Self::PickupItem,
+        Self::TeleportEntity,
+        Self::SetTickingState,
+        Self::StepTick,
+        Self::Transfer,
+        Self::UpdateAttributes,
+        Self::UpdateRecipes,
+    ];
+
+    /// Id in `Version::CURRENT`, used by the packet builders
+    pub fn id(self) -> i32 {
+        Version::CURRENT.packet_id(self)
+    }
+
+    pub fn state(self) -> ConnectionState {
+        match self {
+            Self::StatusResponse | Self::PongResponse => ConnectionState::Status,
+            Self::LoginDisconnect | Self::LoginSuccess | Self::SetCompression | Self::LoginCookieRequest => ConnectionState::Login,
+            Self::ConfigurationCookieRequest | Self::ConfigurationDisconnect | Self::FinishConfiguration | Self::RegistryData | Self::FeatureFlags | Self::ConfigurationUpdateTags | Self::KnownPacks | Self::ConfigurationServerLinks
+                | Self::ConfigurationRemoveResourcePack | Self::ConfigurationAddResourcePack => ConnectionState::Configuration,
+            _ => ConnectionState::Play,
+        }
+    }
+
+    fn id_1_21_1(self) -> i32 {
+        match self {
+            Self::StatusResponse => 0x00,
+            Self::PongResponse => 0x01,
+            Self::LoginDisconnect => 0x00,
+            Self::LoginSuccess => 0x02,
+            Self::SetCompression => 0x03,
+            Self::LoginCookieRequest => 0x05,
+            Self::ConfigurationCookieRequest => 0x00,
+            Self::ConfigurationDisconnect => 0x02,
+            Self::FinishConfiguration => 0x03,
+            Self::RegistryData => 0x07,
+            Self::FeatureFlags => 0x0C,
+            Self::ConfigurationUpdateTags => 0x0D,
+            Self::KnownPacks => 0x0E,
+            Self::ConfigurationServerLinks => 0x10,
+            Self::ConfigurationRemoveResourcePack => 0x08,
+            Self::ConfigurationAddResourcePack => 0x09,
+            Self::SpawnEntity => 0x01,
+            Self::EntityAnimation => 0x03,
+            Self::AwardStatistics => 0x04,
+            Self::AcknowledgeBlockChange => 0x05,
+            Self::BlockEntityData => 0x07,
+            Self::BlockUpdate => 0x09,
+            Self::ChunkBatchFinished => 0x0C,
+            Self::ChunkBatchStart => 0x0D,
+            Self::CloseContainer => 0x12,
+            Self::SetContainerSlot => 0x15,
+            Self::CookieRequest => 0x16,
+            Self::DamageEvent => 0x1A,
+            Self::Disconnect => 0x1D,
+            Self::DisguisedChatMessage => 0x1E,
+            Self::EntityEvent => 0x1F,
+            Self::UnloadChunk => 0x21,
+            Self::GameEvent => 0x22,
+            Self::InitializeWorldBorder => 0x25,
+            Self::KeepAlive => 0x26,
+            Self::ChunkDataAndUpdateLight => 0x27,
+            Self::Login => 0x2B,
+            Self::UpdateEntityPosition => 0x2E,
+            Self::UpdateEntityPositionAndRotation => 0x2F,
+            Self::UpdateEntityRotation => 0x30,
+            Self::MoveVehicle => 0x31,
+            Self::OpenSignEditor => 0x34,
+            Self::Ping => 0x35,
+            Self::PlayerAbilities => 0x38,
+            Self::CombatDeath => 0x3C,
+            Self::PlayerInfoRemove => 0x3D,
+            Self::PlayerInfoUpdate => 0x3E,
+            Self::LookAt => 0x3F,
+            Self::SynchronizePlayerPosition => 0x40,
+            Self::RemoveEntities => 0x42,
+            Self::RemoveResourcePack => 0x45,
+            Self::AddResourcePack => 0x46,
+            Self::Respawn => 0x47,
+            Self::UpdateSectionBlocks => 0x49,
+            Self::ServerData => 0x4B,
+            Self::SetBorderCenter => 0x4D,
+            Self::SetBorderLerpSize => 0x4E,
+            Self::SetBorderSize => 0x4F,
+            Self::SetBorderWarningDelay => 0x50,
+            Self::SetBorderWarningDistance => 0x51,
+            Self::SetCamera => 0x52,
+            Self::SetCenterChunk => 0x54,
+            Self::SetRenderDistance => 0x55,
+            Self::SetDefaultSpawnPosition => 0x56,
+            Self::SetEntityMetadata => 0x58,
+            Self::SetEntityVelocity => 0x5A,
+            Self::SetExperience => 0x5C,
+            Self::SetHealth => 0x5D,
+            Self::SetPassengers => 0x5F,
+            Self::SetSimulationDistance => 0x62,
+            Self::UpdateTime => 0x64,
+            Self::EntitySoundEffect => 0x67,
+            Self::SoundEffect => 0x68,
+            Self::StopSound => 0x6A,
+            Self::StoreCookie => 0x6B,
+            Self::SystemChatMessage => 0x6C,
+            Self::PickupItem => 0x6F,
+            Self::TeleportEntity => 0x70,
+            Self::SetTickingState => 0x71,
+            Self::StepTick => 0x72,
+            Self::Transfer => 0x73,
+            Self::UpdateAttributes => 0x75,
+            Self::UpdateRecipes => 0x77,
+        }
+    }
+
+    /// Only play ids changed in 1.21.2
+    fn id_1_21_2(self) -> i32 {
+        match self {
+            Self::SpawnEntity => 0x01,
+            Self::EntityAnimation => 0x03,
+            Self::AwardStatistics => 0x04,
+            Self::AcknowledgeBlockChange => 0x05,
+            Self::BlockEntityData => 0x07,
+            Self::BlockUpdate => 0x09,
+            Self::ChunkBatchFinished => 0x0C,
+            Self::ChunkBatchStart => 0x0D,
+            Self::CloseContainer => 0x12,
+            Self::SetContainerSlot => 0x15,
+            Self::CookieRequest => 0x16,
+            Self::DamageEvent => 0x1A,
+            Self::Disconnect => 0x1D,
+            Self::DisguisedChatMessage => 0x1E,
+            Self::EntityEvent => 0x1F,
+            Self::UnloadChunk => 0x22,
+            Self::GameEvent => 0x23,
+            Self::InitializeWorldBorder => 0x26,
+            Self::KeepAlive => 0x27,
+            Self::ChunkDataAndUpdateLight => 0x28,
+            Self::Login => 0x2C,
+            Self::UpdateEntityPosition => 0x2F,
+            Self::UpdateEntityPositionAndRotation => 0x30,
+            Self::UpdateEntityRotation => 0x32,
+            Self::MoveVehicle => 0x33,
+            Self::OpenSignEditor => 0x36,
+            Self::Ping => 0x37,
+            Self::PlayerAbilities => 0x3A,
+            Self::CombatDeath => 0x3E,
+            Self::PlayerInfoRemove => 0x3F,
+            Self::PlayerInfoUpdate => 0x40,
+            Self::LookAt => 0x41,
+            Self::SynchronizePlayerPosition => 0x42,
+            Self::RemoveEntities => 0x47,
+            Self::RemoveResourcePack => 0x4A,
+            Self::AddResourcePack => 0x4B,
+            Self::Respawn => 0x4C,
+            Self::UpdateSectionBlocks => 0x4E,
+            Self::ServerData => 0x50,
+            Self::SetBorderCenter => 0x52,
+            Self::SetBorderLerpSize => 0x53,
+            Self::SetBorderSize => 0x54,
+            Self::SetBorderWarningDelay => 0x55,
+            Self::SetBorderWarningDistance => 0x56,
+            Self::SetCamera => 0x57,
+            Self::SetCenterChunk => 0x58,
+            Self::SetRenderDistance => 0x59,
+            Self::SetDefaultSpawnPosition => 0x5B,
+            Self::SetEntityMetadata => 0x5D,
+            Self::SetEntityVelocity => 0x5F,
+            Self::SetExperience => 0x61,
+            Self::SetHealth => 0x62,
+            Self::SetPassengers => 0x65,
+            Self::SetSimulationDistance => 0x69,
+            Self::UpdateTime => 0x6B,
+            Self::EntitySoundEffect => 0x6E,
+            Self::SoundEffect => 0x6F,
+            Self::StopSound => 0x71,
+            Self::StoreCookie => 0x72,
+            Self::SystemChatMessage => 0x73,
+            Self::PickupItem => 0x76,
+            Self::TeleportEntity => 0x77,
+            Self::SetTickingState => 0x78,
+            Self::StepTick => 0x79,
+            Self::Transfer => 0x7A,
+            Self::UpdateAttributes => 0x7C,
+            Self::UpdateRecipes => 0x7E,
+            _ => self.id_1_21_1(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn named_packet_has_an_id_per_version() {
+        assert_eq!(Version::V1_21_1.packet_id(Packet::KeepAlive), 0x26);
+        assert_eq!(Version::V1_21_2.packet_id(Packet::KeepAlive), 0x27);
+        assert_eq!(Version::CURRENT.packet(ConnectionState::Play, 0x26), Some(Packet::KeepAlive));
+    }
+
+    #[test]
+    fn remaps_only_the_id() {
+        let mut packet: Vec<u8> = Vec::new();
+        write_ivar(&mut packet, Version::CURRENT.packet_id(Packet::KeepAlive));
+        packet.extend_from_slice(&42i64.to_be_bytes());
+        let remapped = Version::V1_21_2.remap(ConnectionState::Play, &packet).unwrap();
+        assert_eq!(remapped[0], 0x27);
+        assert_eq!(remapped[1..], packet[1..]);
+        assert_eq!(Version::V1_21_1.remap(ConnectionState::Play, &packet), None);
+    }
+
+    #[test]
+    fn only_negotiates_translated_versions() {
+        assert_eq!(Version::from_protocol(767), Some(Version::V1_21_1));
+        assert_eq!(Version::from_protocol(768), None);
+        assert_eq!(Version::known(768), Some(Version::V1_21_2));
+        assert_eq!(Version::known(766), None);
+    }
+}
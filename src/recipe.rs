@@ -0,0 +1,204 @@
+// Crafting recipes sent to clients in Update Recipes
+// https://wiki.vg/Protocol#Update_Recipes
+
+use crate::{types::slot::Slot, utils::{write_ivar, write_utf8_string}};
+
+/// Items used by the starter recipes, ids from the 1.21.1 item registry
+pub const OAK_PLANKS: i32 = 36;
+pub const OAK_LOG: i32 = 130;
+pub const CRAFTING_TABLE: i32 = 298;
+pub const STICK: i32 = 848;
+
+/// Recipe book tab the recipe is shown under, only the tabs of the starter recipes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CraftingCategory {
+    Building = 0,
+    Misc = 3,
+}
+
+/// Any one of the stacks is accepted, an empty ingredient matches an empty grid slot
+pub type Ingredient = Vec<Slot>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecipeKind {
+    /// Ingredients are row by row, `width` * `height` of them
+    Shaped {
+        width: i32,
+        height: i32,
+        ingredients: Vec<Ingredient>,
+        show_notification: bool,
+    },
+    /// Ingredients can be anywhere in the grid
+    Shapeless {
+        ingredients: Vec<Ingredient>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recipe {
+    pub id: String,
+    /// Recipes in the same group are shown together in the recipe book
+    pub group: String,
+    pub category: CraftingCategory,
+    pub result: Slot,
+    pub kind: RecipeKind,
+}
+
+impl Recipe {
+    pub fn shaped(id: &str, category: CraftingCategory, width: i32, height: i32, ingredients: Vec<Ingredient>, result: Slot) -> Self {
+        Self {
+            id: id.into(),
+            group: String::new(),
+            category,
+            result,
+            kind: RecipeKind::Shaped { width, height, ingredients, show_notification: true },
+        }
+    }
+
+    pub fn shapeless(id: &str, category: CraftingCategory, ingredients: Vec<Ingredient>, result: Slot) -> Self {
+        Self {
+            id: id.into(),
+            group: String::new(),
+            category,
+            result,
+            kind: RecipeKind::Shapeless { ingredients },
+        }
+    }
+
+    pub fn with_group(mut self, group: &str) -> Self {
+        self.group = group.into();
+        self
+    }
+
+    /// Id in the recipe serializer registry
+    pub fn type_id(&self) -> i32 {
+        match self.kind {
+            RecipeKind::Shaped { .. } => 0,
+            RecipeKind::Shapeless { .. } => 1,
+        }
+    }
+
+    /// Ingredients laid out row by row in a square grid of `size`, None if the recipe doesn't fit
+    pub fn grid_layout(&self, size: usize) -> Option<Vec<Option<&Ingredient>>> {
+        let mut layout = vec![None; size * size];
+        match &self.kind {
+            RecipeKind::Shaped { width, height, ingredients, .. } => {
+                let (width, height) = (*width as usize, *height as usize);
+                if width > size || height > size || ingredients.len() != width * height {
+                    return None;
+                }
+                for (i, ingredient) in ingredients.iter().enumerate() {
+                    if !ingredient.is_empty() {
+                        layout[i / width * size + i % width] = Some(ingredient);
+                    }
+                }
+            },
+            RecipeKind::Shapeless { ingredients } => {
+                if ingredients.len() > size * size {
+                    return None;
+                }
+                for (i, ingredient) in ingredients.iter().enumerate() {
+                    layout[i] = Some(ingredient);
+                }
+            },
+        }
+        Some(layout)
+    }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut buffer: Vec<u8> = Vec::new();
+        write_utf8_string(&mut buffer, self.id.clone());
+        write_ivar(&mut buffer, self.type_id());
+        write_utf8_string(&mut buffer, self.group.clone());
+        write_ivar(&mut buffer, self.category as i32);
+        match &self.kind {
+            RecipeKind::Shaped { width, height, ingredients, show_notification } => {
+                write_ivar(&mut buffer, *width);
+                write_ivar(&mut buffer, *height);
+                for ingredient in ingredients {
+                    write_ingredient(&mut buffer, ingredient);
+                }
+                buffer.append(&mut self.result.as_bytes());
+                buffer.push(*show_notification as u8);
+            },
+            RecipeKind::Shapeless { ingredients } => {
+                write_ivar(&mut buffer, ingredients.len() as i32);
+                for ingredient in ingredients {
+                    write_ingredient(&mut buffer, ingredient);
+                }
+                buffer.append(&mut self.result.as_bytes());
+            },
+        }
+        buffer
+    }
+}
+
+fn write_ingredient(buffer: &mut Vec<u8>, ingredient: &Ingredient) {
+    write_ivar(buffer, ingredient.len() as i32);
+    for slot in ingredient {
+        buffer.append(&mut slot.as_bytes());
+    }
+}
+
+pub fn find(id: &str) -> Option<Recipe> {
+    starter_recipes().into_iter().find(|recipe| recipe.id == id)
+}
+
+/// Enough to get started in an empty world, vanilla uses tags to accept every wood type
+pub fn starter_recipes() -> Vec<Recipe> {
+    let log = || vec![Slot::new(OAK_LOG, 1)];
+    let planks = || vec![Slot::new(OAK_PLANKS, 1)];
+    vec![
+        Recipe::shapeless("minecraft:oak_planks", CraftingCategory::Building, vec![log()], Slot::new(OAK_PLANKS, 4)).with_group("planks"),
+        Recipe::shaped("minecraft:stick", CraftingCategory::Misc, 1, 2, vec![planks(), planks()], Slot::new(STICK, 4)).with_group("sticks"),
+        Recipe::shaped("minecraft:crafting_table", CraftingCategory::Misc, 2, 2, vec![planks(), planks(), planks(), planks()], Slot::new(CRAFTING_TABLE, 1)),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shapeless_recipes_list_their_ingredients() {
+        let recipe = Recipe::shapeless("minecraft:oak_planks", CraftingCategory::Building, vec![vec![Slot::new(OAK_LOG, 1)]], Slot::new(OAK_PLANKS, 4)).with_group("planks");
+        let mut expected = Vec::new();
+        write_utf8_string(&mut expected, "minecraft:oak_planks".into());
+        // Shapeless, group, building
+        expected.push(1);
+        write_utf8_string(&mut expected, "planks".into());
+        expected.push(0);
+        // One ingredient accepting one stack
+        expected.extend_from_slice(&[1, 1]);
+        expected.append(&mut Slot::new(OAK_LOG, 1).as_bytes());
+        expected.append(&mut Slot::new(OAK_PLANKS, 4).as_bytes());
+        assert_eq!(recipe.as_bytes(), expected);
+    }
+
+    #[test]
+    fn shaped_recipes_have_a_size_and_notification() {
+        let planks = || vec![Slot::new(OAK_PLANKS, 1)];
+        let recipe = Recipe::shaped("minecraft:stick", CraftingCategory::Misc, 1, 2, vec![planks(), planks()], Slot::new(STICK, 4));
+        let mut expected = Vec::new();
+        write_utf8_string(&mut expected, "minecraft:stick".into());
+        // Shaped, no group, misc, 1 wide and 2 high
+        expected.extend_from_slice(&[0, 0, 3, 1, 2]);
+        for _ in 0..2 {
+            expected.push(1);
+            expected.append(&mut Slot::new(OAK_PLANKS, 1).as_bytes());
+        }
+        expected.append(&mut Slot::new(STICK, 4).as_bytes());
+        expected.push(1);
+        assert_eq!(recipe.as_bytes(), expected);
+    }
+
+    #[test]
+    fn shaped_recipes_are_laid_out_row_by_row() {
+        let table = find("minecraft:crafting_table").unwrap();
+        let layout = table.grid_layout(3).unwrap();
+        let filled: Vec<usize> = (0..9).filter(|i| layout[*i].is_some()).collect();
+        assert_eq!(filled, vec![0, 1, 3, 4]);
+        assert!(table.grid_layout(1).is_none());
+        assert!(find("minecraft:diamond_pickaxe").is_none());
+    }
+}
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use crate::registry_data::RegistryEntry;
+
 #[derive(Serialize, Deserialize)]
 pub struct ChatType {
     chat: Decoration,
@@ -29,3 +31,36 @@ impl Default for ChatType {
         }
     }
 }
+
+const NAMES: &[&str] = &[
+    "chat",
+    "emote_command",
+    "msg_command_incoming",
+    "msg_command_outgoing",
+    "say_command",
+    "team_msg_command_incoming",
+    "team_msg_command_outgoing",
+];
+
+/// Registry id of a chat type, the index it is sent at in the registry data
+pub fn id(name: &str) -> Option<i32> {
+    let name = name.strip_prefix("minecraft:").unwrap_or(name);
+    NAMES.iter().position(|n| *n == name).map(|i| i as i32)
+}
+
+/// Every chat type uses the plain "<sender> content" format
+pub fn entries() -> Vec<RegistryEntry> {
+    NAMES.iter().map(|name| super::entry(name, &ChatType::default())).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ids_are_registry_indexes() {
+        assert_eq!(id("minecraft:chat"), Some(0));
+        assert_eq!(id("say_command"), Some(4));
+        assert_eq!(id("minecraft:missing"), None);
+    }
+}
@@ -16,7 +16,6 @@ const NAMES: &[&str] = &[
     "arrow",
     "bad_respawn_point",
     "cactus",
-    "cramming",
     "campfire",
     "cramming",
     "dragon_breath",
@@ -62,23 +61,31 @@ const NAMES: &[&str] = &[
     "wither_skull",
 ];
 
+/// Registry id of a damage type, the index it is sent at in the registry data
+pub fn id(name: &str) -> Option<i32> {
+    let name = name.strip_prefix("minecraft:").unwrap_or(name);
+    NAMES.iter().position(|n| *n == name).map(|i| i as i32)
+}
+
 pub fn entries() -> Vec<RegistryEntry> {
-    let items: Vec<_> = NAMES
-        .iter()
-        .map(|name| RegistryEntry {
-            entry_id: name.to_string(),
-            has_data: true,
-            data: Some(fastnbt::to_bytes(
-                &DamageType {
-                    exhaustion: 0.1,
-                    message_id: "inFire".into(),
-                    scaling: "when_caused_by_living_non_player".into(),
-                    death_message_type: None,
-                    effects: None,
-                },
-            ).unwrap()),
-        })
-        .collect();
+    NAMES.iter().map(|name| super::entry(name, &DamageType {
+        exhaustion: 0.1,
+        message_id: "inFire".into(),
+        scaling: "when_caused_by_living_non_player".into(),
+        death_message_type: None,
+        effects: None,
+    })).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    items
+    #[test]
+    fn ids_follow_the_sent_order() {
+        assert_eq!(id("minecraft:arrow"), NAMES.iter().position(|n| *n == "arrow").map(|i| i as i32));
+        assert_eq!(id("outside_border"), id("minecraft:outside_border"));
+        assert_eq!(entries().len(), NAMES.len());
+        assert_eq!(id("minecraft:missing"), None);
+    }
 }
@@ -2,6 +2,8 @@ use std::default;
 
 use serde::{Deserialize, Serialize};
 
+use crate::world::settings::WorldSettings;
+
 #[derive(Serialize, Deserialize)]
 pub struct DimensionType {
     fixed_time: Option<i64>,
@@ -59,3 +61,15 @@ impl Default for DimensionType {
         }
     }
 }
+
+impl DimensionType {
+    /// Overworld dimension with the configured height
+    pub fn with_settings(settings: &WorldSettings) -> Self {
+        Self {
+            min_y: settings.min_y,
+            height: settings.height,
+            logical_height: settings.height,
+            ..Default::default()
+        }
+    }
+}
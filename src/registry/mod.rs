@@ -6,3 +6,61 @@ pub mod damage_type;
 pub mod dimension_type;
 pub mod wolf_variant;
 pub mod painting_variant;
+pub mod tags;
+
+use fastnbt::SerOpts;
+use serde::Serialize;
+
+use crate::{registry_data::RegistryEntry, world::settings::WorldSettings};
+
+/// Every registry the server can send, in the order they are sent by default
+pub const REGISTRIES: &[&str] = &[
+    "minecraft:dimension_type",
+    "minecraft:worldgen/biome",
+    "minecraft:chat_type",
+    "minecraft:damage_type",
+    "minecraft:wolf_variant",
+    "minecraft:painting_variant",
+];
+
+pub fn is_implemented(registry: &str) -> bool {
+    REGISTRIES.contains(&registry)
+}
+
+/// Entries of a registry, None if it isn't implemented
+pub fn entries(registry: &str, settings: &WorldSettings) -> Option<Vec<RegistryEntry>> {
+    let entries = match registry {
+        "minecraft:dimension_type" => vec![entry("minecraft:overworld", &dimension_type::DimensionType::with_settings(settings))],
+        "minecraft:worldgen/biome" => vec![entry("minecraft:plains", &biomes::Biome::default())],
+        "minecraft:chat_type" => chat_type::entries(),
+        "minecraft:damage_type" => damage_type::entries(),
+        "minecraft:wolf_variant" => vec![entry("minecraft:ashen", &wolf_variant::WolfVariant::default())],
+        "minecraft:painting_variant" => vec![entry("minecraft:pigscene", &painting_variant::PaintingVariant::default())],
+        _ => return None,
+    };
+    Some(entries)
+}
+
+/// Entry with its data encoded as network NBT
+pub fn entry(id: &str, data: &impl Serialize) -> RegistryEntry {
+    RegistryEntry {
+        entry_id: id.into(),
+        has_data: true,
+        data: Some(fastnbt::to_bytes_with_opts(data, SerOpts::network_nbt()).unwrap()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_listed_registries_are_implemented() {
+        for registry in REGISTRIES {
+            assert!(is_implemented(registry));
+            assert!(entries(registry, &WorldSettings::default()).is_some_and(|e| !e.is_empty()), "{registry}");
+        }
+        assert!(!is_implemented("minecraft:trim_material"));
+        assert!(entries("minecraft:trim_material", &WorldSettings::default()).is_none());
+    }
+}
@@ -0,0 +1,67 @@
+use crate::utils::{write_ivar, write_utf8_string};
+
+// https://wiki.vg/Protocol#Update_Tags
+
+/// Tags for one registry, each tag maps a name to the numeric ids of its entries
+pub struct TagGroup {
+    pub registry: String,
+    pub tags: Vec<(String, Vec<i32>)>,
+}
+
+impl TagGroup {
+    pub fn new(registry: impl Into<String>) -> Self {
+        Self {
+            registry: registry.into(),
+            tags: Vec::new(),
+        }
+    }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut buffer: Vec<u8> = Vec::new();
+        write_utf8_string(&mut buffer, self.registry.clone());
+        write_ivar(&mut buffer, self.tags.len() as i32);
+        for (name, entries) in &self.tags {
+            write_utf8_string(&mut buffer, name.clone());
+            write_ivar(&mut buffer, entries.len() as i32);
+            for entry in entries {
+                write_ivar(&mut buffer, *entry);
+            }
+        }
+        buffer
+    }
+}
+
+/// Empty groups for the registries the client expects tags for
+pub fn core_groups() -> Vec<TagGroup> {
+    vec![
+        TagGroup::new("minecraft:block"),
+        TagGroup::new("minecraft:item"),
+        TagGroup::new("minecraft:fluid"),
+        TagGroup::new("minecraft:entity_type"),
+        TagGroup::new("minecraft:game_event"),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn group_with_one_tag_of_two_ids() {
+        let mut group = TagGroup::new("minecraft:item");
+        group.tags.push(("minecraft:logs".into(), vec![1, 300]));
+        let mut expected: Vec<u8> = Vec::new();
+        write_utf8_string(&mut expected, "minecraft:item".into());
+        expected.push(1);
+        write_utf8_string(&mut expected, "minecraft:logs".into());
+        expected.extend_from_slice(&[2, 1, 0xAC, 0x02]);
+        assert_eq!(group.as_bytes(), expected);
+    }
+
+    #[test]
+    fn core_groups_are_empty() {
+        let groups = core_groups();
+        assert!(groups.iter().any(|group| group.registry == "minecraft:block"));
+        assert!(groups.iter().all(|group| group.tags.is_empty()));
+    }
+}
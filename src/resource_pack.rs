@@ -0,0 +1,190 @@
+// Resource packs pushed to a client, each one is identified by its own uuid so several can be
+// applied at once and removed separately.
+// https://wiki.vg/Protocol#Add_Resource_Pack_(play)
+
+use crate::{types::text_component::TextComponent, utils::{sha256, write_utf8_string}};
+
+/// Longest url the client accepts
+pub const MAX_URL_LENGTH: usize = 32767;
+/// Length of a hex encoded SHA-1 hash
+pub const HASH_LENGTH: usize = 40;
+
+#[derive(Debug, Clone)]
+pub struct ResourcePack {
+    pub uuid: u128,
+    pub url: String,
+    /// Hex encoded SHA-1 of the pack, empty if the client shouldn't check it
+    pub hash: String,
+    /// The client is disconnected if it declines a forced pack
+    pub forced: bool,
+    /// Shown on the prompt in place of the default message
+    pub prompt: Option<TextComponent>,
+}
+
+impl ResourcePack {
+    pub fn new(uuid: u128, url: &str, hash: &str) -> Result<Self, &'static str> {
+        if url.is_empty() || url.len() > MAX_URL_LENGTH {
+            return Err("Resource pack url is empty or too long");
+        }
+        if !hash.is_empty() && (hash.len() != HASH_LENGTH || !hash.chars().all(|c| c.is_ascii_hexdigit())) {
+            return Err("Resource pack hash must be a hex encoded SHA-1");
+        }
+        Ok(Self {
+            uuid,
+            url: url.to_string(),
+            hash: hash.to_lowercase(),
+            forced: false,
+            prompt: None,
+        })
+    }
+
+    pub fn forced(mut self, forced: bool) -> Self {
+        self.forced = forced;
+        self
+    }
+
+    pub fn with_prompt(mut self, prompt: TextComponent) -> Self {
+        self.prompt = Some(prompt);
+        self
+    }
+
+    /// Body of Add Resource Pack, shared by the Configuration and Play packets
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut buffer: Vec<u8> = Vec::new();
+        buffer.extend_from_slice(&self.uuid.to_be_bytes());
+        write_utf8_string(&mut buffer, self.url.clone());
+        write_utf8_string(&mut buffer, self.hash.clone());
+        buffer.push(self.forced as u8);
+        match &self.prompt {
+            Some(prompt) => {
+                buffer.push(1);
+                buffer.append(&mut prompt.to_nbt_bytes());
+            },
+            None => buffer.push(0),
+        }
+        buffer
+    }
+}
+
+/// The same url always gets the same uuid, so sending a pack again replaces it rather than stacking a copy
+pub fn url_uuid(url: &str) -> u128 {
+    let hash = sha256(url.as_bytes());
+    u128::from_be_bytes(hash[..16].try_into().unwrap())
+}
+
+/// Result sent back in Resource Pack Response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourcePackStatus {
+    Loaded,
+    Declined,
+    FailedDownload,
+    Accepted,
+    Downloaded,
+    InvalidUrl,
+    FailedReload,
+    Discarded,
+}
+
+impl ResourcePackStatus {
+    pub fn from_id(id: i32) -> Option<Self> {
+        Some(match id {
+            0 => Self::Loaded,
+            1 => Self::Declined,
+            2 => Self::FailedDownload,
+            3 => Self::Accepted,
+            4 => Self::Downloaded,
+            5 => Self::InvalidUrl,
+            6 => Self::FailedReload,
+            7 => Self::Discarded,
+            _ => return None,
+        })
+    }
+
+    /// Whether the client won't be applying the pack
+    pub fn is_failure(self) -> bool {
+        matches!(self, Self::Declined | Self::FailedDownload | Self::InvalidUrl | Self::FailedReload | Self::Discarded)
+    }
+}
+
+/// Packs a player has been sent, in the order they were added
+#[derive(Debug, Default)]
+pub struct ResourcePackStack {
+    packs: Vec<ResourcePack>,
+}
+
+impl ResourcePackStack {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adding a pack with a uuid that is already on the stack replaces it, like the client does
+    pub fn add(&mut self, pack: ResourcePack) {
+        self.packs.retain(|p| p.uuid != pack.uuid);
+        self.packs.push(pack);
+    }
+
+    pub fn remove(&mut self, uuid: u128) -> Option<ResourcePack> {
+        let index = self.packs.iter().position(|p| p.uuid == uuid)?;
+        Some(self.packs.remove(index))
+    }
+
+    pub fn clear(&mut self) {
+        self.packs.clear();
+    }
+
+    pub fn get(&self, uuid: u128) -> Option<&ResourcePack> {
+        self.packs.iter().find(|p| p.uuid == uuid)
+    }
+
+    pub fn packs(&self) -> &[ResourcePack] {
+        &self.packs
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.packs.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HASH: &str = "2fd4e1c67a2d28fced849ee1bb76e7391b93eb12";
+
+    #[test]
+    fn removing_one_pack_leaves_the_other() {
+        let mut stack = ResourcePackStack::new();
+        stack.add(ResourcePack::new(1, "https://example.com/one.zip", HASH).unwrap());
+        stack.add(ResourcePack::new(2, "https://example.com/two.zip", "").unwrap());
+        assert_eq!(stack.remove(1).map(|p| p.url), Some("https://example.com/one.zip".to_string()));
+        assert!(stack.remove(1).is_none());
+        assert!(stack.get(1).is_none());
+        assert_eq!(stack.packs().iter().map(|p| p.uuid).collect::<Vec<u128>>(), vec![2]);
+        stack.clear();
+        assert!(stack.is_empty());
+    }
+
+    #[test]
+    fn adding_a_uuid_again_replaces_the_pack() {
+        let mut stack = ResourcePackStack::new();
+        stack.add(ResourcePack::new(1, "https://example.com/old.zip", "").unwrap());
+        stack.add(ResourcePack::new(1, "https://example.com/new.zip", "").unwrap());
+        assert_eq!(stack.packs().len(), 1);
+        assert_eq!(stack.get(1).unwrap().url, "https://example.com/new.zip");
+    }
+
+    #[test]
+    fn urls_and_hashes_are_checked() {
+        assert!(ResourcePack::new(1, "", "").is_err());
+        assert!(ResourcePack::new(1, &"a".repeat(MAX_URL_LENGTH + 1), "").is_err());
+        assert!(ResourcePack::new(1, "https://example.com/pack.zip", "abc").is_err());
+        assert!(ResourcePack::new(1, "https://example.com/pack.zip", &"g".repeat(HASH_LENGTH)).is_err());
+        assert_eq!(ResourcePack::new(1, "https://example.com/pack.zip", &HASH.to_uppercase()).unwrap().hash, HASH);
+    }
+
+    #[test]
+    fn the_same_url_gets_the_same_uuid() {
+        assert_eq!(url_uuid("https://example.com/pack.zip"), url_uuid("https://example.com/pack.zip"));
+        assert_ne!(url_uuid("https://example.com/pack.zip"), url_uuid("https://example.com/other.zip"));
+    }
+}
@@ -0,0 +1,292 @@
+// RSA signature verification for secure chat, only the public key operation is needed.
+// Players' keys arrive as DER encoded X.509 SubjectPublicKeyInfo and sign with PKCS#1 v1.5 and SHA-256.
+// https://www.rfc-editor.org/rfc/rfc8017#section-8.2.2
+
+use std::cmp::Ordering;
+
+use crate::utils::sha256;
+
+/// DigestInfo that precedes a SHA-256 hash in a PKCS#1 v1.5 signature
+const SHA256_DIGEST_INFO: [u8; 19] = [
+    0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01, 0x05, 0x00, 0x04, 0x20,
+];
+/// Clients always generate 2048 bit keys with this exponent, anything else is refused
+/// so a client can't make the server verify against a huge key
+const PUBLIC_EXPONENT: u32 = 65537;
+const MODULUS_BITS: usize = 2048;
+/// 1.2.840.113549.1.1.1
+const RSA_ENCRYPTION_OID: [u8; 9] = [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01];
+
+const DER_INTEGER: u8 = 0x02;
+const DER_BIT_STRING: u8 = 0x03;
+const DER_OBJECT_IDENTIFIER: u8 = 0x06;
+const DER_SEQUENCE: u8 = 0x30;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RsaPublicKey {
+    modulus: BigUint,
+    exponent: BigUint,
+    /// Length of the modulus in bytes, which every signature must be
+    size: usize,
+}
+
+impl RsaPublicKey {
+    /// Parses a DER encoded SubjectPublicKeyInfo, None if it isn't a 2048 bit RSA key with the usual exponent
+    pub fn from_der(der: &[u8]) -> Option<Self> {
+        let (info, _) = read_der(der, DER_SEQUENCE)?;
+        let (algorithm, rest) = read_der(info, DER_SEQUENCE)?;
+        let (oid, _) = read_der(algorithm, DER_OBJECT_IDENTIFIER)?;
+        if oid != RSA_ENCRYPTION_OID {
+            return None;
+        }
+        let (bits, _) = read_der(rest, DER_BIT_STRING)?;
+        // The first byte of a bit string is the number of unused bits
+        let (&0, key) = bits.split_first()? else {
+            return None;
+        };
+        let (key, _) = read_der(key, DER_SEQUENCE)?;
+        let (modulus, rest) = read_der(key, DER_INTEGER)?;
+        let (exponent, _) = read_der(rest, DER_INTEGER)?;
+        let modulus = BigUint::from_be_bytes(modulus);
+        let exponent = BigUint::from_be_bytes(exponent);
+        if modulus.bits() != MODULUS_BITS || exponent.limbs != [PUBLIC_EXPONENT] {
+            return None;
+        }
+        Some(Self {
+            size: MODULUS_BITS / 8,
+            modulus,
+            exponent,
+        })
+    }
+
+    /// Checks a PKCS#1 v1.5 signature over the SHA-256 hash of the data
+    pub fn verify_sha256(&self, data: &[u8], signature: &[u8]) -> bool {
+        if signature.len() != self.size {
+            return false;
+        }
+        let signature = BigUint::from_be_bytes(signature);
+        if signature.cmp(&self.modulus) != Ordering::Less {
+            return false;
+        }
+        let encoded = signature.mod_pow(&self.exponent, &self.modulus).to_be_bytes(self.size);
+
+        let mut expected = vec![0xFF; self.size];
+        let prefix_length = SHA256_DIGEST_INFO.len() + 32;
+        if self.size < prefix_length + 11 {
+            return false;
+        }
+        expected[0] = 0x00;
+        expected[1] = 0x01;
+        let digest_start = self.size - prefix_length;
+        expected[digest_start - 1] = 0x00;
+        expected[digest_start..digest_start + SHA256_DIGEST_INFO.len()].copy_from_slice(&SHA256_DIGEST_INFO);
+        expected[self.size - 32..].copy_from_slice(&sha256(data));
+        encoded == expected
+    }
+}
+
+/// Contents of the DER element with the tag and what follows it
+fn read_der(der: &[u8], tag: u8) -> Option<(&[u8], &[u8])> {
+    let (&actual, rest) = der.split_first()?;
+    if actual != tag {
+        return None;
+    }
+    let (&first, rest) = rest.split_first()?;
+    let (length, rest) = match first {
+        0..=0x7F => (first as usize, rest),
+        // Long form, the low bits are how many length bytes follow
+        0x81..=0x84 => {
+            let count = (first & 0x7F) as usize;
+            if rest.len() < count {
+                return None;
+            }
+            let length = rest[..count].iter().fold(0usize, |length, b| length << 8 | *b as usize);
+            (length, &rest[count..])
+        },
+        _ => return None,
+    };
+    if rest.len() < length {
+        return None;
+    }
+    Some(rest.split_at(length))
+}
+
+/// Unsigned integer with little endian 32 bit limbs, just enough for modular exponentiation
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct BigUint {
+    limbs: Vec<u32>,
+}
+
+impl BigUint {
+    fn from_be_bytes(bytes: &[u8]) -> Self {
+        let limbs = bytes.rchunks(4).map(|chunk| {
+            chunk.iter().fold(0u32, |limb, b| limb << 8 | *b as u32)
+        }).collect();
+        let mut number = Self { limbs };
+        number.normalize();
+        number
+    }
+
+    /// Big endian bytes left padded with zeroes to `length`
+    fn to_be_bytes(&self, length: usize) -> Vec<u8> {
+        let mut bytes: Vec<u8> = self.limbs.iter().rev().flat_map(|limb| limb.to_be_bytes()).collect();
+        let leading_zeroes = bytes.iter().take_while(|b| **b == 0).count();
+        bytes.drain(..leading_zeroes);
+        if bytes.len() < length {
+            let mut padded = vec![0; length - bytes.len()];
+            padded.append(&mut bytes);
+            return padded;
+        }
+        bytes
+    }
+
+    fn normalize(&mut self) {
+        while self.limbs.last() == Some(&0) {
+            self.limbs.pop();
+        }
+    }
+
+    fn bits(&self) -> usize {
+        match self.limbs.last() {
+            Some(top) => self.limbs.len() * 32 - top.leading_zeros() as usize,
+            None => 0,
+        }
+    }
+
+    fn bit(&self, index: usize) -> bool {
+        self.limbs.get(index / 32).is_some_and(|limb| limb >> (index % 32) & 1 == 1)
+    }
+
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.limbs.len().cmp(&other.limbs.len())
+            .then_with(|| self.limbs.iter().rev().cmp(other.limbs.iter().rev()))
+    }
+
+    /// Requires self >= other
+    fn sub_assign(&mut self, other: &Self) {
+        let mut borrow = 0i64;
+        for i in 0..self.limbs.len() {
+            let difference = self.limbs[i] as i64 - *other.limbs.get(i).unwrap_or(&0) as i64 - borrow;
+            borrow = (difference < 0) as i64;
+            self.limbs[i] = difference.rem_euclid(1 << 32) as u32;
+        }
+        self.normalize();
+    }
+
+    /// self * 2 + bit
+    fn shift_in(&mut self, bit: bool) {
+        let mut carry = bit as u32;
+        for limb in self.limbs.iter_mut() {
+            let next = *limb >> 31;
+            *limb = *limb << 1 | carry;
+            carry = next;
+        }
+        if carry != 0 {
+            self.limbs.push(carry);
+        }
+    }
+
+    fn mul(&self, other: &Self) -> Self {
+        let mut limbs = vec![0u32; self.limbs.len() + other.limbs.len()];
+        for (i, a) in self.limbs.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, b) in other.limbs.iter().enumerate() {
+                let product = *a as u64 * *b as u64 + limbs[i + j] as u64 + carry;
+                limbs[i + j] = product as u32;
+                carry = product >> 32;
+            }
+            limbs[i + other.limbs.len()] = carry as u32;
+        }
+        let mut number = Self { limbs };
+        number.normalize();
+        number
+    }
+
+    /// Binary long division, slow but only a handful are done per signature
+    fn rem(&self, modulus: &Self) -> Self {
+        let mut remainder = Self { limbs: Vec::new() };
+        for i in (0..self.bits()).rev() {
+            remainder.shift_in(self.bit(i));
+            if remainder.cmp(modulus) != Ordering::Less {
+                remainder.sub_assign(modulus);
+            }
+        }
+        remainder
+    }
+
+    fn mod_pow(&self, exponent: &Self, modulus: &Self) -> Self {
+        let base = self.rem(modulus);
+        let mut result = Self { limbs: vec![1] }.rem(modulus);
+        for i in (0..exponent.bits()).rev() {
+            result = result.mul(&result).rem(modulus);
+            if exponent.bit(i) {
+                result = result.mul(&base).rem(modulus);
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "30820122300d06092a864886f70d01010105000382010f003082010a0282010100aefd6e4b0ab423b6fee76048ac6e9eb5098aec7763ce0bcebd4940f38c3be478144aa421db7c91b448c05dcbbb642cba0a3fcc45d9dfe3861ae20221e34794f1d318afce31846f8719ed0b6e1d328aeecce9b1db08fcfee7be8df52bd68bf69e01b16bb314d7f6856d3e22f51a1a7f60a2c39d322aa4ad5cfdf29b39b61eae339548181e1f25ae2b7e265413bdf7709b1d69817e83c041db82e63d6b7da6d0da7685493d449e9f26557d758a96795c6c6963e4e4d418c952a24403eafeb81ed43fe8dad7d4421a46d5d14e424571b3f15eb0d509450b9fda915954ff0e6469ca079af202973455dd3347f23a1ef77f34d8a1acfd68adf9f51a0e59225333645b0203010001";
+    /// PKCS#1 v1.5 SHA-256 signature of "blahaj" made with the private half of `KEY`
+    const SIGNATURE: &str = "7cda6345908f67c4f931f5a87796ed2ae71fad7fe969a662ac2ab40da3e52c19c1ab12a04dedbbe47176aa5feb70d81a57a18aeac79d1cfa653cb291539d8202dfcb58796c44a5045501234aff1bc85cc7b28660bb8ecc2a049fdef5c6f4b7154941826c33703e8f298c94f8af955c7114f54b158f6ad4d5e30e7915a2ebdd5088a488d932dd2e45ff286820a3b683b2bbc6c273d67a75dcbc693d76df6350fe49c488c92225056d0c7a48130255d455d8d245dfdacd429629fd71a1653c029e1444dd57f1169ec0c2da44bf1498536683bb324017baaff4b90b12a390f3959544d6696a0525cc6a91974cbee256f07fa7066b58f7f614244a14aadfe08fc0cc";
+
+    fn hex(text: &str) -> Vec<u8> {
+        (0..text.len()).step_by(2).map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap()).collect()
+    }
+
+    fn der(tag: u8, contents: &[u8]) -> Vec<u8> {
+        let mut element = vec![tag];
+        match contents.len() {
+            length @ 0..=0x7F => element.push(length as u8),
+            length => element.extend_from_slice(&[0x82, (length >> 8) as u8, length as u8]),
+        }
+        element.extend_from_slice(contents);
+        element
+    }
+
+    /// SubjectPublicKeyInfo for any modulus and exponent
+    fn public_key(modulus: &[u8], exponent: &[u8]) -> Vec<u8> {
+        let mut integers = der(DER_INTEGER, &[&[0], modulus].concat());
+        integers.extend(der(DER_INTEGER, exponent));
+        let mut bits = vec![0];
+        bits.extend(der(DER_SEQUENCE, &integers));
+        let mut algorithm = der(DER_OBJECT_IDENTIFIER, &RSA_ENCRYPTION_OID);
+        algorithm.extend_from_slice(&[0x05, 0x00]);
+        let mut info = der(DER_SEQUENCE, &algorithm);
+        info.extend(der(DER_BIT_STRING, &bits));
+        der(DER_SEQUENCE, &info)
+    }
+
+    fn modulus() -> Vec<u8> {
+        hex(KEY)[33..289].to_vec()
+    }
+
+    #[test]
+    fn accepts_a_valid_signature() {
+        let key = RsaPublicKey::from_der(&hex(KEY)).unwrap();
+        assert!(key.verify_sha256(b"blahaj", &hex(SIGNATURE)));
+        assert_eq!(RsaPublicKey::from_der(&public_key(&modulus(), &[0x01, 0x00, 0x01])), Some(key));
+    }
+
+    #[test]
+    fn rejects_tampered_messages_and_signatures() {
+        let key = RsaPublicKey::from_der(&hex(KEY)).unwrap();
+        assert!(!key.verify_sha256(b"blahaJ", &hex(SIGNATURE)));
+        let mut signature = hex(SIGNATURE);
+        signature[100] ^= 1;
+        assert!(!key.verify_sha256(b"blahaj", &signature));
+        assert!(!key.verify_sha256(b"blahaj", &hex(SIGNATURE)[1..]));
+    }
+
+    #[test]
+    fn only_accepts_2048_bit_keys_with_the_usual_exponent() {
+        assert_eq!(RsaPublicKey::from_der(&public_key(&modulus(), &[0x03])), None);
+        assert_eq!(RsaPublicKey::from_der(&public_key(&modulus()[..128], &[0x01, 0x00, 0x01])), None);
+        assert_eq!(RsaPublicKey::from_der(&public_key(&[modulus(), vec![0]].concat(), &[0x01, 0x00, 0x01])), None);
+    }
+}
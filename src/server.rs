@@ -0,0 +1,789 @@
+use std::{collections::HashMap, io::Write, net::{Shutdown, TcpStream}, sync::{atomic::{AtomicBool, AtomicI32, AtomicI64, Ordering}, Arc, Mutex, MutexGuard}, time::{Duration, Instant}};
+
+use log::{error, info, warn};
+
+use crate::{compression::PacketCompression, config::ServerConfig, connection::ConnectionState, events::{Event, Listener}, packets, player::Player, profile::ProfileCache, registry::damage_type, status_response::ServerIcon, types::{position::Position, slot::Slot, text_component::TextComponent}, utils::write_packet, world::{block_entity::BlockEntity, border::WorldBorder, chunk, light::LightData, item_entity::ItemEntity, vehicle::Vehicle, weather::Weather, edit::{self, BlockChanges, EditError}, save::{self, SaveError}}};
+
+/// Socket of a connection thread, kept so the server can close it from elsewhere
+struct OpenConnection {
+    stream: TcpStream,
+    opened: Instant,
+    /// Last state the connection thread reported
+    state: ConnectionState,
+    /// Set once the connection has sent Set Compression
+    compression: Option<PacketCompression>,
+}
+
+/// State shared between every connection thread.
+/// Lock order is always `connections`/`players` before an individual `Player`,
+/// and an individual `Player` before `item_entities` or `border`.
+pub struct Server {
+    pub config: ServerConfig,
+    icon: Option<ServerIcon>,
+    connections: Mutex<HashMap<String, OpenConnection>>,
+    players: Mutex<HashMap<u128, Arc<Mutex<Player>>>>,
+    next_entity_id: AtomicI32,
+    block_entities: Mutex<HashMap<Position, BlockEntity>>,
+    blocks: Mutex<BlockChanges>,
+    world_age: AtomicI64,
+    time_of_day: AtomicI64,
+    /// Current weather and how many ticks are left until it clears, None if it lasts forever
+    weather: Mutex<(Weather, Option<u64>)>,
+    listeners: Mutex<Vec<Listener>>,
+    item_entities: Mutex<HashMap<i32, ItemEntity>>,
+    vehicles: Mutex<HashMap<i32, Vehicle>>,
+    border: Mutex<WorldBorder>,
+    profiles: Mutex<ProfileCache>,
+    frozen: AtomicBool,
+    stopping: AtomicBool,
+    respawn_screen: AtomicBool,
+    /// Ticks left to run while frozen
+    steps: AtomicI32,
+}
+
+impl Server {
+    pub fn new(config: ServerConfig) -> Self {
+        let respawn_screen = config.enable_respawn_screen;
+        let profile_ttl = Duration::from_secs(config.profile_cache_ttl);
+        // A saved world already has the platform, including any of it that was broken
+        let saved = match &config.world_directory {
+            Some(path) => match save::load(path, &config.world) {
+                Ok(saved) => {
+                    info!("Loaded {} changed blocks from {path}", saved.len());
+                    Some(saved)
+                },
+                Err(SaveError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+                    info!("No world found at {path}, starting a new one");
+                    None
+                },
+                Err(e) => {
+                    error!("Could not load the world from {path}!\n{e}");
+                    None
+                },
+            },
+            None => None,
+        };
+        let mut blocks = BlockChanges::new();
+        let loaded = saved.is_some();
+        for (position, state) in saved.unwrap_or_else(|| config.world.spawn_platform()) {
+            blocks.set(position, state);
+        }
+        // A new world's platform is left modified so the first save writes it
+        if loaded {
+            blocks.mark_saved();
+        }
+        let icon = match ServerIcon::load(&config.server_icon) {
+            Ok(icon) => Some(icon),
+            Err(e) => {
+                warn!("Server list icon {} won't be shown!\n{e}", config.server_icon);
+                None
+            },
+        };
+        Self {
+            config,
+            icon,
+            connections: Mutex::new(HashMap::new()),
+            players: Mutex::new(HashMap::new()),
+            next_entity_id: AtomicI32::new(1),
+            block_entities: Mutex::new(HashMap::new()),
+            blocks: Mutex::new(blocks),
+            world_age: AtomicI64::new(0),
+            time_of_day: AtomicI64::new(0),
+            weather: Mutex::new((Weather::Clear, None)),
+            listeners: Mutex::new(Vec::new()),
+            item_entities: Mutex::new(HashMap::new()),
+            vehicles: Mutex::new(HashMap::new()),
+            border: Mutex::new(WorldBorder::default()),
+            profiles: Mutex::new(ProfileCache::new(profile_ttl)),
+            frozen: AtomicBool::new(false),
+            stopping: AtomicBool::new(false),
+            respawn_screen: AtomicBool::new(respawn_screen),
+            steps: AtomicI32::new(0),
+        }
+    }
+
+    /// Unique id for a new entity, shared between players and every other entity
+    pub fn next_entity_id(&self) -> i32 {
+        self.next_entity_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    pub fn block_entities(&self) -> MutexGuard<'_, HashMap<Position, BlockEntity>> {
+        self.block_entities.lock().unwrap()
+    }
+
+    /// Drops an item into the world, spawning it for the players in tracking range, returns its entity id
+    pub fn spawn_item(&self, item: Slot, position: (f64, f64, f64)) -> i32 {
+        let entity = ItemEntity::new(self.next_entity_id(), item, position);
+        let entity_id = entity.entity_id;
+        self.item_entities.lock().unwrap().insert(entity_id, entity);
+        for player in self.online_players() {
+            let mut player = player.lock().unwrap();
+            if let Err(e) = player.update_entities(&self.item_entities()) {
+                error!("Could not spawn item for {}!\n{e:?}", player.name());
+            }
+        }
+        entity_id
+    }
+
+    pub fn item_entities(&self) -> MutexGuard<'_, HashMap<i32, ItemEntity>> {
+        self.item_entities.lock().unwrap()
+    }
+
+    /// Gives items to any player close enough to them, removing the items that were fully picked up
+    pub fn pickup_items(&self) {
+        let players = self.online_players();
+        let mut packets: Vec<(i32, Vec<u8>)> = Vec::new();
+        let mut removed: Vec<i32> = Vec::new();
+        for entity in self.item_entities().values_mut() {
+            entity.pickup_delay = entity.pickup_delay.saturating_sub(1);
+        }
+        for player in &players {
+            let mut player = player.lock().unwrap();
+            let mut items = self.item_entities();
+            let mut picked_up: Vec<i32> = Vec::new();
+            for entity in items.values_mut() {
+                if entity.pickup_delay > 0 || !entity.in_pickup_range(player.position()) {
+                    continue;
+                }
+                let before = entity.item.count;
+                if let Err(e) = player.give(&mut entity.item) {
+                    error!("Could not give item to {}!\n{e:?}", player.name());
+                    continue;
+                }
+                let collected = before - entity.item.count;
+                if collected == 0 {
+                    continue;
+                }
+                packets.push((entity.entity_id, packets::play::pickup_item(entity.entity_id, player.entity_id(), collected)));
+                match entity.item.is_empty() {
+                    true => picked_up.push(entity.entity_id),
+                    false => packets.push((entity.entity_id, entity.metadata_packet())),
+                }
+            }
+            for entity_id in &picked_up {
+                items.remove(entity_id);
+            }
+            removed.append(&mut picked_up);
+        }
+        if packets.is_empty() {
+            return;
+        }
+        // Only players tracking an item are told about it
+        for player in &players {
+            let mut player = player.lock().unwrap();
+            let mut sent = Ok(());
+            for (entity_id, packet) in &packets {
+                if player.is_tracking(*entity_id) {
+                    sent = sent.and(player.send_packet(packet));
+                }
+            }
+            let forgotten = player.forget_entities(&removed);
+            if !forgotten.is_empty() {
+                sent = sent.and(player.send_packet(&packets::play::remove_entities(&forgotten)));
+            }
+            if let Err(e) = sent {
+                error!("Could not send item pickups to {}!\n{e:?}", player.name());
+            }
+        }
+    }
+
+    pub fn world_age(&self) -> i64 {
+        self.world_age.load(Ordering::Relaxed)
+    }
+
+    pub fn time_of_day(&self) -> i64 {
+        self.time_of_day.load(Ordering::Relaxed)
+    }
+
+    pub fn set_time_of_day(&self, time: i64) {
+        self.time_of_day.store(time, Ordering::Relaxed);
+    }
+
+    /// Advances the world age and time of day by one tick
+    pub fn advance_time(&self) {
+        self.world_age.fetch_add(1, Ordering::Relaxed);
+        self.time_of_day.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn broadcast_time(&self) {
+        self.broadcast(&packets::play::update_time(self.world_age(), self.time_of_day()));
+    }
+
+    pub fn vehicles(&self) -> MutexGuard<'_, HashMap<i32, Vehicle>> {
+        self.vehicles.lock().unwrap()
+    }
+
+    pub fn border(&self) -> MutexGuard<'_, WorldBorder> {
+        self.border.lock().unwrap()
+    }
+
+    pub fn set_border_center(&self, x: f64, z: f64) {
+        {
+            let mut border = self.border();
+            border.center_x = x;
+            border.center_z = z;
+        }
+        self.broadcast(&packets::play::set_border_center(x, z));
+    }
+
+    /// Resizes the border, moving it over `speed` milliseconds or straight away when that is 0
+    pub fn set_border_size(&self, diameter: f64, speed: i64) {
+        let old_diameter = {
+            let mut border = self.border();
+            let old_diameter = border.new_diameter;
+            border.old_diameter = if speed > 0 { old_diameter } else { diameter };
+            border.new_diameter = diameter;
+            border.speed = speed;
+            old_diameter
+        };
+        match speed {
+            0 => self.broadcast(&packets::play::set_border_size(diameter)),
+            _ => self.broadcast(&packets::play::set_border_lerp_size(old_diameter, diameter, speed)),
+        }
+    }
+
+    pub fn set_border_warning(&self, warning_time: i32, warning_blocks: i32) {
+        {
+            let mut border = self.border();
+            border.warning_time = warning_time;
+            border.warning_blocks = warning_blocks;
+        }
+        self.broadcast(&packets::play::set_border_warning_delay(warning_time));
+        self.broadcast(&packets::play::set_border_warning_distance(warning_blocks));
+    }
+
+    /// Hurts every player past the border's damage buffer
+    pub fn apply_border_damage(&self) {
+        for player in self.online_players() {
+            let mut player = player.lock().unwrap();
+            let (x, _, z) = player.position();
+            let damage = self.border().damage_at(x, z);
+            if damage <= 0.0 || player.health() <= 0.0 {
+                continue;
+            }
+            // Shows the hurt effect, the border isn't an entity so there is no cause or position
+            let source = damage_type::id("outside_border").unwrap_or(0);
+            let event = packets::play::damage_event(player.entity_id(), source, None, None, None);
+            let _ = player.send_packet(&event);
+            let health = player.health() - damage;
+            let result = match health > 0.0 {
+                true => player.set_health(health),
+                false => {
+                    let name = TextComponent::new(player.name());
+                    player.kill(&TextComponent::translate("death.attack.outsideBorder", vec![name]))
+                },
+            };
+            if let Err(e) = result {
+                error!("Could not apply border damage to {}!\n{e:?}", player.name());
+            }
+        }
+    }
+
+    /// Sends every player their next batch of chunks
+    pub fn send_chunks(&self) {
+        let section_count = self.config.world.section_count();
+        let data = chunk::empty_chunk_data(section_count);
+        let light = LightData::full_bright(section_count);
+        if cfg!(debug_assertions) {
+            // Every chunk shares the same data, so checking one packet covers them all
+            let packet = packets::play::chunk_data(0, 0, &data, &light);
+            if let Err(e) = chunk::validate_chunk_packet(&packet, section_count) {
+                error!("Built invalid chunk data: {e}");
+                return;
+            }
+        }
+        for player in self.online_players() {
+            let mut player = player.lock().unwrap();
+            if let Err(e) = player.send_chunk_batch(&data, &light, &self.blocks()) {
+                error!("Could not send chunks to {}!\n{e:?}", player.name());
+            }
+        }
+    }
+
+    /// Blocks changed from the generated world, lock after any Player
+    pub fn blocks(&self) -> MutexGuard<'_, BlockChanges> {
+        self.blocks.lock().unwrap()
+    }
+
+    /// Saves the chunks changed since the last save to `world_directory`, returns how many were saved
+    pub fn save_world(&self) -> Result<usize, SaveError> {
+        let Some(path) = &self.config.world_directory else {
+            return Ok(0);
+        };
+        let mut blocks = self.blocks();
+        let saved = save::save(path, &blocks, &self.config.world, self.config.compression_level)?;
+        blocks.mark_saved();
+        info!("Saved {saved} changed chunks to {path}");
+        Ok(saved)
+    }
+
+    /// Changes a block for everyone who has its chunk loaded, false if it already had the state
+    pub fn set_block(&self, position: Position, state: i32) -> Result<bool, EditError> {
+        self.check_in_world(position)?;
+        let changed = self.blocks().set(position, state);
+        if changed {
+            self.send_block_changes(&[(position, state)]);
+        }
+        Ok(changed)
+    }
+
+    /// Sets every block between the corners, returns how many changed
+    pub fn fill(&self, from: Position, to: Position, state: i32) -> Result<usize, EditError> {
+        self.check_in_world(from)?;
+        self.check_in_world(to)?;
+        let region = edit::region(from, to)?;
+        let changes: Vec<(Position, i32)> = {
+            let mut blocks = self.blocks();
+            region.into_iter().filter(|p| blocks.set(*p, state)).map(|p| (p, state)).collect()
+        };
+        self.send_block_changes(&changes);
+        Ok(changes.len())
+    }
+
+    fn check_in_world(&self, position: Position) -> Result<(), EditError> {
+        let world = &self.config.world;
+        match (world.min_y..world.max_y()).contains(&position.y) {
+            true => Ok(()),
+            false => Err(EditError::OutOfWorld),
+        }
+    }
+
+    /// Players without the chunk loaded get the changes with its Chunk Data instead
+    fn send_block_changes(&self, changes: &[(Position, i32)]) {
+        if changes.is_empty() {
+            return;
+        }
+        let packets = edit::change_packets(changes);
+        for player in self.players.lock().unwrap().values() {
+            let mut player = player.lock().unwrap();
+            for (chunk, packet) in &packets {
+                if !player.has_chunk(*chunk) {
+                    continue;
+                }
+                if let Err(e) = player.send_packet(packet) {
+                    error!("Could not send block changes to {}!\n{e:?}", player.name());
+                    break;
+                }
+            }
+        }
+    }
+
+    pub fn respawn_screen_enabled(&self) -> bool {
+        self.respawn_screen.load(Ordering::Relaxed)
+    }
+
+    /// Turns the death screen on or off for everyone
+    pub fn set_respawn_screen(&self, enabled: bool) {
+        self.respawn_screen.store(enabled, Ordering::Relaxed);
+        // 0 shows the respawn screen, 1 respawns immediately
+        let value = match enabled { true => 0.0, false => 1.0 };
+        self.broadcast(&packets::play::game_event(packets::play::GameEvent::EnableRespawnScreen, value));
+    }
+
+    /// The server list icon, None if it couldn't be loaded
+    pub fn icon(&self) -> Option<&ServerIcon> {
+        self.icon.as_ref()
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.frozen.load(Ordering::Relaxed)
+    }
+
+    /// Pauses or resumes the world, clients are told so they stop predicting movement
+    pub fn set_frozen(&self, frozen: bool) {
+        self.frozen.store(frozen, Ordering::Relaxed);
+        self.steps.store(0, Ordering::Relaxed);
+        self.broadcast(&packets::play::set_ticking_state(self.config.tick_rate, frozen));
+    }
+
+    /// Runs the given number of ticks while frozen
+    pub fn step(&self, steps: i32) {
+        self.steps.store(steps, Ordering::Relaxed);
+        self.broadcast(&packets::play::step_tick(steps));
+    }
+
+    /// Whether the world should tick this time round, using up a step if frozen
+    pub fn should_tick(&self) -> bool {
+        if !self.is_frozen() {
+            return true;
+        }
+        self.steps.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |s| (s > 0).then(|| s - 1)).is_ok()
+    }
+
+    pub fn weather(&self) -> Weather {
+        self.weather.lock().unwrap().0
+    }
+
+    pub fn set_weather(&self, weather: Weather, duration: Option<u64>) {
+        *self.weather.lock().unwrap() = (weather, duration);
+        for packet in weather.packets() {
+            self.broadcast(&packet);
+        }
+    }
+
+    /// Counts down the weather duration, clearing it once it runs out
+    pub fn tick_weather(&self) {
+        let expired = {
+            let mut weather = self.weather.lock().unwrap();
+            match &mut weather.1 {
+                Some(0) => true,
+                Some(remaining) => {
+                    *remaining -= 1;
+                    false
+                },
+                None => false,
+            }
+        };
+        if expired {
+            self.set_weather(Weather::Clear, None);
+        }
+    }
+
+    pub fn add_listener(&self, listener: Listener) {
+        self.listeners.lock().unwrap().push(listener);
+    }
+
+    /// Calls every listener with the event, listeners must not register new listeners
+    pub fn fire_event(&self, event: &Event) {
+        for listener in self.listeners.lock().unwrap().iter() {
+            listener(self, event);
+        }
+    }
+
+    pub fn add_connection(&self, address: String, stream: TcpStream) {
+        let connection = OpenConnection { stream, opened: Instant::now(), state: ConnectionState::Handshake, compression: None };
+        self.connections.lock().unwrap().insert(address, connection);
+    }
+
+    /// Kicks every player and closes every other connection, the listener and tick loop finish once they notice
+    pub fn stop(&self, reason: &TextComponent) {
+        if self.stopping.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        info!("Stopping the server");
+        // Players still logging in or configuring are kicked too
+        let players: Vec<Arc<Mutex<Player>>> = self.players.lock().unwrap().values().cloned().collect();
+        for player in players {
+            let _ = player.lock().unwrap().disconnect(reason);
+        }
+        for connection in self.connections.lock().unwrap().values() {
+            let _ = connection.stream.shutdown(Shutdown::Both);
+        }
+        if let Err(e) = self.save_world() {
+            error!("Could not save the world!\n{e}");
+        }
+        // The listener is blocked waiting for a connection, give it one so it sees the server is stopping
+        let _ = TcpStream::connect(&self.config.address);
+    }
+
+    pub fn is_stopping(&self) -> bool {
+        self.stopping.load(Ordering::Relaxed)
+    }
+
+    pub fn set_connection_state(&self, address: &str, state: ConnectionState) {
+        if let Some(connection) = self.connections.lock().unwrap().get_mut(address) {
+            connection.state = state;
+        }
+    }
+
+    pub fn set_connection_compression(&self, address: &str, compression: Option<PacketCompression>) {
+        if let Some(connection) = self.connections.lock().unwrap().get_mut(address) {
+            connection.compression = compression;
+        }
+    }
+
+    /// Disconnects clients that have been connected for longer than `deadline` without reaching Play,
+    /// status pings are left alone as they never log in
+    pub fn kick_stalled_logins(&self, deadline: Duration) {
+        let reason = TextComponent::new("Took too long to log in.");
+        for (address, connection) in self.connections.lock().unwrap().iter() {
+            if connection.opened.elapsed() < deadline {
+                continue;
+            }
+            if matches!(connection.state, ConnectionState::Play | ConnectionState::Status) {
+                continue;
+            }
+            let packet = packets::disconnect(connection.state, &reason);
+            info!("Disconnecting {address}, Reason: Took too long to log in");
+            if let Some(packet) = packet {
+                let _ = match connection.compression {
+                    Some(compression) => compression.frame(&packet).and_then(|frame| (&connection.stream).write_all(&frame)),
+                    None => write_packet(&mut &connection.stream, &packet),
+                };
+            }
+            // The connection thread's read fails and it cleans up after itself
+            let _ = connection.stream.shutdown(Shutdown::Both);
+        }
+    }
+
+    pub fn remove_connection(&self, address: &str) {
+        self.connections.lock().unwrap().remove(address);
+    }
+
+    pub fn add_player(&self, player: Arc<Mutex<Player>>) {
+        let uuid = player.lock().unwrap().uuid();
+        self.players.lock().unwrap().insert(uuid, player);
+    }
+
+    /// Removes the player, drops them from everyone else's tab list and announces that they left.
+    /// Players that left before their join was announced are removed quietly
+    pub fn remove_player(&self, uuid: u128) -> Option<Arc<Mutex<Player>>> {
+        let player = self.players.lock().unwrap().remove(&uuid);
+        if let Some(player) = &player {
+            let (name, announced) = {
+                let player = player.lock().unwrap();
+                (player.name(), player.is_announced())
+            };
+            if announced {
+                self.broadcast(&packets::play::player_info_remove(&[uuid]));
+                let message = TextComponent::translate("multiplayer.player.left", vec![TextComponent::new(name)]).color("yellow");
+                self.broadcast(&packets::play::system_chat(&message, false));
+                self.fire_event(&Event::PlayerLeave { player: uuid });
+            }
+        }
+        player
+    }
+
+    /// Profiles of players that have logged in or been looked up
+    pub fn profiles(&self) -> MutexGuard<'_, ProfileCache> {
+        self.profiles.lock().unwrap()
+    }
+
+    pub fn get_player(&self, uuid: u128) -> Option<Arc<Mutex<Player>>> {
+        self.players.lock().unwrap().get(&uuid).cloned()
+    }
+
+    /// Sends a packet to every player in Play, players still logging in or configuring can't take Play packets
+    pub fn broadcast(&self, packet: &[u8]) {
+        for player in self.players.lock().unwrap().values() {
+            let mut player = player.lock().unwrap();
+            if player.state() != ConnectionState::Play {
+                continue;
+            }
+            if let Err(e) = player.send_packet(packet) {
+                error!("Could not broadcast to {}!\n{e:?}", player.name());
+            }
+        }
+    }
+
+    /// Sends the packet to every player other than `except`, such as the one who caused it
+    pub fn broadcast_except(&self, packet: &[u8], except: u128) {
+        for (uuid, player) in self.players.lock().unwrap().iter() {
+            if *uuid == except {
+                continue;
+            }
+            let mut player = player.lock().unwrap();
+            if player.state() != ConnectionState::Play {
+                continue;
+            }
+            if let Err(e) = player.send_packet(packet) {
+                error!("Could not broadcast to {}!\n{e:?}", player.name());
+            }
+        }
+    }
+
+    /// Snapshot of every player in Play, the map lock is released before any of them are used.
+    /// Players are added on logging in so duplicate logins are caught, but only count as online once in Play.
+    pub fn online_players(&self) -> Vec<Arc<Mutex<Player>>> {
+        let players: Vec<Arc<Mutex<Player>>> = self.players.lock().unwrap().values().cloned().collect();
+        players.into_iter().filter(|player| player.lock().unwrap().state() == ConnectionState::Play).collect()
+    }
+
+    /// Writes out whatever was sent to each player since the last flush
+    pub fn flush_players(&self) {
+        let players: Vec<Arc<Mutex<Player>>> = self.players.lock().unwrap().values().cloned().collect();
+        for player in players {
+            let _ = player.lock().unwrap().flush();
+        }
+    }
+
+    pub fn get_player_by_name(&self, name: &str) -> Option<Arc<Mutex<Player>>> {
+        self.players.lock().unwrap().values().find(|player| {
+            player.lock().unwrap().name().eq_ignore_ascii_case(name)
+        }).cloned()
+    }
+
+    pub fn connection_count(&self) -> usize {
+        self.connections.lock().unwrap().len()
+    }
+
+    /// Players in Play, the same ones `online_players` returns
+    pub fn player_count(&self) -> usize {
+        self.online_players().len()
+    }
+
+    /// Snapshot of the (name, uuid) of every online player
+    pub fn players(&self) -> Vec<(String, u128)> {
+        self.online_players().iter().map(|player| {
+            let player = player.lock().unwrap();
+            (player.name(), player.uuid())
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::io::Read;
+
+    use super::*;
+    use crate::{protocol::Packet, types::reader::PacketReader, utils::tests::{drain, read_packet, socket_pair}};
+
+    #[test]
+    fn players_only_count_as_online_in_play() {
+        let server = Server::new(ServerConfig::default());
+        let (stream, client) = socket_pair();
+        let _received = drain(client);
+        let player = Arc::new(Mutex::new(Player::new(stream, "Steve".into(), 1, 1, &server.config)));
+        server.add_player(Arc::clone(&player));
+        assert!(server.get_player(1).is_some());
+        assert!(server.online_players().is_empty());
+
+        player.lock().unwrap().set_state(ConnectionState::Configuration);
+        assert!(server.online_players().is_empty());
+
+        player.lock().unwrap().set_state(ConnectionState::Play);
+        assert_eq!(server.online_players().len(), 1);
+    }
+
+    /// Adds a player in Play, returning them with the client end of their connection
+    pub fn join(server: &Server, name: &str, uuid: u128) -> (Arc<Mutex<Player>>, TcpStream) {
+        let (stream, client) = socket_pair();
+        let player = Arc::new(Mutex::new(Player::new(stream, name.into(), uuid, uuid as i32, &server.config)));
+        server.add_player(Arc::clone(&player));
+        player.lock().unwrap().set_state(ConnectionState::Play);
+        player.lock().unwrap().set_announced();
+        (player, client)
+    }
+
+    #[test]
+    fn counts_follow_logins_and_disconnects() {
+        let server = Server::new(ServerConfig::default());
+        let (stream, _client) = socket_pair();
+        server.add_connection("127.0.0.1:50000".into(), stream);
+        assert_eq!(server.connection_count(), 1);
+
+        let _steve = join(&server, "Steve", 1);
+        let _alex = join(&server, "Alex", 2);
+        assert_eq!(server.player_count(), 2);
+        let mut players = server.players();
+        players.sort();
+        assert_eq!(players, vec![("Alex".to_string(), 2), ("Steve".to_string(), 1)]);
+
+        server.remove_player(1);
+        assert_eq!(server.player_count(), 1);
+        assert_eq!(server.players(), vec![("Alex".to_string(), 2)]);
+
+        server.remove_connection("127.0.0.1:50000");
+        assert_eq!(server.connection_count(), 0);
+    }
+
+    #[test]
+    fn leaving_players_are_announced_and_removed_from_the_tab_list() {
+        let server = Server::new(ServerConfig::default());
+        let _steve = join(&server, "Steve", 1);
+        let (_, mut alex) = join(&server, "Alex", 2);
+        server.remove_player(1);
+        server.flush_players();
+        assert_eq!(read_packet(&mut alex), packets::play::player_info_remove(&[1]));
+        let message = TextComponent::translate("multiplayer.player.left", vec![TextComponent::new("Steve")]).color("yellow");
+        assert_eq!(read_packet(&mut alex), packets::play::system_chat(&message, false));
+    }
+
+    #[test]
+    fn players_past_the_border_are_hurt() {
+        let server = Server::new(ServerConfig::default());
+        server.border().new_diameter = 10.0;
+        let (player, mut client) = join(&server, "Steve", 1);
+        player.lock().unwrap().set_position(100.0, 64.0, 0.0);
+        server.apply_border_damage();
+        server.flush_players();
+
+        let source = damage_type::id("outside_border").unwrap();
+        assert_eq!(read_packet(&mut client), packets::play::damage_event(1, source, None, None, None));
+        assert!(player.lock().unwrap().health() < 20.0);
+    }
+
+    #[test]
+    fn border_warnings_are_sent_to_everyone() {
+        let server = Server::new(ServerConfig::default());
+        let (_player, mut client) = join(&server, "Steve", 1);
+        server.set_border_warning(30, 8);
+        server.flush_players();
+        let border = server.border();
+        assert_eq!((border.warning_time, border.warning_blocks), (30, 8));
+        drop(border);
+        assert_eq!(read_packet(&mut client), packets::play::set_border_warning_delay(30));
+        assert_eq!(read_packet(&mut client), packets::play::set_border_warning_distance(8));
+    }
+
+    #[test]
+    fn connections_stuck_in_configuration_are_dropped() {
+        let server = Server::new(ServerConfig::default());
+        let (stuck, mut stuck_client) = socket_pair();
+        let (playing, playing_client) = socket_pair();
+        server.add_connection("127.0.0.1:1".into(), stuck);
+        server.add_connection("127.0.0.1:2".into(), playing);
+        server.set_connection_state("127.0.0.1:1", ConnectionState::Configuration);
+        server.set_connection_state("127.0.0.1:2", ConnectionState::Play);
+
+        // Not past the deadline yet
+        server.kick_stalled_logins(Duration::from_secs(60));
+        server.kick_stalled_logins(Duration::ZERO);
+
+        let reason = TextComponent::new("Took too long to log in.");
+        assert_eq!(read_packet(&mut stuck_client), packets::configuration::disconnect(&reason));
+        assert_eq!(drain(stuck_client).join().unwrap(), Vec::<u8>::new());
+        playing_client.set_nonblocking(true).unwrap();
+        let mut buffer = [0; 1];
+        assert_eq!((&playing_client).read(&mut buffer).unwrap_err().kind(), std::io::ErrorKind::WouldBlock);
+    }
+
+    #[test]
+    fn joining_sends_the_spawn_platform() {
+        let server = Server::new(ServerConfig { view_distance: 2, ..ServerConfig::default() });
+        let (player, client) = join(&server, "Steve", 1);
+        let received = drain(client);
+        player.lock().unwrap().update_chunks().unwrap();
+        for _ in 0..25 {
+            server.send_chunks();
+            player.lock().unwrap().chunk_batch_received(25.0);
+        }
+        player.lock().unwrap().disconnect(&TextComponent::new("Done")).unwrap();
+
+        let frames = received.join().unwrap();
+        let mut reader = PacketReader::new(&frames);
+        let (mut updates, mut section_updates) = (Vec::new(), 0);
+        while reader.remaining() > 0 {
+            let length = reader.read_ivar().unwrap() as usize;
+            let packet = reader.read_bytes(length).unwrap();
+            match PacketReader::new(packet).read_ivar().unwrap() {
+                id if id == Packet::BlockUpdate.id() => updates.push(packet.to_vec()),
+                id if id == Packet::UpdateSectionBlocks.id() => section_updates += 1,
+                _ => (),
+            }
+        }
+        // The 3x3 platform around 0, 0 is split over four chunks, only the corner chunk has a single block
+        assert_eq!(updates, vec![packets::play::block_update(Position::new(-1, 63, -1), 1)]);
+        assert_eq!(section_updates, 3);
+    }
+
+    #[test]
+    fn players_leaving_during_configuration_are_removed_quietly() {
+        let server = Server::new(ServerConfig::default());
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let received = Arc::clone(&events);
+        server.add_listener(Box::new(move |_, event| received.lock().unwrap().push(event.clone())));
+        let (stream, _steve) = socket_pair();
+        let steve = Arc::new(Mutex::new(Player::new(stream, "Steve".into(), 1, 1, &server.config)));
+        server.add_player(steve);
+        let (_, alex) = join(&server, "Alex", 2);
+        let alex = drain(alex);
+
+        assert!(server.remove_player(1).is_some());
+        assert_eq!(server.player_count(), 1);
+        server.stop(&TextComponent::new("Done"));
+        let received = alex.join().unwrap();
+        assert!(!String::from_utf8_lossy(&received).contains("multiplayer.player.left"));
+        assert!(events.lock().unwrap().is_empty());
+    }
+}
@@ -1,11 +1,125 @@
+use std::fs;
+
 use serde::Serialize;
 
+use crate::{config::{ServerConfig, StatusPrivacy}, protocol, utils::encode_base64};
+
+pub const VERSION_NAME: &str = "1.21.1";
+pub const DEFAULT_MOTD: &str = "OwO";
+
+pub const DEFAULT_SERVER_ICON: &str = "server-icon.png";
+/// Width and height the client expects the icon to be
+pub const ICON_SIZE: u32 = 64;
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+
+#[derive(Debug)]
+pub enum IconError {
+    Io(std::io::Error),
+    NotPng,
+    /// Width and height of an icon that isn't 64x64
+    WrongSize(u32, u32),
+}
+
+impl std::fmt::Display for IconError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "Could not read the icon: {e}"),
+            Self::NotPng => write!(f, "Icon isn't a PNG"),
+            Self::WrongSize(width, height) => write!(f, "Icon is {width}x{height}, it must be {ICON_SIZE}x{ICON_SIZE}"),
+        }
+    }
+}
+
+/// The server list icon, loaded once and shared by every status response
+#[derive(Debug, Clone)]
+pub struct ServerIcon {
+    png: Vec<u8>,
+    data_url: String,
+}
+
+impl ServerIcon {
+    pub fn load(path: &str) -> Result<Self, IconError> {
+        Self::from_png(fs::read(path).map_err(IconError::Io)?)
+    }
+
+    /// Only the header is checked, the size is read from the IHDR chunk which must come first
+    pub fn from_png(png: Vec<u8>) -> Result<Self, IconError> {
+        if png.len() < 24 || png[..8] != PNG_SIGNATURE || &png[12..16] != b"IHDR" {
+            return Err(IconError::NotPng);
+        }
+        let width = u32::from_be_bytes(png[16..20].try_into().unwrap());
+        let height = u32::from_be_bytes(png[20..24].try_into().unwrap());
+        if width != ICON_SIZE || height != ICON_SIZE {
+            return Err(IconError::WrongSize(width, height));
+        }
+        let data_url = format!("data:image/png;base64,{}", encode_base64(&png));
+        Ok(Self { png, data_url })
+    }
+
+    /// Raw PNG bytes, for packets that don't take the data url
+    pub fn png(&self) -> &[u8] {
+        &self.png
+    }
+
+    pub fn data_url(&self) -> &str {
+        &self.data_url
+    }
+}
+
+/// Live values substituted into the MOTD
+#[derive(Debug, Clone, Copy)]
+pub struct MotdPlaceholders<'a> {
+    pub online: usize,
+    pub max: usize,
+    pub version: &'a str,
+}
+
+impl MotdPlaceholders<'_> {
+    /// Replaces %online%, %max% and %version%, none of which can put quotes or backslashes into json
+    pub fn expand(&self, motd: &str) -> String {
+        motd.replace("%online%", &self.online.to_string())
+            .replace("%max%", &self.max.to_string())
+            .replace("%version%", self.version)
+    }
+}
+
+/// The configured MOTD is either a json text component or plain text with legacy § formatting codes
+#[derive(Debug, Clone, PartialEq)]
+pub enum Motd {
+    Legacy(String),
+    Component(serde_json::Value),
+}
+
+impl Motd {
+    /// Placeholders are expanded before the json is parsed, text that isn't a valid component is sent as is
+    pub fn new(motd: &str, placeholders: MotdPlaceholders) -> Self {
+        let expanded = placeholders.expand(motd);
+        if expanded.trim_start().starts_with('{') {
+            if let Ok(component) = serde_json::from_str(&expanded) {
+                return Self::Component(component);
+            }
+        }
+        Self::Legacy(expanded)
+    }
+
+    /// Text for packets that can't take an arbitrary component, only the top level text of a component is kept
+    pub fn plain_text(&self) -> String {
+        match self {
+            Self::Legacy(text) => text.clone(),
+            Self::Component(component) => component.get("text").and_then(|t| t.as_str()).unwrap_or_default().to_string(),
+        }
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct StatusResponse<'a> {
     version: Version <'a>,
     players: Players ,
-    description: Description <'a>,
-    favicon: &'static str,
+    description: Description,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    favicon: Option<&'a str>,
+    #[serde(rename = "enforcesSecureChat")]
+    enforces_secure_chat: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -24,33 +138,147 @@ struct DisplayPlayer {
 struct Players {
     max: usize,
     online: usize,
-    sample: Vec<DisplayPlayer>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sample: Option<Vec<DisplayPlayer>>,
+}
+
+impl Players {
+    /// Drops what the config doesn't want shown, clients need the counts so a hidden online count is sent as 0
+    fn with_privacy(mut self, privacy: StatusPrivacy) -> Self {
+        match privacy {
+            StatusPrivacy::Show => (),
+            StatusPrivacy::HideSample => self.sample = None,
+            StatusPrivacy::HideCount => {
+                self.online = 0;
+                self.sample = None;
+            },
+        }
+        self
+    }
 }
 
 #[derive(Debug, Serialize)]
-struct Description<'a> {
-    text: &'a str,
+#[serde(untagged)]
+enum Description {
+    Legacy { text: String },
+    Component(serde_json::Value),
+}
+
+impl From<Motd> for Description {
+    fn from(motd: Motd) -> Self {
+        match motd {
+            Motd::Legacy(text) => Self::Legacy { text },
+            Motd::Component(component) => Self::Component(component),
+        }
+    }
 }
 
-impl StatusResponse<'_> {
-    pub fn new() -> Self {
+impl<'a> StatusResponse<'a> {
+    pub fn new(config: &ServerConfig, online: usize, icon: Option<&'a ServerIcon>) -> Self {
+        let placeholders = MotdPlaceholders {
+            online,
+            max: config.max_players,
+            version: VERSION_NAME,
+        };
         Self {
             version: Version {
-                name: "1.21.1",
-                protocol: 767,
+                name: VERSION_NAME,
+                protocol: protocol::Version::CURRENT.protocol() as usize,
             },
             players: Players {
-                max: 100,
-                online: 12,
-                sample: vec!(DisplayPlayer {
+                max: config.max_players,
+                online,
+                sample: Some(vec!(DisplayPlayer {
                     name: "thinkofdeath",
                     id: "4566e69f-c907-48ee-8d71-d7ba5aa00d20"
-                }),
-            },
-            description: Description {
-                text: "OwO",
-            },
-            favicon: "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAEAAAABACAYAAACqaXHeAAAACXBIWXMAAAsSAAALEgHS3X78AAAgAElEQVR42m17B3idZ3n2ffY+OkNH0tHelizZ8t6JbQzBTuI4JiaQkBCchBVCSQopBXpRU9pSaAuEtn/pT1tGIATIIpvEjh3He8iWLUvW3jo6U2fv8d/vKyfAdf12FGscfed7n3GP530/hUZXWdLoNMjmsoBCDaVOD0Upj3wmiRL/KnQWKGpaUVTmoJmbglpjRk6hhKrciZxOC2QKUAAoFXL8vQKUyQxKDitKai2KBfETfpQU8p8b/3v/j6KY58+KUCjF9wvyc5RKKPD64lONSg2F4o+/UxI/K/B1vFd1PoOKMgPqa2tQbinDeN81pMJJqJRK5HjdZVYTvrj8JtjUBhwPDOOYOoC3TvdBrVJB46zmdYrIerge6EzIFDMolACduQwKlQb5ZAwqlRFKoxklZwUKBhEUPfLVLVDyBhQ6HbIMFqMCqBUo8U0VcgEFFI38Nm+2KBetXLpzLlDBFSnFghnUpQ/AaTbAwN9XxDJwux1Q69XI5RWIZtLQGk2YW/AjnEgjz2sp1SooefNa/qrTZkOVWQ+DVonQhBcLwTEYVCWoeR/iylqlCrc2roBJqUW+WOSdFZAVb1jVjILPg1IwhEI6CQVvWa1xMpNBP2+2hFxicekCNheK6TSykRDvXcEb06PAixf4b6Yk881s8M1uZHbpbZVceEn+XyRNJRPHisimgVgEWgautqYRaq0WGrUaWgYxNDeGVCQIZU6DOc88Km0OVLvdqODP7K4GNDkaUNAqsJgIY3xyGj5RgeEUFFYrgh4Gk9dU8a9Jo4FOp0RU3DMzu7WqFp2WcuT4ubiN2WwUM/EkCqwcVS4jC1JtMkLB16iz8TjyicSN8svLBYmvNdVNKMZCvPkwkE4BZossz//fnyKjXOUqx/LWOhw9e4FBY+vw99SxKHTZDLPGIDIr+dkZ2WZgaSs1WkR9s0iy1TZt2oIVK5owNT6JoWvXYDNYkPJ4UGQFFbUaqJw22LIpBL1zzK4R2pxeBlGn0bHoishzYTmWsAi8SaPAjroG3q4ONVvrkMtkUXzmAiuLlWuyQtVigkpUFKujEF6EurWzS1TxUquKIPBPNpWBPxSBylWJrNXGSlcyk6LHSyztkvx6qZ9vlHixhHQmg3A0Dk2WfejzQsOg6dQa6LlQ0bvi7pTqItqX1UCr0uL02V6kSjnkNcCem3bg8S9/DKmMD/c88GUcPdoLh5mLttlRZjFjvm8SE+zXoqKI8goHNMy4CLrZqJV3EAznZdAzhTw2VDag3VyOtjtaoag34PnfvovBUBAxpY5p18priN9VFFn/cSYpEfJCpywxKVro9SYJIkYu2mx3wWQrQ4TlW2AWjQoV1MQCFaPoDwaZRRWy0RhMJjU0Rg1CizFcPnUMioVJlDI5GLgAHUEyzjfRMNp8Z3x0yx584x8+DbU5h/33fhlzkRIrYBHemXm89cwRbL2lG64yE8rsNuSzBazp7sHn/uIgpuaH8fhXvo45rw+FfA5L8VTAF4nJ+KtUSgnYelbXdlcj3F0u6Jq0GLw+Ba8/hIF5D8LVbcQ3JfKslLUdLbg2MomCuxaqaDR9qJ7ZW2u0Y2RyFInFKNL+AJJ+H9L8RQWzisWQxInEwjyygSDLO8rIxaDkh39yHCW2jDoZRGisD6VcEstb2vGv3/sWHnvi0+jvu4JkMIUygxFNvLnjR87gdO9ZLmoKupwC1oIeDU43jl88gXO9l+Gyu9HR3YFLl/qwqaUHlZV27Lt/N9LhKI68exI59rDF5GQAWJUCXG+wRI7t22lxYV/ncrQdaIbJyWQSUI++3Yvzk2FkbeWyEt1VlfjhVz6P8ek5TDI4ahX7TUNgumflzVhZNYPfDF2CN5tlmWqI2iyrRBwmgw4uVwXm/F4yRILVwn7O52U7KNhjM0ODROrwDcBnD67ciRZjEywGA+6766P4afR51JjdSOQS2LClC0lVDD++/24kfAW89Nuz+Ni+HVixtwVT0zMwlgy4fOYK/Df50XVTN3793LO4Nn4Fc9M+NLrbeQ9TbLcg7AxaKkFQI7ZICOa9bq8hcG6pgaHCKLEmHIjhzFW+noxS4npKTPSazhbs3roKff39OHHhiqge/SHvoh/NRP7NtW1Ybq9EMBHFbJxZJlipGOV8vohwLCbfSLyhkWyQYymqCUS5XI5MWsTG9WuQTiRRzKsQiixicS6CHz35Q1jYw5+8+2EUfSU4rWasXt+AFT0dGLkyiqeffQ6n+8+TAbx4/vWXMEakt/L1f3jlHWzs2QqTwoSe7m5s2rUer75xDMFglO2kxuMHH8I3Dz2CeCqGq7yOgm3bQMq+f+M6ONrLYKg0wzMTwg//4yW80z+GrN1BNjFK2Jqc85H7S/j5r55DYHSIcTJUlUrs52UGE75z812wqHUSTF4Y7cObc+OsDr1cdBHFPxEkkOWXZxCsJgu+9a0vYu89e/Dsr36Drz7xLwiEA7h390dw/yf2oIOLHT0/gx//5Nc4PXSBfaggVWURT8apHxSwE2tQUCHBtsrnRUbZzQQpBxOSTEawbct6/NP3noDfmyY4nsNrL79Osirh+9/5JqL8e88nv8p71GBvfSse2b8D9buaobarcOJoP868dQU/P3UeYwYXcgyQkCUlVkpjuR3e6UmyR4YkqjMfUhOwvMF51Fhs6GAPil7prKyBlTQzGJhHHgopMhTyr1KWuQhKikjf2cCqcS7DC6+8hHg0yfLaDUvJinVdXShz6vHMC7/Hv//sl+ifHpdlWFVdjzL2o46Am88pUemqIdLb4LDb4XK64HRWEv2dIsJSAI1NTuIXDOy13mFU5Guxd+ctSJayOH2sF2fYKkMz0yDEYnN7DW49uA26Sr0UTC6bGa8dO4vZaBbbN69GKLqIKKtV/CwSZzVTlCmyIgAq46FiJiUXPRH2ottZg0pTGUGlgAa2Qy0/v+6dQYzZ1pJ+RB0JxE2lkoiRIWZ9U1jwLCC4EMQte7ajvaUGveyt10+9hd/8/k309o9Cb7CKEKKi3CWlqIAtAxklHCZDCKqk/BZlJcIr0iRaS8fKKyMb2VkJUQZ2eGIEnqgH/RP9rBon+zuKAIF5IeRnIpJoqLCyNalmGbiRyXn81df/B2/3TjCo5djS3kbKLKFvJkgmpKKMJ1CYnaTOCVCwqU2HxOK1ZU7ERBAWpuGgtrbrjfKGqs12rKB2Hg7MYYGRE0EwGY14+LN3Y/O2Lly4eAXuihp85dHPw1quwYOPPYa+sWEUyfU2ZtPNDKtJoVkCq8VqkYFWyr8KUiRvJJeHjeVZKBJTlEW4zSYkSKMZgmueSk/DgOUZ8DDptCRUn9aA2ZlJzPhmsJiKLmGRFEBqHO6bxvN/OI///u2rGJz0s6rKZcn752bRXF0FTzaPUIqimNlXEhBVZCYZAAXfRGWvotorQ5Y+oNJiRbutkoppSeXZ9WasrWnCCNthaGECKxrbceua3ZicGUZ7Zwc+9al78PSvXsA/PPkj+PwxdLSsZA+XM7sUKuTdPBef58KtXJyW2RUVsM7tgoEiKcEb+lBnKxdf4KKLeGDDSvZ4DqF4CnaDWer5HDFjjgnQKDWw6MywE3csJvoULXW+IocgJftkJII0aW/eF8ACQf2mLRtxz8dvx9DQBBmCGobv31jvwoAnQEmvY6FRjTJAKqXaeEjlIBC5qgFmPcOIzU4MM6LMoM7Af/XCE0LP/hWBuTg3ign/GEpJJQauTcNlqICVNPns4d9jesGHzrZumhQDjQZBk4s3E6Aay2yUsErcubKD7kyFDbUV2N5URRSn66SC29lSja0tjVikXreZ9PjAyjZ0VpXT8Fhg1eqwur4OfROjSFLru6gOLRRYBbrPbx76Ev7yiYM4euw02yGIzz98D6vRhoGBAXz321/H/Qf34+iR06wYH99fgXK9ClNkuGiRieHahDql6XMdUlI+lkh5UgqTuzM0MBVc8OrKRphZysJdkfUxEfEjkIlhKDCOcDKKBtpkBQP27GsvY3RqEtW8jo2UQxjCxvomZFJp1LuMWN3gxqrGKnRXM+Isv4Zyi6SuDO1yi7tCmqm6chum/POYINV11xOHhOQlDkX4Pg5mu3d8GN5oGJVMlsNsRoL32N29jKBajhdfeBMmtRHf/7t/QJnWhleOvInR8Slc7xvBAGlQtLKWWBMmZgwJ7yMwqViSlahWMMNKon1BaHyFQlJTsaIaL49fJyj60VlOlGYp+1IR+GibRdSchkrypwYT3iGWsY6/r4CZN+UkaClIae1Ubzc1uOi8yNu0rTpm3W7QEqwyBPcSNMygMDHCUtQ57bLk0zQ0cdLbkYHrMLAS17XVo8D2mAyFkMoyUxoDfX4BAQahjG4xQ8r8t3//GfJP5jEyNUrbCzz0mS9LZkqT3i5cHcT4VACVxDYHhZBJZ8RiJCrnCVI73vB1ajHIKPHCQifLP8JVcXFqvQGDQQ+CNCw50kWEUtdkshMjkrAaKTbYi2ZSWTm9wURqnjeUQSMDF+NNu4wGqr40fMkwelyitNMwCzzgaxK800SSIERsmQ0t4uq8jzJZIyvi6nwEodgizkzMYWwxLil3NrDAm19kq2il7A2w3ysYaN4wApTtnuCcmKxIZ3j8yjEpzDavXIurbJlIIohsPklssVPZKnkflPUqyGFIT1szpmZpr0ssJUUuJQwytKSIlrpals01aHJiKGEk3aUImjnUV1aiylmFvoEhWQVFtouR/ami549TLmcZ2cV4hH0tglmF8+y7mUgKvuvDRHU1gcsAt8MslVzvNP0GMzgrMsLKy7ItdKzEDINjYbWk03HEknoZgAQzmmGSDGxNJdsxy98bW5gjMKbJ5wHeRwEddV2yzHV6Qa0xtCxbjoo6N1589VVJrzOkyWmyhqi0Yn2zrNZvPnoQL1JUKYsCpTNpuqw8epYvw7cfeQBVjnLSS5GUp5J2M0UvvrFrGx69+4vk6CJLyUcdHpNqLsOFi3I0kS7j9NwpIvalhQUMEZTivPkFln2EVtpLi91PhL5KoLy+GMRkjEpQjNaEwBJWWbE08lpX0YAcb1iOzMRgpaRkK6Rhp1iyU5PkWUVB6gHxITDL7ahFpa2K7edENp2V1XCV5b+6p4uA3M5069C2sge2hnrkK+tRNFWijNXdXutCTbmTCVRbDikIMioi7nef+Dzu3rERvlAC7x4/BT17Xk1pnM4kydkpDFwfxfziNJx8w3KrQ2qChXCIlBVjwAicFC1itCaGFUIqh0hHKlaJqDsNg6lTLc0RlArFH5uQny/xvRppVuNahwMhBjYnJbia8imPZWajxAe7xUFPEmKPp2HSl1GGO9HkbpYAl2MCZwKiMjJSZTAn2LP7ZvRevo5qgiVFCTyEZxXXE6GNnp3z4I3T528EQNwQaaGQK1EwVOKff/pbhLwemBUFBkCLIsVKKOrFuH+ECyyQOrQw60yS7jzCKrOEXbxJq9nKilASG7RSdxsIRmx1ZNmvYu16tea9cahcvuJGADJUonqWbzpLKb2lkwHPYS6RgV4jTFcCH12xAiPeBejY+9UUVmK6I+SU3WLnR5kE0wATEYgEodVb+BMKqXSe9GzAtpvWEndybM005kIxMbCTlTZD5Zrg+zI11kMQGtlk5AuiGJ7y4MzgOBUSFxoLIs1sFOi17WUOfo8LIT2KG43QLcbY+yKbVt5EGVWckW2QZ84MVGViNGrnmyXzKWp3lSxzg1b7fgDE14ob/856piiIoiBB4AsHb0GWMvvKmA9GCiEiAg5+YgsQU2KOwKjkNYTZSmcSskWlM+N/noAfmVwSap1FSnUtTdci7bCGmVi3ohspynaXUYNmu4GUXInFYIhAXVqqAHERNVVggZp8xutfSg1Lukjll2VZuh3VXKiSWJBmr7lQQY8gpGmc/l7MEipIlRXUo2o1gYtZExMapZi+sA6tbIcIqVEOMoxL4uO98bhCTtNKsoLsrB4fWWcxuSgx6crwOINXRG25Ffvv3oToYgoX+wYRTSdYjUHSopoYk2QQtATNLJJsC9GGRqOVyK6VQCr8hhhzeoanYVfq4GSluMkWZiZ8epZaRmm6EYAbgFMiOBQoWxVyxk8DTFlMlpcBCFATUDuQKXQoI15E+QbCxTVXt8o9he4yu5yzqQhUQuKKyWuO31/vsOMi6SaWiKCc4KpUqGTGROZF8gpcfIltdef67ah2m7BlazflrBf9IxMQA22nhdfSKokhOfgnghRf3WJoCSN7OZ4VHJRGkgCrUAocyKLBXUdZraZqzPEjStzRkDbtCCRCUFKTTI5MY3whgHGVkQk3QP0eEJXii9BmoixzhbSMQr8XGalyWwVBJ4V4Js6eNEiejUbF65Qsab3MQJbleFPDMgqnAHxaK+odbgz7pnF07Ap4STTwa5OpCkV1iWWalcNUIUhMDCYhg7ogR3gqoL7KigP37MJK+oRSyA6jyoTb99Rjxd4e0Plg6kQQM2SP1Wy5AYJe2GSjHb7MynIyMQY4yyqkTzDri6RRMhSDMOOblz3fs6ELhwdHECJ+5FmpOb11aSNHIWFhaaJvonvTGMrkLM1A4WEnyhq0JgqKMNREfKESkwyEP+whahNqcrxYgSKZpVHRUYE9H1yOYjyEcVrks4Nn4aMHX9+5AvvXbcRGBqiVmVhX04gKVpBQft0VVdhOXrawX+OskLUrmlg2BXgXmIysEnVWFRrqqjAzNI1Lg2M4Nz2Ammo17ty57gajZHgtUplCK91lhauSGKCn9TXKMb2WGXa72wmJGly5fh3+rAYpawWlsEXuX4jVq/842y8hSqozcEE6an+tSicZQEQywXJXkqa0rAAxpdFqlHCVV1MkZWHWGIm8Clybm0Jzczki0UmMzySwc8V6qj8brFZq9pWVOPzmdXL3PA7cvAvLkxV4tjcuRBlq7XY6PvaLIY01G9u4pgzO9g5gYHISUwvAfxx5hlmPIJwRk2kNapbV4aQnT7ZJ486WVjzfH4He4qJ61RI0TVStebaLjpRcTpp0MLBpLG9YA3u9CvGJQQwv5iU+vTfaUv/pBkeJ5VGkkImR94W0rC9rotVMyhbQKc1Qi0Gp3BMQAoaWUpFHNBbGttaNpPogdn98OxpYlsePXEVdXRtqHZXomx+F3qFGbasSJ16ZxJx/Dm3V9djTtRyL4SgldoyMksSyTgfGZj3ovzqK5985hYsT48iWin+2AZNgv3/v109Lcba+thVuAqeD2Xa7G1BJE+aw6nFqaHBpuMJWLidN+nzDTG4Wd229FUlbEdff6IWK9CiyX0okxTzAfOjG8qEx0qVR4BRKWbmpIcokEJ6X4kKjNhBhbWwFHYNE7U33pdUaUcxG0O4UW14K3LxjGSraaHP9Qbx9/jz6AtPMrga9wyPYsK0VI5dHaZBUqK6qJlDSnbHsZ4NedK1uwO13bKDCjOONo2fx6pkLcnymUhlgsdbCaHWjqCW9EUDXL1tDalOh3zOOPu8UmYhqkxqlvsKOnmo35gJhpJhIwTHdLfXwhGaJN1SY9fVwN5fh1bOXUVDpJQUVQwt/GgAmkaCmodAQ05xC2EcQSdNdxbhQNXR0U2oGQcn2MBvNUg8YyRpVdGYNFhMBkxyvTdAq+/Dsu2fxv0fewvGhPhwbuoSr4+NIEMQe/sitmBydlZsTTuqK186fxNr1rXjo4Q/D5jJL3f/9/30BFaZ6dHdsw0KepoxlXGKwu3u60da+nKzgxkM3HWCpZzHsGZPMtLG1CytqG+lC3UgRW6YXF1FOnLlnz2aMB6dw/soEam0O3LSmB1e9bNH5sNxJKvln39vbWuLkAumlRPFTCs5A5F8t5nV6uj5SWxlLWwCfkKdu8n5DZTVpL4sCdUCDQYVEfAGf+9t/x+57v4L/+7tX6NczUhcIYeQjjvz07aN4Y2oMD/zFbtrdNAKhIGnPhb0HtsBgUQkQgp/ZW0PV97WDn+Hvk4bZ80InZOgleqjtDx78CAaI6vVbmvC5/fQsZZXSTDVXNmBbW5ccemxsbkCN1YRVzHhLU6WcZuf598TgAOYG5vCvj3wK7RUmFBIJKAv5P8EAocwoVjTMvImlLYaHgsfLqfvzxRQX3EHxE0GM3ArqcluZWV7Au7iAgQU1zs4M47pnmh7BieaadkpXH9TGKokZ7moXSy2FS5c9GFkVxL6HduGZHx+Gm9fQWrXyJgWKW6lGv/3lT1BwmRF7MUM6M/K9M8hrS/j10y/hpWff5lvnUdHtgrOtGo7/MqOnsYFapFy+TozTTXod1jXWwOq0UDpTCLHaCsLSs22ePXwcX6i7A7/4m0dx7xN/j/Hx5J9XgBhOlhmsMPFDKDcTs5+nD8hQadU5q1FmtMrXlRFtha5XCxLlz//twtt4d35EurJH9z1G27wMChP7lq9JM0P7D9yGRx/9HNRlrJzmZlhrHAzCTqRUSfgWwrKtCuzbjrYGuJxW2PjzsnIHxLi+RF6vO3g3zKuWQ8GAGfl9LRHfG/NjkYpwRU0DemprpS2WfS3Mh9gAVWeJBXGhtOQ2WpqSvEj1+KOnnsZc/yB+94O/xR17dixVANuBWWefC2SkhIuJTQpmV08e9QVnSXs6Ak9RDjQFhZRTNrfStl4iiKUJmGKYoaCi2bfxDuzauhnn5vuRI4Ib1BQ8YnLzw/9i2VtRYJVFyMFiKltZ78BjT3xMonVRnPpQLG1xielwmVWJW+/YhbMUSl0GDX53/AQaV7dg79cfQf+FQXJ8BWb7p3B7+1q0dDSibl0jFgamkQ3nCJgmuWGjN2phYMDEjEFPfxCXJ2AUaG9bgd8ePgbv757Hts0blwIgMmnQaWQkwhQyeWa6jJEXFlWc+lBT7RkoMKzMvJqobiP9WKi8tnVsRkNFM54/+SymQ5NoJAqvua0TFedpnIY8pFINQbUC1m0b4X39DAw2m9xmk4cpmHGDQSs9vdw+f//4DJNAl7ZzTSMuDZEy71qHQ7etlrvXg8EEerwuvPzUr3HmudexsrERO3ZvhqPehWQwhrJmCyvEjJOD14hbRqi4JiG4hMw3s62nF+bQ01SBu/buxQN/9Q0cO9NH38C31IuDBiVxEiNBqZqhodeizkYAocYW1ldPpBfcWs3v+SlK9ER/3NgnMvLGehrX8l8lzl47S479HB555F5cnv8pnOtXIDA2jFAggjs++1HUdTYir0i/PwtYOjegxPvTAcWSOSokolhT58DagTie/O/foKqlGcqiFqGL19B9vR9HBvqxvZkOz6VBVW0lgt5F2BpcsNM4CXaAioEwmWVbCNkizHdPTS0GCMLTc3PYtGUtHv/sA/jHf/nPpbMRYvMhTguaEgcHuCAty98lzgEkvMyiWTrBCBVgq3sZahw1LOsbByr4+kp7BXS0rcj6MD8zit89/SLu++L9WLXxDLo+tB0tLfsRiCvgdJpx4kKf3CBFMbZU+kUxjcqhQO+u5J3Ivf9MVtrudDSB+yh7e97xIjLvJ/ipKWy0aN65EqE1TZi46kHDB7dgepLvO+fH5q1UkQS7yfEgF25BbbNbDklEkspYsavqq7Cpox7PnziJgTNX8NB9d+LiYD/FHRdXoJjIUloqKH9lRZDjrezVOczDwFLS0uL6AiF0ujVwEZiMlMS8thx2rNnoZqllcX5QDztbJ396DANdp/HVj92CJ98aQF/MiaS2iOAlKsacDo0GHRL+RZRInQJAS1lmidgijFI+lYKeNCv28XI0MuXUCkr6gSr+fHVTIxQ6BTQMgsqnwaKxgpLXir/77k/w6Qf2y4FLJplH34V5VNfbUVFp5HXiiEUycFjKESdWffTu7fTDCvz0F2+gtqkeTx76hmgB2iAuWMcSSwtXyFUZlfT05OAoRZCZfaQjGyTE4SK2iTi/U2Skm9vL4fGE5TT45u3NWHWiE/P9Gjnbf/GJb6GpuRrbtm5B/xvss8UQNiyrxtoWNwtFDYV2aT4gKEgcmhLeXsz6xJZbgFb16NuXYHI5MTPVi/VrVsCTCOD4yBA+uHElzl4ZwkiUzJ7U41s/+gUsrNTOtho5guu/7IXfk8HtdzVBzF7m531yp+iD1BYWijUNKXfNqlY88X8C+NfnXsA3DEY5hCFFiJmcRu6bK9k3TRQWi9EwnV8CTrtbmqLFpNh6SkomyDJrQlbu+8QK9qCF0lKB225bD29qEXU0N1/auhMfqnDigyYlHmwswzcO3IQ2uxHDU/NQ2MwwVdrkPFFrZXWxNZQWPXQOG86OzOCXr5yAubwO+z+9j7RJeuQ17vzUhzAeWcTo4DxUGbZdmNb43EWMery4c8d2uCvL6CFmcfj1YTSy9CvceqSTGczz56FIAo0MptPA7+XiqKVmuHlTF14fvozv/PKpJR2QIVdmilIKEuhscFdUYzowRWusZ/k4qPv1CDEAC3RzXD+UdIPXGO1YOAu9SSNnA6sZ2Q/fugXBsB/L6m3YfcdWXPfO492xCfz0jcO4PONBjIH63bGzOD08iRi9g4E3rjYTZC1GhGMpTEz48bFP3oZROstkKI4Hv3AAl6+PwEfpum//zXh3eAKJAltHWcApLm7DqhW4464NGJufwU9/fpRUa8GaTS7JNJmUGid7r7MyDNQsSjlZStMZanRKPHTPXmmnX+07x+qnclLa2Lt0UxpzBboaV2Fwph8LqSCxQCs3Rq30AWInR2xaiJCpGICJST96T87hwqkp/Px/3sSpM/144ME7seHgTry4MIW//sGvMeGP42P33U58Ia/v34WuVcswPjaNdCyHcwOT8lSqmEPImbiSkpXo30Bev2nXCjz3uzfJ5Xp84APrcepwH6obauDNBnHt0jgm40Lvx/DRfR9GhBl+5PEfsF1C2L6jlfpCT1mehnc2DI83RjvO/o8l4XCUkUrVSPDzdcvbsZ7vI6y3siBoj9SjIap113aQCuMY9c8gSzTWsedrbNVyS7qCrbCwyKyn41IFUlzyTRexMJXB7FgR3/3Bizjw2W/iF799HRp/Fk6tCfs+/kcT6H4AAA9TSURBVCH0DQ+jjW9odZfhxNlePPLwPuy6pRvBBS/6aVLEyVSx6XnmXD+uXp3E4NVpbPzABuJRFuPXJtBERxeLppGPpLkYGz60YwPWbejGzvUrEQ8G8bm/ehJzvjxaCWot7U5JT/lCDhdOXML1wRnYLDauJ0QwTMPCZIrju1pqj0/dvVeKOqWeJW4i6HU7a9nbSfQH56G0V0FttdPP18ChsyGZXCQFNtL9sdcmrrB81HJ0JgBUMIHNbKco6YF/Jk4UHsMMubaiygG704YrfYPYsK4dU6OT/B0dajqa8Pa7V6DOqzBydRzTs37MzXrl4ccvfOVTOPzKKVpsYN2mHoyMLdAoibPJBUQCUdjp6LTVFhy9cIW2N4Ef/PIlEA9hpfNrJOgarUsbvOIIbN/QEOLUBOJY7SgZ7ML1KSx4Q/xeGsGgH/v27MTWjavF0UEFGlj+oVQUngwjLcpS7tQo6bLqpRESQ5KG8ioCoQXXxnvlXqFWbGRQEJj5erfbAlWA8pZ0Yzc6sHfXzbg4cI19mGIVaRgIC7l3hFmqkVPaYZb/Xft2sQoWESQlNtQ7kS0m0drdAtvJczLzra0NDOYQitQJ7tpyRBYzlLF1CITDuHBtCpOeKIxmEzm+DMlYHA11FaSUnJRU4YU4Bue8NFU5LAQmaap0eHe6H8/8zevIEOQbq2vw1S9+Gn/5mfuhrOAPZ0IBzOUoQjRL+3FFLqyCnF5b7kaEyC78v412WIgKD23ssGdCTowiDFqWwahvchAAO7C+pwPjtKtIFZBL5dmfETlKE14htMgMEv1TcYogan+Hy8oe9dF/aKn9LQh6KMETKbR2NePSpWHSm4l970IimcKObWthMhvgD8dx5MhZDI7PSiHl4D1FqBmStNtVDqM8jpuj0Oxla12dHqewytBy2/Hww7sRScSwzLkRH269E/qEG6+9cQof2LIGao/Pj4xev7RxSN0sToyJq/SsvFluiy3GfbTCzXJ0PTI7yHIv48WvoK2uXfqE2cAEBvpN+Mg93Xj88b3om76M/3zzGFY5HciwTHWU0UoFg0ALXU4kjoTCcqtaTc4Xe/hnzw4i7lmB69fm4J32o7unHWPXZ5CLp3DL7i2YYOmevzCA5w/34uqwB2Zh2dVmlDvdSKcSWPB7sbZ1BVxV4kivgoGM4c1T5/meGjz28INkkn34q7/+W7x65DB29SjRaK0ipunx5tFLWLuiE+p4NsYFJ7B0XECI8RxcBJuWqib4FwOwM8p2owXzkXnMByeQzuYRTwcwzGC0VbXh9ycF/Zjwxksagl43vvHYw3j4S9+j7p7ApLaAKirJq/2TWNnZDgezMTQ+g5oKBygokGCPfv1nv4TxKY18puA2ts72tR9ATa0dr71yFFNE+BdePo2p+aic8JqNZYgmA7CwGiY9I3IemWGyVnc38dpmed771JmrODFwBWuW99BPNONvvvY9/PKZIwRuK965ehyTgXHafQvc5Y2YHg1D7TAZEGaZFYpLBsXODG/o3IQ4jZGVC7OSW2cWZ8jj5/Ddb38Vz734Mt589xzOD5xDV80yansHXqAbVCs/Dv0LJuze345PE+m/+vc/wqVXj0mb/eSrb2HtynbsvHkFKojC23euXaK+/JL/SxK1BbAOnh/GtdgcXj95BYPXPDROGrpQLtzkkKN7sZMdomcRY66s2NKXcxwly9xJg6aG3xehkHoT3sQCwXk5fvjUL/DS2cMwaJxkGzVxi8A7N4m1XSvlACUUCENlNdkP1bkqUVdRxY8a1LvqxW4mau21UgOIndbZ0CxB7RK51IbHDz6Ic2euYcQzJW9qeUMn3u47TFU2DAW1fmi+hI72RrZSAUPD41Jh5rjo0ZkgTl0YwdHLo7jYP47B3nGW/Siuzc8vzQLY06cnJvFu7yziUVIVZaqeelapvDG0YhuJzZSF0DSznnh/kLWyfTm++RefhsWuwVNPv4SfPfsSKitcUBA864kjBVUW84seOTvUiOM+/Egk06gsr0WKekSVSKcOpcnD4riajyUvSnFjx0aUsdziySRFTB5T3kmpFV4/fhQTBKA83dusz0uhM4Gaymo5Opvwj+Pq5CWMz87AM53CnNcLj2+KoolWWmxz88NAYSNm9uFYHsMUUpO0yUoKIHEu0awxIUaccJKC9VrxqEzpT47kK+RoTUjzYHSB7VpYGuIS9P7xa18mSPbg1ddP4e/++b/xkdvvwD137ENnTsN2/DgeevQOFlsRx89fkg95iCCIbaBye6Uc6opTi4dEOUVTcXQ3rsCetbcQEIuYC/lJTUVoeDMLsQACsQUifgyNehuymhzMTuEQfZj2T6GuqoGam69nKft4g1MLI3IHOZYKc+0GeT5AMIg4dyxuWmydGw1LG6UaurRDm3Zjtc2N4/OTDJAROq3mxoTgj88kiN8PRnzEn0X5M8ECB+/+OB578JN4+eVj+Np3fgy9wY5H77sPI+fP45ZVnaj5cAOluhq7tq9DNp3BsXOXoaHmEQOScmcFnBZxQAKlQ06++W2b9jHzGzA4NYYJavhqG19AMRSmI/SEF+iqRvHg+h34SMsqWGhwfvDkP+LyxVEMTkxw8YGlh52KS02doW4IJ8Lya5VKL4cq8qixePxGbGmLGQTNkBheZLnOhWwKA4s+ur6Y3HARR11ES5Te20IXTyQVC/ASi/K07WI/8tatt2DXui34n6d+g+//7y8QIGCqqfk981NYbijDro9shLpchyKTKbbw1zEgrx4+A68vLq9nIbBXkhFU65ZvPbR3w35oFVqcvHqOWSyhp3EZXCYL4pkUhtjrAWZ3e10d7mpeBwMjOEWlV6QIQUaDy7Sp4tCh2CEulfJSHb6/0yTsLsFHLatg6akctZwrquQUSpzospFqA5k4MpRvZvrRcCYpq0Nx4xSJ5CZ+nqTmCMfFDnVenv4MBkN47vAruDjcjxT5Xs0gZwopuCntPvPhD6P65jr5dMjS4IUW30Id4QniGGlXp9FK+1zrqoO63d2BIxffwpRvDu31K7G6uQcWcQghEZFj7hiDYGcb3N6wCgpxypQ3tcrRhB8/+RNcC/rkGT+xZa4iSKXSKt5EFJXOcqymB/f7A7g2MrFUCQySfORGHI9jFeRzBXqABKqo1dvcbfKIfjDhkeJFnP01M0PvHaQQGiRGN1rvrkXnihZMTU5haGQUeXlaRQer0Y77DhxAY00VtOfH0bDODeiKcnz//ryN2NZVXQutculRPNGuCQZb/dK5Vwl2CdRR7GxoX0Npy0hFAwS0a+wpixwxV+q0sBGkNBo13N0uhEZ8aNZbMV+VxeZla3Dh/ABLTQN/QAkXtfeDd30MDdWtmKQrvDb8H0gQX8RxGjFkLZa0cvG+eFCeQwizpL1j/cjdePZIqFBxVsgs3ps3HmXm00T9BKuk1VmPfTsPYH7ah6ejv6INHoXL3iwt++07dmC09yI2b1oOc4eFNLf0AFjpBlWKz9wWO/WAVj5ZZtAa5QEvVU5pOKRhSexcuQ01ZRXyRMiF4fMwUwyZDCaEeKNrXOVYRqtcu7kabXe3IUW+NfoBQ0sl/umfv07NPoCx8Tm+3oDdO7djWUcb4lz07197BWqTkmbFiBSpJ5ONLwGbQhx/S6DV5YbDakXRbmOFmNHd0EKgC1AgEZSTUflAhBicWgxmVoQBAUp2hSqHVWtbYXdYcZ2KMZ9XMMAxxEMh1KRU+MCBtdCWa//sATd5yJ+YM0N3+MybJ5EuqRm4MtipYdRiBOayOdFM/hfVcmboJCK5CFyGBqTlc3h56NkvWr0KTma/pCyg+qY6RIYWcX3kOv7w9FE0mOt5438gfekwN+mBMqlEbWMlHjpwL5o6G2g5lfjO93+Mi1cuUWBF5clxIVstdHfzvlnYampZ/j50tWyHJzCPcyMXSVEW+dhOQ3ULHv/iJ2GzU1Fevo76xlqsX9OJ3ku9FEpF0qYZyzvqUBaPY8/tt8DcYpUsJrN+Y9IubG8xnsHV3mHalNLSg2FikCuO5gs/W2urhEVlxIx/Fv1TV7C8dbUEmqwYWpZw4/HWpUGIKFFDFRViux1dHhdee/kd2CpplWmYHGXl2L5jG46fOIkX33mFctNNJ2bFpp5NsJE+77vvAMXRMC5c7KfiLsrdJnHeMEj80bKfg8EwAxlBT2cnPnrnHdiycS1OvHEOhoQGAbrGCwSw3zz/Cqorq1DfUgurRY8v3X8f6uscCB85j7qNlUxQSViUP3tKV0l2mD0xjqO04dmiAlaDWm7UFN87H6CUZ/dKBLyIPLAkRuHvl8+N8zzvb1vIwWkJtR9sQC6UwZqLSTx15d2lsz4UKJOkRe9iUBwWRLyQofa/SGvaLw9O/dPd38SO9TdDm/gNvME5tFa0IkMZbksHKbxskhb37d6NDtpmXcmM8GgCs1Nz+NlzLyCWjlAfmOQDFiZzGo9/7lNw0IqPj40idCmBT+7ZCm21jiC6hPylP8m+GK9NXZilRE7RgijRVNsqj96L6leL6YN87pcpnvMvQC/O/9HBpdmjybQ4ZqbBXCy6dBpNWZT/Cm41ukxov7dLPkH2zvQAgm4H4uEUfvL8b4Xwk/ghDkC66dqqHFUYmh3GE1/7NlbWrceGZRtw5549CE0HsbyuFeQCqSx3792Grs5WPPXjZ/HyqSPwp4KUvTliiA3lrNICAyoOQg+PDeLeBz+LdLKAzWyRW9dsQfPOJi5BBbVCCg4pfeVjvWy1+UuzCExHMRMLyYNaLrtTKl+W81IABPCJAIgBh6ArLXl7Mebl14yY3sgbSUktpymqIGaIKvEAA5G4xJao3d+CXcPdWF3jZiXp8PUffA/ZfB4mXieRKRB11ZgjXTrKXMhmMugb65VvrFFtoptbJBDp8MD+O/CfTz2L+QUPLl46i6sTY4jmo7BYrNBllk6RZeQWl0ImaJG/1z88ik/svRc18SIc7ZW4NjcNJW1FhdslgVWpXXpEN8+kJK/EMOD3wEs12OZ2yiEuS+M9naEuie3wjR2baWjGJeo3ulsRiPpJJQWCkRk6LvhD/F55uwNlHTacuTCILLRSA6TSUSiCeUyHFglGPXj93cPyhIbYYRbP/YjJUSIZlUfYBCeJJ7waK2sJhil5jnBV13JsWrsCp8/1Y8Hnxwx9R0djN65N9FP7p+R5YhNdqRjAiF1k0ZOhSEi6uebaZrnt5bTpsax7GTErh5m5WRjUJWxa0wFLmRXB8SBiV0M4MTOES34vZXutvJa4F2Gu/h+IZe38Olsk8QAAAABJRU5ErkJggg==",
+                })),
+            }.with_privacy(config.status_privacy),
+            description: Motd::new(&config.motd, placeholders).into(),
+            favicon: icon.map(ServerIcon::data_url),
+            enforces_secure_chat: config.enforce_secure_chat,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secure_chat_flag_follows_the_config() {
+        for enforced in [true, false] {
+            let config = ServerConfig { enforce_secure_chat: enforced, ..ServerConfig::default() };
+            let response = StatusResponse::new(&config, 0, None);
+            assert_eq!(response.enforces_secure_chat, enforced);
+            let json = serde_json::to_string(&response).unwrap();
+            assert!(json.contains(&format!("\"enforcesSecureChat\":{enforced}")), "{json}");
+        }
+    }
+
+    fn players_json(privacy: StatusPrivacy) -> String {
+        let config = ServerConfig { max_players: 20, status_privacy: privacy, ..ServerConfig::default() };
+        serde_json::to_string(&StatusResponse::new(&config, 3, None).players).unwrap()
+    }
+
+    #[test]
+    fn shown_players_include_the_count_and_sample() {
+        let sample = r#"[{"name":"thinkofdeath","id":"4566e69f-c907-48ee-8d71-d7ba5aa00d20"}]"#;
+        assert_eq!(players_json(StatusPrivacy::Show), format!(r#"{{"max":20,"online":3,"sample":{sample}}}"#));
+    }
+
+    #[test]
+    fn hidden_sample_is_left_out() {
+        assert_eq!(players_json(StatusPrivacy::HideSample), r#"{"max":20,"online":3}"#);
+    }
+
+    #[test]
+    fn hidden_count_is_zero_without_a_sample() {
+        assert_eq!(players_json(StatusPrivacy::HideCount), r#"{"max":20,"online":0}"#);
+    }
+
+    #[test]
+    fn placeholders_expand_to_the_current_counts() {
+        let placeholders = MotdPlaceholders { online: 3, max: 20, version: "1.21" };
+        assert_eq!(placeholders.expand("Welcome! %online%/%max% online on %version%"), "Welcome! 3/20 online on 1.21");
+        assert_eq!(placeholders.expand("100% fun"), "100% fun");
+    }
+
+    #[test]
+    fn legacy_motds_keep_their_formatting_codes() {
+        let placeholders = MotdPlaceholders { online: 0, max: 5, version: "1.21" };
+        let motd = Motd::new("§aGreen %max%", placeholders);
+        assert_eq!(motd, Motd::Legacy("§aGreen 5".to_string()));
+        assert_eq!(motd.plain_text(), "§aGreen 5");
+        // Text that only looks like json is sent as written
+        assert_eq!(Motd::new("{not json", placeholders), Motd::Legacy("{not json".to_string()));
+    }
+
+    /// Just the signature and IHDR chunk, which is all that is checked
+    fn png_header(width: u32, height: u32) -> Vec<u8> {
+        let mut png = PNG_SIGNATURE.to_vec();
+        png.extend_from_slice(&13u32.to_be_bytes());
+        png.extend_from_slice(b"IHDR");
+        png.extend_from_slice(&width.to_be_bytes());
+        png.extend_from_slice(&height.to_be_bytes());
+        png
+    }
+
+    #[test]
+    fn valid_icons_are_loaded_as_a_data_url() {
+        let path = std::env::temp_dir().join(format!("blahaj-icon-{}.png", std::process::id())).to_string_lossy().to_string();
+        fs::write(&path, png_header(64, 64)).unwrap();
+        let icon = ServerIcon::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(icon.png(), png_header(64, 64));
+        assert_eq!(icon.data_url(), format!("data:image/png;base64,{}", encode_base64(&png_header(64, 64))));
+    }
+
+    #[test]
+    fn invalid_icons_are_skipped() {
+        assert!(matches!(ServerIcon::from_png(png_header(128, 64)), Err(IconError::WrongSize(128, 64))));
+        assert!(matches!(ServerIcon::from_png(b"GIF89a".to_vec()), Err(IconError::NotPng)));
+        assert!(matches!(ServerIcon::load("missing-icon.png"), Err(IconError::Io(_))));
+        let server = crate::server::Server::new(ServerConfig { server_icon: "missing-icon.png".into(), ..ServerConfig::default() });
+        assert!(server.icon().is_none());
+    }
+}
@@ -0,0 +1,200 @@
+use std::{sync::Arc, thread, time::{Duration, Instant}};
+
+use log::error;
+
+use crate::{packets, server::Server};
+
+pub const DEFAULT_TICK_RATE: f32 = 20.0;
+/// Same range vanilla's /tick rate accepts
+pub const TICK_RATE_RANGE: std::ops::RangeInclusive<f32> = 1.0..=10000.0;
+
+/// Runs the server tick loop until the server stops, meant to be given its own thread
+pub fn run(server: Arc<Server>) {
+    let tick_duration = Duration::from_secs_f32(1.0 / server.config.tick_rate);
+    let ticks_per_second = (server.config.tick_rate.round() as u64).max(1);
+    let tab_refresh_ticks = interval_ticks(server.config.tab_refresh_interval, server.config.tick_rate);
+    let keep_alive_ticks = interval_ticks(server.config.keep_alive_interval * 1000, server.config.tick_rate);
+    let mut tick_count: u64 = 0;
+    while !server.is_stopping() {
+        let start = Instant::now();
+        tick(&server, tick_count, ticks_per_second, tab_refresh_ticks);
+        if tick_count.is_multiple_of(keep_alive_ticks) {
+            send_keep_alives(&server);
+        }
+        // Everything sent this tick leaves in as few writes as possible
+        server.flush_players();
+        tick_count += 1;
+        let elapsed = start.elapsed();
+        if elapsed < tick_duration {
+            thread::sleep(tick_duration - elapsed);
+        }
+    }
+}
+
+/// Number of ticks in `millis` at the given tick rate, at least one
+pub fn interval_ticks(millis: u64, tick_rate: f32) -> u64 {
+    ((millis as f32 / 1000.0 * tick_rate).round() as u64).max(1)
+}
+
+fn tick(server: &Server, tick_count: u64, ticks_per_second: u64, tab_refresh_ticks: u64) {
+    if server.should_tick() {
+        server.advance_time();
+        server.tick_weather();
+        server.pickup_items();
+        // Players can only be hurt twice a second, same as vanilla's invulnerability time
+        if tick_count.is_multiple_of(10) {
+            server.apply_border_damage();
+        }
+    }
+    // Connections are still looked after while the world is frozen
+    server.send_chunks();
+    if tick_count.is_multiple_of(ticks_per_second) {
+        ping_players(server, tick_count as i32);
+        server.broadcast_time();
+        server.profiles().remove_expired(Instant::now());
+        let auto_save = server.config.auto_save_interval;
+        if auto_save > 0 && tick_count.is_multiple_of(auto_save * ticks_per_second) && server.blocks().is_modified() {
+            if let Err(e) = server.save_world() {
+                error!("Could not save the world!\n{e}");
+            }
+        }
+        if server.config.login_timeout > 0 {
+            server.kick_stalled_logins(Duration::from_secs(server.config.login_timeout));
+        }
+    }
+    if tick_count.is_multiple_of(tab_refresh_ticks) {
+        update_latency(server);
+    }
+}
+
+/// Keeps every player's connection alive, removing the ones that stopped answering
+fn send_keep_alives(server: &Server) {
+    let now = Instant::now();
+    let mut timed_out: Vec<u128> = Vec::new();
+    for player in server.online_players() {
+        let mut player = player.lock().unwrap();
+        match player.send_keep_alive(now) {
+            Ok(true) => (),
+            Ok(false) => timed_out.push(player.uuid()),
+            Err(e) => error!("Could not send a keep alive to {}!\n{e:?}", player.name()),
+        }
+    }
+    for uuid in timed_out {
+        server.remove_player(uuid);
+    }
+}
+
+/// Pings every player to measure their latency
+fn ping_players(server: &Server, ping_id: i32) {
+    for player in server.online_players() {
+        let _ = player.lock().unwrap().send_ping(ping_id);
+    }
+}
+
+/// Sends the last latency measurements to the tab list
+fn update_latency(server: &Server) {
+    let entries: Vec<(u128, i32)> = server.online_players().iter().map(|player| {
+        let player = player.lock().unwrap();
+        (player.uuid(), player.latency())
+    }).collect();
+    if !entries.is_empty() {
+        server.broadcast(&packets::play::player_info_latency(&entries));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::ServerConfig, server::tests::join, types::text_component::TextComponent, utils::tests::read_packet};
+
+    #[test]
+    fn intervals_round_to_whole_ticks() {
+        assert_eq!(interval_ticks(1000, 20.0), 20);
+        assert_eq!(interval_ticks(30000, 20.0), 600);
+        assert_eq!(interval_ticks(1024, 20.0), 20);
+        assert_eq!(interval_ticks(0, 20.0), 1);
+    }
+
+    #[test]
+    fn latency_is_sent_to_everyone() {
+        let server = Server::new(ServerConfig::default());
+        let (_steve, mut steve_client) = join(&server, "Steve", 1);
+        let (_alex, mut alex_client) = join(&server, "Alex", 2);
+        update_latency(&server);
+        server.flush_players();
+
+        let packet = read_packet(&mut steve_client);
+        assert_eq!(read_packet(&mut alex_client), packet);
+        let mut entries = [(1, 0), (2, 0)];
+        if packet != packets::play::player_info_latency(&entries) {
+            entries.reverse();
+            assert_eq!(packet, packets::play::player_info_latency(&entries));
+        }
+    }
+
+    #[test]
+    fn freezing_stops_the_world_until_stepped() {
+        let server = Server::new(ServerConfig::default());
+        tick(&server, 1, 20, 20);
+        assert_eq!(server.world_age(), 1);
+
+        server.set_frozen(true);
+        for tick_count in 2..10 {
+            tick(&server, tick_count, 20, 20);
+        }
+        assert_eq!(server.world_age(), 1);
+
+        server.step(3);
+        for tick_count in 10..20 {
+            tick(&server, tick_count, 20, 20);
+        }
+        assert_eq!(server.world_age(), 4);
+
+        server.set_frozen(false);
+        tick(&server, 21, 20, 20);
+        assert_eq!(server.world_age(), 5);
+    }
+
+    #[test]
+    fn configured_tick_rate_sets_the_pace() {
+        let config = ServerConfig { tick_rate: 10.0, address: "127.0.0.1:1".into(), ..ServerConfig::default() };
+        let server = Arc::new(Server::new(config));
+        let tick_server = Arc::clone(&server);
+        let handle = thread::spawn(move || run(tick_server));
+        thread::sleep(Duration::from_secs(1));
+        server.stop(&TextComponent::new("Done"));
+        handle.join().unwrap();
+        assert!((8..=12).contains(&server.world_age()), "{} ticks in a second", server.world_age());
+    }
+
+    #[test]
+    fn players_outside_the_border_are_hurt_over_ticks() {
+        let server = Server::new(ServerConfig::default());
+        server.border().new_diameter = 10.0;
+        let (player, _client) = join(&server, "Steve", 1);
+        // 2 blocks past the damage buffer takes the minimum of 1 damage
+        player.lock().unwrap().set_position(12.0, 64.0, 0.0);
+        for tick_count in 1..=30 {
+            tick(&server, tick_count, 20, 20);
+        }
+        assert_eq!(player.lock().unwrap().health(), 17.0);
+    }
+
+    #[test]
+    fn latency_is_refreshed_at_the_configured_cadence() {
+        let server = Server::new(ServerConfig::default());
+        let (_player, mut client) = join(&server, "Steve", 1);
+        // Once a second is far enough away that only the tab list refresh lands in these ticks
+        for tick_count in 1..=12 {
+            tick(&server, tick_count, 1000, 5);
+        }
+        let marker = packets::play::system_chat(&TextComponent::new("Done"), false);
+        server.broadcast(&marker);
+        server.flush_players();
+
+        let latency = packets::play::player_info_latency(&[(1, 0)]);
+        assert_eq!(read_packet(&mut client), latency);
+        assert_eq!(read_packet(&mut client), latency);
+        assert_eq!(read_packet(&mut client), marker);
+    }
+}
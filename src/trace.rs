@@ -0,0 +1,153 @@
+// Records every packet sent and received to a file so sessions can be inspected or replayed later.
+// Replay a trace against a server with `--replay <address> <file>`.
+// Each entry is a big endian u32 length followed by the direction, the state name and the unframed packet.
+
+use std::{fs::File, io::{Read, Write}, sync::{Mutex, OnceLock}};
+
+use log::{error, info};
+
+use crate::{types::reader::PacketReader, utils::write_utf8_string};
+
+static TRACE: OnceLock<Mutex<File>> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Serverbound = 0,
+    Clientbound = 1,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEntry {
+    pub direction: Direction,
+    pub state: String,
+    /// Packet id followed by its body, without the length prefix
+    pub packet: Vec<u8>,
+}
+
+impl TraceEntry {
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut body: Vec<u8> = Vec::new();
+        body.push(self.direction as u8);
+        write_utf8_string(&mut body, self.state.clone());
+        body.extend_from_slice(&self.packet);
+
+        let mut buffer = (body.len() as u32).to_be_bytes().to_vec();
+        buffer.append(&mut body);
+        buffer
+    }
+
+    fn from_bytes(body: &[u8]) -> Result<Self, &'static str> {
+        let mut reader = PacketReader::new(body);
+        let direction = match reader.read_u8()? {
+            0 => Direction::Serverbound,
+            1 => Direction::Clientbound,
+            _ => return Err("Invalid trace direction"),
+        };
+        let state = reader.read_utf8_string(32)?;
+        let packet = reader.read_bytes(reader.remaining())?.to_vec();
+        Ok(Self { direction, state, packet })
+    }
+}
+
+/// Starts recording to the file, replacing anything already in it
+pub fn init(path: &str) -> Result<(), std::io::Error> {
+    let file = File::create(path)?;
+    if TRACE.set(Mutex::new(file)).is_err() {
+        error!("Packet trace already started, ignoring {path}");
+    }
+    info!("Recording packets to {path}");
+    Ok(())
+}
+
+/// Appends a packet to the trace, does nothing unless recording was started with `init`
+pub fn record(direction: Direction, state: &str, packet: &[u8]) {
+    let Some(trace) = TRACE.get() else {
+        return;
+    };
+    let entry = TraceEntry {
+        direction,
+        state: state.to_string(),
+        packet: packet.to_vec(),
+    };
+    if let Err(e) = trace.lock().unwrap().write_all(&entry.as_bytes()) {
+        error!("Could not write to the packet trace!\n{e:?}");
+    }
+}
+
+pub fn read_entries(path: &str) -> Result<Vec<TraceEntry>, std::io::Error> {
+    let mut contents: Vec<u8> = Vec::new();
+    File::open(path)?.read_to_end(&mut contents)?;
+
+    let mut entries = Vec::new();
+    let mut position = 0;
+    while position + 4 <= contents.len() {
+        let length = u32::from_be_bytes(contents[position..position + 4].try_into().unwrap()) as usize;
+        position += 4;
+        let Some(body) = contents.get(position..position + length) else {
+            error!("Packet trace {path} ends part way through an entry");
+            break;
+        };
+        match TraceEntry::from_bytes(body) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => error!("Skipping invalid trace entry: {e}"),
+        }
+        position += length;
+    }
+    Ok(entries)
+}
+
+/// Logs every entry of a trace file
+pub fn dump(path: &str) -> Result<(), std::io::Error> {
+    for entry in read_entries(path)? {
+        let id = PacketReader::new(&entry.packet).read_ivar().unwrap_or(-1);
+        info!("{:?} {} {id:#04x}: {:?}", entry.direction, entry.state, entry.packet);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::write_ivar;
+
+    fn trace_path(name: &str) -> String {
+        std::env::temp_dir().join(format!("blahaj-trace-{name}-{}.bin", std::process::id())).to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn recorded_handshake_is_read_back() {
+        let mut handshake: Vec<u8> = Vec::new();
+        write_ivar(&mut handshake, 0x00);
+        write_ivar(&mut handshake, 767);
+        write_utf8_string(&mut handshake, "localhost".to_string());
+        handshake.extend_from_slice(&25565u16.to_be_bytes());
+        write_ivar(&mut handshake, 2);
+        let entries = vec![
+            TraceEntry { direction: Direction::Serverbound, state: "Handshake".into(), packet: handshake },
+            TraceEntry { direction: Direction::Clientbound, state: "Login".into(), packet: vec![0x02, 0x01] },
+        ];
+
+        let path = trace_path("handshake");
+        let mut file = File::create(&path).unwrap();
+        for entry in &entries {
+            file.write_all(&entry.as_bytes()).unwrap();
+        }
+        drop(file);
+        let read = read_entries(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(read, entries);
+    }
+
+    #[test]
+    fn truncated_entries_are_dropped() {
+        let entry = TraceEntry { direction: Direction::Serverbound, state: "Status".into(), packet: vec![0x00] };
+        let mut bytes = entry.as_bytes();
+        bytes.extend_from_slice(&entry.as_bytes()[..5]);
+
+        let path = trace_path("truncated");
+        File::create(&path).unwrap().write_all(&bytes).unwrap();
+        let read = read_entries(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(read, vec![entry]);
+    }
+}
@@ -0,0 +1,30 @@
+// https://wiki.vg/Protocol#Angle
+
+/// Rotation in steps of 1/256 of a full turn, as entity rotations are sent over the network
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Angle(pub u8);
+
+impl Angle {
+    pub fn from_degrees(degrees: f32) -> Self {
+        Self((degrees.rem_euclid(360.0) * 256.0 / 360.0) as i32 as u8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn degrees_are_steps_of_a_256th_turn() {
+        assert_eq!(Angle::from_degrees(0.0), Angle(0));
+        assert_eq!(Angle::from_degrees(90.0), Angle(64));
+        assert_eq!(Angle::from_degrees(180.0), Angle(128));
+    }
+
+    #[test]
+    fn degrees_wrap_around_a_full_turn() {
+        assert_eq!(Angle::from_degrees(-90.0), Angle(192));
+        assert_eq!(Angle::from_degrees(450.0), Angle(64));
+        assert_eq!(Angle::from_degrees(360.0), Angle(0));
+    }
+}
@@ -0,0 +1,60 @@
+use crate::utils::write_ivar;
+
+// https://wiki.vg/Protocol#BitSet
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BitSet {
+    longs: Vec<i64>,
+}
+
+impl BitSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&mut self, index: usize) {
+        let long = index / 64;
+        if long >= self.longs.len() {
+            self.longs.resize(long + 1, 0);
+        }
+        self.longs[long] |= 1 << (index % 64);
+    }
+
+    /// VarInt count of longs followed by the longs, trailing empty longs are left out
+    pub fn encode(&self) -> Vec<u8> {
+        let length = self.longs.iter().rposition(|long| *long != 0).map_or(0, |i| i + 1);
+        let mut buffer: Vec<u8> = Vec::new();
+        write_ivar(&mut buffer, length as i32);
+        for long in &self.longs[..length] {
+            buffer.extend_from_slice(&long.to_be_bytes());
+        }
+        buffer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_set_has_no_longs() {
+        assert_eq!(BitSet::new().encode(), vec![0]);
+    }
+
+    #[test]
+    fn single_bit_is_one_long() {
+        let mut bits = BitSet::new();
+        bits.set(3);
+        assert_eq!(bits.encode(), vec![1, 0, 0, 0, 0, 0, 0, 0, 0x08]);
+    }
+
+    #[test]
+    fn bit_past_64_is_two_longs() {
+        let mut bits = BitSet::new();
+        bits.set(65);
+        let mut expected = vec![2];
+        expected.extend_from_slice(&0i64.to_be_bytes());
+        expected.extend_from_slice(&2i64.to_be_bytes());
+        assert_eq!(bits.encode(), expected);
+    }
+}
@@ -0,0 +1,101 @@
+// Block faces and the way players are facing
+// https://wiki.vg/Protocol#Player_Action
+
+/// Ids are the same as the block faces sent by the client
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Down = 0,
+    Up = 1,
+    North = 2,
+    South = 3,
+    West = 4,
+    East = 5,
+}
+
+/// Degrees of pitch past which a player counts as looking straight up or down
+const VERTICAL_PITCH: f32 = 45.0;
+
+impl Direction {
+    pub fn from_id(id: i32) -> Option<Self> {
+        match id {
+            0 => Some(Self::Down),
+            1 => Some(Self::Up),
+            2 => Some(Self::North),
+            3 => Some(Self::South),
+            4 => Some(Self::West),
+            5 => Some(Self::East),
+            _ => None,
+        }
+    }
+
+    /// Closest horizontal direction, yaw 0 is south (+Z) and increases clockwise towards west (-X)
+    pub fn from_yaw(yaw: f32) -> Self {
+        match ((yaw / 90.0 + 0.5).floor() as i32).rem_euclid(4) {
+            0 => Self::South,
+            1 => Self::West,
+            2 => Self::North,
+            _ => Self::East,
+        }
+    }
+
+    /// Up or down when looking far enough in either, positive pitch is down
+    pub fn from_rotation(yaw: f32, pitch: f32) -> Self {
+        match pitch {
+            p if p > VERTICAL_PITCH => Self::Down,
+            p if p < -VERTICAL_PITCH => Self::Up,
+            _ => Self::from_yaw(yaw),
+        }
+    }
+
+    /// Unit step (x, y, z) in this direction
+    pub fn offset(&self) -> (i32, i32, i32) {
+        match self {
+            Self::Down => (0, -1, 0),
+            Self::Up => (0, 1, 0),
+            Self::North => (0, 0, -1),
+            Self::South => (0, 0, 1),
+            Self::West => (-1, 0, 0),
+            Self::East => (1, 0, 0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn yaw_follows_minecraft_conventions() {
+        assert_eq!(Direction::from_yaw(0.0), Direction::South);
+        assert_eq!(Direction::from_yaw(90.0), Direction::West);
+        assert_eq!(Direction::from_yaw(180.0), Direction::North);
+        assert_eq!(Direction::from_yaw(-90.0), Direction::East);
+        // Wraps around either way
+        assert_eq!(Direction::from_yaw(359.0), Direction::South);
+        assert_eq!(Direction::from_yaw(450.0), Direction::West);
+        assert_eq!(Direction::from_yaw(-181.0), Direction::North);
+        assert_eq!(Direction::from_yaw(44.0), Direction::South);
+        assert_eq!(Direction::from_yaw(46.0), Direction::West);
+    }
+
+    #[test]
+    fn steep_pitch_faces_up_or_down() {
+        assert_eq!(Direction::from_rotation(90.0, 45.0), Direction::West);
+        assert_eq!(Direction::from_rotation(90.0, 46.0), Direction::Down);
+        assert_eq!(Direction::from_rotation(90.0, -46.0), Direction::Up);
+        assert_eq!(Direction::from_rotation(0.0, -45.0), Direction::South);
+    }
+
+    #[test]
+    fn offsets_match_the_block_faces() {
+        for id in 0..6 {
+            let direction = Direction::from_id(id).unwrap();
+            assert_eq!(direction as i32, id);
+            let (x, y, z) = direction.offset();
+            assert_eq!(x.abs() + y.abs() + z.abs(), 1);
+        }
+        assert_eq!(Direction::West.offset(), (-1, 0, 0));
+        assert_eq!(Direction::South.offset(), (0, 0, 1));
+        assert_eq!(Direction::from_id(6), None);
+    }
+}
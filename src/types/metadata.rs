@@ -0,0 +1,107 @@
+use crate::types::{slot::Slot, varint::ivar};
+
+// https://wiki.vg/Entity_metadata#Entity_Metadata_Format
+
+/// Indexes of fields shared by every entity
+pub const ENTITY_FLAGS_INDEX: u8 = 0;
+pub const POSE_INDEX: u8 = 6;
+
+/// Bits of the entity flags byte
+pub const FLAG_CROUCHING: i8 = 0x02;
+pub const FLAG_SPRINTING: i8 = 0x08;
+
+const TERMINATOR: u8 = 0xFF;
+
+#[derive(Debug, Clone)]
+pub enum MetadataValue {
+    Byte(i8),
+    Slot(Slot),
+    Pose(Pose),
+}
+
+impl MetadataValue {
+    fn type_id(&self) -> i32 {
+        match self {
+            Self::Byte(_) => 0,
+            Self::Slot(_) => 7,
+            Self::Pose(_) => 21,
+        }
+    }
+
+    fn as_bytes(&self) -> Vec<u8> {
+        let mut buffer: Vec<u8> = Vec::new();
+        match self {
+            Self::Byte(value) => buffer.push(*value as u8),
+            Self::Slot(slot) => buffer.append(&mut slot.as_bytes()),
+            Self::Pose(pose) => buffer.append(&mut ivar::new(*pose as i32).as_bytes()),
+        }
+        buffer
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pose {
+    Standing = 0,
+    Sneaking = 5,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Metadata {
+    entries: Vec<(u8, MetadataValue)>,
+}
+
+impl Metadata {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the field at the given index, replacing any previous value
+    pub fn set(&mut self, index: u8, value: MetadataValue) {
+        match self.entries.iter_mut().find(|(i, _)| *i == index) {
+            Some(entry) => entry.1 = value,
+            None => self.entries.push((index, value)),
+        }
+    }
+
+    pub fn get(&self, index: u8) -> Option<&MetadataValue> {
+        self.entries.iter().find(|(i, _)| *i == index).map(|(_, value)| value)
+    }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut buffer: Vec<u8> = Vec::new();
+        for (index, value) in &self.entries {
+            buffer.push(*index);
+            buffer.append(&mut ivar::new(value.type_id()).as_bytes());
+            buffer.append(&mut value.as_bytes());
+        }
+        buffer.push(TERMINATOR);
+        buffer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_index_type_and_value_then_terminator() {
+        let mut metadata = Metadata::new();
+        metadata.set(ENTITY_FLAGS_INDEX, MetadataValue::Byte(FLAG_CROUCHING));
+        metadata.set(POSE_INDEX, MetadataValue::Pose(Pose::Sneaking));
+        assert_eq!(metadata.as_bytes(), vec![0, 0, 0x02, 6, 21, 5, 0xFF]);
+    }
+
+    #[test]
+    fn setting_an_index_again_replaces_it() {
+        let mut metadata = Metadata::new();
+        metadata.set(POSE_INDEX, MetadataValue::Pose(Pose::Sneaking));
+        metadata.set(POSE_INDEX, MetadataValue::Pose(Pose::Standing));
+        assert!(matches!(metadata.get(POSE_INDEX), Some(MetadataValue::Pose(Pose::Standing))));
+        assert_eq!(metadata.as_bytes(), vec![6, 21, 0, 0xFF]);
+    }
+
+    #[test]
+    fn empty_metadata_is_only_the_terminator() {
+        assert_eq!(Metadata::new().as_bytes(), vec![0xFF]);
+    }
+}
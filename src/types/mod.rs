@@ -1 +1,10 @@
 pub mod varint;
+pub mod metadata;
+pub mod text_component;
+pub mod reader;
+pub mod bitset;
+pub mod varlong;
+pub mod position;
+pub mod slot;
+pub mod angle;
+pub mod direction;
@@ -0,0 +1,31 @@
+// https://wiki.vg/Protocol#Position
+
+/// Block position, sent over the network packed into a single long
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Position {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl Position {
+    pub fn new(x: i32, y: i32, z: i32) -> Self {
+        Self { x, y, z }
+    }
+
+    pub fn encode(&self) -> i64 {
+        ((self.x as i64 & 0x3FFFFFF) << 38) | ((self.z as i64 & 0x3FFFFFF) << 12) | (self.y as i64 & 0xFFF)
+    }
+
+    pub fn decode(value: i64) -> Self {
+        Self {
+            x: (value >> 38) as i32,
+            y: (value << 52 >> 52) as i32,
+            z: (value << 26 >> 38) as i32,
+        }
+    }
+
+    pub fn as_bytes(&self) -> [u8; 8] {
+        self.encode().to_be_bytes()
+    }
+}
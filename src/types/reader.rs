@@ -0,0 +1,102 @@
+use crate::types::{position::Position, varint::ivar, varlong::VarLong};
+
+/// Cursor over a received packet body, every read advances past the value
+pub struct PacketReader<'a> {
+    buffer: &'a [u8],
+    position: usize,
+}
+
+impl<'a> PacketReader<'a> {
+    pub fn new(buffer: &'a [u8]) -> Self {
+        Self {
+            buffer,
+            position: 0,
+        }
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.buffer.len() - self.position
+    }
+
+    pub fn read_bytes(&mut self, length: usize) -> Result<&'a [u8], &'static str> {
+        if self.remaining() < length {
+            return Err("Packet ended unexpectedly");
+        }
+        let bytes = &self.buffer[self.position..self.position + length];
+        self.position += length;
+        Ok(bytes)
+    }
+
+    pub fn read_ivar(&mut self) -> Result<i32, &'static str> {
+        let value = match ivar::read(&self.buffer[self.position..]) {
+            Ok(v) => v,
+            Err(_) => return Err("Could not read VarInt"),
+        };
+        self.position += value.length();
+        Ok(value.value)
+    }
+
+    pub fn read_lvar(&mut self) -> Result<i64, &'static str> {
+        let value = match VarLong::read(&self.buffer[self.position..]) {
+            Ok(v) => v,
+            Err(_) => return Err("Could not read VarLong"),
+        };
+        self.position += value.length();
+        Ok(value.value)
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, &'static str> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    pub fn read_bool(&mut self) -> Result<bool, &'static str> {
+        match self.read_u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err("Invalid boolean"),
+        }
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16, &'static str> {
+        Ok(u16::from_be_bytes(self.read_bytes(2)?.try_into().unwrap()))
+    }
+
+    pub fn read_i16(&mut self) -> Result<i16, &'static str> {
+        Ok(i16::from_be_bytes(self.read_bytes(2)?.try_into().unwrap()))
+    }
+
+    pub fn read_i32(&mut self) -> Result<i32, &'static str> {
+        Ok(i32::from_be_bytes(self.read_bytes(4)?.try_into().unwrap()))
+    }
+
+    pub fn read_i64(&mut self) -> Result<i64, &'static str> {
+        Ok(i64::from_be_bytes(self.read_bytes(8)?.try_into().unwrap()))
+    }
+
+    pub fn read_f32(&mut self) -> Result<f32, &'static str> {
+        Ok(f32::from_be_bytes(self.read_bytes(4)?.try_into().unwrap()))
+    }
+
+    pub fn read_f64(&mut self) -> Result<f64, &'static str> {
+        Ok(f64::from_be_bytes(self.read_bytes(8)?.try_into().unwrap()))
+    }
+
+    pub fn read_u128(&mut self) -> Result<u128, &'static str> {
+        Ok(u128::from_be_bytes(self.read_bytes(16)?.try_into().unwrap()))
+    }
+
+    pub fn read_position(&mut self) -> Result<Position, &'static str> {
+        Ok(Position::decode(self.read_i64()?))
+    }
+
+    pub fn read_utf8_string(&mut self, max_length: usize) -> Result<String, &'static str> {
+        let length = self.read_ivar()?;
+        if length < 0 || length as usize > max_length * 4 {
+            return Err("String length out of range");
+        }
+        match String::from_utf8(self.read_bytes(length as usize)?.to_vec()) {
+            Ok(s) => Ok(s),
+            Err(_) => Err("Invalid UTF-8 string"),
+        }
+    }
+}
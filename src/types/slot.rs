@@ -0,0 +1,64 @@
+// https://wiki.vg/Slot_Data
+
+use crate::{types::reader::PacketReader, utils::write_ivar};
+
+/// Item stack in an inventory slot, a count of zero is an empty slot.
+/// Data components aren't supported yet, stacks are always sent without any.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Slot {
+    pub count: i32,
+    pub item_id: i32,
+}
+
+impl Slot {
+    pub const EMPTY: Slot = Slot { count: 0, item_id: 0 };
+
+    pub fn new(item_id: i32, count: i32) -> Self {
+        Self { count, item_id }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count <= 0
+    }
+
+    pub fn read(reader: &mut PacketReader) -> Result<Self, &'static str> {
+        let count = reader.read_ivar()?;
+        if count <= 0 {
+            return Ok(Self::EMPTY);
+        }
+        let item_id = reader.read_ivar()?;
+        let added = reader.read_ivar()?;
+        let removed = reader.read_ivar()?;
+        if added != 0 || removed != 0 {
+            return Err("Slot data components are not supported");
+        }
+        Ok(Self { count, item_id })
+    }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut buffer: Vec<u8> = Vec::new();
+        if self.is_empty() {
+            write_ivar(&mut buffer, 0);
+            return buffer;
+        }
+        write_ivar(&mut buffer, self.count);
+        write_ivar(&mut buffer, self.item_id);
+        write_ivar(&mut buffer, 0);
+        write_ivar(&mut buffer, 0);
+        buffer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stacks_round_trip_and_empty_slots_are_a_zero_count() {
+        let stack = Slot::new(1, 64);
+        assert_eq!(Slot::read(&mut PacketReader::new(&stack.as_bytes())).unwrap(), stack);
+        assert_eq!(Slot::EMPTY.as_bytes(), [0]);
+        assert_eq!(Slot::read(&mut PacketReader::new(&[0])).unwrap(), Slot::EMPTY);
+        assert!(Slot::read(&mut PacketReader::new(&[1, 1, 1, 0])).is_err());
+    }
+}
@@ -0,0 +1,74 @@
+use fastnbt::SerOpts;
+use serde::Serialize;
+
+// https://wiki.vg/Text_formatting#Text_components
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TextComponent {
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub translate: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub with: Vec<TextComponent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bold: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub italic: Option<bool>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub extra: Vec<TextComponent>,
+}
+
+impl TextComponent {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn translate(key: impl Into<String>, with: Vec<TextComponent>) -> Self {
+        Self {
+            translate: Some(key.into()),
+            with,
+            ..Default::default()
+        }
+    }
+
+    pub fn color(mut self, color: impl Into<String>) -> Self {
+        self.color = Some(color.into());
+        self
+    }
+
+    /// JSON form, used by status and the Login state
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    /// Nameless root network NBT form, used by Configuration and Play since 1.20.3
+    pub fn to_nbt_bytes(&self) -> Vec<u8> {
+        fastnbt::to_bytes_with_opts(self, SerOpts::network_nbt()).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nbt_is_a_nameless_root_compound() {
+        let expected = vec![
+            0x0A, // Compound, with no name in network NBT
+            0x08, 0x00, 0x04, b't', b'e', b'x', b't', 0x00, 0x02, b'h', b'i',
+            0x00, // End
+        ];
+        assert_eq!(TextComponent::new("hi").to_nbt_bytes(), expected);
+    }
+
+    #[test]
+    fn json_skips_unset_fields() {
+        assert_eq!(TextComponent::new("hi").to_json(), r#"{"text":"hi"}"#);
+    }
+}
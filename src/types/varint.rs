@@ -50,7 +50,8 @@ impl VarInt {
     }
     
     pub fn as_bytes(&self) -> Vec<u8> {
-        let mut value = self.value as u64;
+        // Negative values are sent as their two's complement, always 5 bytes
+        let mut value = self.value as u32;
         let mut bytes: Vec<u8> = Vec::new();
         loop {
             let byte = (value & 0x7f) as u8;
@@ -95,3 +96,21 @@ pub enum VarIntEncodeError {
     TooLarge,
     OutOfRange,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negative_values_take_five_bytes() {
+        assert_eq!(ivar::new(-1).as_bytes(), vec![0xFF, 0xFF, 0xFF, 0xFF, 0x0F]);
+        assert_eq!(ivar::read(&ivar::new(-3).as_bytes()).unwrap().value, -3);
+        assert_eq!(ivar::new(i32::MIN).length(), 5);
+    }
+
+    #[test]
+    fn small_values_take_one_byte() {
+        assert_eq!(ivar::new(0).as_bytes(), vec![0]);
+        assert_eq!(ivar::new(300).as_bytes(), vec![0xAC, 0x02]);
+    }
+}
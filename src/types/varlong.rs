@@ -0,0 +1,80 @@
+use log::error;
+
+use crate::types::varint::VarIntDecodeError;
+
+#[derive(Debug)]
+pub struct VarLong {
+    pub value: i64,
+    pub bytes: Vec<u8>,
+}
+
+impl VarLong {
+    // Max number of bytes that a VarLong can be when read or written to Minecraft
+    const MAX_SIZE: usize = 10;
+
+    pub fn new(value: i64) -> Self {
+        let mut x = Self {
+            value,
+            bytes: Vec::new(),
+        };
+        x.bytes = x.as_bytes();
+        x
+    }
+
+    /// How many bytes the variable long takes up
+    pub fn length(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn read(bytes: &[u8]) -> Result<Self, VarIntDecodeError> {
+        let mut val: i64 = 0;
+        for i in 0..Self::MAX_SIZE {
+            let byte = match bytes.get(i) {
+                Some(b) => b,
+                None => {
+                    error!("VarLong decode out of range!");
+                    return Err(VarIntDecodeError::OutOfRange);
+                }
+            };
+            val |= (i64::from(*byte) & 0b01111111) << (i * 7);
+            if byte & 0b10000000 == 0 {
+                return Ok(VarLong::new(val));
+            }
+        }
+        Err(VarIntDecodeError::TooLarge)
+    }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut value = self.value as u64;
+        let mut bytes: Vec<u8> = Vec::new();
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+
+            if value == 0 {
+                bytes.push(byte);
+                break;
+            }
+            bytes.push(byte | 0x80);
+        }
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_back_what_it_writes() {
+        for value in [0, 1, 300, i32::MAX as i64 + 1, i64::MAX, -1, i64::MIN] {
+            assert_eq!(VarLong::read(&VarLong::new(value).as_bytes()).unwrap().value, value);
+        }
+    }
+
+    #[test]
+    fn negative_values_take_ten_bytes() {
+        assert_eq!(VarLong::new(-1).length(), 10);
+        assert_eq!(VarLong::new(300).as_bytes(), vec![0xAC, 0x02]);
+    }
+}
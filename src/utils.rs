@@ -1,4 +1,6 @@
-use crate::types::varint::{ivar, VarIntDecodeError};
+use std::io::Write;
+
+use crate::types::{varint::{ivar, VarIntDecodeError}, varlong::VarLong};
 
 pub fn read_packet_id(buffer: &[u8]) -> Result<ivar, VarIntDecodeError> {
     ivar::read(buffer)
@@ -9,8 +11,169 @@ pub fn write_ivar(buffer: &mut Vec<u8>, id: i32) {
     buffer.append(&mut data);
 }
 
+pub fn write_lvar(buffer: &mut Vec<u8>, value: i64) {
+    let mut data = VarLong::new(value).as_bytes();
+    buffer.append(&mut data);
+}
+
 pub fn write_utf8_string(buffer: &mut Vec<u8>, text: String) {
     let mut data: Vec<u8> = text.into_bytes();
     buffer.extend_from_slice(&ivar::new(data.len() as i32).as_bytes());
     buffer.append(&mut data);
 }
+
+/// Writes a packet (id + data) prefixed with its length.
+/// This is the uncompressed framing used before Set Compression, so always for Handshake and Status.
+/// Once compression is on packets are framed by `compression::PacketCompression` instead.
+pub fn write_packet(stream: &mut impl Write, packet: &[u8]) -> Result<(), std::io::Error> {
+    let mut buffer = ivar::new(packet.len() as i32).as_bytes();
+    buffer.extend_from_slice(packet);
+    stream.write_all(&buffer)
+}
+
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encodes as standard padded base64
+pub fn encode_base64(data: &[u8]) -> String {
+    let mut text = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bits = (chunk[0] as u32) << 16 | (*chunk.get(1).unwrap_or(&0) as u32) << 8 | *chunk.get(2).unwrap_or(&0) as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                text.push(BASE64_ALPHABET[(bits >> (18 - i * 6)) as usize & 0x3F] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
+
+/// Decodes standard padded base64, None if it contains anything outside the alphabet
+pub fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let mut bytes: Vec<u8> = Vec::new();
+    let mut bits: u32 = 0;
+    let mut bit_count = 0;
+    for c in text.bytes().filter(|c| *c != b'=') {
+        let value = BASE64_ALPHABET.iter().position(|a| *a == c)? as u32;
+        bits = (bits << 6) | value;
+        bit_count += 6;
+        if bit_count >= 8 {
+            bit_count -= 8;
+            bytes.push((bits >> bit_count) as u8);
+            bits &= (1 << bit_count) - 1;
+        }
+    }
+    Some(bytes)
+}
+
+const SHA256_K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// SHA-256 digest, only used for small inputs such as the world seed
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut state: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+    ];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for block in message.chunks(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes(word.try_into().unwrap());
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = h.wrapping_add(s1).wrapping_add(ch).wrapping_add(SHA256_K[i]).wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (s, v) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *s = s.wrapping_add(v);
+        }
+    }
+
+    let mut digest = [0u8; 32];
+    for (i, s) in state.iter().enumerate() {
+        digest[i * 4..i * 4 + 4].copy_from_slice(&s.to_be_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::{io::Read, net::{TcpListener, TcpStream}, thread, time::Duration};
+
+    /// Server and client ends of a loopback connection
+    pub fn socket_pair() -> (TcpStream, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        (server, client)
+    }
+
+    /// Reads everything the server sends on a background thread until it closes the connection,
+    /// so large writes never block on a full socket buffer
+    pub fn drain(mut client: TcpStream) -> thread::JoinHandle<Vec<u8>> {
+        thread::spawn(move || {
+            let mut received = Vec::new();
+            let _ = client.read_to_end(&mut received);
+            received
+        })
+    }
+
+    /// Reads one uncompressed frame sent to the client, returning the packet id and data
+    pub fn read_packet(client: &mut TcpStream) -> Vec<u8> {
+        client.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let mut length: usize = 0;
+        for shift in (0..35).step_by(7) {
+            let mut byte = [0u8];
+            client.read_exact(&mut byte).unwrap();
+            length |= ((byte[0] & 0x7F) as usize) << shift;
+            if byte[0] & 0x80 == 0 {
+                break;
+            }
+        }
+        let mut packet = vec![0; length];
+        client.read_exact(&mut packet).unwrap();
+        packet
+    }
+
+    #[test]
+    fn base64_round_trips_with_and_without_padding() {
+        for data in [&b""[..], b"f", b"fo", b"foo", b"\x00\xFF\x10blahaj"] {
+            assert_eq!(super::decode_base64(&super::encode_base64(data)).unwrap(), data);
+        }
+        assert_eq!(super::encode_base64(b"fo"), "Zm8=");
+        assert!(super::decode_base64("not base64!").is_none());
+    }
+}
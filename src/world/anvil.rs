@@ -0,0 +1,257 @@
+// Anvil region files, each holding the NBT of a 32x32 area of chunks.
+// The file starts with a table of where each chunk is stored and one of when it was last saved,
+// chunks follow in 4KiB sectors.
+// https://minecraft.wiki/w/Region_file_format
+
+use std::{fs::{File, OpenOptions}, io::{Read, Seek, SeekFrom, Write}, time::{SystemTime, UNIX_EPOCH}};
+
+use flate2::{read::{GzDecoder, ZlibDecoder}, write::ZlibEncoder, Compression};
+use serde::Serialize;
+
+pub const SECTOR_SIZE: usize = 4096;
+/// The location table and the timestamp table
+const HEADER_SECTORS: u32 = 2;
+const CHUNKS_PER_REGION: usize = 1024;
+/// A chunk's sector count is stored in one byte, vanilla moves anything larger to its own file
+pub const MAX_CHUNK_SECTORS: usize = 255;
+
+const COMPRESSION_GZIP: u8 = 1;
+const COMPRESSION_ZLIB: u8 = 2;
+const COMPRESSION_NONE: u8 = 3;
+
+#[derive(Debug)]
+pub enum AnvilError {
+    Io(std::io::Error),
+    Nbt(fastnbt::error::Error),
+    /// Bytes the compressed chunk would take up, over the roughly 1MB that fits in a region file
+    ChunkTooLarge(usize),
+    UnsupportedCompression(u8),
+    Corrupt(&'static str),
+}
+
+impl std::fmt::Display for AnvilError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "Could not access the region file: {e}"),
+            Self::Nbt(e) => write!(f, "Could not serialize the chunk: {e}"),
+            Self::ChunkTooLarge(size) => write!(f, "Chunk is {size} bytes, at most {} fit in a region file", MAX_CHUNK_SECTORS * SECTOR_SIZE),
+            Self::UnsupportedCompression(kind) => write!(f, "Unsupported chunk compression type {kind}"),
+            Self::Corrupt(reason) => write!(f, "Corrupt region file: {reason}"),
+        }
+    }
+}
+
+impl From<std::io::Error> for AnvilError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// Name of the region file holding the chunk, such as r.0.-1.mca
+pub fn region_file_name(chunk_x: i32, chunk_z: i32) -> String {
+    format!("r.{}.{}.mca", chunk_x >> 5, chunk_z >> 5)
+}
+
+pub struct RegionFile {
+    file: File,
+    /// Sector offset in the upper 3 bytes and sector count in the lowest, 0 if the chunk isn't saved
+    locations: [u32; CHUNKS_PER_REGION],
+    /// Seconds since the epoch each chunk was last written
+    timestamps: [u32; CHUNKS_PER_REGION],
+    compression: Compression,
+}
+
+impl RegionFile {
+    /// Opens the region file, creating it with an empty header if it doesn't exist
+    pub fn open(path: &str, compression_level: u32) -> Result<Self, AnvilError> {
+        let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path)?;
+        let header_size = HEADER_SECTORS as u64 * SECTOR_SIZE as u64;
+        if file.metadata()?.len() < header_size {
+            file.set_len(header_size)?;
+        }
+        let mut header = vec![0u8; header_size as usize];
+        file.seek(SeekFrom::Start(0))?;
+        file.read_exact(&mut header)?;
+        let mut locations = [0u32; CHUNKS_PER_REGION];
+        let mut timestamps = [0u32; CHUNKS_PER_REGION];
+        for i in 0..CHUNKS_PER_REGION {
+            locations[i] = u32::from_be_bytes(header[i * 4..i * 4 + 4].try_into().unwrap());
+            let timestamp = SECTOR_SIZE + i * 4;
+            timestamps[i] = u32::from_be_bytes(header[timestamp..timestamp + 4].try_into().unwrap());
+        }
+        Ok(Self {
+            file,
+            locations,
+            timestamps,
+            compression: Compression::new(compression_level),
+        })
+    }
+
+    /// Index into the header tables, chunk coordinates can be world or region relative
+    fn index(chunk_x: i32, chunk_z: i32) -> usize {
+        (chunk_x.rem_euclid(32) + chunk_z.rem_euclid(32) * 32) as usize
+    }
+
+    pub fn has_chunk(&self, chunk_x: i32, chunk_z: i32) -> bool {
+        self.locations[Self::index(chunk_x, chunk_z)] != 0
+    }
+
+    /// Serializes the chunk and stores it zlib compressed, in place if it still fits in its old sectors
+    /// and otherwise in the first gap large enough or at the end of the file
+    pub fn write_chunk<T: Serialize>(&mut self, chunk_x: i32, chunk_z: i32, nbt: &T) -> Result<(), AnvilError> {
+        let data = fastnbt::to_bytes(nbt).map_err(AnvilError::Nbt)?;
+        let mut encoder = ZlibEncoder::new(Vec::new(), self.compression);
+        encoder.write_all(&data)?;
+        let compressed = encoder.finish()?;
+
+        // Length includes the compression type byte that follows it
+        let mut payload: Vec<u8> = Vec::with_capacity(compressed.len() + 5);
+        payload.extend_from_slice(&(compressed.len() as u32 + 1).to_be_bytes());
+        payload.push(COMPRESSION_ZLIB);
+        payload.extend_from_slice(&compressed);
+        let sectors = payload.len().div_ceil(SECTOR_SIZE);
+        if sectors > MAX_CHUNK_SECTORS {
+            return Err(AnvilError::ChunkTooLarge(payload.len()));
+        }
+        payload.resize(sectors * SECTOR_SIZE, 0);
+
+        let index = Self::index(chunk_x, chunk_z);
+        let offset = self.allocate(index, sectors as u32)?;
+        self.file.seek(SeekFrom::Start(offset as u64 * SECTOR_SIZE as u64))?;
+        self.file.write_all(&payload)?;
+
+        self.locations[index] = offset << 8 | sectors as u32;
+        self.timestamps[index] = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as u32).unwrap_or(0);
+        self.file.seek(SeekFrom::Start(index as u64 * 4))?;
+        self.file.write_all(&self.locations[index].to_be_bytes())?;
+        self.file.seek(SeekFrom::Start((SECTOR_SIZE + index * 4) as u64))?;
+        self.file.write_all(&self.timestamps[index].to_be_bytes())?;
+        self.file.flush()?;
+        Ok(())
+    }
+
+    /// First sector of a run of `sectors` free sectors, the chunk's current sectors count as free
+    fn allocate(&self, index: usize, sectors: u32) -> Result<u32, AnvilError> {
+        let current = self.locations[index];
+        if current != 0 && current & 0xFF >= sectors {
+            return Ok(current >> 8);
+        }
+        let file_sectors = (self.file.metadata()?.len() as usize).div_ceil(SECTOR_SIZE) as u32;
+        let mut used = vec![false; file_sectors as usize];
+        used[..HEADER_SECTORS as usize].fill(true);
+        for (i, location) in self.locations.iter().enumerate() {
+            if i == index || *location == 0 {
+                continue;
+            }
+            let (start, count) = (location >> 8, location & 0xFF);
+            for sector in start..(start + count).min(file_sectors) {
+                used[sector as usize] = true;
+            }
+        }
+        let mut run_start = 0;
+        let mut run_length = 0;
+        for (sector, in_use) in used.iter().enumerate() {
+            if *in_use {
+                run_length = 0;
+                continue;
+            }
+            if run_length == 0 {
+                run_start = sector as u32;
+            }
+            run_length += 1;
+            if run_length == sectors {
+                return Ok(run_start);
+            }
+        }
+        // Grow the file, reusing any free sectors at its end
+        Ok(match run_length {
+            0 => file_sectors,
+            _ => run_start,
+        })
+    }
+
+    /// Uncompressed NBT of the chunk, None if it hasn't been saved
+    pub fn read_chunk(&mut self, chunk_x: i32, chunk_z: i32) -> Result<Option<Vec<u8>>, AnvilError> {
+        let location = self.locations[Self::index(chunk_x, chunk_z)];
+        if location == 0 {
+            return Ok(None);
+        }
+        let (offset, sectors) = ((location >> 8) as u64, (location & 0xFF) as usize);
+        if offset < HEADER_SECTORS as u64 {
+            return Err(AnvilError::Corrupt("chunk overlaps the header"));
+        }
+        self.file.seek(SeekFrom::Start(offset * SECTOR_SIZE as u64))?;
+        let mut header = [0u8; 5];
+        self.file.read_exact(&mut header)?;
+        let length = u32::from_be_bytes(header[..4].try_into().unwrap()) as usize;
+        if length == 0 || length + 4 > sectors * SECTOR_SIZE {
+            return Err(AnvilError::Corrupt("chunk length doesn't fit its sectors"));
+        }
+        let mut compressed = vec![0u8; length - 1];
+        self.file.read_exact(&mut compressed)?;
+
+        let mut data: Vec<u8> = Vec::new();
+        match header[4] {
+            COMPRESSION_GZIP => GzDecoder::new(compressed.as_slice()).read_to_end(&mut data)?,
+            COMPRESSION_ZLIB => ZlibDecoder::new(compressed.as_slice()).read_to_end(&mut data)?,
+            COMPRESSION_NONE => {
+                data = compressed;
+                data.len()
+            },
+            kind => return Err(AnvilError::UnsupportedCompression(kind)),
+        };
+        Ok(Some(data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temporary_region(name: &str) -> String {
+        std::env::temp_dir().join(format!("blahaj-{name}-{}.mca", std::process::id())).to_string_lossy().to_string()
+    }
+
+    #[derive(Serialize)]
+    struct TestChunk {
+        #[serde(rename = "xPos")]
+        x: i32,
+        data: fastnbt::LongArray,
+    }
+
+    #[test]
+    fn written_chunk_reads_back() {
+        let path = temporary_region("round-trip");
+        let chunk = TestChunk { x: -33, data: fastnbt::LongArray::new((0..100).collect()) };
+        let mut region = RegionFile::open(&path, 6).unwrap();
+        assert!(!region.has_chunk(-33, 70));
+        region.write_chunk(-33, 70, &chunk).unwrap();
+        assert!(region.has_chunk(-33, 70));
+        assert!(region.timestamps[RegionFile::index(-33, 70)] > 0);
+
+        // Reopened so the header is read back from the file too
+        let mut region = RegionFile::open(&path, 6).unwrap();
+        assert_eq!(region.read_chunk(-33, 70).unwrap(), Some(fastnbt::to_bytes(&chunk).unwrap()));
+        assert_eq!(region.read_chunk(0, 0).unwrap(), None);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn chunks_move_when_they_outgrow_their_sectors() {
+        let path = temporary_region("allocate");
+        let mut region = RegionFile::open(&path, 6).unwrap();
+        region.file.set_len(6 * SECTOR_SIZE as u64).unwrap();
+        region.locations[0] = 2 << 8 | 1;
+        region.locations[1] = 3 << 8 | 2;
+        // Shrinking or staying the same size keeps the chunk in place
+        assert_eq!(region.allocate(1, 1).unwrap(), 3);
+        // Too large for its own sector and the gap after the second chunk, so the file grows
+        assert_eq!(region.allocate(0, 2).unwrap(), 5);
+        assert_eq!(region.allocate(0, 1).unwrap(), 2);
+        // A chunk's own sectors count as free when it moves
+        region.locations[1] = 0;
+        assert_eq!(region.allocate(0, 3).unwrap(), 2);
+        let _ = std::fs::remove_file(&path);
+    }
+}
+
@@ -0,0 +1,167 @@
+// Paces chunk sending with Chunk Batch Start/Finished, the client replies with how many chunks a tick it can take
+// https://wiki.vg/Protocol#Chunk_Batch_Received
+
+use std::collections::VecDeque;
+
+/// Rate used until the client first reports one, same as vanilla
+pub const DEFAULT_CHUNKS_PER_TICK: f32 = 9.0;
+const MIN_CHUNKS_PER_TICK: f32 = 0.01;
+const MAX_CHUNKS_PER_TICK: f32 = 64.0;
+/// Batches that can be in flight once the client has acknowledged its first one, same as vanilla
+pub const DEFAULT_MAX_BATCHES: u32 = 10;
+
+pub struct ChunkBatcher {
+    pending: VecDeque<(i32, i32)>,
+    chunks_per_tick: f32,
+    /// Chunks that can be sent, builds up by `chunks_per_tick` every tick
+    quota: f32,
+    unacknowledged: u32,
+    max_unacknowledged: u32,
+    /// Most batches in flight once the client is known to be keeping up
+    max_batches: u32,
+}
+
+impl Default for ChunkBatcher {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_BATCHES)
+    }
+}
+
+impl ChunkBatcher {
+    /// Sending pauses while `max_batches` batches are waiting on a Chunk Batch Received, at least one
+    pub fn new(max_batches: u32) -> Self {
+        Self {
+            pending: VecDeque::new(),
+            chunks_per_tick: DEFAULT_CHUNKS_PER_TICK,
+            quota: 0.0,
+            unacknowledged: 0,
+            // Only one batch until the client has said how fast it is
+            max_unacknowledged: 1,
+            max_batches: max_batches.max(1),
+        }
+    }
+
+    pub fn max_batches(&self) -> u32 {
+        self.max_batches
+    }
+
+    pub fn chunks_per_tick(&self) -> f32 {
+        self.chunks_per_tick
+    }
+
+    /// Queues chunks to send, closest to the center first
+    pub fn queue(&mut self, chunks: Vec<(i32, i32)>, center: (i32, i32)) {
+        self.pending.extend(chunks);
+        let distance = |(x, z): &(i32, i32)| (x - center.0).abs().max((z - center.1).abs());
+        self.pending.make_contiguous().sort_by_key(distance);
+    }
+
+    /// Drops chunks that are no longer in view before they were sent
+    pub fn cancel(&mut self, chunks: &[(i32, i32)]) {
+        self.pending.retain(|chunk| !chunks.contains(chunk));
+    }
+
+    /// Chunks to send this tick, empty while the client is still catching up
+    pub fn next_batch(&mut self) -> Vec<(i32, i32)> {
+        if self.pending.is_empty() || self.unacknowledged >= self.max_unacknowledged {
+            return Vec::new();
+        }
+        self.quota = (self.quota + self.chunks_per_tick).min(self.chunks_per_tick.max(1.0));
+        let count = (self.quota.floor() as usize).min(self.pending.len());
+        if count == 0 {
+            return Vec::new();
+        }
+        self.quota -= count as f32;
+        self.unacknowledged += 1;
+        self.pending.drain(..count).collect()
+    }
+
+    /// Handles Chunk Batch Received with the rate the client would like chunks at
+    pub fn batch_received(&mut self, chunks_per_tick: f32) {
+        self.unacknowledged = self.unacknowledged.saturating_sub(1);
+        self.chunks_per_tick = match chunks_per_tick.is_nan() {
+            true => MIN_CHUNKS_PER_TICK,
+            false => chunks_per_tick.clamp(MIN_CHUNKS_PER_TICK, MAX_CHUNKS_PER_TICK),
+        };
+        if self.unacknowledged == 0 {
+            self.quota = self.chunks_per_tick;
+        }
+        self.max_unacknowledged = self.max_batches;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn closest_chunks_are_sent_first() {
+        let mut batcher = ChunkBatcher::new(4);
+        batcher.queue(vec![(3, 0), (0, 0), (-1, 2), (1, 1)], (0, 0));
+        batcher.cancel(&[(-1, 2)]);
+        assert_eq!(batcher.next_batch(), vec![(0, 0), (1, 1), (3, 0)]);
+    }
+
+    #[test]
+    fn one_batch_is_sent_until_the_client_answers() {
+        let mut batcher = ChunkBatcher::new(4);
+        batcher.queue((0..40).map(|x| (x, 0)).collect(), (0, 0));
+        assert_eq!(batcher.next_batch().len(), DEFAULT_CHUNKS_PER_TICK as usize);
+        assert!(batcher.next_batch().is_empty());
+
+        batcher.batch_received(2.0);
+        assert_eq!(batcher.chunks_per_tick(), 2.0);
+        assert_eq!(batcher.next_batch().len(), 2);
+        // More batches can be in flight now the client has answered
+        assert_eq!(batcher.next_batch().len(), 2);
+    }
+
+    #[test]
+    fn slow_clients_get_a_chunk_every_few_ticks() {
+        let mut batcher = ChunkBatcher::new(4);
+        batcher.queue((0..40).map(|x| (x, 0)).collect(), (0, 0));
+        batcher.next_batch();
+        batcher.batch_received(0.5);
+        assert_eq!(batcher.next_batch().len(), 1);
+        assert_eq!(batcher.next_batch().len(), 0);
+        assert_eq!(batcher.next_batch().len(), 1);
+        assert_eq!(batcher.next_batch().len(), 0);
+    }
+
+    #[test]
+    fn received_rates_are_clamped() {
+        let mut batcher = ChunkBatcher::new(0);
+        assert_eq!(batcher.max_batches(), 1);
+        batcher.batch_received(f32::NAN);
+        assert_eq!(batcher.chunks_per_tick(), MIN_CHUNKS_PER_TICK);
+        batcher.batch_received(1000.0);
+        assert_eq!(batcher.chunks_per_tick(), MAX_CHUNKS_PER_TICK);
+    }
+
+    #[test]
+    fn a_cap_of_one_waits_for_every_batch() {
+        let mut batcher = ChunkBatcher::new(1);
+        batcher.queue((0..40).map(|x| (x, 0)).collect(), (0, 0));
+        assert!(!batcher.next_batch().is_empty());
+        batcher.batch_received(4.0);
+        assert_eq!(batcher.next_batch().len(), 4);
+        // Still waiting on the second batch
+        assert!(batcher.next_batch().is_empty());
+        assert!(batcher.next_batch().is_empty());
+        batcher.batch_received(4.0);
+        assert_eq!(batcher.next_batch().len(), 4);
+    }
+
+    #[test]
+    fn sending_resumes_below_the_cap() {
+        let mut batcher = ChunkBatcher::new(2);
+        batcher.queue((0..40).map(|x| (x, 0)).collect(), (0, 0));
+        batcher.next_batch();
+        batcher.batch_received(1.0);
+        assert_eq!(batcher.next_batch().len(), 1);
+        assert_eq!(batcher.next_batch().len(), 1);
+        assert!(batcher.next_batch().is_empty());
+        batcher.batch_received(1.0);
+        assert_eq!(batcher.next_batch().len(), 1);
+    }
+}
@@ -0,0 +1,56 @@
+use fastnbt::SerOpts;
+use serde::Serialize;
+
+use crate::types::text_component::TextComponent;
+
+// https://minecraft.wiki/w/Sign#Block_data
+
+pub enum BlockEntity {
+    Sign(SignBlockEntity),
+}
+
+impl BlockEntity {
+    /// Id in the minecraft:block_entity_type registry
+    pub fn type_id(&self) -> i32 {
+        match self {
+            Self::Sign(_) => 7,
+        }
+    }
+
+    pub fn to_nbt_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::Sign(sign) => fastnbt::to_bytes_with_opts(sign, SerOpts::network_nbt()).unwrap(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SignBlockEntity {
+    pub front_text: SignText,
+    pub back_text: SignText,
+    pub is_waxed: i8,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SignText {
+    /// Text components as JSON strings, one per line
+    pub messages: Vec<String>,
+    pub color: String,
+    pub has_glowing_text: i8,
+}
+
+impl Default for SignText {
+    fn default() -> Self {
+        Self {
+            messages: vec![TextComponent::new("").to_json(); 4],
+            color: "black".into(),
+            has_glowing_text: 0,
+        }
+    }
+}
+
+impl SignText {
+    pub fn set_lines(&mut self, lines: &[String; 4]) {
+        self.messages = lines.iter().map(|line| TextComponent::new(line.clone()).to_json()).collect();
+    }
+}
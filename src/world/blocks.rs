@@ -0,0 +1,43 @@
+// Default block states of common blocks for protocol 767, enough to name blocks in commands
+// https://wiki.vg/Data_Generators#Blocks_report
+
+/// (name, default state id)
+const BLOCKS: &[(&str, i32)] = &[
+    ("air", 0),
+    ("stone", 1),
+    ("granite", 2),
+    ("polished_granite", 3),
+    ("diorite", 4),
+    ("polished_diorite", 5),
+    ("andesite", 6),
+    ("polished_andesite", 7),
+    ("grass_block", 9),
+    ("dirt", 10),
+    ("coarse_dirt", 11),
+    ("podzol", 13),
+    ("cobblestone", 14),
+    ("oak_planks", 15),
+    ("spruce_planks", 16),
+    ("birch_planks", 17),
+    ("jungle_planks", 18),
+    ("acacia_planks", 19),
+    ("cherry_planks", 20),
+    ("dark_oak_planks", 21),
+    ("mangrove_planks", 22),
+    ("bamboo_planks", 23),
+    ("bedrock", 79),
+    ("sand", 112),
+    ("red_sand", 117),
+    ("gravel", 118),
+];
+
+/// Name without the namespace of a state in the table
+pub fn name(state: i32) -> Option<&'static str> {
+    BLOCKS.iter().find(|(_, id)| *id == state).map(|(name, _)| *name)
+}
+
+/// Accepts names with or without the `minecraft:` namespace
+pub fn state_id(name: &str) -> Option<i32> {
+    let name = name.strip_prefix("minecraft:").unwrap_or(name);
+    BLOCKS.iter().find(|(n, _)| *n == name).map(|(_, id)| *id)
+}
@@ -0,0 +1,84 @@
+// https://wiki.vg/Protocol#Initialize_World_Border
+
+/// Largest diameter the client accepts, also the default so the border is out of the way
+pub const MAX_DIAMETER: f64 = 59999968.0;
+
+pub struct WorldBorder {
+    pub center_x: f64,
+    pub center_z: f64,
+    pub old_diameter: f64,
+    pub new_diameter: f64,
+    /// Milliseconds until the diameter reaches `new_diameter`
+    pub speed: i64,
+    pub portal_teleport_boundary: i32,
+    pub warning_blocks: i32,
+    /// Seconds
+    pub warning_time: i32,
+    /// Damage taken for each block past the safe zone, server side only
+    pub damage_per_block: f64,
+    /// Blocks past the border players can be before taking damage, server side only
+    pub damage_buffer: f64,
+}
+
+impl Default for WorldBorder {
+    fn default() -> Self {
+        Self {
+            center_x: 0.0,
+            center_z: 0.0,
+            old_diameter: MAX_DIAMETER,
+            new_diameter: MAX_DIAMETER,
+            speed: 0,
+            portal_teleport_boundary: 29999984,
+            warning_blocks: 5,
+            warning_time: 15,
+            damage_per_block: 0.2,
+            damage_buffer: 5.0,
+        }
+    }
+}
+
+impl WorldBorder {
+    /// How far past the border the point is, 0 when inside.
+    /// The border is treated as already at its new diameter while it is moving.
+    pub fn distance_outside(&self, x: f64, z: f64) -> f64 {
+        let radius = self.new_diameter / 2.0;
+        let dx = (x - self.center_x).abs() - radius;
+        let dz = (z - self.center_z).abs() - radius;
+        dx.max(dz).max(0.0)
+    }
+
+    /// Damage a player at this point should take, 0 while inside the border and buffer
+    pub fn damage_at(&self, x: f64, z: f64) -> f32 {
+        let past_buffer = self.distance_outside(x, z) - self.damage_buffer;
+        if past_buffer <= 0.0 {
+            return 0.0;
+        }
+        (past_buffer * self.damage_per_block).floor().max(1.0) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn border(diameter: f64) -> WorldBorder {
+        WorldBorder { new_diameter: diameter, ..WorldBorder::default() }
+    }
+
+    #[test]
+    fn distance_is_measured_from_the_nearest_edge() {
+        let border = border(10.0);
+        assert_eq!(border.distance_outside(0.0, 0.0), 0.0);
+        assert_eq!(border.distance_outside(5.0, -5.0), 0.0);
+        assert_eq!(border.distance_outside(8.0, 2.0), 3.0);
+        assert_eq!(border.distance_outside(-1.0, -12.0), 7.0);
+    }
+
+    #[test]
+    fn damage_starts_past_the_buffer() {
+        let border = border(10.0);
+        assert_eq!(border.damage_at(10.0, 0.0), 0.0);
+        assert_eq!(border.damage_at(10.5, 0.0), 1.0);
+        assert_eq!(border.damage_at(30.0, 0.0), 4.0);
+    }
+}
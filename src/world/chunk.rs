@@ -0,0 +1,226 @@
+// Chunk column data sent in Chunk Data and Update Light
+// https://wiki.vg/Chunk_Format
+
+use crate::{types::reader::PacketReader, utils::write_ivar};
+
+/// Network NBT for an empty compound, the client works heightmaps out itself
+const EMPTY_HEIGHTMAPS: [u8; 2] = [0x0A, 0x00];
+
+/// Data for a column of air with every biome set to the first biome in the registry
+pub fn empty_chunk_data(section_count: usize) -> Vec<u8> {
+    let mut sections: Vec<u8> = Vec::new();
+    for _ in 0..section_count {
+        // Non-air block count
+        sections.extend_from_slice(&0i16.to_be_bytes());
+        // Block states then biomes, both single valued palettes with no data array
+        for _ in 0..2 {
+            sections.push(0);
+            write_ivar(&mut sections, 0);
+            write_ivar(&mut sections, 0);
+        }
+    }
+    let mut buffer: Vec<u8> = Vec::new();
+    buffer.extend_from_slice(&EMPTY_HEIGHTMAPS);
+    write_ivar(&mut buffer, sections.len() as i32);
+    buffer.append(&mut sections);
+    // No block entities
+    write_ivar(&mut buffer, 0);
+    buffer
+}
+
+const BLOCKS_PER_SECTION: usize = 4096;
+const BIOMES_PER_SECTION: usize = 64;
+
+/// Re-reads a built Chunk Data and Update Light packet and checks its sections are consistent:
+/// block counts match the palettes, palette indices are in range and data arrays are the right length.
+/// Light data isn't checked.
+pub fn validate_chunk_packet(packet: &[u8], section_count: usize) -> Result<(), &'static str> {
+    let mut reader = PacketReader::new(packet);
+    reader.read_ivar()?;
+    // Chunk x and z
+    reader.read_bytes(8)?;
+    validate_chunk_data(&mut reader, section_count)
+}
+
+fn validate_chunk_data(reader: &mut PacketReader, section_count: usize) -> Result<(), &'static str> {
+    // Network NBT has the root tag type but no name
+    if reader.read_u8()? != 0x0A {
+        return Err("Heightmaps aren't a compound");
+    }
+    skip_nbt_tag(reader, 0x0A, 0)?;
+    let size = reader.read_ivar()?;
+    if size < 0 {
+        return Err("Negative section data size");
+    }
+    let mut sections = PacketReader::new(reader.read_bytes(size as usize)?);
+    for _ in 0..section_count {
+        let block_count = sections.read_i16()?;
+        let blocks = read_paletted_container(&mut sections, BLOCKS_PER_SECTION, 4, 8)?;
+        read_paletted_container(&mut sections, BIOMES_PER_SECTION, 1, 3)?;
+        // Only block state 0 counts as air, the other air blocks aren't used
+        let non_air = blocks.iter().filter(|state| **state != 0).count();
+        if non_air != block_count as usize {
+            return Err("Block count doesn't match the block states");
+        }
+    }
+    if sections.remaining() != 0 {
+        return Err("Section data is longer than the sections");
+    }
+    Ok(())
+}
+
+/// Reads a paletted container into the value of every entry
+fn read_paletted_container(reader: &mut PacketReader, entries: usize, min_indirect: u8, max_indirect: u8) -> Result<Vec<i32>, &'static str> {
+    let bits = reader.read_u8()?;
+    if bits == 0 {
+        let value = reader.read_ivar()?;
+        if reader.read_ivar()? != 0 {
+            return Err("Single valued container has a data array");
+        }
+        return Ok(vec![value; entries]);
+    }
+    let palette = match bits <= max_indirect {
+        true => {
+            let length = reader.read_ivar()?;
+            if length <= 0 {
+                return Err("Empty palette");
+            }
+            (0..length).map(|_| reader.read_ivar()).collect::<Result<Vec<i32>, _>>()?
+        },
+        false => Vec::new(),
+    };
+    // Smaller indirect sizes are widened by the client
+    let bits = match palette.is_empty() {
+        true => bits,
+        false => bits.max(min_indirect),
+    } as usize;
+    let per_long = 64 / bits;
+    let longs = reader.read_ivar()?;
+    if longs < 0 || longs as usize != entries.div_ceil(per_long) {
+        return Err("Data array length doesn't match the bits per entry");
+    }
+    let mask = (1u64 << bits) - 1;
+    let mut values: Vec<i32> = Vec::with_capacity(entries);
+    for _ in 0..longs {
+        let long = u64::from_be_bytes(reader.read_bytes(8)?.try_into().unwrap());
+        for i in 0..per_long {
+            if values.len() == entries {
+                break;
+            }
+            let index = ((long >> (i * bits)) & mask) as usize;
+            let value = match palette.is_empty() {
+                true => index as i32,
+                false => *palette.get(index).ok_or("Palette index out of range")?,
+            };
+            values.push(value);
+        }
+    }
+    Ok(values)
+}
+
+/// Skips over a tag's payload, compounds and lists can nest at most 512 deep like vanilla
+fn skip_nbt_tag(reader: &mut PacketReader, tag: u8, depth: usize) -> Result<(), &'static str> {
+    if depth > 512 {
+        return Err("NBT is nested too deep");
+    }
+    let skip_array = |reader: &mut PacketReader, size: usize| -> Result<(), &'static str> {
+        let length = reader.read_i32()?;
+        if length < 0 {
+            return Err("Negative NBT array length");
+        }
+        reader.read_bytes(length as usize * size).map(|_| ())
+    };
+    match tag {
+        0x01 => reader.read_bytes(1).map(|_| ()),
+        0x02 => reader.read_bytes(2).map(|_| ()),
+        0x03 | 0x05 => reader.read_bytes(4).map(|_| ()),
+        0x04 | 0x06 => reader.read_bytes(8).map(|_| ()),
+        0x07 => skip_array(reader, 1),
+        0x08 => {
+            let length = u16::from_be_bytes(reader.read_bytes(2)?.try_into().unwrap());
+            reader.read_bytes(length as usize).map(|_| ())
+        },
+        0x09 => {
+            let item = reader.read_u8()?;
+            let length = reader.read_i32()?;
+            for _ in 0..length.max(0) {
+                skip_nbt_tag(reader, item, depth + 1)?;
+            }
+            Ok(())
+        },
+        0x0A => loop {
+            let item = reader.read_u8()?;
+            if item == 0x00 {
+                return Ok(());
+            }
+            // Name
+            skip_nbt_tag(reader, 0x08, depth + 1)?;
+            skip_nbt_tag(reader, item, depth + 1)?;
+        },
+        0x0B => skip_array(reader, 4),
+        0x0C => skip_array(reader, 8),
+        _ => Err("Unknown NBT tag"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{packets, world::light::LightData};
+
+    /// A bedrock, dirt, dirt, grass superflat section with a 4 bit palette
+    fn superflat_section(block_count: i16, longs: usize, palette_index_override: Option<u64>) -> Vec<u8> {
+        let mut section: Vec<u8> = Vec::new();
+        section.extend_from_slice(&block_count.to_be_bytes());
+        section.push(4);
+        // Air, bedrock, dirt, grass block
+        let palette = [0, 85, 10, 9];
+        write_ivar(&mut section, palette.len() as i32);
+        for state in palette {
+            write_ivar(&mut section, state);
+        }
+        write_ivar(&mut section, longs as i32);
+        // 16 entries per long, so 16 longs per layer
+        for long in 0..longs {
+            let index: u64 = match long / 16 {
+                0 => 1,
+                1 | 2 => 2,
+                3 => 3,
+                _ => 0,
+            };
+            let index = palette_index_override.filter(|_| long == 0).unwrap_or(index);
+            let packed = (0..16).fold(0u64, |packed, i| packed | (index << (i * 4)));
+            section.extend_from_slice(&packed.to_be_bytes());
+        }
+        // Plains everywhere
+        section.push(0);
+        write_ivar(&mut section, 0);
+        write_ivar(&mut section, 0);
+        section
+    }
+
+    fn superflat_packet(mut section: Vec<u8>) -> Vec<u8> {
+        let mut data: Vec<u8> = Vec::new();
+        data.extend_from_slice(&EMPTY_HEIGHTMAPS);
+        write_ivar(&mut data, section.len() as i32);
+        data.append(&mut section);
+        write_ivar(&mut data, 0);
+        packets::play::chunk_data(0, 0, &data, &LightData::full_bright(1))
+    }
+
+    #[test]
+    fn built_chunks_pass() {
+        let data = empty_chunk_data(24);
+        let packet = packets::play::chunk_data(3, -2, &data, &LightData::full_bright(24));
+        assert_eq!(validate_chunk_packet(&packet, 24), Ok(()));
+        assert_eq!(validate_chunk_packet(&superflat_packet(superflat_section(1024, 256, None)), 1), Ok(()));
+    }
+
+    #[test]
+    fn corrupted_chunks_fail() {
+        assert_eq!(validate_chunk_packet(&superflat_packet(superflat_section(1000, 256, None)), 1), Err("Block count doesn't match the block states"));
+        assert_eq!(validate_chunk_packet(&superflat_packet(superflat_section(1024, 255, None)), 1), Err("Data array length doesn't match the bits per entry"));
+        assert_eq!(validate_chunk_packet(&superflat_packet(superflat_section(1024, 256, Some(7))), 1), Err("Palette index out of range"));
+        assert_eq!(validate_chunk_packet(&superflat_packet(superflat_section(1024, 256, None)), 2), Err("Packet ended unexpectedly"));
+    }
+}
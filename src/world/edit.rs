@@ -0,0 +1,186 @@
+// Blocks changed from the generated world, which is otherwise empty, and the packets that show
+// those changes to clients that already have the chunks loaded.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{packets, types::position::Position};
+
+pub const AIR: i32 = 0;
+/// Most blocks a single fill may change, the same limit as vanilla's /fill
+pub const MAX_FILL_VOLUME: i64 = 32768;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditError {
+    /// Below the bottom or above the top of the world
+    OutOfWorld,
+    /// The region holds more than `MAX_FILL_VOLUME` blocks
+    TooLarge(i64),
+}
+
+impl std::fmt::Display for EditError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::OutOfWorld => write!(f, "Position is outside of the world"),
+            Self::TooLarge(volume) => write!(f, "Too many blocks in the specified area (maximum {MAX_FILL_VOLUME}, specified {volume})"),
+        }
+    }
+}
+
+/// Block states keyed by chunk column, air is never stored since it is what every chunk is generated with
+#[derive(Debug, Default)]
+pub struct BlockChanges {
+    chunks: HashMap<(i32, i32), HashMap<Position, i32>>,
+    /// Chunk columns changed since the last save
+    modified: HashSet<(i32, i32)>,
+}
+
+impl BlockChanges {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, position: Position) -> i32 {
+        self.chunks.get(&chunk_of(position)).and_then(|c| c.get(&position)).copied().unwrap_or(AIR)
+    }
+
+    /// False if the block already had the state
+    pub fn set(&mut self, position: Position, state: i32) -> bool {
+        let chunk = chunk_of(position);
+        if state == AIR {
+            let Some(blocks) = self.chunks.get_mut(&chunk) else {
+                return false;
+            };
+            let removed = blocks.remove(&position).is_some();
+            if blocks.is_empty() {
+                self.chunks.remove(&chunk);
+            }
+            if removed {
+                self.modified.insert(chunk);
+            }
+            return removed;
+        }
+        let changed = self.chunks.entry(chunk).or_default().insert(position, state) != Some(state);
+        if changed {
+            self.modified.insert(chunk);
+        }
+        changed
+    }
+
+    pub fn is_modified(&self) -> bool {
+        !self.modified.is_empty()
+    }
+
+    /// Chunk columns to save, including ones whose changes were all undone
+    pub fn modified_chunks(&self) -> Vec<(i32, i32)> {
+        self.modified.iter().copied().collect()
+    }
+
+    /// Call once the changes have been saved
+    pub fn mark_saved(&mut self) {
+        self.modified.clear();
+    }
+
+    /// Changed blocks in a chunk column, sent after its Chunk Data
+    pub fn in_chunk(&self, chunk_x: i32, chunk_z: i32) -> Vec<(Position, i32)> {
+        match self.chunks.get(&(chunk_x, chunk_z)) {
+            Some(blocks) => blocks.iter().map(|(p, s)| (*p, *s)).collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+pub fn chunk_of(position: Position) -> (i32, i32) {
+    (position.x >> 4, position.z >> 4)
+}
+
+pub fn section_of(position: Position) -> (i32, i32, i32) {
+    (position.x >> 4, position.y >> 4, position.z >> 4)
+}
+
+/// Every position between two corners, inclusive
+pub fn region(from: Position, to: Position) -> Result<Vec<Position>, EditError> {
+    let (min, max) = (
+        Position::new(from.x.min(to.x), from.y.min(to.y), from.z.min(to.z)),
+        Position::new(from.x.max(to.x), from.y.max(to.y), from.z.max(to.z)),
+    );
+    let volume = (max.x - min.x + 1) as i64 * (max.y - min.y + 1) as i64 * (max.z - min.z + 1) as i64;
+    if volume > MAX_FILL_VOLUME {
+        return Err(EditError::TooLarge(volume));
+    }
+    let mut positions = Vec::with_capacity(volume as usize);
+    for x in min.x..=max.x {
+        for y in min.y..=max.y {
+            for z in min.z..=max.z {
+                positions.push(Position::new(x, y, z));
+            }
+        }
+    }
+    Ok(positions)
+}
+
+/// Fewest packets that show the changes, grouped by the chunk column they need loaded.
+/// A section with one change gets a Block Update, anything more gets a Multi Block Change.
+pub fn change_packets(changes: &[(Position, i32)]) -> Vec<((i32, i32), Vec<u8>)> {
+    let mut sections: HashMap<(i32, i32, i32), Vec<(Position, i32)>> = HashMap::new();
+    for (position, state) in changes {
+        sections.entry(section_of(*position)).or_default().push((*position, *state));
+    }
+    sections.into_iter().map(|(section, blocks)| {
+        let packet = match blocks.as_slice() {
+            [(position, state)] => packets::play::block_update(*position, *state),
+            _ => {
+                let relative: Vec<(u8, u8, u8, i32)> = blocks.iter()
+                    .map(|(p, s)| ((p.x & 0xF) as u8, (p.y & 0xF) as u8, (p.z & 0xF) as u8, *s))
+                    .collect();
+                packets::play::multi_block_change(section, &relative)
+            },
+        };
+        ((section.0, section.2), packet)
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::Packet;
+
+    #[test]
+    fn unchanged_blocks_are_air() {
+        let mut changes = BlockChanges::new();
+        let position = Position::new(1, 2, 3);
+        assert_eq!(changes.get(position), AIR);
+        assert!(changes.set(position, 5));
+        assert!(!changes.set(position, 5));
+        assert_eq!(changes.get(position), 5);
+        assert!(changes.set(position, AIR));
+        assert_eq!(changes.in_chunk(0, 0), Vec::new());
+        // Still saved so the undone change is written over
+        assert_eq!(changes.modified_chunks(), vec![(0, 0)]);
+    }
+
+    #[test]
+    fn a_small_fill_is_one_multi_block_change_per_section() {
+        // 3x3x3 straddling the x and y section borders
+        let region = region(Position::new(15, 15, 0), Position::new(17, 17, 2)).unwrap();
+        assert_eq!(region.len(), 27);
+        let changes: Vec<(Position, i32)> = region.into_iter().map(|p| (p, 1)).collect();
+        let mut packets = change_packets(&changes);
+        packets.sort_by_key(|(chunk, _)| *chunk);
+        assert_eq!(packets.len(), 4);
+        assert!(packets.iter().all(|(_, packet)| packet[0] as i32 == Packet::UpdateSectionBlocks.id()));
+        assert_eq!(packets.iter().map(|(chunk, _)| *chunk).collect::<Vec<(i32, i32)>>(), vec![(0, 0), (0, 0), (1, 0), (1, 0)]);
+    }
+
+    #[test]
+    fn single_changes_are_block_updates() {
+        let packets = change_packets(&[(Position::new(0, 64, 0), 1), (Position::new(32, 64, 0), 1)]);
+        assert_eq!(packets.len(), 2);
+        assert!(packets.iter().all(|(_, packet)| packet[0] as i32 == Packet::BlockUpdate.id()));
+    }
+
+    #[test]
+    fn fills_are_limited_to_the_max_volume() {
+        assert_eq!(region(Position::new(0, 0, 0), Position::new(31, 31, 31)).unwrap().len(), MAX_FILL_VOLUME as usize);
+        assert_eq!(region(Position::new(0, 0, 0), Position::new(32, 31, 31)), Err(EditError::TooLarge(33792)));
+    }
+}
@@ -0,0 +1,83 @@
+// Keeps track of which entities a client has been sent, so only those near it are spawned
+
+use std::collections::HashSet;
+
+/// Blocks along each horizontal axis, vanilla's default for most entities
+pub const DEFAULT_TRACKING_RANGE: f64 = 64.0;
+
+pub struct EntityTracker {
+    range: f64,
+    tracked: HashSet<i32>,
+}
+
+impl EntityTracker {
+    pub fn new(range: f64) -> Self {
+        Self {
+            range,
+            tracked: HashSet::new(),
+        }
+    }
+
+    pub fn is_tracking(&self, entity_id: i32) -> bool {
+        self.tracked.contains(&entity_id)
+    }
+
+    pub fn in_range(&self, (x, _, z): (f64, f64, f64), (entity_x, _, entity_z): (f64, f64, f64)) -> bool {
+        (x - entity_x).abs() <= self.range && (z - entity_z).abs() <= self.range
+    }
+
+    /// Compares the entities in the world against the ones already sent from `position`,
+    /// returns the entities to spawn and the entities to remove.
+    /// Tracked entities missing from `entities` have left the world and are removed too.
+    pub fn update(&mut self, position: (f64, f64, f64), entities: impl IntoIterator<Item = (i32, (f64, f64, f64))>) -> (Vec<i32>, Vec<i32>) {
+        let in_view: HashSet<i32> = entities.into_iter()
+            .filter(|(_, entity)| self.in_range(position, *entity))
+            .map(|(id, _)| id)
+            .collect();
+        let spawn: Vec<i32> = in_view.difference(&self.tracked).copied().collect();
+        let remove: Vec<i32> = self.tracked.difference(&in_view).copied().collect();
+        self.tracked = in_view;
+        (spawn, remove)
+    }
+
+    /// Stops tracking entities removed from the world, returns the ones that were tracked
+    pub fn forget(&mut self, entity_ids: &[i32]) -> Vec<i32> {
+        entity_ids.iter().copied().filter(|id| self.tracked.remove(id)).collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.tracked.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entities_are_tracked_while_in_range() {
+        let mut tracker = EntityTracker::new(16.0);
+        let entities = [(1, (10.0, 64.0, 0.0)), (2, (0.0, 64.0, 40.0))];
+        assert_eq!(tracker.update((0.0, 64.0, 0.0), entities), (vec![1], vec![]));
+        assert!(tracker.is_tracking(1));
+        assert_eq!(tracker.update((0.0, 64.0, 0.0), entities), (vec![], vec![]));
+        // Walking towards the second entity leaves the first behind
+        assert_eq!(tracker.update((0.0, 64.0, 30.0), entities), (vec![2], vec![1]));
+        assert!(!tracker.is_tracking(1));
+    }
+
+    #[test]
+    fn entities_leaving_the_world_are_removed() {
+        let mut tracker = EntityTracker::new(16.0);
+        tracker.update((0.0, 64.0, 0.0), [(1, (0.0, 64.0, 0.0)), (2, (1.0, 64.0, 0.0))]);
+        assert_eq!(tracker.forget(&[1, 3]), vec![1]);
+        assert_eq!(tracker.update((0.0, 64.0, 0.0), []), (vec![], vec![2]));
+    }
+
+    #[test]
+    fn the_range_is_a_square() {
+        let tracker = EntityTracker::new(16.0);
+        assert!(tracker.in_range((0.0, 0.0, 0.0), (16.0, 300.0, -16.0)));
+        assert!(!tracker.in_range((0.0, 0.0, 0.0), (16.5, 0.0, 0.0)));
+    }
+}
@@ -0,0 +1,79 @@
+// Dropped items lying in the world
+// https://wiki.vg/Entity_metadata#Item_Entity
+
+use crate::{packets::{self, play::SpawnEntity}, types::{metadata::{Metadata, MetadataValue}, slot::Slot}};
+
+/// Entity type id of `minecraft:item`
+const ITEM_ENTITY_TYPE: i32 = 58;
+const ITEM_INDEX: u8 = 8;
+
+/// How far from a player's feet an item can be picked up, per axis
+const PICKUP_RANGE: (f64, f64, f64) = (1.3, 1.8, 1.3);
+/// Ticks before a dropped item can be picked up, so it isn't straight back in the dropper's inventory
+const PICKUP_DELAY: u32 = 40;
+
+#[derive(Debug, Clone)]
+pub struct ItemEntity {
+    pub entity_id: i32,
+    pub uuid: u128,
+    pub item: Slot,
+    pub position: (f64, f64, f64),
+    /// Ticks left until the item can be picked up
+    pub pickup_delay: u32,
+}
+
+impl ItemEntity {
+    pub fn new(entity_id: i32, item: Slot, position: (f64, f64, f64)) -> Self {
+        Self {
+            entity_id,
+            // Only needs to be unique, entity ids already are
+            uuid: entity_id as u32 as u128 | (ITEM_ENTITY_TYPE as u128) << 64,
+            item,
+            position,
+            pickup_delay: PICKUP_DELAY,
+        }
+    }
+
+    pub fn metadata_packet(&self) -> Vec<u8> {
+        let mut metadata = Metadata::new();
+        metadata.set(ITEM_INDEX, MetadataValue::Slot(self.item));
+        packets::play::set_entity_metadata(self.entity_id, &metadata)
+    }
+
+    /// Spawn Entity followed by the metadata holding the item
+    pub fn spawn_packets(&self) -> Vec<Vec<u8>> {
+        let spawn = SpawnEntity {
+            entity_id: self.entity_id,
+            uuid: self.uuid,
+            entity_type: ITEM_ENTITY_TYPE,
+            x: self.position.0,
+            y: self.position.1,
+            z: self.position.2,
+            pitch: 0,
+            yaw: 0,
+            head_yaw: 0,
+            data: 0,
+            velocity: (0, 0, 0),
+        };
+        vec![packets::play::spawn_entity(&spawn), self.metadata_packet()]
+    }
+
+    pub fn in_pickup_range(&self, (x, y, z): (f64, f64, f64)) -> bool {
+        let (dx, dy, dz) = (self.position.0 - x, self.position.1 - y, self.position.2 - z);
+        dx.abs() <= PICKUP_RANGE.0 && (-0.5..=PICKUP_RANGE.1).contains(&dy) && dz.abs() <= PICKUP_RANGE.2
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pickup_range_is_a_box_around_the_feet() {
+        let item = ItemEntity::new(1, Slot::new(1, 1), (0.0, 64.0, 0.0));
+        assert!(item.in_pickup_range((1.0, 63.0, -1.0)));
+        assert!(item.in_pickup_range((0.0, 64.4, 0.0)));
+        assert!(!item.in_pickup_range((0.0, 65.0, 0.0)));
+        assert!(!item.in_pickup_range((1.5, 64.0, 0.0)));
+    }
+}
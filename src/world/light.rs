@@ -0,0 +1,83 @@
+use crate::{types::bitset::BitSet, utils::write_ivar};
+
+// https://wiki.vg/Protocol#Update_Light
+
+/// Bytes in a light array, one nibble per block of a 16x16x16 section
+pub const LIGHT_ARRAY_SIZE: usize = 2048;
+
+/// Light for one chunk column, covers one section below and above the world as well
+pub struct LightData {
+    pub sky_light_mask: BitSet,
+    pub block_light_mask: BitSet,
+    pub empty_sky_light_mask: BitSet,
+    pub empty_block_light_mask: BitSet,
+    pub sky_light: Vec<Vec<u8>>,
+    pub block_light: Vec<Vec<u8>>,
+}
+
+impl LightData {
+    /// Max sky light everywhere and no block light, avoids dark chunks without a lighting engine
+    pub fn full_bright(section_count: usize) -> Self {
+        let light_sections = section_count + 2;
+        let mut sky_light_mask = BitSet::new();
+        let mut empty_block_light_mask = BitSet::new();
+        for i in 0..light_sections {
+            sky_light_mask.set(i);
+            empty_block_light_mask.set(i);
+        }
+        Self {
+            sky_light_mask,
+            block_light_mask: BitSet::new(),
+            empty_sky_light_mask: BitSet::new(),
+            empty_block_light_mask,
+            sky_light: vec![vec![0xFF; LIGHT_ARRAY_SIZE]; light_sections],
+            block_light: Vec::new(),
+        }
+    }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut buffer: Vec<u8> = Vec::new();
+        buffer.append(&mut self.sky_light_mask.encode());
+        buffer.append(&mut self.block_light_mask.encode());
+        buffer.append(&mut self.empty_sky_light_mask.encode());
+        buffer.append(&mut self.empty_block_light_mask.encode());
+        for arrays in [&self.sky_light, &self.block_light] {
+            write_ivar(&mut buffer, arrays.len() as i32);
+            for array in arrays {
+                write_ivar(&mut buffer, array.len() as i32);
+                buffer.extend_from_slice(array);
+            }
+        }
+        buffer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::reader::PacketReader;
+
+    #[test]
+    fn full_bright_lights_every_section_with_sky_light() {
+        // 24 sections plus the ones below and above the world fit in one long
+        let bytes = LightData::full_bright(24).as_bytes();
+        let mut reader = PacketReader::new(&bytes);
+        let all_sections = (1i64 << 26) - 1;
+
+        assert_eq!(reader.read_ivar().unwrap(), 1);
+        assert_eq!(reader.read_i64().unwrap(), all_sections);
+        // No block light and no sections without sky light
+        assert_eq!(reader.read_ivar().unwrap(), 0);
+        assert_eq!(reader.read_ivar().unwrap(), 0);
+        assert_eq!(reader.read_ivar().unwrap(), 1);
+        assert_eq!(reader.read_i64().unwrap(), all_sections);
+
+        assert_eq!(reader.read_ivar().unwrap(), 26);
+        for _ in 0..26 {
+            assert_eq!(reader.read_ivar().unwrap(), LIGHT_ARRAY_SIZE as i32);
+            assert!(reader.read_bytes(LIGHT_ARRAY_SIZE).unwrap().iter().all(|b| *b == 0xFF));
+        }
+        assert_eq!(reader.read_ivar().unwrap(), 0);
+        assert_eq!(reader.remaining(), 0);
+    }
+}
@@ -0,0 +1,35 @@
+pub mod block_entity;
+pub mod border;
+pub mod light;
+pub mod weather;
+pub mod item_entity;
+pub mod tracker;
+pub mod settings;
+pub mod vehicle;
+pub mod chunk;
+pub mod batch;
+pub mod edit;
+pub mod blocks;
+pub mod entity_tracker;
+pub mod save;
+pub mod anvil;
+
+use crate::utils::sha256;
+
+/// First 8 bytes of the SHA-256 of the seed, sent in Login (play) and Respawn for the client's biome noise.
+/// Both the seed and the hash are little endian, matching vanilla.
+pub fn seed_hash(seed: i64) -> i64 {
+    let digest = sha256(&seed.to_le_bytes());
+    i64::from_le_bytes(digest[..8].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seed_hashes_match_vanilla() {
+        assert_eq!(seed_hash(0), 8794265229978523055);
+        assert_eq!(seed_hash(12345), 293737985876514017);
+    }
+}
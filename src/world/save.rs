@@ -0,0 +1,324 @@
+// Saves the blocks changed from the generated world to Anvil region files in `<world>/region`,
+// so they survive a restart. Only chunks with changes are written, and only their block states.
+// https://minecraft.wiki/w/Chunk_format
+
+use std::{collections::{hash_map::Entry, HashMap}, fs, path::Path};
+
+use fastnbt::LongArray;
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::{types::position::Position, world::{anvil::{region_file_name, AnvilError, RegionFile}, blocks, edit::{BlockChanges, AIR}, settings::WorldSettings}};
+
+/// 1.21.1
+pub const DATA_VERSION: i32 = 3955;
+const SECTION_VOLUME: usize = 4096;
+/// Block state palettes always use at least this many bits per block
+const MIN_BITS_PER_BLOCK: u32 = 4;
+const FULL_STATUS: &str = "minecraft:full";
+/// Biomes aren't generated, every section is saved as plains
+const BIOME: &str = "minecraft:plains";
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(std::io::Error),
+    Anvil(AnvilError),
+    Nbt(fastnbt::error::Error),
+}
+
+impl std::fmt::Display for SaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "Could not access the world directory: {e}"),
+            Self::Anvil(e) => write!(f, "{e}"),
+            Self::Nbt(e) => write!(f, "Invalid chunk: {e}"),
+        }
+    }
+}
+
+impl From<AnvilError> for SaveError {
+    fn from(e: AnvilError) -> Self {
+        Self::Anvil(e)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ChunkNbt {
+    #[serde(rename = "DataVersion")]
+    data_version: i32,
+    #[serde(rename = "xPos")]
+    x: i32,
+    #[serde(rename = "zPos")]
+    z: i32,
+    #[serde(rename = "yPos")]
+    y: i32,
+    #[serde(rename = "Status")]
+    status: String,
+    sections: Vec<SectionNbt>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SectionNbt {
+    #[serde(rename = "Y")]
+    y: i8,
+    block_states: PalettedNbt<BlockStateNbt>,
+    biomes: PalettedNbt<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PalettedNbt<T> {
+    palette: Vec<T>,
+    /// Left out when the palette has a single entry
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    data: Option<LongArray>,
+}
+
+/// Only default states are placed, so properties are never written
+#[derive(Debug, Serialize, Deserialize)]
+struct BlockStateNbt {
+    #[serde(rename = "Name")]
+    name: String,
+}
+
+fn region_directory(directory: &str) -> String {
+    format!("{directory}/region")
+}
+
+/// Writes every chunk changed since the last save, returns how many were saved
+pub fn save(directory: &str, blocks: &BlockChanges, settings: &WorldSettings, compression_level: u32) -> Result<usize, SaveError> {
+    let regions = region_directory(directory);
+    fs::create_dir_all(&regions).map_err(SaveError::Io)?;
+    let mut files: HashMap<String, RegionFile> = HashMap::new();
+    let mut saved = 0;
+    for (chunk_x, chunk_z) in blocks.modified_chunks() {
+        let nbt = chunk_nbt(chunk_x, chunk_z, &blocks.in_chunk(chunk_x, chunk_z), settings);
+        let path = format!("{regions}/{}", region_file_name(chunk_x, chunk_z));
+        let region = match files.entry(path) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let file = RegionFile::open(entry.key(), compression_level)?;
+                entry.insert(file)
+            },
+        };
+        region.write_chunk(chunk_x, chunk_z, &nbt)?;
+        saved += 1;
+    }
+    Ok(saved)
+}
+
+/// Every block that isn't air in the saved chunks, a missing region directory is a NotFound error
+pub fn load(directory: &str, settings: &WorldSettings) -> Result<Vec<(Position, i32)>, SaveError> {
+    let mut saved = Vec::new();
+    for entry in fs::read_dir(region_directory(directory)).map_err(SaveError::Io)? {
+        let path = entry.map_err(SaveError::Io)?.path();
+        let Some((region_x, region_z)) = region_coordinates(&path) else {
+            continue;
+        };
+        let mut region = RegionFile::open(&path.to_string_lossy(), 0)?;
+        for i in 0..32 * 32 {
+            let (chunk_x, chunk_z) = (region_x * 32 + i % 32, region_z * 32 + i / 32);
+            if !region.has_chunk(chunk_x, chunk_z) {
+                continue;
+            }
+            let Some(data) = region.read_chunk(chunk_x, chunk_z)? else {
+                continue;
+            };
+            let nbt: ChunkNbt = fastnbt::from_bytes(&data).map_err(SaveError::Nbt)?;
+            if nbt.data_version > DATA_VERSION {
+                warn!("Chunk {chunk_x}, {chunk_z} was saved by a newer version ({}), it may not load correctly", nbt.data_version);
+            }
+            // Vanilla keeps unfinished chunks around the edge of what was generated, they have no blocks worth keeping
+            if nbt.status != FULL_STATUS {
+                continue;
+            }
+            saved.extend(chunk_blocks(&nbt, settings));
+        }
+    }
+    Ok(saved)
+}
+
+/// Region coordinates from a file name such as r.0.-1.mca
+fn region_coordinates(path: &Path) -> Option<(i32, i32)> {
+    let name = path.file_name()?.to_str()?;
+    let mut parts = name.strip_prefix("r.")?.strip_suffix(".mca")?.split('.');
+    let coordinates = (parts.next()?.parse().ok()?, parts.next()?.parse().ok()?);
+    parts.next().is_none().then_some(coordinates)
+}
+
+/// Index of a block within its section
+fn section_index(position: Position) -> usize {
+    ((position.y & 15) << 8 | (position.z & 15) << 4 | (position.x & 15)) as usize
+}
+
+fn chunk_nbt(chunk_x: i32, chunk_z: i32, changes: &[(Position, i32)], settings: &WorldSettings) -> ChunkNbt {
+    let min_section = settings.min_y >> 4;
+    let mut sections = vec![[AIR; SECTION_VOLUME]; settings.section_count()];
+    for (position, state) in changes {
+        if let Some(section) = sections.get_mut(((position.y >> 4) - min_section) as usize) {
+            section[section_index(*position)] = *state;
+        }
+    }
+    let sections = sections.iter().enumerate().map(|(i, states)| {
+        let (palette, indices) = palette_of(states);
+        SectionNbt {
+            y: (min_section + i as i32) as i8,
+            block_states: PalettedNbt {
+                data: (palette.len() > 1).then(|| LongArray::new(pack(&indices, palette.len()))),
+                palette: palette.into_iter().map(|state| BlockStateNbt { name: state_name(state) }).collect(),
+            },
+            biomes: PalettedNbt { palette: vec![BIOME.into()], data: None },
+        }
+    }).collect();
+    ChunkNbt {
+        data_version: DATA_VERSION,
+        x: chunk_x,
+        z: chunk_z,
+        y: min_section,
+        status: FULL_STATUS.into(),
+        sections,
+    }
+}
+
+fn chunk_blocks(nbt: &ChunkNbt, settings: &WorldSettings) -> Vec<(Position, i32)> {
+    let mut blocks = Vec::new();
+    for section in &nbt.sections {
+        let section_y = section.y as i32;
+        if section_y < settings.min_y >> 4 || section_y >= settings.max_y() >> 4 {
+            continue;
+        }
+        let palette: Vec<i32> = section.block_states.palette.iter().map(|state| {
+            blocks::state_id(&state.name).unwrap_or_else(|| {
+                warn!("Unknown block {} in chunk {}, {} is loaded as air", state.name, nbt.x, nbt.z);
+                AIR
+            })
+        }).collect();
+        if palette.is_empty() {
+            continue;
+        }
+        let indices = match &section.block_states.data {
+            Some(data) => match unpack(&data.iter().copied().collect::<Vec<i64>>(), palette.len()) {
+                Some(indices) => indices,
+                None => {
+                    warn!("Section {section_y} of chunk {}, {} has malformed block states", nbt.x, nbt.z);
+                    continue;
+                },
+            },
+            None => vec![0; SECTION_VOLUME],
+        };
+        for (i, index) in indices.into_iter().enumerate() {
+            let state = palette.get(index).copied().unwrap_or(AIR);
+            if state == AIR {
+                continue;
+            }
+            let i = i as i32;
+            blocks.push((Position::new(nbt.x * 16 + (i & 15), section_y * 16 + (i >> 8), nbt.z * 16 + (i >> 4 & 15)), state));
+        }
+    }
+    blocks
+}
+
+fn state_name(state: i32) -> String {
+    match blocks::name(state) {
+        Some(name) => format!("minecraft:{name}"),
+        None => {
+            warn!("Block state {state} has no name, it is saved as air");
+            "minecraft:air".into()
+        },
+    }
+}
+
+/// Distinct states in the order they first appear and each block's index into them
+fn palette_of(states: &[i32]) -> (Vec<i32>, Vec<usize>) {
+    let mut palette: Vec<i32> = Vec::new();
+    let indices = states.iter().map(|state| match palette.iter().position(|s| s == state) {
+        Some(index) => index,
+        None => {
+            palette.push(*state);
+            palette.len() - 1
+        },
+    }).collect();
+    (palette, indices)
+}
+
+fn bits_per_block(palette_length: usize) -> u32 {
+    (usize::BITS - (palette_length - 1).leading_zeros()).max(MIN_BITS_PER_BLOCK)
+}
+
+/// Packs palette indices into longs, entries never span two longs
+fn pack(indices: &[usize], palette_length: usize) -> Vec<i64> {
+    let bits = bits_per_block(palette_length);
+    let per_long = (64 / bits) as usize;
+    let mut data = vec![0i64; indices.len().div_ceil(per_long)];
+    for (i, index) in indices.iter().enumerate() {
+        data[i / per_long] |= (*index as i64) << ((i % per_long) as u32 * bits);
+    }
+    data
+}
+
+/// Inverse of `pack`, None if there are too few longs for a whole section
+fn unpack(data: &[i64], palette_length: usize) -> Option<Vec<usize>> {
+    let bits = bits_per_block(palette_length);
+    let per_long = (64 / bits) as usize;
+    if data.len() < SECTION_VOLUME.div_ceil(per_long) {
+        return None;
+    }
+    let mask = (1u64 << bits) - 1;
+    Some((0..SECTION_VOLUME).map(|i| {
+        (data[i / per_long] as u64 >> ((i % per_long) as u32 * bits) & mask) as usize
+    }).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn palette_indices_pack_without_spanning_longs() {
+        // 5 bits per block leaves 4 unused bits in each long
+        let palette_length = 17;
+        let indices: Vec<usize> = (0..SECTION_VOLUME).map(|i| i % palette_length).collect();
+        let data = pack(&indices, palette_length);
+        assert_eq!(data.len(), SECTION_VOLUME.div_ceil(12));
+        assert_eq!(data[0] as u64 >> 60, 0);
+        assert_eq!(unpack(&data, palette_length), Some(indices));
+        assert_eq!(unpack(&data[1..], palette_length), None);
+    }
+
+    #[test]
+    fn small_palettes_use_four_bits() {
+        assert_eq!(bits_per_block(2), 4);
+        assert_eq!(bits_per_block(16), 4);
+        assert_eq!(bits_per_block(17), 5);
+    }
+
+    #[test]
+    fn chunk_nbt_keeps_the_changed_blocks() {
+        let settings = WorldSettings::default();
+        let changes = vec![(Position::new(-16, -64, 31), 1), (Position::new(-1, 100, 16), 15)];
+        let nbt = chunk_nbt(-1, 1, &changes, &settings);
+        assert_eq!(nbt.sections.len(), settings.section_count());
+        assert_eq!(nbt.sections[0].block_states.palette.len(), 2);
+        // Untouched sections are a single palette entry without data
+        assert!(nbt.sections[1].block_states.data.is_none());
+        let mut blocks = chunk_blocks(&nbt, &settings);
+        blocks.sort_by_key(|(position, _)| position.y);
+        assert_eq!(blocks, changes);
+    }
+
+    #[test]
+    fn modified_chunk_is_written_and_read_back() {
+        let directory = std::env::temp_dir().join(format!("blahaj-save-{}", std::process::id()));
+        let directory = directory.to_string_lossy().to_string();
+        let settings = WorldSettings::default();
+        let mut changes = BlockChanges::new();
+        changes.set(Position::new(40, 70, -300), 14);
+        changes.set(Position::new(-5, 0, 5), 1);
+        assert_eq!(save(&directory, &changes, &settings, 6).unwrap(), 2);
+        assert!(Path::new(&format!("{directory}/region/r.0.-1.mca")).exists());
+
+        let mut loaded = load(&directory, &settings).unwrap();
+        loaded.sort_by_key(|(position, _)| position.x);
+        assert_eq!(loaded, vec![(Position::new(-5, 0, 5), 1), (Position::new(40, 70, -300), 14)]);
+        let _ = fs::remove_dir_all(&directory);
+    }
+}
@@ -0,0 +1,148 @@
+use log::error;
+use serde::Deserialize;
+
+use crate::types::position::Position;
+
+const MAX_PLATFORM_SIZE: i32 = 16;
+
+/// Vertical extent of the world, shared by the dimension type, chunk sections and coordinate checks,
+/// and where players spawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct WorldSettings {
+    /// Lowest block y, must be a multiple of 16
+    pub min_y: i32,
+    /// Number of blocks from `min_y` upwards, must be a multiple of 16
+    pub height: i32,
+    /// Block players spawn and respawn at
+    pub spawn: (i32, i32, i32),
+    /// Block state of the platform under the spawn, 1 is stone
+    pub platform_block: i32,
+    /// Width of the square platform under the spawn so players don't fall through the empty world, 0 for none
+    pub platform_size: i32,
+    /// Only sent to clients hashed, nothing is generated from it
+    pub seed: i64,
+}
+
+impl Default for WorldSettings {
+    fn default() -> Self {
+        Self {
+            min_y: -64,
+            height: 384,
+            spawn: (0, 64, 0),
+            platform_block: 1,
+            platform_size: 3,
+            seed: 0,
+        }
+    }
+}
+
+impl WorldSettings {
+    /// One past the highest block y
+    pub fn max_y(&self) -> i32 {
+        self.min_y + self.height
+    }
+
+    pub fn section_count(&self) -> usize {
+        (self.height / 16) as usize
+    }
+
+    pub fn contains_y(&self, y: f64) -> bool {
+        (self.min_y as f64..=self.max_y() as f64).contains(&y)
+    }
+
+    /// Limits the client accepts for a dimension type
+    pub fn validate(&self) -> Result<(), &'static str> {
+        if self.min_y % 16 != 0 || self.height % 16 != 0 {
+            return Err("min_y and height must be multiples of 16");
+        }
+        if self.height < 16 || self.height > 4064 {
+            return Err("height must be between 16 and 4064");
+        }
+        if self.min_y < -2032 || self.max_y() > 2032 {
+            return Err("the world must fit between y -2032 and 2032");
+        }
+        if !self.contains_y(self.spawn.1 as f64) {
+            return Err("spawn must be inside the world");
+        }
+        if !(0..=MAX_PLATFORM_SIZE).contains(&self.platform_size) || self.platform_block < 0 {
+            return Err("platform_size must be between 0 and 16 and platform_block can't be negative");
+        }
+        if self.platform_size > 0 && self.spawn.1 - 1 < self.min_y {
+            return Err("the spawn platform must be inside the world");
+        }
+        Ok(())
+    }
+
+    /// Blocks of the platform centred under the spawn
+    pub fn spawn_platform(&self) -> Vec<(Position, i32)> {
+        let (x, y, z) = self.spawn;
+        let start = -(self.platform_size - 1) / 2;
+        let mut blocks = Vec::new();
+        for dx in start..start + self.platform_size {
+            for dz in start..start + self.platform_size {
+                blocks.push((Position::new(x + dx, y - 1, z + dz), self.platform_block));
+            }
+        }
+        blocks
+    }
+
+    /// Falls back to the vanilla overworld height when the settings are invalid
+    pub fn validated(self) -> Self {
+        match self.validate() {
+            Ok(()) => self,
+            Err(e) => {
+                error!("Invalid world settings, using defaults: {e}");
+                Self::default()
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{types::position::Position, world::chunk};
+
+    #[test]
+    fn custom_height_sets_the_section_count() {
+        let settings = WorldSettings { min_y: 0, height: 256, ..WorldSettings::default() };
+        assert!(settings.validate().is_ok());
+        assert_eq!(settings.section_count(), 16);
+        assert_eq!(settings.max_y(), 256);
+        assert!(!settings.contains_y(-1.0));
+        // Two bytes of block count and two single valued palettes per empty section
+        let data = chunk::empty_chunk_data(settings.section_count());
+        assert_eq!(data[2..4], [0x80, 0x01]);
+        assert_eq!(WorldSettings::default().section_count(), 24);
+    }
+
+    #[test]
+    fn rejects_heights_the_client_can_not_use() {
+        for settings in [
+            WorldSettings { min_y: -60, ..WorldSettings::default() },
+            WorldSettings { height: 100, ..WorldSettings::default() },
+            WorldSettings { min_y: -2048, ..WorldSettings::default() },
+            WorldSettings { spawn: (0, 400, 0), ..WorldSettings::default() },
+            WorldSettings { min_y: 64, height: 256, spawn: (0, 64, 0), ..WorldSettings::default() },
+        ] {
+            assert!(settings.validate().is_err(), "{settings:?}");
+            assert_eq!(settings.validated(), WorldSettings::default());
+        }
+    }
+
+    #[test]
+    fn platform_is_centred_under_the_spawn() {
+        let settings = WorldSettings { spawn: (10, 70, -5), platform_block: 7, ..WorldSettings::default() };
+        let platform = settings.spawn_platform();
+        assert_eq!(platform.len(), 9);
+        for x in 9..=11 {
+            for z in -6..=-4 {
+                assert!(platform.contains(&(Position::new(x, 69, z), 7)));
+            }
+        }
+        assert!(WorldSettings { platform_size: 0, ..settings }.spawn_platform().is_empty());
+        assert!(WorldSettings { platform_size: 17, ..settings }.validate().is_err());
+        assert!(WorldSettings { spawn: (0, -64, 0), ..settings }.validate().is_err());
+    }
+}
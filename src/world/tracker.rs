@@ -0,0 +1,112 @@
+// Keeps track of which chunks a client has loaded as it moves around
+
+use std::collections::HashSet;
+
+pub const DEFAULT_VIEW_DISTANCE: i32 = 10;
+pub const DEFAULT_SIMULATION_DISTANCE: i32 = 10;
+/// Range of view distances the client supports
+pub const VIEW_DISTANCE_RANGE: std::ops::RangeInclusive<i32> = 2..=32;
+
+/// Chunks are only sent as far as both the server and the client want them, None until the client says
+pub fn effective_view_distance(server: i32, client: Option<i32>) -> i32 {
+    client.map_or(server, |client| client.min(server))
+        .clamp(*VIEW_DISTANCE_RANGE.start(), *VIEW_DISTANCE_RANGE.end())
+}
+
+/// Chunks to load and chunks to unload
+pub type ChunkChanges = (Vec<(i32, i32)>, Vec<(i32, i32)>);
+
+#[derive(Default)]
+pub struct ChunkTracker {
+    center: Option<(i32, i32)>,
+    view_distance: i32,
+    loaded: HashSet<(i32, i32)>,
+}
+
+impl ChunkTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn center(&self) -> Option<(i32, i32)> {
+        self.center
+    }
+
+    /// Chunk containing the block coordinates
+    pub fn chunk_of(x: f64, z: f64) -> (i32, i32) {
+        ((x.floor() as i32) >> 4, (z.floor() as i32) >> 4)
+    }
+
+    pub fn is_loaded(&self, chunk: (i32, i32)) -> bool {
+        self.loaded.contains(&chunk)
+    }
+
+    /// Moves the view to a new center chunk, returns the chunks to load and the chunks to unload.
+    /// Nothing changes while the center and view distance stay the same.
+    pub fn update(&mut self, center_x: i32, center_z: i32, view_distance: i32) -> ChunkChanges {
+        if self.center == Some((center_x, center_z)) && self.view_distance == view_distance {
+            return (Vec::new(), Vec::new());
+        }
+        self.center = Some((center_x, center_z));
+        self.view_distance = view_distance;
+
+        let mut in_view: HashSet<(i32, i32)> = HashSet::new();
+        for x in center_x - view_distance..=center_x + view_distance {
+            for z in center_z - view_distance..=center_z + view_distance {
+                in_view.insert((x, z));
+            }
+        }
+        let load: Vec<(i32, i32)> = in_view.difference(&self.loaded).copied().collect();
+        let unload: Vec<(i32, i32)> = self.loaded.difference(&in_view).copied().collect();
+        self.loaded = in_view;
+        (load, unload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn view_distance_is_the_smaller_of_server_and_client() {
+        assert_eq!(effective_view_distance(10, Some(4)), 4);
+        assert_eq!(effective_view_distance(10, Some(16)), 10);
+        assert_eq!(effective_view_distance(10, None), 10);
+        // The client can't go below the smallest distance vanilla allows
+        assert_eq!(effective_view_distance(10, Some(0)), 2);
+    }
+
+    #[test]
+    fn moving_one_chunk_loads_and_unloads_a_row() {
+        let mut tracker = ChunkTracker::new();
+        let (load, unload) = tracker.update(0, 0, 2);
+        assert_eq!(load.len(), 25);
+        assert!(unload.is_empty());
+        assert_eq!(tracker.update(0, 0, 2), (Vec::new(), Vec::new()));
+
+        let (mut load, mut unload) = tracker.update(1, 0, 2);
+        load.sort();
+        unload.sort();
+        assert_eq!(load, (-2..=2).map(|z| (3, z)).collect::<Vec<_>>());
+        assert_eq!(unload, (-2..=2).map(|z| (-2, z)).collect::<Vec<_>>());
+        assert!(tracker.is_loaded((3, 0)));
+        assert!(!tracker.is_loaded((-2, 0)));
+        assert_eq!(tracker.center(), Some((1, 0)));
+    }
+
+    #[test]
+    fn chunks_of_negative_coordinates() {
+        assert_eq!(ChunkTracker::chunk_of(-0.5, 15.9), (-1, 0));
+        assert_eq!(ChunkTracker::chunk_of(-16.0, 16.0), (-1, 1));
+    }
+
+    #[test]
+    fn a_shorter_view_distance_unloads_the_outer_ring() {
+        let mut tracker = ChunkTracker::new();
+        tracker.update(0, 0, 3);
+        let (load, unload) = tracker.update(0, 0, 2);
+        assert!(load.is_empty());
+        assert_eq!(unload.len(), 49 - 25);
+        assert!(unload.iter().all(|(x, z)| x.abs() == 3 || z.abs() == 3));
+    }
+}
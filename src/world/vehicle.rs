@@ -0,0 +1,8 @@
+/// Entity a player can ride and steer, such as a boat
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Vehicle {
+    pub entity_id: i32,
+    pub position: (f64, f64, f64),
+    pub yaw: f32,
+    pub pitch: f32,
+}
@@ -0,0 +1,54 @@
+use crate::packets::{self, play::GameEvent};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Weather {
+    Clear,
+    Rain,
+    Thunder,
+}
+
+impl Weather {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "clear" => Some(Self::Clear),
+            "rain" => Some(Self::Rain),
+            "thunder" => Some(Self::Thunder),
+            _ => None,
+        }
+    }
+
+    /// Game Events that bring a client from clear skies to this weather
+    pub fn packets(&self) -> Vec<Vec<u8>> {
+        let (raining, rain_level, thunder_level) = match self {
+            Self::Clear => (false, 0.0, 0.0),
+            Self::Rain => (true, 1.0, 0.0),
+            Self::Thunder => (true, 1.0, 1.0),
+        };
+        let rain_event = match raining {
+            true => GameEvent::BeginRaining,
+            false => GameEvent::EndRaining,
+        };
+        vec![
+            packets::play::game_event(rain_event, 0.0),
+            packets::play::game_event(GameEvent::RainLevelChange, rain_level),
+            packets::play::game_event(GameEvent::ThunderLevelChange, thunder_level),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thunder_raises_both_levels() {
+        assert_eq!(Weather::parse("thunder"), Some(Weather::Thunder));
+        assert_eq!(Weather::parse("snow"), None);
+        assert_eq!(Weather::Thunder.packets(), vec![
+            packets::play::game_event(GameEvent::BeginRaining, 0.0),
+            packets::play::game_event(GameEvent::RainLevelChange, 1.0),
+            packets::play::game_event(GameEvent::ThunderLevelChange, 1.0),
+        ]);
+        assert_eq!(Weather::Clear.packets()[0], packets::play::game_event(GameEvent::EndRaining, 0.0));
+    }
+}